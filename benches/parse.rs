@@ -0,0 +1,71 @@
+//! Benchmarks for parsing the control tables of the fixture pages, which a
+//! scrape of the whole E-Manual does for every model.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use dynamixel_scraper::download::{merge_tables, DEFAULT_TABLE_INDEXES};
+use dynamixel_scraper::parse_control_table;
+use dynamixel_scraper::serialize::parse_servo;
+use scraper::Html;
+use std::fs;
+use std::path::Path;
+
+static PAGES: &[&str] = &["ax-12a", "xm430-w210", "h54-200-s500-r", "2xc430-w250"];
+
+fn fixture(page: &str) -> String {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join(format!("fixtures/{}.html", page));
+    fs::read_to_string(path).unwrap()
+}
+
+fn parse_document(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parse_document");
+    for page in PAGES {
+        let text = fixture(page);
+        group.bench_function(*page, |b| b.iter(|| Html::parse_document(black_box(&text))));
+    }
+    group.finish();
+}
+
+fn parse_table(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parse_table");
+    for page in PAGES {
+        let document = Html::parse_document(&fixture(page));
+        group.bench_function(*page, |b| {
+            b.iter(|| merge_tables(black_box(&document), DEFAULT_TABLE_INDEXES).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn parse_servo_rows(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parse_servo");
+    for page in PAGES {
+        let document = Html::parse_document(&fixture(page));
+        let table = merge_tables(&document, DEFAULT_TABLE_INDEXES).unwrap();
+        group.bench_function(*page, |b| {
+            b.iter(|| parse_servo(black_box(table.clone())).unwrap())
+        });
+    }
+    group.finish();
+}
+
+/// Everything done to a page once it's fetched: parsing its HTML once, and
+/// then every table and note on it
+fn parse_page(c: &mut Criterion) {
+    let mut group = c.benchmark_group("parse_control_table");
+    for page in PAGES {
+        let text = fixture(page);
+        group.bench_function(*page, |b| {
+            b.iter(|| parse_control_table(black_box(&text)).unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    parse_document,
+    parse_table,
+    parse_servo_rows,
+    parse_page
+);
+criterion_main!(benches);
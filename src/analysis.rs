@@ -1,35 +1,554 @@
-// use prettytable::{Cell, Row, Table};
-// use std::collections::HashMap;
+use crate::download::{TableHeadings, HEADING_ALIASES};
+use crate::report::ErrorCategory;
+use crate::serialize::{
+    deserialize_servo_with_warnings, ControlTableData, IndexEntry, ParseWarning,
+};
+use crate::INDEX_PATH;
+use anyhow::{Context, Error, Result};
+use prettytable::{format, Cell, Row, Table};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
 
-// pub struct FileAnalysis {}
+/// The control table of a model, named for display
+#[derive(Clone, Debug)]
+pub struct ModelTable {
+    pub name: String,
+    /// The model's URL slug, eg "xm430-w210"
+    pub slug: String,
+    pub data: Vec<ControlTableData>,
+    /// Where the model's control table could only be parsed leniently
+    pub warnings: Vec<ParseWarning>,
+}
 
-// pub struct GroupAnalysis {
-//     pub files_analysed: HashMap<String, FileAnalysis>,
-//     pub recurring_cols: Vec<String>,
-//     pub unique_cols: Vec<String>,
-// }
+/// Where an item is found in each of the models being compared
+#[derive(Clone, Debug, PartialEq)]
+pub struct ItemComparison {
+    pub data_name: String,
+    /// The address of the item in each model, in the order the models were
+    /// given, or `None` if the model doesn't have it
+    pub addresses: Vec<Option<u16>>,
+}
 
-// fn analyse_file(contents: &str) -> Vec<Vec<&str>> {
-//     vec![vec![]]
-// }
+impl ItemComparison {
+    /// Whether every model has the item
+    pub fn is_common(&self) -> bool {
+        self.addresses.iter().all(Option::is_some)
+    }
 
-// pub fn display_analysis(contents: &str) {
-//     let mut rows: Vec<Vec<&str>> = Vec::new();
+    /// Whether the models with the item disagree on its address
+    pub fn differs(&self) -> bool {
+        let mut addresses = self.addresses.iter().flatten();
+        match addresses.next() {
+            Some(first) => addresses.any(|address| address != first),
+            None => false,
+        }
+    }
+}
 
-//     for row in contents.split('\n') {
-//         let mut current_row: Vec<&str> = Vec::new();
-//         for col in row.split(", ") {
-//             current_row.push(col);
-//         }
+/// Which items exist in which of a set of models, and at which addresses
+#[derive(Clone, Debug)]
+pub struct Comparison {
+    pub models: Vec<String>,
+    /// Every item found in any model, ordered by its lowest address
+    pub items: Vec<ItemComparison>,
+}
 
-//         rows.push(current_row);
-//     }
+/// Compare the items of each model's control table by name
+pub fn compare(tables: &[ModelTable]) -> Comparison {
+    let mut items: BTreeMap<&str, Vec<Option<u16>>> = BTreeMap::new();
+    for (i, table) in tables.iter().enumerate() {
+        for row in &table.data {
+            if let Some(name) = &row.data_name {
+                let addresses = items
+                    .entry(name.trim())
+                    .or_insert_with(|| vec![None; tables.len()]);
+                // Keep the first address of items listed more than once
+                addresses[i].get_or_insert(row.address);
+            }
+        }
+    }
 
-//     let mut table = Table::new();
+    let mut items: Vec<ItemComparison> = items
+        .into_iter()
+        .map(|(data_name, addresses)| ItemComparison {
+            data_name: data_name.to_string(),
+            addresses,
+        })
+        .collect();
+    items.sort_by_key(|item| item.addresses.iter().flatten().min().copied());
 
-//     for row in rows {
-//         table.add_row(Row::new(row.iter().map(|x| Cell::new(x)).collect()));
-//     }
+    Comparison {
+        models: tables.iter().map(|table| table.name.clone()).collect(),
+        items,
+    }
+}
 
-//     table.printstd();
-// }
+/// Where a register is found in each model of a series that has it
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SeriesRegister {
+    pub data_name: String,
+    /// The address and size of the register in each model with it, by slug
+    pub models: BTreeMap<String, (u16, u8)>,
+    /// Whether every model with the register has it at the same address and
+    /// with the same size, so a driver can access it without knowing which
+    /// model it's talking to
+    pub consistent: bool,
+}
+
+/// The registers of every model in a series, for writing drivers generic
+/// over the series
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SeriesObject {
+    pub series: String,
+    /// The slugs of the models in the series
+    pub models: Vec<String>,
+    /// The registers every model in the series has, by name
+    pub intersection: Vec<String>,
+    /// The registers any model in the series has, ordered by their lowest
+    /// address
+    pub union: Vec<SeriesRegister>,
+}
+
+/// Aggregate the control tables of the models in a series. Items of each
+/// joint are told apart by their joint, eg "Goal Position (Joint 2)".
+pub fn series_object(series: &str, tables: &[ModelTable]) -> SeriesObject {
+    let mut registers: BTreeMap<String, BTreeMap<String, (u16, u8)>> = BTreeMap::new();
+    for table in tables {
+        for row in &table.data {
+            if let Some(name) = &row.data_name {
+                let name = match row.joint {
+                    Some(joint) => format!("{} (Joint {})", name.trim(), joint),
+                    None => name.trim().to_string(),
+                };
+                // Keep the first address of items listed more than once
+                registers
+                    .entry(name)
+                    .or_default()
+                    .entry(table.slug.clone())
+                    .or_insert((row.address, row.size));
+            }
+        }
+    }
+
+    let mut union: Vec<SeriesRegister> = registers
+        .into_iter()
+        .map(|(data_name, models)| {
+            let mut locations = models.values();
+            let first = locations.next();
+            let consistent = locations.all(|location| Some(location) == first);
+            SeriesRegister {
+                data_name,
+                models,
+                consistent,
+            }
+        })
+        .collect();
+    union.sort_by_key(|register| register.models.values().map(|(address, _)| *address).min());
+
+    SeriesObject {
+        series: series.to_string(),
+        models: tables.iter().map(|table| table.slug.clone()).collect(),
+        intersection: union
+            .iter()
+            .filter(|register| register.models.len() == tables.len())
+            .map(|register| register.data_name.clone())
+            .collect(),
+        union,
+    }
+}
+
+impl Comparison {
+    /// A matrix of the address of each item in each model, with differing
+    /// addresses marked. If `differences_only` is set, items at the same
+    /// address in every model are left out.
+    pub fn table(&self, differences_only: bool) -> Table {
+        let mut table = Table::new();
+        table.set_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR);
+
+        let mut titles = vec![Cell::new("Data Name")];
+        titles.extend(self.models.iter().map(|model| Cell::new(model)));
+        table.set_titles(Row::new(titles));
+
+        for item in &self.items {
+            let differs = item.differs() || !item.is_common();
+            if differences_only && !differs {
+                continue;
+            }
+
+            let mut cells = vec![Cell::new(&item.data_name)];
+            cells.extend(item.addresses.iter().map(|address| {
+                let text = address.map_or("-".to_string(), |address| address.to_string());
+                match item.differs() {
+                    true => Cell::new(&text).style_spec("Fy"),
+                    false => Cell::new(&text),
+                }
+            }));
+            table.add_row(Row::new(cells));
+        }
+
+        table
+    }
+
+    /// A line counting the items shared by every model and those that differ
+    pub fn summary(&self) -> String {
+        let common = self.items.iter().filter(|item| item.is_common()).count();
+        let differing = self.items.iter().filter(|item| item.differs()).count();
+
+        format!(
+            "{} items across {} models: {} in every model, {} at differing addresses",
+            self.items.len(),
+            self.models.len(),
+            common,
+            differing
+        )
+    }
+}
+
+/// A combination of headings used by tables across the E-Manual, and the
+/// models whose pages have a table with them
+#[derive(Clone, Debug, PartialEq)]
+pub struct HeadingLayout {
+    /// The headings as written on the page
+    pub headings: Vec<String>,
+    /// The headings as the parser reads them
+    pub normalized: Vec<String>,
+    pub control_table: bool,
+    pub models: Vec<String>,
+}
+
+impl HeadingLayout {
+    /// The normalized headings the parser doesn't know, which only matter in
+    /// control tables
+    pub fn unrecognized(&self) -> Vec<&str> {
+        self.normalized
+            .iter()
+            .filter(|heading| {
+                !HEADING_ALIASES
+                    .iter()
+                    .any(|(canonical, _)| canonical == heading)
+            })
+            .map(String::as_str)
+            .collect()
+    }
+}
+
+/// Group the tables of each model's page by their headings. Control tables
+/// come first, then the layouts used by the most models. If `all_tables`
+/// isn't set, only control tables are included.
+pub fn heading_layouts(
+    pages: &[(String, Vec<TableHeadings>)],
+    all_tables: bool,
+) -> Vec<HeadingLayout> {
+    let mut layouts: BTreeMap<&[String], HeadingLayout> = BTreeMap::new();
+    for (model, tables) in pages {
+        for table in tables {
+            if !all_tables && !table.control_table {
+                continue;
+            }
+
+            let layout = layouts.entry(&table.raw).or_insert_with(|| HeadingLayout {
+                headings: table.raw.clone(),
+                normalized: table.normalized.clone(),
+                control_table: table.control_table,
+                models: vec![],
+            });
+            if !layout.models.contains(model) {
+                layout.models.push(model.clone());
+            }
+        }
+    }
+
+    let mut layouts: Vec<HeadingLayout> = layouts.into_values().collect();
+    layouts.sort_by_key(|layout| {
+        (
+            !layout.control_table,
+            std::cmp::Reverse(layout.models.len()),
+        )
+    });
+
+    layouts
+}
+
+/// A table of each heading layout and the models using it, with headings
+/// the parser doesn't know in control tables marked
+pub fn headings_table(layouts: &[HeadingLayout]) -> Table {
+    let mut table = Table::new();
+    table.set_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR);
+    table.set_titles(Row::new(vec![
+        Cell::new("Headings"),
+        Cell::new("Read as"),
+        Cell::new("Control Table"),
+        Cell::new("Models"),
+    ]));
+
+    for layout in layouts {
+        let unrecognized = layout.control_table && !layout.unrecognized().is_empty();
+        let read_as = Cell::new(&layout.normalized.join(" | "));
+        table.add_row(Row::new(vec![
+            Cell::new(&match layout.headings.is_empty() {
+                true => "(none)".to_string(),
+                false => layout.headings.join(" | "),
+            }),
+            match unrecognized {
+                true => read_as.style_spec("Fy"),
+                false => read_as,
+            },
+            Cell::new(if layout.control_table { "yes" } else { "no" }),
+            Cell::new(&format!(
+                "{} ({})",
+                layout.models.len(),
+                layout.models.join(", ")
+            )),
+        ]));
+    }
+
+    table
+}
+
+/// A part of the control table. EEPROM items are saved when the model is
+/// turned off, while RAM items are reset.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Area {
+    Eeprom,
+    Ram,
+    /// The whole control table, for models with no Torque Enable item to
+    /// divide it at
+    Whole,
+}
+
+impl fmt::Display for Area {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Area::Eeprom => write!(f, "EEPROM"),
+            Area::Ram => write!(f, "RAM"),
+            Area::Whole => write!(f, "Control table"),
+        }
+    }
+}
+
+/// A span of bytes in an area of the control table
+#[derive(Clone, Debug, PartialEq)]
+pub enum Span {
+    Item {
+        address: u16,
+        size: u8,
+        data_name: String,
+    },
+    /// Bytes no item covers
+    Gap { address: u16, size: u16 },
+    /// An item starting before the previous items end, which usually means
+    /// a column was parsed into the wrong field
+    Overlap {
+        address: u16,
+        size: u8,
+        data_name: String,
+        overlaps: String,
+    },
+}
+
+impl Span {
+    pub fn is_issue(&self) -> bool {
+        !matches!(self, Span::Item { .. })
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Span::Item {
+                address,
+                size,
+                data_name,
+            } => {
+                write!(
+                    f,
+                    "{:>5}  {:<8}  {}",
+                    address,
+                    "#".repeat(*size as usize),
+                    data_name
+                )
+            }
+            Span::Gap { address, size } => write!(
+                f,
+                "{:>5}  {:<8}  ({} unused byte{})",
+                address,
+                ".".repeat((*size).min(8) as usize),
+                size,
+                if *size == 1 { "" } else { "s" }
+            ),
+            Span::Overlap {
+                address,
+                size,
+                data_name,
+                overlaps,
+            } => write!(
+                f,
+                "{:>5}  {:<8}  {} overlaps {}",
+                address,
+                "!".repeat(*size as usize),
+                data_name,
+                overlaps
+            ),
+        }
+    }
+}
+
+/// The bytes of an area of the control table, in order of address
+#[derive(Clone, Debug)]
+pub struct AreaLayout {
+    pub area: Area,
+    pub spans: Vec<Span>,
+}
+
+/// Rebuild the byte layout of a control table from the address and size of
+/// each item, split into its EEPROM and RAM areas. RAM is taken to start at
+/// Torque Enable, as it does on every model. The items of each joint of
+/// dual-joint models are laid out along with the rest.
+pub fn layout(data: &[ControlTableData]) -> Vec<AreaLayout> {
+    let ram_start = data
+        .iter()
+        .find(|row| row.data_name.as_deref().map(str::trim) == Some("Torque Enable"))
+        .map(|row| row.address);
+    let areas = match ram_start {
+        Some(ram_start) => vec![
+            (Area::Eeprom, 0, Some(ram_start)),
+            (Area::Ram, ram_start, None),
+        ],
+        None => vec![(Area::Whole, 0, None)],
+    };
+
+    let mut rows: Vec<&ControlTableData> = data.iter().collect();
+    rows.sort_by_key(|row| (row.address, row.size));
+
+    areas
+        .into_iter()
+        .map(|(area, start, end)| {
+            let mut spans = vec![];
+            // The end of the furthest item so far, and its name
+            let mut covered: (u32, String) = (start as u32, String::new());
+
+            for row in rows
+                .iter()
+                .filter(|row| row.address >= start && end.is_none_or(|end| row.address < end))
+            {
+                let data_name = match row.joint {
+                    Some(joint) => format!(
+                        "{} (Joint {})",
+                        row.data_name.as_deref().unwrap_or("-").trim(),
+                        joint
+                    ),
+                    None => row.data_name.as_deref().unwrap_or("-").trim().to_string(),
+                };
+                let (address, row_end) = (row.address as u32, row.address as u32 + row.size as u32);
+
+                if address > covered.0 {
+                    spans.push(Span::Gap {
+                        address: covered.0 as u16,
+                        size: (address - covered.0) as u16,
+                    });
+                }
+                match address < covered.0 {
+                    true => spans.push(Span::Overlap {
+                        address: row.address,
+                        size: row.size,
+                        data_name: data_name.clone(),
+                        overlaps: covered.1.clone(),
+                    }),
+                    false => spans.push(Span::Item {
+                        address: row.address,
+                        size: row.size,
+                        data_name: data_name.clone(),
+                    }),
+                }
+                if row_end > covered.0 {
+                    covered = (row_end, data_name);
+                }
+            }
+
+            AreaLayout { area, spans }
+        })
+        .collect()
+}
+
+/// Only the gaps and overlaps in the layout of a control table
+pub fn layout_issues(data: &[ControlTableData]) -> Vec<(Area, Span)> {
+    layout(data)
+        .into_iter()
+        .flat_map(|layout| {
+            let area = layout.area;
+            layout
+                .spans
+                .into_iter()
+                .filter(Span::is_issue)
+                .map(move |span| (area, span))
+        })
+        .collect()
+}
+
+/// Draw the layout of a control table as a map of its bytes, with a `#` for
+/// each byte of an item, a `.` for unused bytes and a `!` for each byte of an
+/// item overlapping another
+pub fn memory_map(name: &str, data: &[ControlTableData]) -> String {
+    let mut map = String::new();
+    for layout in layout(data) {
+        map.push_str(&format!("{} {}\n", name, layout.area));
+        for span in &layout.spans {
+            map.push_str(&format!("{}\n", span));
+        }
+        map.push('\n');
+    }
+
+    map
+}
+
+/// Load the control tables of previously scraped models from the RON objects
+/// under `dir`, by their URL slugs (eg "xm430-w210"). Every model in the
+/// index is loaded if no slugs are given.
+pub fn load_objects(dir: &Path, slugs: &[String]) -> Result<Vec<ModelTable>> {
+    let index_path = dir.join(Path::new(INDEX_PATH).strip_prefix("objects")?);
+    let index: Vec<IndexEntry> = ron::from_str(
+        &fs::read_to_string(&index_path)
+            .with_context(|| format!("Failed to read {}", index_path.display()))?,
+    )
+    .with_context(|| format!("Failed to parse {}", index_path.display()))?;
+
+    let entries: Vec<&IndexEntry> = match slugs.is_empty() {
+        true => index.iter().collect(),
+        false => slugs
+            .iter()
+            .map(|slug| {
+                index
+                    .iter()
+                    .find(|entry| entry.slug.eq_ignore_ascii_case(slug))
+                    .ok_or_else(|| {
+                        Error::new(ErrorCategory::InvalidSelection).context(format!(
+                            "No model {:?} in {}, scrape it with --ron first",
+                            slug,
+                            index_path.display()
+                        ))
+                    })
+            })
+            .collect::<Result<_>>()?,
+    };
+
+    entries
+        .into_iter()
+        .map(|entry| {
+            let path = dir.join(&entry.path);
+            let text = fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            let (data, warnings) = deserialize_servo_with_warnings(&text)
+                .with_context(|| format!("Failed to parse {}", path.display()))?;
+
+            Ok(ModelTable {
+                name: entry.name.clone(),
+                slug: entry.slug.clone(),
+                data,
+                warnings,
+            })
+        })
+        .collect()
+}
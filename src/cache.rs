@@ -0,0 +1,191 @@
+use crate::fetch::{ActuatorIndex, HttpClient, Revalidation, Validators};
+use crate::{split_url, write_atomic};
+use anyhow::{Context, Result};
+use futures_util::stream::StreamExt;
+use ron::ser::{to_string_pretty, PrettyConfig};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio_stream as stream;
+use tracing::debug;
+
+/// How long a saved page is used before it's checked for changes, unless
+/// `--cache-ttl` says otherwise
+pub static DEFAULT_CACHE_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// What's known about a saved page, to tell when it goes stale and to ask
+/// the server whether it has changed since
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CacheEntry {
+    /// When the page was last downloaded or found to be unchanged, in
+    /// seconds since the Unix epoch
+    pub checked: u64,
+    pub validators: Validators,
+}
+
+/// What refreshing a saved page did
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Refreshed {
+    /// The page was checked within the TTL, so wasn't checked again
+    Fresh,
+    /// The server said the page hasn't changed, or sent it unchanged
+    Unchanged,
+    /// The page had changed, and the new version was saved
+    Changed,
+    /// No page is saved for the URL, so there was nothing to refresh
+    Missing,
+}
+
+/// Pages saved under a directory by series and model, as under artifacts/
+/// by `--keep-intermediates`. Pages are used until they're older than the
+/// TTL, and then checked with the server before they're used again.
+#[derive(Clone, Debug)]
+pub struct PageCache {
+    pub dir: PathBuf,
+    pub ttl: Duration,
+}
+
+fn seconds_since_epoch(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs())
+}
+
+impl PageCache {
+    pub fn new(dir: impl Into<PathBuf>, ttl: Duration) -> PageCache {
+        PageCache {
+            dir: dir.into(),
+            ttl,
+        }
+    }
+
+    /// The pages saved under artifacts/ in the working directory
+    pub fn artifacts(ttl: Duration) -> PageCache {
+        PageCache::new("artifacts", ttl)
+    }
+
+    fn model_dir(&self, url: &str) -> PathBuf {
+        let (series, raw_name) = split_url(url);
+        self.dir.join(series).join(raw_name)
+    }
+
+    /// Where the page at `url` is saved
+    pub fn page_path(&self, url: &str) -> PathBuf {
+        self.model_dir(url).join("page.html")
+    }
+
+    fn entry_path(&self, url: &str) -> PathBuf {
+        self.model_dir(url).join("cache.ron")
+    }
+
+    /// The entry of the page saved for `url`, if one is saved. Pages saved
+    /// without an entry, such as by `--keep-intermediates`, were last checked
+    /// when they were written.
+    pub fn entry(&self, url: &str) -> Result<Option<CacheEntry>> {
+        let page = self.page_path(url);
+        if !page.is_file() {
+            return Ok(None);
+        }
+
+        let path = self.entry_path(url);
+        if path.is_file() {
+            let text = fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            let entry = ron::from_str(&text)
+                .with_context(|| format!("Failed to parse {}", path.display()))?;
+            return Ok(Some(entry));
+        }
+
+        let modified = fs::metadata(&page)
+            .and_then(|metadata| metadata.modified())
+            .with_context(|| format!("Failed to read {}", page.display()))?;
+        Ok(Some(CacheEntry {
+            checked: seconds_since_epoch(modified),
+            validators: Validators::default(),
+        }))
+    }
+
+    /// Whether an entry was last checked longer ago than the TTL
+    pub fn is_stale(&self, entry: &CacheEntry) -> bool {
+        seconds_since_epoch(SystemTime::now()).saturating_sub(entry.checked) >= self.ttl.as_secs()
+    }
+
+    /// Save the page at `url`, downloaded just now with `validators`
+    pub fn save(&self, url: &str, text: &str, validators: Validators) -> Result<()> {
+        fs::create_dir_all(self.model_dir(url))?;
+        write_atomic(self.page_path(url), text)?;
+        self.write_entry(
+            url,
+            &CacheEntry {
+                checked: seconds_since_epoch(SystemTime::now()),
+                validators,
+            },
+        )
+    }
+
+    fn write_entry(&self, url: &str, entry: &CacheEntry) -> Result<()> {
+        write_atomic(
+            self.entry_path(url),
+            to_string_pretty(entry, PrettyConfig::new())?,
+        )
+    }
+
+    /// Check the page saved for `url` with the server if it's stale, with a
+    /// conditional GET, and save it again if it's changed. Pages checked
+    /// within the TTL aren't requested at all.
+    pub async fn refresh(&self, client: &HttpClient, url: &str) -> Result<Refreshed> {
+        let mut entry = match self.entry(url)? {
+            Some(entry) => entry,
+            None => return Ok(Refreshed::Missing),
+        };
+        if !self.is_stale(&entry) {
+            return Ok(Refreshed::Fresh);
+        }
+
+        let refreshed = match client.revalidate(url, &entry.validators).await? {
+            Revalidation::Unchanged => Refreshed::Unchanged,
+            Revalidation::Changed { text, validators } => {
+                let path = self.page_path(url);
+                let saved = tokio::fs::read_to_string(&path)
+                    .await
+                    .with_context(|| format!("Failed to read {}", path.display()))?;
+                entry.validators = validators;
+                match text == saved {
+                    true => Refreshed::Unchanged,
+                    false => {
+                        write_atomic(&path, text)?;
+                        Refreshed::Changed
+                    }
+                }
+            }
+        };
+        entry.checked = seconds_since_epoch(SystemTime::now());
+        self.write_entry(url, &entry)?;
+        debug!(%url, ?refreshed, "Re-validated saved page");
+
+        Ok(refreshed)
+    }
+
+    /// Refresh the saved page of every Dynamixel as [`refresh`] does.
+    /// Returns each Dynamixel with what refreshing it did, in no particular
+    /// order.
+    ///
+    /// [`refresh`]: PageCache::refresh
+    pub async fn refresh_all(
+        &self,
+        client: &HttpClient,
+        indexes: Vec<ActuatorIndex>,
+    ) -> Vec<(ActuatorIndex, Result<Refreshed>)> {
+        stream::iter(indexes)
+            .map(|dxl| async move {
+                let refreshed = self
+                    .refresh(client, &dxl.url)
+                    .await
+                    .with_context(|| format!("Failed to refresh {} from {}", dxl.name, dxl.url));
+                (dxl, refreshed)
+            })
+            .buffer_unordered(client.max_in_flight())
+            .collect()
+            .await
+    }
+}
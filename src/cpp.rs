@@ -0,0 +1,125 @@
+use crate::create_lib::{collect_models, ItemFields, NamingStrategy};
+use crate::serialize::ControlTableData;
+use crate::{write_atomic, Actuator};
+use anyhow::Result;
+use std::path::Path;
+use tracing::instrument;
+
+static HEADER_PREAMBLE: &str = "// Dynamixel control tables scraped from the Robotis E-Manual
+// (https://emanual.robotis.com) by dynamixel-scraper. Regenerate this file
+// rather than editing it.
+//
+// Every supported model is an enumerator of Model, and every item found in
+// any control table is an enumerator of DataName. Items are looked up at
+// compile time through the control table of a model, eg
+// ControlTable<Model::XM430W210>::GoalPosition, or at run time with data().
+#pragma once
+
+#include <cstdint>
+#include <optional>
+
+namespace dynamixel {
+
+/// The levels of permission a user is granted in terms of an item in the
+/// control table
+enum class Access { Read, ReadWrite };
+
+/// The primitive type an item's bytes should be decoded as, where Bytes is an
+/// item whose size doesn't match a primitive
+enum class ValueType { U8, U16, U32, I8, I16, I32, Bytes };
+
+/// Where and how an item is stored in the control table of a model
+struct ControlTableData {
+    std::uint16_t address;
+    std::uint8_t size;
+    Access access;
+    ValueType value_type;
+    /// The initial value, if the E-Manual gives a single number
+    std::optional<std::int32_t> initial_value;
+    /// The bounds of the item's range, if they're numbers rather than the
+    /// values of other items
+    std::optional<std::int32_t> min;
+    std::optional<std::int32_t> max;
+};
+";
+
+/// Write a C++17 header of every model's control table to `path`
+#[instrument(skip(actuators), fields(models = actuators.len()))]
+pub fn write_header(path: &Path, actuators: &[Actuator], naming: NamingStrategy) -> Result<()> {
+    write_atomic(path, create_header(actuators, naming)?)
+}
+
+/// Generate a C++17 header with the same models and items as the library,
+/// where the control table of each model is a specialization of the
+/// `ControlTable` template, whose `constexpr` members are the model's items
+pub fn create_header(actuators: &[Actuator], naming: NamingStrategy) -> Result<String> {
+    let models = collect_models(actuators, naming)?;
+
+    let mut header = HEADER_PREAMBLE.to_string();
+
+    header.push_str("\n/// A Dynamixel model with a known control table\nenum class Model {\n");
+    for (model, (name, url)) in &models.docs {
+        header.push_str(&format!("    /// {} ({})\n    {},\n", name, url, model));
+    }
+    header.push_str("};\n");
+
+    header
+        .push_str("\n/// An item found in the control table of any model\nenum class DataName {\n");
+    for name in &models.data_names {
+        header.push_str(&format!("    {},\n", name));
+    }
+    header.push_str("};\n");
+
+    header.push_str("\n/// The control table of a model, whose members are its items\ntemplate <Model M>\nstruct ControlTable;\n");
+    for (model, items) in &models.items {
+        header.push_str(&format!(
+            "\ntemplate <>\nstruct ControlTable<Model::{}> {{\n",
+            model
+        ));
+        for (name, data) in items {
+            header.push_str(&format!(
+                "    static constexpr ControlTableData {}{{{}}};\n",
+                name,
+                initializer(data)
+            ));
+        }
+        header.push_str("};\n");
+    }
+
+    header.push_str(
+        "\n/// Look up where and how `name` is stored in the control table of `model`\nconstexpr std::optional<ControlTableData> data(Model model, DataName name) {\n    switch (model) {\n",
+    );
+    for (model, items) in &models.items {
+        header.push_str(&format!(
+            "    case Model::{}:\n        switch (name) {{\n",
+            model
+        ));
+        for name in items.keys() {
+            header.push_str(&format!(
+                "        case DataName::{}:\n            return ControlTable<Model::{}>::{};\n",
+                name, model, name
+            ));
+        }
+        header.push_str("        default:\n            return std::nullopt;\n        }\n");
+    }
+    header.push_str("    }\n    return std::nullopt;\n}\n\n} // namespace dynamixel\n");
+
+    Ok(header)
+}
+
+/// The fields of an item's `ControlTableData`, in order
+fn initializer(data: &ControlTableData) -> String {
+    let fields = ItemFields::new(data);
+    let number = |value: Option<i32>| value.map_or("std::nullopt".to_string(), |i| i.to_string());
+
+    format!(
+        "{}, {}, Access::{}, ValueType::{}, {}, {}, {}",
+        data.address,
+        data.size,
+        fields.access,
+        fields.value_type,
+        number(fields.initial_value),
+        number(fields.min),
+        number(fields.max)
+    )
+}
@@ -1,19 +1,72 @@
-use crate::{Actuator, ControlTableData};
-use anyhow::Result;
-use std::collections::BTreeMap;
-use std::fs::{create_dir_all, File};
-use std::io::Write;
+use crate::serialize::{
+    edit_distance, strip_thousands_separators, AccessLevel, BitField, DataNameRef, InitialValue,
+    RangeValue, Units,
+};
+use crate::{write_if_changed, Actuator, ControlTableData};
+use anyhow::{bail, ensure, Context, Error, Result};
+use convert_case::{Case, Casing};
+use regex::Regex;
+use ron::ser::{to_string_pretty, PrettyConfig};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::fs::{self, create_dir_all};
 use std::iter::FromIterator;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use tracing::{debug, instrument, warn};
 
+// Marks manifests created by the scraper, which are regenerated from scratch
+static GENERATED_MANIFEST: &str = "# Generated by dynamixel-scraper";
 static CARGO_PREAMBLE: &str = "[package]
-name = \"dxl-control-tables\"
-version = \"0.1.0\"
-edition = \"2018\"
-
+{package}
 [dependencies]
 thiserror = \"1.0.26\"
 
 [features]
+";
+// Manifests generated before the marker was added start with this instead
+static LEGACY_PREAMBLE: &str = "[package]
+name = \"dxl-control-tables\"
+version = \"0.1.0\"
+";
+static PACKAGE_DESCRIPTION: &str = "Dynamixel control tables scraped from the Robotis E-Manual";
+// The generated features of an existing crate are kept between these lines
+static FEATURES_BEGIN: &str = "# BEGIN dynamixel-scraper features";
+static FEATURES_END: &str = "# END dynamixel-scraper features";
+static CRATE_DOCS: &str =
+    "//! Dynamixel control tables scraped from the [Robotis E-Manual](https://emanual.robotis.com).
+//!
+//! Every supported model is a variant of [`Model`], and every item found in
+//! any control table is a variant of [`DataName`]. Use [`data`] to look up
+//! where (and how) a given item is stored on a given model. Both enums can
+//! be parsed from and displayed as their names in the E-Manual, and
+//! [`ALL_MODELS`] lists every enabled model. [`supported`] lists the items
+//! a model has, as does its `SUPPORTED` constant in [`models`].
+//!
+//! Items found at the same address in every model of a series are also
+//! constants of a trait for the series (eg `XSeriesCommon`), implemented by
+//! the types in [`models`], so drivers can be written once for a series.
+//! Series documenting their hardware errors also have a set of flags for
+//! them (eg `XHardwareError`), to decode the Hardware Error Status item, and
+//! items documented with a table of values have an enum of them for each
+//! series (eg `XOperatingMode`), converting to and from the raw value.
+//!
+//! Items with a unit have [`Units`], giving the size of a step of the raw
+//! value (eg 0.229 [`Unit::Rpm`]), which [`Units::to_si`] converts with.
+//! [`validate_write`] checks a value against an item's access and range, so
+//! drivers can reject it before touching the bus.
+//!
+//! Each model is only compiled in with its feature, named after its page in
+//! the E-Manual (eg `xm430-w210`). Every series also has a feature (eg `X`)
+//! enabling all of its models, and every series is enabled by default.
+//!
+//! This crate was generated by
+//! [dynamixel-scraper](https://github.com/kiros-rs/dynamixel-scraper) and
+//! should not be edited by hand. To regenerate it, run the scraper with the
+//! `--lib` flag (optionally filtered with `--series` or `--dxl`), which
+//! overwrites it with the current contents of the E-Manual.
+
 ";
 static ERROR_DEFINITION: &str = "use thiserror::Error;
 
@@ -21,6 +74,34 @@ static ERROR_DEFINITION: &str = "use thiserror::Error;
 pub enum ControlTableError {
     #[error(\"Dynamixel model {model:?} does not support field {name:?}\")]
     NoMatchingAddress { model: Model, name: DataName },
+    #[error(\"Field {name:?} of Dynamixel model {model:?} is read-only\")]
+    ReadOnly { model: Model, name: DataName },
+    #[error(\"{value} is out of range for field {name:?} of Dynamixel model {model:?}, which accepts {min} ~ {max}\")]
+    OutOfRange {
+        model: Model,
+        name: DataName,
+        value: i64,
+        min: i64,
+        max: i64,
+    },
+}
+
+/// The error returned when converting a raw value to one of the documented
+/// values of an item
+#[derive(Error, Debug)]
+#[error(\"{value} isn't a documented value of {name:?}\")]
+pub struct UnknownValue {
+    pub name: DataName,
+    pub value: i64,
+}
+
+/// The error returned when parsing a [`Model`] or [`DataName`] from a name
+#[derive(Error, Debug)]
+pub enum ParseNameError {
+    #[error(\"Unknown or disabled Dynamixel model {0:?}\")]
+    UnknownModel(String),
+    #[error(\"Unknown control table item {0:?}\")]
+    UnknownDataName(String),
 }
 
 ";
@@ -33,6 +114,106 @@ pub enum AccessLevel {
     ReadWrite,
 }
 
+/// The primitive type an item's bytes should be decoded as
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ValueType {
+    U8,
+    U16,
+    U32,
+    I8,
+    I16,
+    I32,
+    /// An item whose size doesn't match a primitive, kept as raw bytes
+    Bytes(u8),
+}
+
+impl ValueType {
+    /// The smallest and largest values of the type, which are unbounded for
+    /// `Bytes`
+    pub {const_control_flow}fn bounds(self) -> (i64, i64) {
+        match self {
+            ValueType::U8 => (0, 255),
+            ValueType::U16 => (0, 65_535),
+            ValueType::U32 => (0, 4_294_967_295),
+            ValueType::I8 => (-128, 127),
+            ValueType::I16 => (-32_768, 32_767),
+            ValueType::I32 => (-2_147_483_648, 2_147_483_647),
+            ValueType::Bytes(_) => (i64::MIN, i64::MAX),
+        }
+    }
+}
+
+/// A unit the E-Manual measures values in
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Unit {
+    /// A step of the position encoder, whose size depends on the model
+    Pulse,
+    Degree,
+    Rpm,
+    DegreePerSecond,
+    Percent,
+    Milliamp,
+    Volt,
+    Millivolt,
+    Millisecond,
+    Microsecond,
+    Second,
+    Celsius,
+    /// A count with no unit, or a unit the scraper doesn't know
+    Raw,
+}
+
+impl Unit {
+    /// How much of the SI unit one of this unit is, eg radians per second
+    /// for `Rpm`, or `None` for units without a fixed size
+    pub {const_floats}fn si_factor(self) -> Option<f64> {
+        match self {
+            Unit::Degree => Some(0.017453292519943295),
+            Unit::Rpm => Some(0.10471975511965977),
+            Unit::DegreePerSecond => Some(0.017453292519943295),
+            Unit::Percent => Some(0.01),
+            Unit::Milliamp | Unit::Millivolt | Unit::Millisecond => Some(0.001),
+            Unit::Microsecond => Some(0.000001),
+            Unit::Volt | Unit::Second | Unit::Celsius => Some(1.0),
+            Unit::Pulse | Unit::Raw => None,
+        }
+    }
+
+    /// What to add once a value is scaled to the SI unit, which is only
+    /// needed for `Celsius` (converted to kelvin)
+    pub {const_floats}fn si_offset(self) -> f64 {
+        match self {
+            Unit::Celsius => 273.15,
+            _ => 0.0,
+        }
+    }
+}
+
+/// The unit of an item's value and the size of a step of it, eg 0.229 rpm
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Units {
+    pub unit: Unit,
+    /// How much of the unit a step of the raw value is
+    pub scale: f64,
+}
+
+impl Units {
+    /// Convert a raw value of the item to its SI unit, eg Present Velocity
+    /// to radians per second. Returns `None` for units without a fixed size.
+    pub fn to_si(&self, raw: i64) -> Option<f64> {
+        Some(raw as f64 * self.scale * self.unit.si_factor()? + self.unit.si_offset())
+    }
+}
+
+/// One or more bits of an item with a documented meaning
+#[derive(Debug)]
+pub struct BitField {
+    /// The lowest bit, counting from 0
+    pub bit: u8,
+    pub width: u8,
+    pub name: &'static str,
+}
+
 /// An item that represents either the min, max, or initial value of a given address
 #[derive(Debug)]
 pub enum RangeValue {
@@ -40,6 +221,28 @@ pub enum RangeValue {
     Address { name: DataName, negative: bool },
 }
 
+/// The initial value of an item, which isn't always a plain value
+#[derive(Debug)]
+pub enum InitialValue {
+    Value(RangeValue),
+    None,
+    /// The value for each model listed on the page, if any are
+    ModelDependent(&'static [(&'static str, i32)]),
+    /// A value the E-Manual gives as text, such as a formula
+    Expression(&'static str),
+}
+
+/// Where an item's initial value comes from
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InitialValueSource {
+    /// The value every unit starts with and is reset to
+    Default,
+    /// Set for each unit at the factory, so the initial value is only typical
+    FactorySet,
+    /// Differs between firmware versions
+    FirmwareDependent,
+}
+
 /// A representation of an item in the control table, where only information
 /// is stored. When applicable, items in the control table are represented in
 /// this format, along with any optional data such as range or description.
@@ -49,73 +252,1715 @@ pub struct ControlTableData {
     pub size: u8,
     pub description: Option<&'static str>,
     pub access: AccessLevel,
-    pub initial_value: Option<RangeValue>,
+    pub initial_value: InitialValue,
+    pub initial_value_source: InitialValueSource,
     pub range: Option<(RangeValue, RangeValue)>,
+    pub value_type: ValueType,
+    pub min_firmware: Option<u8>,
+    pub bits: Option<&'static [BitField]>,
+    pub units: Option<Units>,
+    /// The address of the item's Modbus register, on models that can also be
+    /// controlled over Modbus RTU
+    pub modbus_address: Option<u16>,
+}
+
+";
+pub static DEFAULT_LIB_PATH: &str = "lib";
+pub static DEFAULT_LIB_NAME: &str = "dxl-control-tables";
+pub static DEFAULT_LIB_VERSION: &str = "0.1.0";
+
+static ACCESSORS: &str = "
+/// Look up the address of `name` in the control table of `model`
+pub {const_control_flow}fn address(model: Model, name: DataName) -> Result<u16, ControlTableError> {
+    match data(model, name) {
+        Ok(data) => Ok(data.address),
+        Err(e) => Err(e),
+    }
+}
+
+/// Look up the size (in bytes) of `name` in the control table of `model`
+pub {const_control_flow}fn size(model: Model, name: DataName) -> Result<u8, ControlTableError> {
+    match data(model, name) {
+        Ok(data) => Ok(data.size),
+        Err(e) => Err(e),
+    }
 }
 
+/// Check that `value` may be written to `name` on `model` before sending it:
+/// that the item is writable and the value is within its range. Bounds given
+/// by another item (eg `-PWMLimit ~ PWMLimit`) depend on what that item is
+/// set to, so only the item's type limits the value there.
+pub fn validate_write(model: Model, name: DataName, value: i64) -> Result<(), ControlTableError> {
+    let data = data(model, name)?;
+    if let AccessLevel::Read = data.access {
+        return Err(ControlTableError::ReadOnly { model, name });
+    }
+
+    let (type_min, type_max) = data.value_type.bounds();
+    let bound = |value: &RangeValue, default: i64| match value {
+        RangeValue::Integer(i) => i64::from(*i),
+        RangeValue::Address { .. } => default,
+    };
+    let (min, max) = match &data.range {
+        Some((min, max)) => (bound(min, type_min), bound(max, type_max)),
+        None => (type_min, type_max),
+    };
+    if value < min || value > max {
+        return Err(ControlTableError::OutOfRange {
+            model,
+            name,
+            value,
+            min,
+            max,
+        });
+    }
+
+    Ok(())
+}
+";
+static REGISTER_TRAIT: &str = "
+/// An item in the control table of a model, known at compile time. Implemented
+/// by the types in [`models`] for each item in [`registers`] on that model,
+/// eg `models::XM430W210::<registers::GoalPosition>::ADDRESS`. The items a
+/// model supports are listed by its `SUPPORTED` constant, eg
+/// `models::XM430W210::SUPPORTED`.
+pub trait Register {
+    const ADDRESS: u16;
+    const SIZE: u8;
+    /// The type the item's bytes are decoded as
+    type Value;
+}
+
+#[allow(unused_macros)]
+macro_rules! impl_registers {
+    ($model:ident { $($name:ident: $address:expr, $size:expr, $value:ty;)* }) => {
+        impl models::$model {
+            /// Every item in the control table of this model, in order of address
+            pub const SUPPORTED: &'static [DataName] = &[$(DataName::$name),*];
+        }
+
+        $(
+            impl Register for models::$model<registers::$name> {
+                const ADDRESS: u16 = $address;
+                const SIZE: u8 = $size;
+                type Value = $value;
+            }
+        )*
+    };
+}
+";
+static HARDWARE_ERROR_METHODS: &str = "
+    /// No errors
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+
+    /// Every known error
+    pub const fn all() -> Self {
+        Self({all})
+    }
+
+    /// The errors in `bits`, ignoring any unknown bits
+    pub const fn from_bits_truncate(bits: u8) -> Self {
+        Self(bits & Self::all().0)
+    }
+
+    pub const fn bits(self) -> u8 {
+        self.0
+    }
+
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Whether every error in `other` is set
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// The name and flag of each error that is set
+    pub fn iter(self) -> impl Iterator<Item = (&'static str, Self)> {
+        Self::ALL
+            .iter()
+            .{copied}()
+            .filter(move |(_, error)| self.contains(*error))
+    }
 ";
 static DERIVES: &str = "#[derive(Clone, Copy, Debug)]";
+static ENUM_ATTRIBUTES: &str = "{non_exhaustive}#[repr(u16)]";
 static INDENT: &str = "    ";
 
-/// Append RangeValue:: to any variants of the enum
-fn fix_formatting(text: String) -> String {
-    text.replace("Read,", "AccessLevel::Read,")
-        .replace("ReadWrite,", "AccessLevel::ReadWrite,")
+/// How the variants of `Model` (and the types in `models`) are named after
+/// the URL of each model's page. Names can't start with a digit, so those
+/// that would (such as the 2XL430-W250's) are prefixed with "Dxl".
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NamingStrategy {
+    /// Only the letters and digits, eg `XM430W210`
+    #[default]
+    Compact,
+    /// With dashes as underscores, eg `XM430_W210`
+    Underscored,
+}
+
+pub static NAMING_STRATEGIES: &[&str] = &["compact", "underscored"];
+
+impl NamingStrategy {
+    /// The name of a model from its URL slug, eg "xm430-w210"
+    pub fn model_name(&self, raw_name: &str) -> String {
+        let separator = match self {
+            NamingStrategy::Compact => "",
+            NamingStrategy::Underscored => "_",
+        };
+        let name = raw_name
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<&str>>()
+            .join(separator)
+            .to_uppercase();
+
+        match name.starts_with(|c: char| c.is_numeric()) {
+            true => format!("Dxl{}", name),
+            false => name,
+        }
+    }
+}
+
+impl FromStr for NamingStrategy {
+    type Err = Error;
+
+    fn from_str(name: &str) -> Result<NamingStrategy> {
+        match name {
+            "compact" => Ok(NamingStrategy::Compact),
+            "underscored" => Ok(NamingStrategy::Underscored),
+            _ => bail!(
+                "Unknown naming strategy {:?}, expected one of {:?}",
+                name,
+                NAMING_STRATEGIES
+            ),
+        }
+    }
+}
+
+/// The crate the library is generated into. If the crate already exists and
+/// wasn't created by the scraper, the code goes in `src/generated.rs` and only
+/// the generated features of its manifest are replaced, leaving the rest of
+/// the crate untouched.
+#[derive(Clone, Debug)]
+pub struct LibTarget {
+    /// The root directory of the crate
+    pub path: PathBuf,
+    /// The package name given to a crate created by the scraper
+    pub name: String,
+    pub naming: NamingStrategy,
+    pub package: PackageMetadata,
+    /// The oldest compiler the library should build with, if not the latest.
+    /// The generated code avoids newer language features (such as `const fn`
+    /// with floats) and the manifest of a created crate gets `rust-version`.
+    pub msrv: Option<RustVersion>,
+    /// The revision of the E-Manual's source the control tables were
+    /// scraped from, if pinned, exported as `MANUAL_REVISION`
+    pub manual_revision: Option<String>,
+}
+
+impl Default for LibTarget {
+    fn default() -> LibTarget {
+        LibTarget {
+            path: PathBuf::from(DEFAULT_LIB_PATH),
+            name: DEFAULT_LIB_NAME.to_string(),
+            naming: NamingStrategy::default(),
+            package: PackageMetadata::default(),
+            msrv: None,
+            manual_revision: None,
+        }
+    }
+}
+
+/// The package metadata given to a crate created by the scraper, which needs
+/// a license to be published to crates.io
+#[derive(Clone, Debug)]
+pub struct PackageMetadata {
+    pub version: String,
+    pub authors: Vec<String>,
+    /// An SPDX license expression, eg "MIT OR Apache-2.0"
+    pub license: Option<String>,
+    pub repository: Option<String>,
+}
+
+impl Default for PackageMetadata {
+    fn default() -> PackageMetadata {
+        PackageMetadata {
+            version: DEFAULT_LIB_VERSION.to_string(),
+            authors: vec![],
+            license: None,
+            repository: None,
+        }
+    }
+}
+
+impl PackageMetadata {
+    /// The keys of the `[package]` table, including the crate's name and the
+    /// version of Rust it needs
+    fn manifest_keys(&self, name: &str, msrv: Option<RustVersion>) -> String {
+        let mut keys = vec![
+            format!("name = {:?}", name),
+            format!("version = {:?}", self.version),
+            "edition = \"2018\"".to_string(),
+            format!("description = {:?}", PACKAGE_DESCRIPTION),
+            "readme = \"README.md\"".to_string(),
+            "keywords = [\"dynamixel\", \"robotis\", \"servo\", \"robotics\"]".to_string(),
+        ];
+        if !self.authors.is_empty() {
+            keys.push(format!("authors = {:?}", self.authors));
+        }
+        if let Some(license) = &self.license {
+            keys.push(format!("license = {:?}", license));
+        }
+        if let Some(repository) = &self.repository {
+            keys.push(format!("repository = {:?}", repository));
+        }
+        if let Some(msrv) = msrv {
+            keys.push(format!("rust-version = \"{}\"", msrv));
+        }
+
+        keys.iter().map(|key| format!("{}\n", key)).collect()
+    }
+}
+
+/// A version of Rust, eg "1.56" or "1.56.1"
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RustVersion {
+    pub minor: u32,
+    pub patch: Option<u32>,
+}
+
+impl RustVersion {
+    /// Whether the version is at least 1.`minor`, which is where language
+    /// features are stabilized
+    fn at_least(self, minor: u32) -> bool {
+        self.minor >= minor
+    }
+}
+
+impl FromStr for RustVersion {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<RustVersion> {
+        let parts = text
+            .trim()
+            .split('.')
+            .map(|part| part.parse::<u32>())
+            .collect::<Result<Vec<u32>, _>>()
+            .with_context(|| format!("Invalid Rust version {:?}, expected eg 1.56", text))?;
+        let version = match parts[..] {
+            [1, minor] => RustVersion { minor, patch: None },
+            [1, minor, patch] => RustVersion {
+                minor,
+                patch: Some(patch),
+            },
+            _ => bail!("Invalid Rust version {:?}, expected eg 1.56", text),
+        };
+        ensure!(
+            version.at_least(EDITION_2018),
+            "The generated library needs at least Rust 1.{} for the 2018 edition",
+            EDITION_2018
+        );
+
+        Ok(version)
+    }
+}
+
+impl fmt::Display for RustVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.patch {
+            Some(patch) => write!(f, "1.{}.{}", self.minor, patch),
+            None => write!(f, "1.{}", self.minor),
+        }
+    }
+}
+
+// The minor versions of Rust that stabilized the features the generated code
+// adapts to. Patterns rely on default binding modes (1.26), which the 2018
+// edition already needs a newer compiler than.
+static EDITION_2018: u32 = 31;
+static TRY_FROM: u32 = 34;
+static ITERATOR_COPIED: u32 = 36;
+static NON_EXHAUSTIVE: u32 = 40;
+static CONST_CONTROL_FLOW: u32 = 46;
+static CONST_FLOATS: u32 = 82;
+
+/// The language features the generated code uses, which are only those the
+/// oldest supported compiler has
+#[derive(Clone, Copy, Debug)]
+struct LanguageFeatures {
+    /// `TryFrom`, which converting raw values to the enums of values needs
+    try_from: bool,
+    /// Iterator::copied, otherwise cloned
+    copied: bool,
+    non_exhaustive: bool,
+    /// `match` in `const fn`, which every lookup of an item needs
+    const_control_flow: bool,
+    /// Floats as the arguments and results of `const fn`
+    const_floats: bool,
+}
+
+impl LanguageFeatures {
+    fn new(msrv: Option<RustVersion>) -> LanguageFeatures {
+        let supports = |minor| msrv.is_none_or(|msrv| msrv.at_least(minor));
+        LanguageFeatures {
+            try_from: supports(TRY_FROM),
+            copied: supports(ITERATOR_COPIED),
+            non_exhaustive: supports(NON_EXHAUSTIVE),
+            const_control_flow: supports(CONST_CONTROL_FLOW),
+            const_floats: supports(CONST_FLOATS),
+        }
+    }
+
+    /// Fill in the placeholders a template has for the features
+    fn apply(&self, template: &str) -> String {
+        let constness = |enabled: bool| if enabled { "const " } else { "" };
+        template
+            .replace("{const_control_flow}", constness(self.const_control_flow))
+            .replace("{const_floats}", constness(self.const_floats))
+            .replace("{copied}", if self.copied { "copied" } else { "cloned" })
+            .replace(
+                "{non_exhaustive}",
+                if self.non_exhaustive {
+                    "#[non_exhaustive]\n"
+                } else {
+                    ""
+                },
+            )
+    }
+}
+
+impl LibTarget {
+    pub fn manifest_path(&self) -> PathBuf {
+        self.path.join("Cargo.toml")
+    }
+
+    pub fn source_path(&self) -> PathBuf {
+        match self.is_existing_crate() {
+            true => self.path.join("src/generated.rs"),
+            false => self.path.join("src/lib.rs"),
+        }
+    }
+
+    pub fn discriminants_path(&self) -> PathBuf {
+        self.path.join("discriminants.ron")
+    }
+
+    /// The README listing the models, only written for crates created by the
+    /// scraper
+    pub fn readme_path(&self) -> PathBuf {
+        self.path.join("README.md")
+    }
+
+    /// The test checking the library builds with the minimum supported Rust
+    /// version, only written for crates created by the scraper with one
+    pub fn msrv_test_path(&self) -> PathBuf {
+        self.path.join("tests/msrv.rs")
+    }
+
+    /// Whether the target is a crate with a manifest not written by the scraper
+    pub fn is_existing_crate(&self) -> bool {
+        match fs::read_to_string(self.manifest_path()) {
+            // Manifests generated before the marker was added start with the
+            // preamble instead
+            Ok(manifest) => {
+                !manifest.starts_with(GENERATED_MANIFEST) && !manifest.starts_with(LEGACY_PREAMBLE)
+            }
+            Err(_) => false,
+        }
+    }
+}
+
+/// Replace the generated features in an existing manifest, adding the marker
+/// comments (and a `[features]` table if needed) the first time
+fn splice_features(manifest: &str, features: &str) -> Result<String> {
+    let section = format!("{}\n{}{}\n", FEATURES_BEGIN, features, FEATURES_END);
+
+    if let Some(begin) = manifest.find(FEATURES_BEGIN) {
+        let end = manifest[begin..]
+            .find(FEATURES_END)
+            .map(|end| begin + end + FEATURES_END.len())
+            .context("Manifest has no end marker for the generated features")?;
+        let rest = manifest[end..]
+            .strip_prefix('\n')
+            .unwrap_or(&manifest[end..]);
+
+        return Ok(format!("{}{}{}", &manifest[..begin], section, rest));
+    }
+
+    match manifest
+        .lines()
+        .position(|line| line.trim() == "[features]")
+    {
+        Some(table) => {
+            let mut lines: Vec<&str> = manifest.lines().collect();
+            lines.insert(table + 1, section.trim_end());
+            Ok(lines.join("\n") + "\n")
+        }
+        None => Ok(format!("{}\n[features]\n{}", manifest.trim_end(), section)),
+    }
+}
+
+/// The scraped text used to document a single DataName variant
+#[derive(Default)]
+struct DataNameDocs {
+    names: BTreeSet<String>,
+    descriptions: BTreeSet<String>,
+    /// The variants of the items any description links to
+    related: BTreeSet<String>,
+}
+
+/// The discriminants assigned to each variant of the generated enums. These
+/// are persisted alongside the library so that regenerating it never
+/// renumbers an existing variant.
+#[derive(Default, Serialize, Deserialize)]
+struct Discriminants {
+    data_names: BTreeMap<String, u16>,
+    models: BTreeMap<String, u16>,
+}
+
+impl Discriminants {
+    fn load(path: &Path) -> Result<Discriminants> {
+        match path.exists() {
+            true => Ok(ron::de::from_str(&fs::read_to_string(path)?)?),
+            false => Ok(Discriminants::default()),
+        }
+    }
+
+    fn save(&self, path: &Path) -> Result<bool> {
+        write_if_changed(path, to_string_pretty(self, PrettyConfig::new())?)
+    }
+}
+
+/// Give every new variant the next unused discriminant, in the order given.
+/// Variants that are no longer generated keep their entry so that their
+/// discriminant is never reused.
+fn assign_discriminants<'a>(
+    assigned: &mut BTreeMap<String, u16>,
+    variants: impl Iterator<Item = &'a String>,
+) {
+    let mut next = assigned.values().max().map_or(0, |max| max + 1);
+    for variant in variants {
+        if !assigned.contains_key(variant) {
+            assigned.insert(variant.clone(), next);
+            next += 1;
+        }
+    }
+}
+
+/// Format text as a `///` doc comment, prefixing every line with the indent
+fn doc_comment(text: &str, indent: &str) -> String {
+    text.lines()
+        .map(|line| match line.trim() {
+            "" => format!("{}///\n", indent),
+            trimmed => format!("{}/// {}\n", indent, trimmed),
+        })
+        .collect()
+}
+
+/// A cfg predicate enabled by any of the features
+fn any_feature<'a>(features: impl Iterator<Item = &'a str>) -> String {
+    format!(
+        "any({})",
+        features
+            .map(|feature| format!("feature = \"{}\"", feature))
+            .collect::<Vec<String>>()
+            .join(", ")
+    )
+}
+
+/// 64-bit FNV-1a hash, used to name tables since its output (unlike std's
+/// hashers) is stable across Rust versions
+fn fnv1a(text: &str) -> u64 {
+    text.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// Generate the body of a function matching each data name to its item in
+/// a single control table
+fn table_body(data_names: &BTreeMap<String, ControlTableData>) -> String {
+    let mut body = String::new();
+    body.push_str(&format!("\n{}match name {{", INDENT));
+
+    // Sort the addresses lowest-first
+    let mut sorted_names = Vec::from_iter(data_names);
+    sorted_names.sort_by_key(|&(_, data)| data.address);
+
+    for (data_name, data) in sorted_names {
+        body.push_str(&format!(
+            "\n{}DataName::{} => Ok(ControlTableData {{",
+            INDENT.repeat(2),
+            data_name
+        ));
+        body.push_str(&format!("\n{}address: {},", INDENT.repeat(3), data.address));
+        body.push_str(&format!("\n{}size: {},", INDENT.repeat(3), data.size));
+        body.push_str(&format!(
+            "\n{}description: {:?},",
+            INDENT.repeat(3),
+            data.description
+        ));
+        // Items whose access isn't understood are taken to be read-only, so
+        // they're never written by mistake
+        body.push_str(&format!(
+            "\n{}access: AccessLevel::{},",
+            INDENT.repeat(3),
+            match data.access {
+                AccessLevel::ReadWrite => "ReadWrite",
+                AccessLevel::Read | AccessLevel::Unknown(_) => "Read",
+            }
+        ));
+        body.push_str(&format!(
+            "\n{}initial_value: {},",
+            INDENT.repeat(3),
+            data.initial_value
+        ));
+        body.push_str(&format!(
+            "\n{}initial_value_source: {},",
+            INDENT.repeat(3),
+            data.initial_value_source
+        ));
+        body.push_str(&format!(
+            "\n{}range: {},",
+            INDENT.repeat(3),
+            match &data.range {
+                Some(val) => format!("Some(({}, {}))", val.0, val.1),
+                None => "None".to_string(),
+            }
+        ));
+        body.push_str(&format!(
+            "\n{}value_type: ValueType::{:?},",
+            INDENT.repeat(3),
+            data.value_type
+        ));
+        body.push_str(&format!(
+            "\n{}min_firmware: {:?},",
+            INDENT.repeat(3),
+            data.min_firmware
+        ));
+        body.push_str(&format!(
+            "\n{}bits: {},",
+            INDENT.repeat(3),
+            match &data.bits {
+                Some(bits) => format!(
+                    "Some(&[{}])",
+                    bits.iter()
+                        .map(|b| format!(
+                            "BitField {{ bit: {}, width: {}, name: {:?} }}",
+                            b.bit, b.width, b.name
+                        ))
+                        .collect::<Vec<String>>()
+                        .join(", ")
+                ),
+                None => "None".to_string(),
+            }
+        ));
+        body.push_str(&format!(
+            "\n{}units: {},",
+            INDENT.repeat(3),
+            match data.units.as_deref().and_then(Units::parse) {
+                Some(units) => format!(
+                    "Some(Units {{ unit: Unit::{:?}, scale: {:?} }})",
+                    units.unit, units.scale
+                ),
+                None => "None".to_string(),
+            }
+        ));
+        body.push_str(&format!(
+            "\n{}modbus_address: {:?},",
+            INDENT.repeat(3),
+            data.modbus_address
+        ));
+        body.push_str(&format!("\n{}}}),", INDENT.repeat(2)))
+    }
+
+    // Add error handling
+    body.push_str(&format!(
+        "\n{}_ => Err(ControlTableError::NoMatchingAddress {{ model, name }}),",
+        INDENT.repeat(2)
+    ));
+    body.push_str(&format!("\n{}}}", INDENT));
+
+    body
+}
+
+/// Format a set of strings as an array literal
+fn string_array(strings: BTreeSet<&str>) -> String {
+    let strings: Vec<String> = strings.iter().map(|s| format!("{:?}", s)).collect();
+    format!("[{}]", strings.join(", "))
+}
+
+/// Generate `Display` and `FromStr` for `Model` and `DataName`, along with
+/// `ALL_MODELS`. Names are displayed as in the E-Manual, and parsed from
+/// either that, the variant name or (for models) the feature name, ignoring
+/// case.
+fn string_conversions(
+    data_names: &BTreeMap<String, DataNameDocs>,
+    addresses: &BTreeMap<String, BTreeMap<String, BTreeMap<String, ControlTableData>>>,
+    model_docs: &BTreeMap<String, (&str, &str)>,
+    model_features: &BTreeMap<String, &str>,
+) -> String {
+    let models: Vec<&String> = addresses
+        .values()
+        .flat_map(|models| models.keys())
+        .collect();
+    let mut conversions = String::new();
+
+    conversions.push_str("\n/// Every model enabled by the crate's features\n");
+    conversions.push_str("pub const ALL_MODELS: &[Model] = &[");
+    for model in &models {
+        conversions.push_str(&format!(
+            "\n{}#[cfg(feature = \"{}\")]\n{}Model::{},",
+            INDENT, model_features[*model], INDENT, model
+        ));
+    }
+    conversions.push_str("\n];\n");
+
+    conversions.push_str("\nimpl core::fmt::Display for Model {");
+    conversions
+        .push_str("\n    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {");
+    conversions.push_str("\n        match *self {");
+    for model in &models {
+        conversions.push_str(&format!(
+            "\n{}#[cfg(feature = \"{}\")]\n{}Model::{} => f.write_str({:?}),",
+            INDENT.repeat(3),
+            model_features[*model],
+            INDENT.repeat(3),
+            model,
+            model_docs[*model].0
+        ));
+    }
+    conversions.push_str("\n        }\n    }\n}\n");
+
+    conversions.push_str("\nimpl core::str::FromStr for Model {");
+    conversions.push_str("\n    type Err = ParseNameError;\n");
+    conversions.push_str("\n    fn from_str(text: &str) -> Result<Model, ParseNameError> {");
+    conversions.push_str("\n        let text = text.trim();");
+    for model in &models {
+        let names: BTreeSet<&str> = [model_docs[*model].0, model.as_str(), model_features[*model]]
+            .iter()
+            .copied()
+            .collect();
+        conversions.push_str(&format!(
+            "\n{}#[cfg(feature = \"{}\")]\n{}if {}.iter().any(|name| name.eq_ignore_ascii_case(text)) {{\n{}return Ok(Model::{});\n{}}}",
+            INDENT.repeat(2),
+            model_features[*model],
+            INDENT.repeat(2),
+            string_array(names),
+            INDENT.repeat(3),
+            model,
+            INDENT.repeat(2)
+        ));
+    }
+    conversions
+        .push_str("\n        Err(ParseNameError::UnknownModel(text.to_string()))\n    }\n}\n");
+
+    conversions.push_str("\nimpl core::fmt::Display for DataName {");
+    conversions
+        .push_str("\n    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {");
+    conversions.push_str("\n        let name = match *self {");
+    for (data_name, docs) in data_names {
+        let name = docs
+            .names
+            .iter()
+            .next()
+            .map_or(data_name.as_str(), String::as_str);
+        conversions.push_str(&format!(
+            "\n{}DataName::{} => {:?},",
+            INDENT.repeat(3),
+            data_name,
+            name
+        ));
+    }
+    conversions.push_str("\n        };\n        f.write_str(name)\n    }\n}\n");
+
+    conversions.push_str("\nimpl core::str::FromStr for DataName {");
+    conversions.push_str("\n    type Err = ParseNameError;\n");
+    conversions.push_str("\n    fn from_str(text: &str) -> Result<DataName, ParseNameError> {");
+    conversions.push_str("\n        let text = text.trim();");
+    conversions.push_str("\n        let name = match text {");
+    for (data_name, docs) in data_names {
+        conversions.push_str(&format!(
+            "\n{}_ if {}.iter().any(|name| name.eq_ignore_ascii_case(text)) => DataName::{},",
+            INDENT.repeat(3),
+            string_array(
+                std::iter::once(data_name)
+                    .chain(&docs.names)
+                    .map(String::as_str)
+                    .collect()
+            ),
+            data_name
+        ));
+    }
+    conversions.push_str(
+        "\n            _ => return Err(ParseNameError::UnknownDataName(text.to_string())),",
+    );
+    conversions.push_str("\n        };\n        Ok(name)\n    }\n}\n");
+
+    conversions
+}
+
+/// Check that generated code parses, so a mistake in code generation fails
+/// the scrape rather than the build of the generated crate, and format it
+fn format_source(source: &str) -> Result<String> {
+    let file = syn::parse_file(source).context("Generated library isn't valid Rust")?;
+
+    Ok(prettyplease::unparse(&file))
+}
+
+/// Generate a trait for each series, with the address and size of every item
+/// shared by all of its models as constants, implemented by each model's type
+fn series_traits(
+    addresses: &BTreeMap<String, BTreeMap<String, BTreeMap<String, ControlTableData>>>,
+    model_features: &BTreeMap<String, &str>,
+) -> String {
+    let mut traits = String::new();
+
+    for (series, models) in addresses {
+        // Items in the first model that every other model has at the same
+        // address and size
+        let mut shared: Vec<(&String, &ControlTableData)> = match models.values().next() {
+            Some(first) => first
+                .iter()
+                .filter(|(data_name, data)| {
+                    models.values().all(|other| {
+                        other.get(*data_name).is_some_and(|other| {
+                            (other.address, other.size) == (data.address, data.size)
+                        })
+                    })
+                })
+                .collect(),
+            None => continue,
+        };
+        shared.sort_by_key(|(_, data)| data.address);
+
+        let name = format!(
+            "{}SeriesCommon",
+            series
+                .chars()
+                .filter(|c| c.is_alphanumeric())
+                .collect::<String>()
+        );
+        let features = any_feature(models.keys().map(|model| model_features[model]));
+
+        traits.push_str(&format!(
+            "\n/// Items at the same address in every {} series model, as `(address, size)`\n#[cfg({})]\npub trait {} {{",
+            series, features, name
+        ));
+        traits.push_str(&format!(
+            "\n{}/// Every shared item, with its address and size\n{}const COMMON: &'static [(DataName, u16, u8)] = &[",
+            INDENT, INDENT
+        ));
+        for (data_name, data) in &shared {
+            traits.push_str(&format!(
+                "\n{}(DataName::{}, {}, {}),",
+                INDENT.repeat(2),
+                data_name,
+                data.address,
+                data.size
+            ));
+        }
+        traits.push_str(&format!("\n{}];", INDENT));
+        for (data_name, data) in &shared {
+            traits.push_str(&format!(
+                "\n{}const {}: (u16, u8) = ({}, {});",
+                INDENT,
+                data_name.to_case(Case::UpperSnake),
+                data.address,
+                data.size
+            ));
+        }
+        traits.push_str("\n}\n");
+
+        for model in models.keys() {
+            traits.push_str(&format!(
+                "\n#[cfg(feature = \"{}\")]\nimpl<R> {} for models::{}<R> {{}}\n",
+                model_features[model], name, model
+            ));
+        }
+    }
+
+    traits
+}
+
+/// The name of the constant for a bit, eg "OVERLOAD_ERROR"
+fn bit_name(name: &str) -> String {
+    let name = name
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { ' ' })
+        .collect::<String>()
+        .to_case(Case::UpperSnake);
+    match name.starts_with(|c: char| c.is_numeric()) {
+        true => format!("BIT_{}", name),
+        false => name,
+    }
+}
+
+/// Document a field of bits with the values it takes if they're known, such
+/// as the direction Drive Mode's Normal/Reverse Mode bit selects, otherwise
+/// with its description
+fn bit_docs(field: &BitField) -> String {
+    match field.values.is_empty() {
+        true => field.description.clone().unwrap_or_default(),
+        false => field
+            .values
+            .iter()
+            .map(|value| format!("- `{}`: {}", value.value, value.meaning))
+            .collect::<Vec<String>>()
+            .join("\n"),
+    }
+}
+
+/// The mask of a field of bits, unless it's past the 32 bits masks are
+fn field_mask(field: &BitField) -> Option<u32> {
+    if u32::from(field.bit) + u32::from(field.width) > 32 {
+        return None;
+    }
+    let ones = 1u32
+        .checked_shl(field.width.into())
+        .map_or(u32::MAX, |bit| bit - 1);
+
+    ones.checked_shl(field.bit.into())
+}
+
+/// Items whose bits each name a hardware error, either reporting it or
+/// choosing whether it shuts the model down
+static HARDWARE_ERROR_ITEMS: &[&str] = &[
+    "HardwareErrorStatus",
+    "Shutdown",
+    "AlarmShutdown",
+    "AlarmLED",
+];
+
+/// Generate a `HardwareError` set of flags for each series documenting the
+/// bits of its hardware error items, such as `XHardwareError::OVERLOAD`.
+/// Bits are named without their "Error" suffix, and a bit documented
+/// differently by several models keeps the first meaning found.
+fn hardware_errors(
+    addresses: &BTreeMap<String, BTreeMap<String, BTreeMap<String, ControlTableData>>>,
+    model_features: &BTreeMap<String, &str>,
+    language: LanguageFeatures,
+) -> String {
+    let mut types = String::new();
+
+    for (series, models) in addresses {
+        // Map of mask -> (constant name, description)
+        let mut errors: BTreeMap<u32, (String, String)> = BTreeMap::new();
+        for (model, data_names) in models {
+            let fields = HARDWARE_ERROR_ITEMS
+                .iter()
+                .filter_map(|item| data_names.get(*item)?.bits.as_ref())
+                .flatten();
+            for field in fields {
+                let name = bit_name(&field.name);
+                let name = match name.strip_suffix("_ERROR") {
+                    Some(stripped) if !stripped.is_empty() => stripped.to_string(),
+                    _ => name,
+                };
+                let description = field.description.as_ref().unwrap_or(&field.name);
+                let mask = match field_mask(field) {
+                    Some(mask) => mask,
+                    None => {
+                        warn!(%model, bit = %name, "Hardware error is past bit 31, leaving it out");
+                        continue;
+                    }
+                };
+
+                match errors.get(&mask) {
+                    Some((existing, _)) if *existing != name => warn!(
+                        %model,
+                        bit = %name,
+                        %existing,
+                        "Hardware error differs from other models, keeping the first"
+                    ),
+                    Some(_) => (),
+                    None => {
+                        errors.insert(mask, (name, description.trim().to_string()));
+                    }
+                }
+            }
+        }
+        if errors.is_empty() {
+            continue;
+        }
+
+        let name = format!(
+            "{}HardwareError",
+            series
+                .chars()
+                .filter(|c| c.is_alphanumeric())
+                .collect::<String>()
+        );
+        let cfg = format!(
+            "#[cfg({})]",
+            any_feature(models.keys().map(|model| model_features[model]))
+        );
+        let all = errors.keys().fold(0, |all, mask| all | mask);
+
+        types.push_str(&format!(
+            "\n/// Hardware errors of {} series models, as reported by Hardware Error\n/// Status and chosen by Shutdown\n{}\n#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]\npub struct {}(pub u8);\n",
+            series, cfg, name
+        ));
+        types.push_str(&format!("\n{}\nimpl {} {{", cfg, name));
+        for (mask, (error, description)) in &errors {
+            types.push('\n');
+            types.push_str(&doc_comment(description, INDENT));
+            types.push_str(&format!(
+                "{}pub const {}: Self = Self({:#x});",
+                INDENT, error, mask
+            ));
+        }
+        types.push_str(&format!(
+            "\n\n{}/// Every error, with its name\n{}pub const ALL: &'static [(&'static str, Self)] = &[",
+            INDENT, INDENT
+        ));
+        for (error, _) in errors.values() {
+            types.push_str(&format!(
+                "\n{}(\"{}\", Self::{}),",
+                INDENT.repeat(2),
+                error,
+                error
+            ));
+        }
+        types.push_str(&format!("\n{}];\n", INDENT));
+        types.push_str(
+            &language.apply(&HARDWARE_ERROR_METHODS.replace("{all}", &format!("{:#x}", all))),
+        );
+        types.push_str("}\n");
+
+        for (operator, method, symbol) in [("BitOr", "bitor", "|"), ("BitAnd", "bitand", "&")] {
+            types.push_str(&format!(
+                "\n{}\nimpl core::ops::{} for {} {{\n{}type Output = Self;\n\n{}fn {}(self, other: Self) -> Self {{\n{}Self(self.0 {} other.0)\n{}}}\n}}\n",
+                cfg, operator, name, INDENT, INDENT, method, INDENT.repeat(2), symbol, INDENT
+            ));
+        }
+    }
+
+    types
+}
+
+/// The name of an item's value as a variant, eg "Velocity Control Mode" ->
+/// `VelocityControlMode`. Values named by a number, such as the "57,600" of
+/// Baud Rate, are prefixed with the item, as `BaudRate57600`.
+fn value_variant(name: &str, data_name: &str) -> String {
+    lazy_static! {
+        static ref DECIMAL_RE: Regex = Regex::new(r"([0-9])\.([0-9])").unwrap();
+    }
+
+    let name = DECIMAL_RE
+        .replace_all(&strip_thousands_separators(name), "${1}_${2}")
+        .to_string();
+    let variant: String = name
+        .split(|c: char| !c.is_alphanumeric() && c != '_')
+        .flat_map(|word| {
+            let mut chars = word.chars();
+            chars
+                .next()
+                .into_iter()
+                .flat_map(char::to_uppercase)
+                .chain(chars)
+        })
+        .collect();
+    match variant.starts_with(|c: char| c.is_alphabetic()) {
+        true => variant,
+        false => format!("{}{}", data_name, variant),
+    }
+}
+
+/// Generate an enum of the documented values of each item for each series,
+/// such as `XOperatingMode::VelocityControlMode`, converting to the raw value
+/// with `From` and back with `TryFrom`. A value documented differently by
+/// several models keeps the first name found.
+fn value_enums(
+    addresses: &BTreeMap<String, BTreeMap<String, BTreeMap<String, ControlTableData>>>,
+    model_features: &BTreeMap<String, &str>,
+    language: LanguageFeatures,
+) -> String {
+    let mut types = String::new();
+
+    for (series, models) in addresses {
+        // Map of data name -> (largest size, models, value -> (variant, docs))
+        type Values<'a> = (u8, BTreeSet<&'a str>, BTreeMap<i64, (String, String)>);
+        let mut items: BTreeMap<&str, Values> = BTreeMap::new();
+        for (model, data_names) in models {
+            for (data_name, data) in data_names {
+                if data.values.is_empty() || data.joint.is_some() {
+                    continue;
+                }
+                let (size, item_models, values) = items.entry(data_name).or_default();
+                *size = (*size).max(data.size);
+                item_models.insert(model);
+
+                for value in &data.values {
+                    let variant = value_variant(&value.name, data_name);
+                    let docs = match &value.description {
+                        Some(description) => format!("{}\n\n{}", value.name, description),
+                        None => value.name.clone(),
+                    };
+                    match values.get(&value.value) {
+                        Some((existing, _)) if *existing != variant => warn!(
+                            %model,
+                            item = %data_name,
+                            value = value.value,
+                            %existing,
+                            "Value differs from other models, keeping the first"
+                        ),
+                        Some(_) => (),
+                        None if values.values().any(|(name, _)| *name == variant) => warn!(
+                            %model,
+                            item = %data_name,
+                            %variant,
+                            "Values share a name, keeping the first"
+                        ),
+                        None => {
+                            values.insert(value.value, (variant, docs));
+                        }
+                    }
+                }
+            }
+        }
+
+        for (data_name, (size, item_models, mut values)) in items {
+            let raw_type = match size {
+                1 => "u8",
+                2 => "u16",
+                _ => "u32",
+            };
+            let max = match size {
+                1 => u8::MAX as i64,
+                2 => u16::MAX as i64,
+                _ => u32::MAX as i64,
+            };
+            values.retain(|value, _| (0..=max).contains(value));
+            if values.is_empty() {
+                continue;
+            }
+
+            let name = format!(
+                "{}{}",
+                series
+                    .chars()
+                    .filter(|c| c.is_alphanumeric())
+                    .collect::<String>(),
+                data_name
+            );
+            let cfg = format!(
+                "#[cfg({})]",
+                any_feature(item_models.iter().map(|model| model_features[*model]))
+            );
+
+            types.push_str(&format!(
+                "\n/// The values of [`DataName::{}`] on {} series models\n{}\n#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]\npub enum {} {{",
+                data_name, series, cfg, name
+            ));
+            for (variant, docs) in values.values() {
+                types.push('\n');
+                types.push_str(&doc_comment(docs, INDENT));
+                types.push_str(&format!("{}{},", INDENT, variant));
+            }
+            types.push_str("\n}\n");
+
+            types.push_str(&format!(
+                "\n{}\nimpl From<{}> for {} {{\n{}fn from(value: {}) -> {} {{\n{}match value {{",
+                cfg,
+                name,
+                raw_type,
+                INDENT,
+                name,
+                raw_type,
+                INDENT.repeat(2)
+            ));
+            for (value, (variant, _)) in &values {
+                types.push_str(&format!(
+                    "\n{}{}::{} => {},",
+                    INDENT.repeat(3),
+                    name,
+                    variant,
+                    value
+                ));
+            }
+            types.push_str(&format!("\n{}}}\n{}}}\n}}\n", INDENT.repeat(2), INDENT));
+
+            if !language.try_from {
+                continue;
+            }
+            types.push_str(&format!(
+                "\n{}\nimpl core::convert::TryFrom<{}> for {} {{\n{}type Error = UnknownValue;\n\n{}fn try_from(value: {}) -> Result<Self, UnknownValue> {{\n{}match value {{",
+                cfg, raw_type, name, INDENT, INDENT, raw_type, INDENT.repeat(2)
+            ));
+            for (value, (variant, _)) in &values {
+                types.push_str(&format!(
+                    "\n{}{} => Ok({}::{}),",
+                    INDENT.repeat(3),
+                    value,
+                    name,
+                    variant
+                ));
+            }
+            types.push_str(&format!(
+                "\n{}_ => Err(UnknownValue {{\n{}name: DataName::{},\n{}value: value.into(),\n{}}}),\n{}}}\n{}}}\n}}\n",
+                INDENT.repeat(3),
+                INDENT.repeat(4),
+                data_name,
+                INDENT.repeat(4),
+                INDENT.repeat(3),
+                INDENT.repeat(2),
+                INDENT
+            ));
+        }
+    }
+
+    types
+}
+
+/// Generate a module of masks for the bits of each item documented as a set
+/// of flags, such as `bits::shutdown::OVERLOAD_ERROR`. Models documenting the
+/// same bit differently keep the first meaning found.
+fn bit_constants(
+    addresses: &BTreeMap<String, BTreeMap<String, BTreeMap<String, ControlTableData>>>,
+) -> String {
+    // Map of constant name -> (mask, docs)
+    type Masks = BTreeMap<String, (u32, String)>;
+    // Map of data name -> (largest size, masks)
+    let mut flags: BTreeMap<&str, (u8, Masks)> = BTreeMap::new();
+
+    for (model, data_names) in addresses.values().flat_map(|models| models.iter()) {
+        for (data_name, data) in data_names {
+            let bits = match &data.bits {
+                Some(bits) => bits,
+                None => continue,
+            };
+            let (size, masks) = flags.entry(data_name).or_default();
+            *size = (*size).max(data.size);
+
+            for field in bits {
+                let name = bit_name(&field.name);
+                let mask = match field_mask(field) {
+                    Some(mask) => mask,
+                    None => {
+                        warn!(%model, item = %data_name, bit = %name, "Bit is past bit 31, leaving it out");
+                        continue;
+                    }
+                };
+
+                match masks.get(&name) {
+                    Some((existing, _)) if *existing != mask => warn!(
+                        %model,
+                        item = %data_name,
+                        bit = %name,
+                        "Bit differs from other models, keeping the first"
+                    ),
+                    Some(_) => (),
+                    None => {
+                        masks.insert(name, (mask, bit_docs(field)));
+                    }
+                }
+            }
+        }
+    }
+
+    let mut constants = String::new();
+    constants.push_str("\n/// Masks for the bits of items documented as a set of flags\n");
+    constants.push_str("pub mod bits {\n");
+    for (data_name, (size, masks)) in flags {
+        let mask_type = match size {
+            1 => "u8",
+            2 => "u16",
+            _ => "u32",
+        };
+        constants.push_str(&format!(
+            "{}pub mod {} {{\n",
+            INDENT,
+            data_name.to_case(Case::Snake)
+        ));
+        for (name, (mask, docs)) in masks {
+            constants.push_str(&doc_comment(&docs, &INDENT.repeat(2)));
+            constants.push_str(&format!(
+                "{}pub const {}: {} = {:#x};\n",
+                INDENT.repeat(2),
+                name,
+                mask_type,
+                mask
+            ));
+        }
+        constants.push_str(&format!("{}}}\n", INDENT));
+    }
+    constants.push_str("}\n");
+
+    constants
+}
+
+/// A README for a crate created by the scraper, listing its models along with
+/// the pages they were scraped from
+fn readme(
+    target: &LibTarget,
+    addresses: &BTreeMap<String, BTreeMap<String, BTreeMap<String, ControlTableData>>>,
+    model_docs: &BTreeMap<String, (&str, &str)>,
+    model_features: &BTreeMap<String, &str>,
+) -> String {
+    let mut readme = format!(
+        "# {}\n\n{} by [dynamixel-scraper](https://github.com/kiros-rs/dynamixel-scraper) {}. It is generated, so rather than editing it by hand, run the scraper again.\n\n",
+        target.name,
+        PACKAGE_DESCRIPTION.replace("Robotis E-Manual", "[Robotis E-Manual](https://emanual.robotis.com)"),
+        env!("CARGO_PKG_VERSION")
+    );
+    readme.push_str("Each model is enabled by its feature, and every model of a series by the series' feature. Every series is enabled by default.\n\n");
+    readme.push_str("## Models\n\n| Model | Series | Feature | Source |\n|-------|--------|---------|--------|\n");
+    for (series, models) in addresses {
+        for model in models.keys() {
+            let (name, url) = model_docs[model];
+            readme.push_str(&format!(
+                "| {} | {} | `{}` | <{}> |\n",
+                name, series, model_features[model], url
+            ));
+        }
+    }
+
+    readme
+}
+
+/// A test for the crate to check that it builds with its minimum supported
+/// Rust version, by looking up the first item of every model at compile time
+/// if the version allows it (or at run time if not). Building the crate's
+/// tests with that compiler is enough to verify it, without a CI matrix.
+fn msrv_test(
+    target: &LibTarget,
+    msrv: RustVersion,
+    language: LanguageFeatures,
+    addresses: &BTreeMap<String, BTreeMap<String, BTreeMap<String, ControlTableData>>>,
+    model_features: &BTreeMap<String, &str>,
+    discontinued: &BTreeSet<String>,
+) -> String {
+    let mut test = format!(
+        "//! Generated by dynamixel-scraper. Checks that the crate builds with Rust {}, and\n//! that the lookups it declares as `const fn` can be evaluated at compile time.\n\n{}use {}::*;\n",
+        msrv,
+        match discontinued.is_empty() {
+            true => "",
+            false => "#![allow(deprecated)]\n\n",
+        },
+        target.name.replace('-', "_")
+    );
+
+    test.push_str("\n#[test]\nfn looks_up_the_items_of_each_model() {");
+    for models in addresses.values() {
+        for (model, data_names) in models {
+            let first = data_names.iter().min_by_key(|(_, data)| data.address);
+            let (data_name, data) = match first {
+                Some(first) => first,
+                None => continue,
+            };
+            let lookup = format!("address(Model::{}, DataName::{})", model, data_name);
+            test.push_str(&format!(
+                "\n{}#[cfg(feature = \"{}\")]\n{}{{",
+                INDENT, model_features[model], INDENT
+            ));
+            match language.const_control_flow {
+                true => test.push_str(&format!(
+                    "\n{0}{0}const ADDRESS: Result<u16, ControlTableError> = {1};\n{0}{0}assert_eq!(ADDRESS.ok(), Some({2}));",
+                    INDENT, lookup, data.address
+                )),
+                false => test.push_str(&format!(
+                    "\n{0}{0}assert_eq!({1}.ok(), Some({2}));",
+                    INDENT, lookup, data.address
+                )),
+            }
+            test.push_str(&format!(
+                "\n{0}{0}assert_eq!(supported(Model::{1}).len(), {2});",
+                INDENT,
+                model,
+                data_names.len()
+            ));
+            test.push_str(&format!("\n{}}}", INDENT));
+        }
+    }
+    test.push_str("\n}\n");
+
+    if language.const_floats {
+        test.push_str("\n#[test]\nfn converts_units_at_compile_time() {\n    const FACTOR: Option<f64> = Unit::Percent.si_factor();\n    assert_eq!(FACTOR, Some(0.01));\n}\n");
+    }
+
+    test
+}
+
+/// Alternative wordings of the same item on different pages, and the name
+/// each is canonicalized to. Differences in case, spacing and punctuation
+/// (eg "Goal position" and "Goal_Position") are always ignored, so only
+/// names worded differently need to be listed.
+pub static DATA_NAME_ALIASES: &[(&str, &[&str])] = &[
+    ("Present Temperature", &["Present Temp"]),
+    ("Temperature Limit", &["Temp Limit"]),
+];
+
+/// The key items are grouped by to find their variant: the canonical name's
+/// letters, in lowercase
+pub fn data_name_key(name: &str) -> String {
+    let letters = |name: &str| -> String {
+        name.chars()
+            .filter(|c| c.is_alphabetic())
+            .flat_map(char::to_lowercase)
+            .collect()
+    };
+
+    let key = letters(name);
+    DATA_NAME_ALIASES
+        .iter()
+        .find(|(canonical, spellings)| {
+            letters(canonical) == key || spellings.iter().any(|spelling| letters(spelling) == key)
+        })
+        .map_or(key, |(canonical, _)| letters(canonical))
+}
+
+/// Spell a name as a variant, capitalizing each word and keeping only
+/// letters, eg "Goal position" -> "GoalPosition"
+fn variant_spelling(name: &str) -> String {
+    name.split(|c: char| !c.is_alphanumeric())
+        .flat_map(|word| {
+            let mut chars = word.chars().filter(|c| c.is_alphabetic());
+            chars
+                .next()
+                .into_iter()
+                .flat_map(char::to_uppercase)
+                .chain(chars)
+        })
+        .collect()
+}
+
+/// Choose the variant of every data name on the given models, by the key of
+/// [`data_name_key`]. Where models spell an item differently, the most
+/// common spelling wins (the first alphabetically in a tie), or the
+/// canonical name if it's an alias. Warns about names that differ by a
+/// single letter, which are probably the same item misspelled and should be
+/// added to [`DATA_NAME_ALIASES`].
+pub fn canonical_variants(servos: &[&Actuator]) -> BTreeMap<String, String> {
+    let mut spellings: BTreeMap<String, BTreeMap<String, usize>> = BTreeMap::new();
+    for row in servos.iter().flat_map(|dxl| &dxl.data) {
+        if let Some(name) = &row.data_name {
+            let key = data_name_key(name);
+            let canonical = DATA_NAME_ALIASES
+                .iter()
+                .find(|(canonical, _)| data_name_key(canonical) == key)
+                .map_or(name.as_str(), |(canonical, _)| canonical);
+            *spellings
+                .entry(key)
+                .or_default()
+                .entry(variant_spelling(canonical))
+                .or_default() += 1;
+        }
+    }
+
+    let keys: Vec<&String> = spellings.keys().collect();
+    for (i, a) in keys.iter().enumerate() {
+        for b in &keys[i + 1..] {
+            // Differences at the start are usually meaningful, eg CW and CCW
+            let shared_prefix = a.chars().zip(b.chars()).take_while(|(a, b)| a == b).count();
+            if shared_prefix >= 3 && edit_distance(a, b) == 1 {
+                warn!(
+                    first = %a,
+                    second = %b,
+                    "Data names differ by one letter, add an alias if they're the same item"
+                );
+            }
+        }
+    }
+
+    spellings
+        .into_iter()
+        .map(|(key, counts)| {
+            let most_common = counts.values().max().copied().unwrap_or_default();
+            let variant = counts
+                .into_iter()
+                .find(|(_, count)| *count == most_common)
+                .map(|(variant, _)| variant)
+                .unwrap_or_default();
+            (key, variant)
+        })
+        .collect()
+}
+
+/// The variant of each named item of a model, by address: its canonical
+/// variant, followed by its joint on models with a bank of items for each
+/// joint (eg `GoalPositionJoint2`). Items told apart only by numbers or
+/// punctuation, such as "External Port Data 1" and "External Port Data 2",
+/// would share a variant and overwrite each other, so they're numbered
+/// instead (eg `ExternalPortData1`): by the number in their name, or by
+/// their order if those don't tell them apart.
+pub fn item_variants(dxl: &Actuator, variants: &BTreeMap<String, String>) -> BTreeMap<u16, String> {
+    // Map of variant -> address and name of each item it would name
+    let mut shared: BTreeMap<String, Vec<(u16, &str)>> = BTreeMap::new();
+    for row in &dxl.data {
+        if let Some(name) = &row.data_name {
+            let mut variant = variants[&data_name_key(name)].clone();
+            // Each joint's items are distinct items, eg GoalPositionJoint2
+            if let Some(joint) = row.joint {
+                variant = format!("{}Joint{}", variant, joint);
+            }
+            let items = shared.entry(variant).or_default();
+            if !items.iter().any(|(address, _)| *address == row.address) {
+                items.push((row.address, name));
+            }
+        }
+    }
+
+    let mut numbered = BTreeMap::new();
+    for (variant, items) in shared {
+        if let [(address, _)] = items.as_slice() {
+            numbered.insert(*address, variant);
+            continue;
+        }
+
+        let names: Vec<&str> = items.iter().map(|(_, name)| *name).collect();
+        warn!(model = %dxl.name, %variant, ?names, "Data names collide, numbering their variants");
+        let numbers: Vec<String> = names
+            .iter()
+            .map(|name| name.chars().filter(char::is_ascii_digit).collect())
+            .collect();
+        let distinct = numbers.iter().collect::<BTreeSet<_>>().len() == numbers.len();
+        for (i, ((address, _), number)) in items.iter().zip(numbers).enumerate() {
+            let number = match distinct {
+                true => number,
+                false => (i + 1).to_string(),
+            };
+            numbered.insert(*address, format!("{}{}", variant, number));
+        }
+    }
+
+    numbered
+}
+
+/// The models of every actuator and their items, as the libraries generated
+/// for other languages name them
+pub struct Models<'a> {
+    /// Map of model -> data names -> control table data
+    pub items: BTreeMap<String, BTreeMap<String, &'a ControlTableData>>,
+    /// The display name and source URL of each model
+    pub docs: BTreeMap<String, (&'a str, &'a str)>,
+    /// Every item found in the control table of any model
+    pub data_names: BTreeSet<String>,
+}
+
+/// Name the models of `actuators` and their items as the library does,
+/// failing if two models would share a name
+pub fn collect_models(actuators: &[Actuator], naming: NamingStrategy) -> Result<Models<'_>> {
+    let mut servos: Vec<&Actuator> = actuators.iter().collect();
+    servos.sort_by(|a, b| a.url.cmp(&b.url));
+    let variants = canonical_variants(&servos);
+
+    let mut models = Models {
+        items: BTreeMap::new(),
+        docs: BTreeMap::new(),
+        data_names: BTreeSet::new(),
+    };
+    for dxl in servos {
+        let model = naming.model_name(&dxl.raw_name);
+        if let Some((_, url)) = models.docs.get(&model) {
+            bail!(
+                "Both {} and {} would be named {}, try another naming strategy",
+                url,
+                dxl.url,
+                model
+            );
+        }
+        models.docs.insert(model.clone(), (&dxl.name, &dxl.url));
+
+        let items = models.items.entry(model).or_default();
+        let model_variants = item_variants(dxl, &variants);
+        for row in dxl.data.iter().filter(|row| row.data_name.is_some()) {
+            let variant = model_variants[&row.address].clone();
+            models.data_names.insert(variant.clone());
+            items.insert(variant, row);
+        }
+    }
+
+    Ok(models)
+}
+
+/// The fields of an item that the libraries generated for other languages
+/// describe, with numbers only where the E-Manual gives a single one
+pub struct ItemFields {
+    /// "Read" or "ReadWrite". Items whose access isn't understood are taken
+    /// to be read-only, so they're never written by mistake.
+    pub access: &'static str,
+    /// The variant of `ValueType`, without the size `Bytes` carries
+    pub value_type: String,
+    pub initial_value: Option<i32>,
+    pub min: Option<i32>,
+    pub max: Option<i32>,
+}
+
+impl ItemFields {
+    pub fn new(data: &ControlTableData) -> ItemFields {
+        let number = |value: &RangeValue| match value {
+            RangeValue::Integer(i) => Some(*i),
+            _ => None,
+        };
+        let value_type = format!("{:?}", data.value_type);
+
+        ItemFields {
+            access: match data.access {
+                AccessLevel::ReadWrite => "ReadWrite",
+                AccessLevel::Read | AccessLevel::Unknown(_) => "Read",
+            },
+            value_type: value_type.split('(').next().unwrap_or_default().to_string(),
+            initial_value: match &data.initial_value {
+                InitialValue::Value(value) => number(value),
+                _ => None,
+            },
+            min: data.range.as_ref().and_then(|(min, _)| number(min)),
+            max: data.range.as_ref().and_then(|(_, max)| number(max)),
+        }
+    }
 }
 
-pub fn create_lib(servos: &[Actuator]) -> Result<()> {
+/// Rename the items referred to by a row's range and initial value to their
+/// variants: that of the item they were resolved to on the model, if any,
+/// and their canonical variant otherwise. Values referring to items no model
+/// has are dropped, as they'd name a `DataName` that doesn't exist.
+fn canonicalize_references(
+    row: &mut ControlTableData,
+    variants: &BTreeMap<String, String>,
+    items: &BTreeMap<u16, String>,
+) {
+    let variant = |name: &str, reference: &Option<DataNameRef>| {
+        reference
+            .as_ref()
+            .and_then(|reference| items.get(&reference.address))
+            .or_else(|| variants.get(&data_name_key(name)))
+            .cloned()
+    };
+    let known = |value: &RangeValue| match value {
+        RangeValue::Address {
+            name, reference, ..
+        } => variant(name, reference).is_some(),
+        RangeValue::Integer(_) => true,
+    };
+    if let Some((min, max)) = &row.range {
+        if !known(min) || !known(max) {
+            warn!(item = ?row.data_name, "Leaving out a range referring to an unknown item");
+            row.range = None;
+        }
+    }
+    if let InitialValue::Value(value) = &row.initial_value {
+        if !known(value) {
+            warn!(item = ?row.data_name, "Leaving out an initial value referring to an unknown item");
+            row.initial_value = InitialValue::None;
+        }
+    }
+
+    let values = row
+        .range
+        .iter_mut()
+        .flat_map(|(min, max)| vec![min, max])
+        .chain(match &mut row.initial_value {
+            InitialValue::Value(value) => Some(value),
+            _ => None,
+        });
+
+    for value in values {
+        if let RangeValue::Address {
+            name, reference, ..
+        } = value
+        {
+            if let Some(variant) = variant(name, reference) {
+                *name = variant;
+            }
+        }
+    }
+}
+
+/// Generate the library for the given models. Models are taken in order of
+/// URL and everything generated from them is collected in sorted maps, so
+/// the same models always generate the same bytes, whatever order they're
+/// given in.
+#[instrument(skip_all, fields(models = servos.len(), path = %target.path.display()))]
+pub fn create_lib(servos: &[Actuator], target: &LibTarget) -> Result<()> {
+    let mut servos: Vec<&Actuator> = servos.iter().collect();
+    servos.sort_by(|a, b| a.url.cmp(&b.url));
+
     // Map of series -> model -> data names -> control table data
     // Should switch model and data names for improved code readability
     let mut addresses: BTreeMap<String, BTreeMap<String, BTreeMap<String, ControlTableData>>> =
         BTreeMap::new();
 
-    // Keep track of all data names to convert into an enum later
-    let mut data_names: Vec<String> = vec![];
+    // Keep track of all data names to convert into an enum later, along
+    // with the text used to document them
+    let mut data_names: BTreeMap<String, DataNameDocs> = BTreeMap::new();
+
+    // Map of model -> (display name, source URL)
+    let mut model_docs: BTreeMap<String, (&str, &str)> = BTreeMap::new();
+
+    // Map of model -> the feature enabling it, named after its URL (eg
+    // "xm430-w210"). Series features enable every model in the series.
+    let mut model_features: BTreeMap<String, &str> = BTreeMap::new();
+
+    // Models ROBOTIS no longer makes, whose variants are deprecated
+    let mut discontinued: BTreeSet<String> = BTreeSet::new();
+
+    let variants = canonical_variants(&servos);
+    let language = LanguageFeatures::new(target.msrv);
 
     for dxl in servos {
         let series = dxl.series.to_uppercase();
-        // let model = dxl.raw_name.chars().filter(|c| c.is_alphanumeric()).collect::<String>().to_uppercase();
-        let first_letter = dxl
-            .raw_name
-            .chars()
-            .position(|x| x.is_alphabetic())
-            .unwrap();
-        let model = dxl
-            .raw_name
-            .chars()
-            .filter(|x| x.is_alphanumeric())
-            .skip(first_letter)
-            .collect::<String>()
-            .to_uppercase();
+        let model = target.naming.model_name(&dxl.raw_name);
+        // Distinct models named the same would generate duplicate variants
+        if let Some(other) = model_features
+            .get(&model)
+            .filter(|other| **other != dxl.raw_name)
+        {
+            bail!(
+                "Both {} and {} would be named {}, try another naming strategy",
+                other,
+                dxl.raw_name,
+                model
+            );
+        }
 
-        let models = addresses.entry(series).or_insert_with(BTreeMap::new);
+        let models = addresses.entry(series).or_default();
+        model_docs.insert(model.clone(), (&dxl.name, &dxl.url));
+        model_features.insert(model.clone(), &dxl.raw_name);
+        if dxl.is_discontinued() {
+            discontinued.insert(model.clone());
+        }
 
+        let items = item_variants(dxl, &variants);
         for row in &dxl.data {
             if let Some(name) = &row.data_name {
-                let pascal_name: String = name
-                    .to_string()
-                    .chars()
-                    .filter(|c| c.is_alphabetic())
-                    .collect();
-                data_names.push(pascal_name.clone());
+                let pascal_name = items[&row.address].clone();
+                let mut name = name.trim().to_string();
+                if let Some(joint) = row.joint {
+                    name = format!("{} (Joint {})", name, joint);
+                }
+                let docs = data_names.entry(pascal_name.clone()).or_default();
+                docs.names.insert(name);
+                if let Some(description) = &row.description {
+                    docs.descriptions.insert(description.trim().to_string());
+                }
+                for reference in &row.related {
+                    if let Some(variant) = items.get(&reference.address) {
+                        if *variant != pascal_name {
+                            docs.related.insert(variant.clone());
+                        }
+                    }
+                }
 
-                let names = models.entry(model.clone()).or_insert_with(BTreeMap::new);
-                names.insert(pascal_name, row.to_owned());
+                let mut row = row.to_owned();
+                canonicalize_references(&mut row, &variants, &items);
+                let names = models.entry(model.clone()).or_default();
+                names.insert(pascal_name, row);
             }
         }
     }
 
-    data_names.sort();
-    data_names.dedup();
-
     // Create the necessary file structure
-    create_dir_all("lib/src")?;
+    create_dir_all(target.path.join("src"))?;
+    let existing_crate = target.is_existing_crate();
     let mut lib = String::new();
-    let mut cargo = String::new();
+    let mut features = String::new();
 
-    cargo.push_str(CARGO_PREAMBLE);
-    cargo.push_str(&format!(
-        "default = [{}]",
+    features.push_str(&format!(
+        "default = [{}]\n",
         addresses
             .keys()
             .map(|x| format!("\"{}\"", x))
@@ -123,113 +1968,324 @@ pub fn create_lib(servos: &[Actuator]) -> Result<()> {
             .join(", ")
     ));
 
+    // Crate-level documentation
+    lib.push_str(CRATE_DOCS);
+    // The crate's own lookups still name the variants of discontinued models
+    if !discontinued.is_empty() {
+        lib.push_str("#![allow(deprecated)]\n\n");
+    }
+    lib.push_str(&format!(
+        "/// The revision of the E-Manual's source the control tables were scraped\n/// from, if it was pinned rather than scraped from the live site\npub const MANUAL_REVISION: Option<&str> = {:?};\n\n",
+        target.manual_revision
+    ));
+
     // Set up error handling
     lib.push_str(ERROR_DEFINITION);
 
     // Set up ControlTableData struct
-    lib.push_str(CONTROL_TABLE_DATA);
+    lib.push_str(&language.apply(CONTROL_TABLE_DATA));
+
+    // Variants are numbered alphabetically when first generated
+    let mut discriminants = Discriminants::load(&target.discriminants_path())?;
+    assign_discriminants(&mut discriminants.data_names, data_names.keys());
+    assign_discriminants(
+        &mut discriminants.models,
+        addresses
+            .values()
+            .flat_map(|models| models.keys())
+            .collect::<BTreeSet<&String>>()
+            .into_iter(),
+    );
 
     // DataName enum
+    lib.push_str("/// An item in the control table of at least one Dynamixel model\n");
     lib.push_str(DERIVES);
-    lib.push_str("\npub enum DataName {\n    ");
-    lib.push_str(&data_names.join(",\n    "));
-    lib.push_str(",\n}\n\n");
+    lib.push('\n');
+    lib.push_str(&language.apply(ENUM_ATTRIBUTES));
+    lib.push_str("\npub enum DataName {\n");
+    for (data_name, docs) in &data_names {
+        // Fall back to the name from the E-Manual if no model describes it
+        let mut text = match docs.descriptions.is_empty() {
+            true => docs.names.iter().cloned().collect::<Vec<String>>(),
+            false => docs.descriptions.iter().cloned().collect::<Vec<String>>(),
+        };
+        if !docs.related.is_empty() {
+            let links: Vec<String> = docs
+                .related
+                .iter()
+                .map(|variant| format!("[`DataName::{}`]", variant))
+                .collect();
+            text.push(format!("See also {}.", links.join(", ")));
+        }
+        lib.push_str(&doc_comment(&text.join("\n\n"), INDENT));
+        lib.push_str(&format!(
+            "{}{} = {},\n",
+            INDENT, data_name, discriminants.data_names[data_name]
+        ));
+    }
+    lib.push_str("}\n\n");
 
     // Model enum
+    lib.push_str("/// A Dynamixel model with a known control table\n");
     lib.push_str(DERIVES);
+    // Every variant may be disabled by features, and an empty enum can't have
+    // a representation, so only give it one if any model is enabled
+    lib.push_str(&language.apply(&format!(
+        "\n{{non_exhaustive}}#[allow(non_camel_case_types)]\n#[cfg_attr({}, repr(u16))]",
+        any_feature(model_features.values().copied())
+    )));
     lib.push_str("\npub enum Model {\n");
 
-    for (series, models) in &addresses {
+    for models in addresses.values() {
         for model in models.keys() {
-            lib.push_str(&format!("{}#[cfg(feature = \"{}\")]\n", INDENT, series));
-            lib.push_str(&format!("{}{},\n", INDENT, model));
+            let (name, url) = model_docs[model];
+            lib.push_str(&doc_comment(
+                &format!("{}\n\nSource: <{}>", name, url),
+                INDENT,
+            ));
+            lib.push_str(&format!(
+                "{}#[cfg(feature = \"{}\")]\n",
+                INDENT, model_features[model]
+            ));
+            if discontinued.contains(model) {
+                lib.push_str(&format!(
+                    "{}#[deprecated(note = \"discontinued by ROBOTIS\")]\n",
+                    INDENT
+                ));
+            }
+            lib.push_str(&format!(
+                "{}{} = {},\n",
+                INDENT, model, discriminants.models[model]
+            ));
         }
     }
     lib.push_str("}\n");
 
-    lib.push_str(
-        "\npub const fn data(model: Model, name: DataName) -> Result<ControlTableData, ControlTableError> {",
-    );
+    lib.push_str(&string_conversions(
+        &data_names,
+        &addresses,
+        &model_docs,
+        &model_features,
+    ));
+
+    lib.push_str("\n/// Look up where and how `name` is stored in the control table of `model`");
+    lib.push_str(&language.apply(
+        "\npub {const_control_flow}fn data(model: Model, name: DataName) -> Result<ControlTableData, ControlTableError> {",
+    ));
     lib.push_str(&format!("\n{}match model {{", INDENT));
 
+    // Many models share identical control tables, so each distinct table is
+    // generated once and named after a hash of its contents
+    let mut tables: BTreeMap<String, (String, BTreeSet<&str>)> = BTreeMap::new();
+
     for (series, models) in &addresses {
-        cargo.push_str(&format!("\n{} = []", series));
+        features.push_str(&format!(
+            "{} = [{}]\n",
+            series,
+            models
+                .keys()
+                .map(|model| format!("\"{}\"", model_features[model]))
+                .collect::<Vec<String>>()
+                .join(", ")
+        ));
+        for model in models.keys() {
+            features.push_str(&format!("{} = []\n", model_features[model]));
+        }
+
         for (model, data_names) in models {
+            let feature = model_features[model];
+            let body = table_body(data_names);
+            let table = format!("table_{:016x}", fnv1a(&body));
+            let (existing, enabled_by) = tables
+                .entry(table.clone())
+                .or_insert_with(|| (body.clone(), BTreeSet::new()));
+            ensure!(
+                *existing == body,
+                "Hash collision for control table {}",
+                table
+            );
+            enabled_by.insert(feature);
+
             lib.push_str(&format!(
                 "\n{}#[cfg(feature = \"{}\")]",
                 INDENT.repeat(2),
-                series
+                feature
             ));
             lib.push_str(&format!(
-                "\n{}Model::{} => match name {{",
+                "\n{}Model::{} => {}(model, name),",
                 INDENT.repeat(2),
-                model
+                model,
+                table
             ));
+        }
+    }
 
-            // Sort the addresses lowest-first
-            let mut sorted_names = Vec::from_iter(data_names);
-            sorted_names.sort_by(|&(_, b), &(_, a)| b.address.cmp(&a.address));
+    lib.push_str(&format!("\n{}}}", INDENT));
+    lib.push_str("\n}\n");
+
+    for (table, (body, enabled_by)) in &tables {
+        lib.push_str(&format!(
+            "\n#[cfg({})]",
+            any_feature(enabled_by.iter().copied())
+        ));
+        lib.push_str(&language.apply(&format!(
+            "\n{{const_control_flow}}fn {}(model: Model, name: DataName) -> Result<ControlTableData, ControlTableError> {{",
+            table
+        )));
+        lib.push_str(body);
+        lib.push_str("\n}\n");
+    }
+
+    // Shortcuts for when only the location of an item is needed
+    lib.push_str(&language.apply(ACCESSORS));
 
+    // Typed registers, for when the model and item are known at compile time
+    lib.push_str(REGISTER_TRAIT);
+    lib.push_str("\n/// Marker types for each item in the control table of any model\n");
+    lib.push_str("pub mod registers {\n");
+    for data_name in data_names.keys() {
+        lib.push_str(&format!(
+            "{}{}\n{}pub struct {};\n",
+            INDENT, DERIVES, INDENT, data_name
+        ));
+    }
+    lib.push_str("}\n");
+
+    lib.push_str("\n/// Types for each model, generic over the items in its control table\n");
+    // Models may be named with underscores by the naming strategy
+    lib.push_str("#[allow(non_camel_case_types)]\npub mod models {\n");
+    for models in addresses.values() {
+        for model in models.keys() {
+            lib.push_str(&format!(
+                "\n{}/// {}\n{}#[cfg(feature = \"{}\")]\n{}{}\n{}pub struct {}<R = ()>(core::marker::PhantomData<R>);\n",
+                INDENT, model_docs[model].0, INDENT, model_features[model], INDENT, DERIVES, INDENT, model
+            ));
+        }
+    }
+    lib.push_str("}\n");
+
+    lib.push_str(&bit_constants(&addresses));
+    lib.push_str(&series_traits(&addresses, &model_features));
+    lib.push_str(&hardware_errors(&addresses, &model_features, language));
+    lib.push_str(&value_enums(&addresses, &model_features, language));
+
+    for models in addresses.values() {
+        for (model, data_names) in models {
+            lib.push_str(&format!(
+                "\n#[cfg(feature = \"{}\")]\nimpl_registers!({} {{",
+                model_features[model], model
+            ));
+            let mut sorted_names = Vec::from_iter(data_names);
+            sorted_names.sort_by_key(|&(_, data)| data.address);
             for (data_name, data) in sorted_names {
                 lib.push_str(&format!(
-                    "\n{}DataName::{} => Ok(ControlTableData {{",
-                    INDENT.repeat(3),
-                    data_name
-                ));
-                lib.push_str(&fix_formatting(format!(
-                    "\n{}address: {},",
-                    INDENT.repeat(4),
-                    data.address
-                )));
-                lib.push_str(&fix_formatting(format!(
-                    "\n{}size: {},",
-                    INDENT.repeat(4),
-                    data.size
-                )));
-                lib.push_str(&fix_formatting(format!(
-                    "\n{}description: {:?},",
-                    INDENT.repeat(4),
-                    data.description
-                )));
-                lib.push_str(&fix_formatting(format!(
-                    "\n{}access: {:?},",
-                    INDENT.repeat(4),
-                    data.access
-                )));
-                lib.push_str(&format!(
-                    "\n{}initial_value: {},",
-                    INDENT.repeat(4),
-                    match &data.initial_value {
-                        Some(val) => format!("Some({})", val),
-                        None => "None".to_string(),
-                    }
+                    "\n{}{}: {}, {}, {};",
+                    INDENT,
+                    data_name,
+                    data.address,
+                    data.size,
+                    data.value_type.rust_type()
                 ));
-                lib.push_str(&format!(
-                    "\n{}range: {},",
-                    INDENT.repeat(4),
-                    match &data.range {
-                        Some(val) => format!("Some(({}, {}))", val.0, val.1),
-                        None => "None".to_string(),
-                    }
-                ));
-                lib.push_str(&format!("\n{}}}),", INDENT.repeat(3)))
             }
+            lib.push_str("\n});\n");
+        }
+    }
 
-            // Add error handling
+    lib.push_str("\n/// Every item in the control table of `model`, in order of address, eg to\n/// offer only the items a connected servo has");
+    lib.push_str(
+        &language
+            .apply("\npub {const_control_flow}fn supported(model: Model) -> &'static [DataName] {"),
+    );
+    lib.push_str(&format!("\n{}match model {{", INDENT));
+    for models in addresses.values() {
+        for model in models.keys() {
             lib.push_str(&format!(
-                "\n{}_ => Err(ControlTableError::NoMatchingAddress {{ model, name }}),",
-                INDENT.repeat(3)
+                "\n{}#[cfg(feature = \"{}\")]\n{}Model::{} => models::{}::SUPPORTED,",
+                INDENT.repeat(2),
+                model_features[model],
+                INDENT.repeat(2),
+                model,
+                model
             ));
-            lib.push_str(&format!("\n{}}},", INDENT.repeat(2)))
         }
     }
+    lib.push_str(&format!("\n{}}}\n}}\n", INDENT));
 
-    lib.push_str(&format!("\n{}}}", INDENT));
-    lib.push_str("\n}\n");
-    cargo.push('\n');
+    debug!(
+        data_names = data_names.len(),
+        bytes = lib.len(),
+        "Writing library"
+    );
+    // Files are only rewritten if they changed, so that a crate depending on
+    // the library isn't rebuilt when regenerating it changes nothing
+    let mut unchanged = Vec::new();
+    if !write_if_changed(target.source_path(), format_source(&lib)?)? {
+        unchanged.push(target.source_path());
+    }
+    if !discriminants.save(&target.discriminants_path())? {
+        unchanged.push(target.discriminants_path());
+    }
 
-    File::create("lib/src/lib.rs")?.write_all(lib.as_bytes())?;
-    File::create("lib/Cargo.toml")?.write_all(cargo.as_bytes())?;
+    let manifest = match existing_crate {
+        true => {
+            let manifest = fs::read_to_string(target.manifest_path())?;
+            // Leave the choice of default features to the crate if it makes one
+            let user_manifest = splice_features(&manifest, "")?;
+            if user_manifest
+                .lines()
+                .any(|line| line.trim_start().starts_with("default"))
+            {
+                features = features.split_once('\n').unwrap().1.to_string();
+            }
+            if !user_manifest.contains("thiserror") {
+                warn!(
+                    "{} doesn't depend on thiserror, which the generated code needs",
+                    target.manifest_path().display()
+                );
+            }
+
+            splice_features(&manifest, &features)?
+        }
+        false => {
+            if !write_if_changed(
+                target.readme_path(),
+                readme(target, &addresses, &model_docs, &model_features),
+            )? {
+                unchanged.push(target.readme_path());
+            }
+            if let Some(msrv) = target.msrv {
+                create_dir_all(target.path.join("tests"))?;
+                let written = write_if_changed(
+                    target.msrv_test_path(),
+                    format_source(&msrv_test(
+                        target,
+                        msrv,
+                        language,
+                        &addresses,
+                        &model_features,
+                        &discontinued,
+                    ))?,
+                )?;
+                if !written {
+                    unchanged.push(target.msrv_test_path());
+                }
+            }
+
+            format!(
+                "{}\n{}{}",
+                GENERATED_MANIFEST,
+                CARGO_PREAMBLE.replace(
+                    "{package}",
+                    &target.package.manifest_keys(&target.name, target.msrv)
+                ),
+                features
+            )
+        }
+    };
+    if !write_if_changed(target.manifest_path(), manifest)? {
+        unchanged.push(target.manifest_path());
+    }
+    debug!(?unchanged, "Left unchanged files of the library alone");
 
     Ok(())
 }
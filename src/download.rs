@@ -1,55 +1,761 @@
+use crate::locale::canonical_heading;
+use crate::serialize::ParseError;
 use anyhow::Result;
 use convert_case::{Case, Casing};
+use regex::Regex;
 use scraper::{ElementRef, Html, Selector};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use tracing::debug;
 
-fn parse_table(table: ElementRef) -> Result<Vec<Vec<String>>> {
+/// Alternative spellings of the headings used across different layouts of
+/// the E-Manual, such as the PRO series, and the canonical heading each is
+/// normalized to. Headings are compared ignoring case and whitespace, so
+/// "Size (Byte)" matches "Size(byte)".
+pub static HEADING_ALIASES: &[(&str, &[&str])] = &[
+    ("Address", &["Address"]),
+    ("Size(byte)", &["Size(byte)", "Size Byte", "Size"]),
+    ("Data Name", &["Data Name", "Name"]),
+    ("Description", &["Description"]),
+    ("Access", &["Access"]),
+    (
+        "Initial Value",
+        &["Initial Value", "Default Value", "Initial"],
+    ),
+    ("Range", &["Range"]),
+    ("Unit", &["Unit", "Units"]),
+    ("Modbus Address", &["Modbus Address", "Modbus"]),
+];
+
+/// The positions of the EEPROM and RAM control tables among the tables of
+/// most model pages
+pub static DEFAULT_TABLE_INDEXES: (usize, usize) = (1, 2);
+
+/// Models whose control tables are somewhere else on their page, by the URL
+/// slug of the page. A single control table (such as on PRO pages, which
+/// give the area of each item in a column) is at the same index twice.
+pub static TABLE_INDEXES: &[(&str, (usize, usize))] = &[("h54-200-s500-r", (1, 1))];
+
+/// The positions of a model's control tables on its page, from `overrides`
+/// (eg given with `--table-indexes`), then [`TABLE_INDEXES`], then
+/// [`DEFAULT_TABLE_INDEXES`]. Pages without control tables at the positions
+/// are still searched for them.
+pub fn table_indexes(slug: &str, overrides: &BTreeMap<String, (usize, usize)>) -> (usize, usize) {
+    overrides
+        .get(slug)
+        .copied()
+        .or_else(|| {
+            TABLE_INDEXES
+                .iter()
+                .find(|(model, _)| *model == slug)
+                .map(|(_, indexes)| *indexes)
+        })
+        .unwrap_or(DEFAULT_TABLE_INDEXES)
+}
+
+/// Normalize a table heading to its canonical (English) spelling, using
+/// `aliases` for alternative spellings. Headings with no alias are kept in
+/// title case.
+pub fn normalize_heading(heading: &str, aliases: &[(&str, &[&str])]) -> String {
+    // Spellings match regardless of whitespace and case
+    fn compact(text: &str) -> impl Iterator<Item = char> + '_ {
+        text.chars()
+            .filter(|c| !c.is_whitespace())
+            .flat_map(char::to_lowercase)
+    }
+
+    // Localized headings are translated so every locale parses the same
+    if let Some(canonical) = canonical_heading(heading) {
+        return canonical.to_string();
+    }
+
+    aliases
+        .iter()
+        .find(|(_, spellings)| {
+            spellings
+                .iter()
+                .any(|spelling| compact(spelling).eq(compact(heading)))
+        })
+        .map_or_else(
+            || heading.to_case(Case::Title),
+            |(canonical, _)| canonical.to_string(),
+        )
+}
+
+/// Read the rowspan or colspan attribute of a cell, defaulting to 1
+fn span(cell: &ElementRef, attribute: &str) -> usize {
+    cell.value()
+        .attr(attribute)
+        .and_then(|span| span.trim().parse().ok())
+        .filter(|span| *span > 0)
+        .unwrap_or(1)
+}
+
+/// Parse a table into its rows of cells, with the headings as the first row.
+/// Cells spanning several rows or columns (such as grouped Indirect Address
+/// rows) are repeated in every position they cover, so each row has a cell
+/// for every column.
+fn parse_table(table: ElementRef, aliases: &[(&str, &[&str])]) -> Result<Vec<Vec<String>>> {
+    let (headings, body) = parse_table_with(table, aliases, |_| ())?;
+    let mut parsed_table = vec![headings];
+    parsed_table.extend(
+        body.into_iter()
+            .map(|row| row.into_iter().map(|(text, _)| text).collect()),
+    );
+
+    Ok(parsed_table)
+}
+
+/// The text of each cell in a row, and whatever else was extracted from it
+type Row<T> = Vec<(String, T)>;
+
+/// The headings and rows of a table
+type Table<T> = (Vec<String>, Vec<Row<T>>);
+
+/// Parse a table as [`parse_table`] does, along with whatever `content`
+/// extracts from each cell of the body. Returns the normalized headings and
+/// the rows of the body.
+fn parse_table_with<T: Clone + Default>(
+    table: ElementRef,
+    aliases: &[(&str, &[&str])],
+    content: impl Fn(&ElementRef) -> T,
+) -> Result<(Vec<String>, Vec<Row<T>>)> {
     lazy_static! {
-        static ref ROW_SELECTOR: Selector = Selector::parse("tr>*").unwrap();
+        static ref ROW_SELECTOR: Selector = Selector::parse("tr").unwrap();
     };
 
-    let elements = table.select(&ROW_SELECTOR);
+    let mut headings: Row<T> = vec![];
+    let mut body: Vec<Row<T>> = vec![];
+    // For each column, the cell spanning into it from a previous row and how
+    // many more rows it covers
+    let mut spans: Vec<(usize, (String, T))> = vec![];
 
-    let (headings, body): (Vec<ElementRef>, Vec<ElementRef>) =
-        elements.partition(|x| x.value().name() == "th");
+    for row in table.select(&ROW_SELECTOR) {
+        let cells: Vec<ElementRef> = row
+            .children()
+            .filter_map(ElementRef::wrap)
+            .filter(|cell| matches!(cell.value().name(), "th" | "td"))
+            .collect();
+        let is_heading = cells.iter().all(|cell| cell.value().name() == "th");
 
-    let mut parsed_table: Vec<Vec<String>> = vec![vec![]];
-    for item in &headings {
-        let text = item.text().collect::<String>();
-        parsed_table[0].push(text.to_case(Case::Title));
-    }
+        let mut cells = cells.into_iter();
+        let mut grid_row: Row<T> = vec![];
+        loop {
+            let column = grid_row.len();
+            if let Some((remaining, text)) = spans.get_mut(column).filter(|(r, _)| *r > 0) {
+                *remaining -= 1;
+                grid_row.push(text.clone());
+                continue;
+            }
+
+            let cell = match cells.next() {
+                Some(cell) => cell,
+                // Spans from previous rows may still cover later columns
+                None if spans[column.min(spans.len())..].iter().any(|(r, _)| *r > 0) => {
+                    grid_row.push(Default::default());
+                    continue;
+                }
+                None => break,
+            };
 
-    for element in body {
-        let text = element.text().collect::<String>();
+            let text = (cell.text().collect::<String>(), content(&cell));
+            let rows = span(&cell, "rowspan");
+            for _ in 0..span(&cell, "colspan") {
+                let column = grid_row.len();
+                if spans.len() <= column {
+                    spans.resize(column + 1, Default::default());
+                }
+                spans[column] = (rows - 1, text.clone());
+                grid_row.push(text.clone());
+            }
+        }
 
-        if text.is_empty() {
+        if grid_row.is_empty() {
             continue;
         }
 
-        if parsed_table.last().unwrap().len() == headings.len() {
-            parsed_table.push(vec![]);
+        match is_heading && headings.is_empty() {
+            true => headings = grid_row,
+            false => body.push(grid_row),
         }
+    }
+
+    let headings = headings
+        .iter()
+        .map(|(text, _)| normalize_heading(text, aliases))
+        .collect();
+
+    Ok((headings, body))
+}
+
+/// Whether a parsed table looks like (part of) a control table
+fn is_control_table(table: &[Vec<String>]) -> bool {
+    let headings = &table[0];
+    headings.iter().any(|h| h == "Address") && headings.iter().any(|h| h == "Access")
+}
 
-        parsed_table.last_mut().unwrap().push(text);
+/// A table found on a page, summarised to show why the control tables
+/// weren't where they were expected
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct TableSummary {
+    /// The position of the table on the page
+    pub index: usize,
+    pub headings: Vec<String>,
+    /// The number of rows, not counting the headings
+    pub rows: usize,
+    pub control_table: bool,
+}
+
+impl fmt::Display for TableSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "table {}: {} rows{} {:?}",
+            self.index,
+            self.rows,
+            match self.control_table {
+                true => " (control table)",
+                false => "",
+            },
+            self.headings
+        )
     }
+}
 
-    Ok(parsed_table)
+/// Summarise every table on a page if the control tables aren't at the
+/// given indexes, such as on a page with a new layout. Returns `None` if
+/// they're where expected.
+pub fn diagnose_tables(
+    document: &Html,
+    indexes: (usize, usize),
+) -> Result<Option<Vec<TableSummary>>> {
+    lazy_static! {
+        static ref TABLE_SELECTOR: Selector = Selector::parse("table").unwrap();
+    }
+    let tables = document
+        .select(&TABLE_SELECTOR)
+        .map(|table| parse_table(table, HEADING_ALIASES))
+        .collect::<Result<Vec<Vec<Vec<String>>>>>()?;
+
+    if at_indexes(&tables, indexes) {
+        return Ok(None);
+    }
+
+    let summaries = tables
+        .iter()
+        .enumerate()
+        .map(|(index, table)| TableSummary {
+            index,
+            headings: table[0].clone(),
+            rows: table.len() - 1,
+            control_table: is_control_table(table),
+        })
+        .collect();
+
+    Ok(Some(summaries))
 }
 
-pub fn merge_tables(page: &str, indexes: (usize, usize)) -> Result<Vec<Vec<String>>> {
+/// The headings of a table on a page, as written and as the parser reads them
+#[derive(Clone, Debug, PartialEq)]
+pub struct TableHeadings {
+    /// The headings as written on the page, with whitespace collapsed
+    pub raw: Vec<String>,
+    /// The headings once normalized with [`HEADING_ALIASES`]
+    pub normalized: Vec<String>,
+    /// Whether the table looks like (part of) a control table
+    pub control_table: bool,
+}
+
+/// Find the headings of every table on a page, without parsing their bodies
+/// any further than spans need. Tables with no row of headings have none.
+pub fn table_headings(page: &str) -> Result<Vec<TableHeadings>> {
     let document = Html::parse_document(page);
 
     lazy_static! {
         static ref TABLE_SELECTOR: Selector = Selector::parse("table").unwrap();
+        static ref ROW_SELECTOR: Selector = Selector::parse("tr").unwrap();
+    }
+    let collapse = |text: String| text.split_whitespace().collect::<Vec<&str>>().join(" ");
+
+    document
+        .select(&TABLE_SELECTOR)
+        .map(|table| {
+            let raw = table
+                .select(&ROW_SELECTOR)
+                .map(|row| {
+                    row.children()
+                        .filter_map(ElementRef::wrap)
+                        .filter(|cell| matches!(cell.value().name(), "th" | "td"))
+                        .collect::<Vec<ElementRef>>()
+                })
+                .find(|cells| !cells.is_empty())
+                .filter(|cells| cells.iter().all(|cell| cell.value().name() == "th"))
+                .map_or(vec![], |cells| {
+                    cells
+                        .iter()
+                        .flat_map(|cell| {
+                            vec![collapse(cell.text().collect()); span(cell, "colspan")]
+                        })
+                        .collect()
+                });
+            let parsed = parse_table(table, HEADING_ALIASES)?;
+
+            Ok(TableHeadings {
+                raw,
+                normalized: parsed[0].clone(),
+                control_table: is_control_table(&parsed),
+            })
+        })
+        .collect()
+}
+
+/// Whether the tables at `indexes` are control tables
+fn at_indexes(tables: &[Vec<Vec<String>>], indexes: (usize, usize)) -> bool {
+    match (tables.get(indexes.0), tables.get(indexes.1)) {
+        (Some(eeprom), Some(ram)) => is_control_table(eeprom) && is_control_table(ram),
+        _ => false,
+    }
+}
+
+/// The positions of the control tables among the tables of a page. Most
+/// pages split the control table into EEPROM and RAM tables at the given
+/// indexes, but some (such as the PRO series) lay them out differently,
+/// either as a single table with an "Area" column or at other positions on
+/// the page. In that case, every table that looks like a control table is.
+fn control_table_positions(tables: &[Vec<Vec<String>>], indexes: (usize, usize)) -> Vec<usize> {
+    match at_indexes(tables, indexes) {
+        true if indexes.0 == indexes.1 => vec![indexes.0],
+        true => vec![indexes.0, indexes.1],
+        false => {
+            debug!(
+                ?indexes,
+                tables = tables.len(),
+                "Expected control tables not found, searching the page for them"
+            );
+            (0..tables.len())
+                .filter(|&position| is_control_table(&tables[position]))
+                .collect()
+        }
+    }
+}
+
+pub fn merge_tables(document: &Html, indexes: (usize, usize)) -> Result<Vec<Vec<String>>> {
+    merge_tables_with_aliases(document, indexes, HEADING_ALIASES)
+}
+
+/// Merge the control tables of a page as [`merge_tables`] does, normalizing
+/// headings with a custom alias table
+pub fn merge_tables_with_aliases(
+    document: &Html,
+    indexes: (usize, usize),
+    aliases: &[(&str, &[&str])],
+) -> Result<Vec<Vec<String>>> {
+    lazy_static! {
+        static ref TABLE_SELECTOR: Selector = Selector::parse("table").unwrap();
+    }
+    let tables = document
+        .select(&TABLE_SELECTOR)
+        .map(|table| parse_table(table, aliases))
+        .collect::<Result<Vec<Vec<Vec<String>>>>>()?;
+
+    let control_tables: Vec<&Vec<Vec<String>>> = control_table_positions(&tables, indexes)
+        .into_iter()
+        .map(|position| &tables[position])
+        .collect();
+
+    if control_tables.is_empty() {
+        return Err(ParseError::NoControlTable.into());
+    }
+    debug!(tables = control_tables.len(), "Merging control tables");
+
+    let mut merged = control_tables[0].clone();
+    for table in control_tables.iter().skip(1) {
+        // Make sure the headings are equal before combining
+        if merged[0] != table[0] {
+            return Err(ParseError::MismatchedHeadings {
+                first: merged[0].clone(),
+                other: table[0].clone(),
+            }
+            .into());
+        }
+        merged.extend(table.iter().skip(1).cloned());
+    }
+
+    Ok(merged)
+}
+
+/// A hyperlink in a table cell
+#[derive(Clone, Debug, PartialEq)]
+pub struct Link {
+    pub text: String,
+    pub href: String,
+}
+
+/// Parse the control tables of a page (chosen as [`merge_tables`] does) with
+/// whatever `content` extracts from each cell, returning the headings and
+/// rows of each
+fn control_tables_with<T: Clone + Default>(
+    document: &Html,
+    indexes: (usize, usize),
+    content: impl Fn(&ElementRef) -> T + Copy,
+) -> Result<Vec<Table<T>>> {
+    lazy_static! {
+        static ref TABLE_SELECTOR: Selector = Selector::parse("table").unwrap();
+    }
+    let mut tables = document
+        .select(&TABLE_SELECTOR)
+        .map(|table| parse_table_with(table, HEADING_ALIASES, content))
+        .collect::<Result<Vec<_>>>()?;
+
+    // Only the headings are needed to tell which tables are control tables
+    let text_tables: Vec<Vec<Vec<String>>> = tables
+        .iter()
+        .map(|(headings, _)| vec![headings.clone()])
+        .collect();
+    let positions = control_table_positions(&text_tables, indexes);
+
+    let mut position = 0;
+    tables.retain(|_| {
+        position += 1;
+        positions.contains(&(position - 1))
+    });
+
+    Ok(tables)
+}
+
+/// Find the links in the Description cell of each row of a page's control
+/// tables, which often point to the sections of related items. Returns the
+/// text of the row's Address cell and its links, for every row with any.
+pub fn description_links(
+    document: &Html,
+    indexes: (usize, usize),
+) -> Result<Vec<(String, Vec<Link>)>> {
+    lazy_static! {
+        static ref LINK_SELECTOR: Selector = Selector::parse("a[href]").unwrap();
+    }
+    let links = |cell: &ElementRef| -> Vec<Link> {
+        cell.select(&LINK_SELECTOR)
+            .map(|link| Link {
+                text: link.text().collect::<String>().trim().to_string(),
+                href: link.value().attr("href").unwrap_or_default().to_string(),
+            })
+            .collect()
+    };
+
+    let mut found = vec![];
+    for (headings, body) in control_tables_with(document, indexes, links)? {
+        let column = |heading: &str| headings.iter().position(|h| h == heading);
+        let (address, description) = match (column("Address"), column("Description")) {
+            (Some(address), Some(description)) => (address, description),
+            _ => continue,
+        };
+
+        for row in body {
+            if let (Some((address, _)), Some((_, links))) = (row.get(address), row.get(description))
+            {
+                if !links.is_empty() {
+                    found.push((address.trim().to_string(), links.clone()));
+                }
+            }
+        }
+    }
+
+    Ok(found)
+}
+
+/// The notes referenced by footnote markers in a row of a control table
+#[derive(Clone, Debug, PartialEq)]
+pub struct RowNotes {
+    /// The text of the row's Address cell
+    pub address: String,
+    /// The markers as they appear at the end of the row's cells, eg "*" or
+    /// "1" for a superscript
+    pub markers: Vec<String>,
+    /// The text of each footnote the markers refer to, without the marker
+    pub notes: Vec<String>,
+}
+
+/// Reduce a footnote marker to the part that identifies it, eg "(*)" -> "*"
+/// and "[1]" -> "1"
+fn marker_key(marker: &str) -> String {
+    marker
+        .chars()
+        .filter(|c| !c.is_whitespace() && !"()[]".contains(*c))
+        .collect()
+}
+
+/// Find the footnotes referenced by markers in the rows of a page's control
+/// tables, such as an asterisk after an item's name referring to a note
+/// below the table about firmware caveats. Markers are trailing asterisks
+/// (eg "*", "(*)"), reference marks (eg "※1") and superscripts, while
+/// footnotes are paragraphs or list items starting with a marker.
+pub fn footnotes(document: &Html, indexes: (usize, usize)) -> Result<Vec<RowNotes>> {
+    lazy_static! {
+        static ref SUP_SELECTOR: Selector = Selector::parse("sup").unwrap();
+        static ref NOTE_SELECTOR: Selector = Selector::parse("p, li").unwrap();
+        static ref CELL_MARKER_RE: Regex =
+            Regex::new(r"(\*+|\(\s*\*+\s*\)|※\s*[0-9]*)\s*$").unwrap();
+        // Numbers in brackets after an access level are markers too, eg "RW(1)"
+        static ref ACCESS_MARKER_RE: Regex =
+            Regex::new(r"^\s*R\s*(?:/?\s*W)?\s*(\(\s*[0-9]+\s*\)|\[\s*[0-9]+\s*\])\s*$").unwrap();
+        static ref FOOTNOTE_RE: Regex = Regex::new(
+            r"(?s)^\s*(\*+|\(\s*\*+\s*\)|※\s*[0-9]*|\[[0-9]+\]|\([0-9]+\)|[0-9]+\))\s*(\S.*)$"
+        )
+        .unwrap();
+    }
+    let cell_markers = |cell: &ElementRef| -> Vec<String> {
+        let text = cell.text().collect::<String>();
+        let superscripts = cell
+            .select(&SUP_SELECTOR)
+            .map(|sup| sup.text().collect::<String>().trim().to_string())
+            .filter(|sup| text.trim_end().ends_with(sup.as_str()));
+        CELL_MARKER_RE
+            .captures(&text)
+            .map(|captures| captures[1].to_string())
+            .into_iter()
+            .chain(superscripts)
+            .filter(|marker| !marker.is_empty())
+            .collect()
+    };
+
+    let mut notes: Vec<(String, String)> = vec![];
+    for note in document.select(&NOTE_SELECTOR) {
+        let text = note.text().collect::<String>();
+        // A superscript starting the note is its marker
+        let leading_sup = note
+            .children()
+            .filter_map(ElementRef::wrap)
+            .next()
+            .filter(|child| child.value().name() == "sup")
+            .map(|sup| sup.text().collect::<String>());
+        match leading_sup {
+            Some(sup) if text.trim_start().starts_with(sup.trim()) => {
+                let note_text = text.trim_start()[sup.trim().len()..].trim();
+                notes.push((marker_key(&sup), note_text.to_string()));
+            }
+            _ => {
+                if let Some(captures) = FOOTNOTE_RE.captures(&text) {
+                    notes.push((marker_key(&captures[1]), captures[2].trim().to_string()));
+                }
+            }
+        }
+    }
+
+    let mut found = vec![];
+    for (headings, body) in control_tables_with(document, indexes, cell_markers)? {
+        let address = match headings.iter().position(|h| h == "Address") {
+            Some(address) => address,
+            None => continue,
+        };
+        let access = headings.iter().position(|h| h == "Access");
+
+        for row in body {
+            let mut markers: Vec<String> = row
+                .iter()
+                .flat_map(|(_, markers)| markers.clone())
+                .collect();
+            let access_marker = access
+                .and_then(|access| row.get(access))
+                .and_then(|(text, _)| ACCESS_MARKER_RE.captures(text))
+                .map(|captures| captures[1].to_string());
+            if let Some(marker) = access_marker {
+                if !markers.iter().any(|m| marker_key(m) == marker_key(&marker)) {
+                    markers.push(marker);
+                }
+            }
+            if markers.is_empty() {
+                continue;
+            }
+
+            let mut row_notes: Vec<String> = vec![];
+            for marker in &markers {
+                let key = marker_key(marker);
+                for (_, text) in notes.iter().filter(|(note, _)| *note == key) {
+                    if !row_notes.contains(text) {
+                        row_notes.push(text.clone());
+                    }
+                }
+            }
+            if let Some((address, _)) = row.get(address) {
+                found.push(RowNotes {
+                    address: address.trim().to_string(),
+                    markers,
+                    notes: row_notes,
+                });
+            }
+        }
+    }
+
+    Ok(found)
+}
+
+/// Find the protocol versions (eg "2.0") listed in the specifications table
+/// at the top of a model page
+pub fn find_protocols(document: &Html) -> Vec<String> {
+    lazy_static! {
+        static ref TABLE_SELECTOR: Selector = Selector::parse("table").unwrap();
+        static ref PROTOCOL_RE: Regex = Regex::new(r"Protocol\s*([0-9]\.[0-9])").unwrap();
     }
-    let eeprom_table = document.select(&TABLE_SELECTOR).nth(indexes.0).unwrap();
-    let ram_table = document.select(&TABLE_SELECTOR).nth(indexes.1).unwrap();
 
-    let mut eeprom = parse_table(eeprom_table)?;
-    let ram = parse_table(ram_table)?;
+    let specifications = match document.select(&TABLE_SELECTOR).next() {
+        Some(table) => table.text().collect::<String>(),
+        None => return vec![],
+    };
+
+    let mut protocols: Vec<String> = PROTOCOL_RE
+        .captures_iter(&specifications)
+        .map(|captures| captures[1].to_string())
+        .collect();
+    protocols.sort();
+    protocols.dedup();
 
-    // Make sure the headings are equal before combining
-    assert_eq!(eeprom[0], ram[0]);
-    eeprom.extend(ram.into_iter().skip(1));
+    protocols
+}
+
+/// Find the tables whose row of headings satisfies `predicate`, along with
+/// the text of the heading above each, which names the item they describe
+fn tables_under_headings(
+    document: &Html,
+    predicate: impl Fn(&[String]) -> bool,
+) -> Result<Vec<(String, Vec<Vec<String>>)>> {
+    lazy_static! {
+        static ref HEADING_OR_TABLE_SELECTOR: Selector =
+            Selector::parse("h1, h2, h3, h4, h5, h6, table").unwrap();
+    }
+
+    let mut heading: Option<String> = None;
+    let mut tables = vec![];
+    for element in document.select(&HEADING_OR_TABLE_SELECTOR) {
+        if element.value().name() != "table" {
+            heading = Some(element.text().collect::<String>().trim().to_string());
+            continue;
+        }
+
+        let table = parse_table(element, HEADING_ALIASES)?;
+        if let (Some(heading), true) = (&heading, predicate(&table[0])) {
+            tables.push((heading.clone(), table));
+        }
+    }
+
+    Ok(tables)
+}
+
+/// Find the tables describing the bits of an item (those with a "Bit"
+/// column), along with the text of the heading above each, which names the
+/// item (eg "Shutdown(63)")
+pub fn bit_tables(document: &Html) -> Result<Vec<(String, Vec<Vec<String>>)>> {
+    tables_under_headings(document, |headings| headings.iter().any(|h| h == "Bit"))
+}
+
+/// Find the tables listing the values an item takes (those whose first
+/// column is "Value"), along with the text of the heading above each, which
+/// names the item (eg "Operating Mode(11)")
+pub fn value_tables(document: &Html) -> Result<Vec<(String, Vec<Vec<String>>)>> {
+    tables_under_headings(document, |headings| {
+        headings.first().is_some_and(|h| h == "Value") && headings.len() > 1
+    })
+}
+
+/// Find the tables of conversion equations for an item's gains (those with a
+/// "Conversion Equations" column), along with the text of the heading above
+/// each, which names the items (eg "Position PID Gain(80, 82, 84)")
+pub fn conversion_tables(document: &Html) -> Result<Vec<(String, Vec<Vec<String>>)>> {
+    tables_under_headings(document, |headings| {
+        headings
+            .iter()
+            .any(|h| h.starts_with("Conversion Equation"))
+    })
+}
+
+/// Format a parsed table as CSV, quoting any cells that need it
+pub fn table_to_csv(table: &[Vec<String>]) -> String {
+    let mut csv = String::new();
+    for row in table {
+        let cells: Vec<String> = row
+            .iter()
+            .map(|cell| match cell.contains(&[',', '"', '\n', '\r'][..]) {
+                true => format!("\"{}\"", cell.replace('"', "\"\"")),
+                false => cell.clone(),
+            })
+            .collect();
+        csv.push_str(&cells.join(","));
+        csv.push('\n');
+    }
+
+    csv
+}
+
+/// A part of the page tying the items it names to the firmware version that
+/// added them
+#[derive(Clone, Debug, PartialEq)]
+pub enum FirmwareNote {
+    /// A paragraph or list item, eg "Bus Watchdog(98) is available from
+    /// firmware v38", which names items along with their address
+    Prose { text: String, version: u8 },
+    /// The items listed in a row of a table of the features each firmware
+    /// version added, one per line or separated by commas
+    Listing { items: Vec<String>, version: u8 },
+}
+
+/// Find notes tying part of the page to a minimum firmware version, such as
+/// "Bus Watchdog(98) is available from firmware v38", or the rows of a table
+/// listing the features added in each firmware version
+pub fn firmware_notes(document: &Html) -> Vec<FirmwareNote> {
+    lazy_static! {
+        static ref NOTE_SELECTOR: Selector = Selector::parse("p, li").unwrap();
+        static ref TABLE_SELECTOR: Selector = Selector::parse("table").unwrap();
+        static ref ROW_SELECTOR: Selector = Selector::parse("tr").unwrap();
+        static ref CELL_SELECTOR: Selector = Selector::parse("th, td").unwrap();
+        static ref FIRMWARE_RE: Regex =
+            Regex::new(r"(?i)(?:firmware|F/W)\s*(?:version\s*)?v?\s*([0-9]{1,3})\b").unwrap();
+        static ref VERSION_RE: Regex = Regex::new(r"^(?i)v?\s*([0-9]{1,3})\b").unwrap();
+    }
+
+    let mut notes: Vec<FirmwareNote> = vec![];
+    for note in document.select(&NOTE_SELECTOR) {
+        let text = note.text().collect::<String>();
+        for captures in FIRMWARE_RE.captures_iter(&text) {
+            if let Ok(version) = captures[1].parse::<u8>() {
+                notes.push(FirmwareNote::Prose {
+                    text: text.clone(),
+                    version,
+                });
+            }
+        }
+    }
+
+    // Tables of firmware features start each row with the version
+    for table in document.select(&TABLE_SELECTOR) {
+        let mut rows = table.select(&ROW_SELECTOR);
+        let headings = match rows.next() {
+            Some(row) => row.text().collect::<String>(),
+            None => continue,
+        };
+        if !headings.to_lowercase().contains("firmware") {
+            continue;
+        }
+
+        for row in rows {
+            let cells: Vec<String> = row
+                .select(&CELL_SELECTOR)
+                .map(|cell| cell.text().collect::<String>().trim().to_string())
+                .collect();
+            let version = cells
+                .first()
+                .and_then(|cell| VERSION_RE.captures(cell))
+                .and_then(|captures| captures[1].parse::<u8>().ok());
+            if let Some(version) = version {
+                let items = cells[1..]
+                    .iter()
+                    .flat_map(|cell| cell.split([',', ';', '\n']))
+                    .map(|item| item.trim().to_string())
+                    .filter(|item| !item.is_empty())
+                    .collect();
+                notes.push(FirmwareNote::Listing { items, version });
+            }
+        }
+    }
 
-    Ok(eeprom)
+    notes
 }
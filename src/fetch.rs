@@ -0,0 +1,1157 @@
+use crate::cache::{PageCache, Refreshed};
+use crate::download::diagnose_tables;
+use crate::locale::{is_series_title, strip_discontinued};
+use crate::progress::{emit, ProgressEvent, ProgressSender};
+use crate::report::{ErrorCategory, ModelReport, ModelStatus, NetworkMetrics};
+use crate::robots::{Disallowed, Robots};
+use crate::source::PageSource;
+use crate::{
+    output_series, relative_object_path, split_url, write_intermediates, Actuator, ActuatorId,
+    ParseOptions,
+};
+use anyhow::{anyhow, bail, Context, Result};
+use futures_util::stream::{FuturesUnordered, StreamExt};
+use indicatif::{ProgressBar, ProgressStyle};
+use reqwest::header::{HeaderName, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
+use reqwest::{redirect::Policy, Client, ClientBuilder, StatusCode, Url};
+use scraper::Html;
+use serde::{Deserialize, Serialize};
+use serde_yaml::Value;
+use std::collections::hash_map::RandomState;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::future;
+use std::hash::BuildHasher;
+use std::str::FromStr;
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, watch, Mutex, Semaphore};
+use tokio::task;
+use tokio_stream as stream;
+use tracing::{debug, info_span, warn, Instrument};
+
+/// A Dynamixel listed in the E-Manual's navigation, before it is downloaded
+#[derive(Clone, Debug)]
+pub struct ActuatorIndex {
+    pub url: String,
+    pub name: String,
+    /// The title of the navigation entry the Dynamixel is listed under
+    pub series: String,
+    /// Whether the navigation marks the model as discontinued
+    pub discontinued: bool,
+    /// The navigation section the page was found in, for hardware other
+    /// than the Dynamixels opted into with [`Filters::sections`]
+    pub section: Option<String>,
+}
+
+impl ActuatorIndex {
+    /// The series directory and raw name the page's files are written
+    /// under, which is under [`HARDWARE_DIR`] for hardware from a section
+    ///
+    /// [`HARDWARE_DIR`]: crate::HARDWARE_DIR
+    pub fn output_name(&self) -> (String, String) {
+        let (series, raw_name) = split_url(&self.url);
+        (output_series(&series, self.section.as_deref()), raw_name)
+    }
+}
+
+/// Which Dynamixels to scrape. If both lists are empty, every Dynamixel is.
+#[derive(Clone, Default, Debug)]
+pub struct Filters {
+    /// The URL slugs of individual models, eg "xm430-w210"
+    pub dynamixels: Vec<String>,
+    /// Either the first word of a series' navigation title (eg "PRO") or
+    /// its URL segment (eg "pro"), case-insensitively
+    pub series: Vec<String>,
+    /// Navigation sections listing hardware other than the Dynamixels (eg
+    /// "Parts"), by title or dot-separated path, to scrape the pages of too.
+    /// The filters above apply to their pages as well.
+    pub sections: Vec<String>,
+}
+
+impl Filters {
+    /// Whether every Dynamixel is selected
+    pub fn is_empty(&self) -> bool {
+        self.dynamixels.is_empty() && self.series.is_empty()
+    }
+
+    fn matches(&self, dxl: &ActuatorIndex) -> bool {
+        if self.is_empty() {
+            return true;
+        }
+
+        let (url_series, raw_name) = split_url(&dxl.url);
+        if self.dynamixels.contains(&raw_name) {
+            return true;
+        }
+
+        let title_series = dxl.series.split(' ').next().unwrap();
+        self.series
+            .iter()
+            .any(|s| s.eq_ignore_ascii_case(title_series) || s.eq_ignore_ascii_case(&url_series))
+    }
+}
+
+/// A few of the selected Dynamixels to scrape instead of all of them, for
+/// quick end to end runs while working on the parser
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Sample {
+    /// The first this many, in the order of the navigation, as `first:N`
+    First(usize),
+    /// This many picked at random, differently each run, as `random:N`
+    Random(usize),
+}
+
+impl FromStr for Sample {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (kind, count) = s
+            .split_once(':')
+            .ok_or_else(|| anyhow!("Expected first:N or random:N, not {:?}", s))?;
+        let count = count
+            .parse()
+            .with_context(|| format!("{:?} isn't a number of models", count))?;
+        match kind {
+            "first" => Ok(Sample::First(count)),
+            "random" => Ok(Sample::Random(count)),
+            _ => bail!("Unknown sample {:?}, expected first:N or random:N", kind),
+        }
+    }
+}
+
+impl Sample {
+    /// Pick the sample from the selected Dynamixels, keeping their order
+    pub fn apply(&self, mut indexes: Vec<ActuatorIndex>) -> Vec<ActuatorIndex> {
+        match *self {
+            Sample::First(count) => indexes.truncate(count),
+            Sample::Random(count) => {
+                // Hashing with random keys shuffles the pages without a
+                // random number generator
+                let state = RandomState::new();
+                let mut order: Vec<(u64, usize)> = indexes
+                    .iter()
+                    .enumerate()
+                    .map(|(i, dxl)| (state.hash_one(&dxl.url), i))
+                    .collect();
+                order.sort_unstable();
+                let chosen: BTreeSet<usize> =
+                    order.into_iter().take(count).map(|(_, i)| i).collect();
+                indexes = indexes
+                    .into_iter()
+                    .enumerate()
+                    .filter(|(i, _)| chosen.contains(i))
+                    .map(|(_, dxl)| dxl)
+                    .collect();
+            }
+        }
+
+        indexes
+    }
+}
+
+/// A page given by URL (or as the path of a saved page) rather than found in
+/// the navigation, such as a model Robotis has published before listing it.
+/// It's named after its URL, eg "XM430-W210" in the "X" series.
+pub fn extra_page(location: &str) -> Result<ActuatorIndex> {
+    let url = match location.contains("://") {
+        true => location.to_string(),
+        false => {
+            let path = std::fs::canonicalize(location)
+                .with_context(|| format!("Failed to find the page {}", location))?;
+            format!("file://{}", path.display())
+        }
+    };
+    let (series, raw_name) = split_url(&url);
+
+    Ok(ActuatorIndex {
+        name: raw_name.to_uppercase(),
+        series: series.to_uppercase(),
+        url,
+        discontinued: false,
+        section: None,
+    })
+}
+
+/// Fail if two different pages would be written to the same files, naming
+/// both. Paths are compared ignoring case, as the filesystem may.
+pub fn check_output_paths(indexes: &[ActuatorIndex]) -> Result<()> {
+    let mut paths: BTreeMap<String, &str> = BTreeMap::new();
+    for dxl in indexes {
+        let (series, raw_name) = dxl.output_name();
+        let path = relative_object_path(&series, &raw_name);
+        match paths.get(&path.to_lowercase()) {
+            Some(other) if *other != dxl.url => bail!(
+                "Both {} and {} would be written to objects/{}",
+                other,
+                dxl.url,
+                path
+            ),
+            Some(_) => (),
+            None => {
+                paths.insert(path.to_lowercase(), &dxl.url);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// The dot-separated path of the navigation entries listing each series,
+/// where numbers index into lists
+pub static DEFAULT_NAVIGATION_PATH: &str = "main.0.children";
+
+/// Follow a dot-separated path of keys (or list indexes) into the YAML
+fn resolve_path<'a>(root: &'a Value, path: &str) -> Result<&'a Value> {
+    let mut node = root;
+    for (depth, key) in path.split('.').enumerate() {
+        let next = match (node, key.parse::<usize>()) {
+            (Value::Sequence(items), Ok(index)) => items.get(index),
+            (Value::Mapping(_), _) => node.get(key),
+            _ => None,
+        };
+
+        node = match next {
+            Some(next) => next,
+            None => {
+                let found: Vec<String> = match node {
+                    Value::Mapping(map) => map
+                        .iter()
+                        .filter_map(|(key, _)| key.as_str().map(String::from))
+                        .collect(),
+                    Value::Sequence(items) => vec![format!("a list of {} items", items.len())],
+                    _ => vec![],
+                };
+                bail!(
+                    "Navigation has no {:?} at {:?} (found {:?})",
+                    key,
+                    path.split('.').take(depth).collect::<Vec<&str>>().join("."),
+                    found
+                );
+            }
+        };
+    }
+
+    Ok(node)
+}
+
+/// Collect every entry under `node` whose title names a series, without
+/// looking inside the series themselves
+fn find_series<'a>(node: &'a Value, series: &mut Vec<&'a Value>) {
+    match node {
+        Value::Sequence(items) => items.iter().for_each(|item| find_series(item, series)),
+        Value::Mapping(map) => {
+            if node["title"].as_str().is_some_and(is_series_title) {
+                series.push(node);
+                return;
+            }
+            map.iter().for_each(|(_, value)| find_series(value, series));
+        }
+        _ => (),
+    }
+}
+
+/// Find the navigation section with the given title (ignoring case and
+/// emphasis) or at the given dot-separated path, returning it and its title
+fn find_section<'a>(navigation: &'a Value, section: &str) -> Result<(&'a Value, String)> {
+    fn by_title<'a>(node: &'a Value, title: &str) -> Option<&'a Value> {
+        match node {
+            Value::Sequence(items) => items.iter().find_map(|item| by_title(item, title)),
+            Value::Mapping(map) => {
+                let matches = node["title"]
+                    .as_str()
+                    .is_some_and(|text| text.replace('*', "").trim().eq_ignore_ascii_case(title));
+                match matches {
+                    true => Some(node),
+                    false => map.iter().find_map(|(_, value)| by_title(value, title)),
+                }
+            }
+            _ => None,
+        }
+    }
+
+    let node = match by_title(navigation, section.trim()) {
+        Some(node) => node,
+        None if section.contains('.') => resolve_path(navigation, section)?,
+        None => {
+            let mut titles = vec![];
+            find_titles(navigation, &mut titles);
+            titles.truncate(20);
+            bail!(
+                "No section {:?} in the navigation (found titles {:?})",
+                section,
+                titles
+            );
+        }
+    };
+    let title = node["title"]
+        .as_str()
+        .map_or_else(|| section.to_string(), |title| title.replace('*', ""));
+
+    Ok((node, title.trim().to_string()))
+}
+
+/// Collect every page under `node` as its title, URL and the title of the
+/// group listing it, which is `group` for pages listed directly
+fn find_pages<'a>(node: &'a Value, group: &str, pages: &mut Vec<(&'a str, &'a str, String)>) {
+    match node {
+        Value::Sequence(items) => items.iter().for_each(|item| find_pages(item, group, pages)),
+        Value::Mapping(_) => {
+            let title = node["title"].as_str();
+            if let (Some(title), Some(url)) = (title, node["url"].as_str()) {
+                pages.push((title, url, group.to_string()));
+            }
+            if let Some(children) = node.get("children") {
+                let group = title.map_or(group.to_string(), |title| {
+                    title.replace('*', "").trim().to_string()
+                });
+                find_pages(children, &group, pages);
+            }
+        }
+        _ => (),
+    }
+}
+
+/// Collect every title under `node`, to explain what the navigation holds
+/// when no series can be found
+fn find_titles(node: &Value, titles: &mut Vec<String>) {
+    match node {
+        Value::Sequence(items) => items.iter().for_each(|item| find_titles(item, titles)),
+        Value::Mapping(map) => {
+            if let Some(title) = node["title"].as_str() {
+                titles.push(title.to_string());
+            }
+            map.iter().for_each(|(_, value)| find_titles(value, titles));
+        }
+        _ => (),
+    }
+}
+
+/// Find every Dynamixel in the E-Manual's navigation YAML that passes the
+/// filters. Model URLs in the navigation are relative to `base_url`.
+///
+/// Series are searched for under `navigation_path` if one is given. If not,
+/// they're searched for under [`DEFAULT_NAVIGATION_PATH`], or anywhere in
+/// the navigation if it has been reorganised.
+pub fn build_index(
+    yaml: &str,
+    base_url: &str,
+    navigation_path: Option<&str>,
+    filters: &Filters,
+) -> Result<Vec<ActuatorIndex>> {
+    let navigation: Value = serde_yaml::from_str(yaml)?;
+    let root = match navigation_path {
+        Some(path) => resolve_path(&navigation, path)?,
+        None => resolve_path(&navigation, DEFAULT_NAVIGATION_PATH).unwrap_or_else(|e| {
+            warn!("{:#}, searching the whole navigation for Dynamixels", e);
+            &navigation
+        }),
+    };
+
+    let mut series = vec![];
+    find_series(root, &mut series);
+    if series.is_empty() {
+        let mut titles = vec![];
+        find_titles(root, &mut titles);
+        titles.truncate(20);
+        bail!(
+            "No Dynamixel series found in the navigation{} (found titles {:?})",
+            navigation_path.map_or(String::new(), |path| format!(" under {:?}", path)),
+            titles
+        );
+    }
+
+    let mut indexes: Vec<ActuatorIndex> = Vec::new();
+
+    for element in series {
+        let title: String = element["title"]
+            .as_str()
+            .unwrap()
+            .chars()
+            .filter(|x| x != &'*')
+            .collect();
+        let children = match element["children"].as_sequence() {
+            Some(children) => children,
+            None => {
+                warn!(series = %title, "Series has no models listed in the navigation");
+                continue;
+            }
+        };
+
+        for child in children {
+            let (url, name) = match (child["url"].as_str(), child["title"].as_str()) {
+                (Some(url), Some(name)) => (url, name),
+                _ => {
+                    warn!(series = %title, entry = ?child, "Skipping navigation entry without a URL and title");
+                    continue;
+                }
+            };
+            let (name, discontinued) = strip_discontinued(name);
+            let dxl = ActuatorIndex {
+                url: format!("{}{}", base_url, url),
+                name: name.to_string(),
+                series: title.clone(),
+                discontinued,
+                section: None,
+            };
+            debug!(model = %dxl.name, url = %dxl.url, series = %title, "Found Dynamixel");
+
+            if filters.matches(&dxl) {
+                indexes.push(dxl);
+            }
+        }
+    }
+
+    for section in &filters.sections {
+        let (node, section) = find_section(&navigation, section)?;
+        let mut pages = vec![];
+        find_pages(node, &section, &mut pages);
+        if pages.is_empty() {
+            warn!(%section, "Section has no pages listed in the navigation");
+        }
+
+        for (name, url, group) in pages {
+            let url = format!("{}{}", base_url, url);
+            // Sections may overlap the Dynamixels or each other
+            if indexes.iter().any(|dxl| dxl.url == url) {
+                continue;
+            }
+            let (name, discontinued) = strip_discontinued(name);
+            let page = ActuatorIndex {
+                url,
+                name: name.to_string(),
+                series: group,
+                discontinued,
+                section: Some(section.clone()),
+            };
+            debug!(name = %page.name, url = %page.url, %section, "Found hardware");
+
+            if filters.matches(&page) {
+                indexes.push(page);
+            }
+        }
+    }
+
+    Ok(indexes)
+}
+
+/// The number of requests allowed in flight at once by default
+pub static DEFAULT_MAX_IN_FLIGHT: usize = 20;
+
+/// How long downloading or parsing a page may take by default
+pub static DEFAULT_PAGE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The User-Agent requests are sent with by default, so the E-Manual's
+/// operators can tell who is scraping it and how to get in touch
+pub static USER_AGENT: &str = concat!(
+    "dynamixel-scraper/",
+    env!("CARGO_PKG_VERSION"),
+    " (+https://github.com/kiros-rs/dynamixel-scraper)"
+);
+
+/// Start building a client that keeps connections to each host open between
+/// requests, negotiates HTTP/2 where the server supports it and accepts
+/// gzipped responses
+pub fn client_builder() -> ClientBuilder {
+    Client::builder()
+        .user_agent(USER_AGENT)
+        .gzip(true)
+        .pool_max_idle_per_host(DEFAULT_MAX_IN_FLIGHT)
+        .pool_idle_timeout(Duration::from_secs(90))
+        .tcp_keepalive(Duration::from_secs(60))
+        .http2_adaptive_window(true)
+        .redirect(Policy::limited(10))
+}
+
+/// A page that no longer exists, such as a retired model's, as opposed to
+/// one that couldn't be downloaded
+#[derive(Debug)]
+pub struct PageUnavailable {
+    pub url: String,
+    pub status: StatusCode,
+}
+
+impl fmt::Display for PageUnavailable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} is unavailable ({})", self.url, self.status)
+    }
+}
+
+impl std::error::Error for PageUnavailable {}
+
+/// A client shared by every request, with a single semaphore limiting how
+/// many are in flight at once
+#[derive(Clone, Debug)]
+pub struct HttpClient {
+    client: Client,
+    permits: Arc<Semaphore>,
+    max_in_flight: usize,
+    /// The robots.txt rules of the site being scraped, and its origin
+    robots: Option<Arc<(Url, Robots)>>,
+    /// The earliest a request may be sent to the site being scraped, to
+    /// space them by the crawl delay its robots.txt asks for
+    next_request: Arc<Mutex<Instant>>,
+    metrics: NetworkMetrics,
+    /// Where pages are read from instead of the network, if anywhere
+    source: Option<Arc<dyn PageSource>>,
+}
+
+impl HttpClient {
+    pub fn new(client: Client, max_in_flight: usize) -> HttpClient {
+        HttpClient {
+            client,
+            permits: Arc::new(Semaphore::new(max_in_flight)),
+            max_in_flight,
+            robots: None,
+            next_request: Arc::new(Mutex::new(Instant::now())),
+            metrics: NetworkMetrics::default(),
+            source: None,
+        }
+    }
+
+    /// Read every page from `source` instead of the network, eg a mirror of
+    /// the E-Manual on disk
+    pub fn with_source(mut self, source: Arc<dyn PageSource>) -> HttpClient {
+        self.source = Some(source);
+        self
+    }
+
+    /// Fetch the robots.txt of the site `base_url` is on, and refuse to
+    /// request the pages on it that it disallows for `user_agent` from then
+    /// on, spacing requests by any crawl delay it asks for. A site without a
+    /// robots.txt allows everything.
+    pub async fn respect_robots(&mut self, base_url: &str, user_agent: &str) -> Result<()> {
+        let origin = Url::parse(base_url)?.join("/")?;
+        if !origin.scheme().starts_with("http") || self.source.is_some() {
+            return Ok(());
+        }
+
+        let url = origin.join("robots.txt")?;
+        let text = match self.get_text(url.as_str()).await {
+            Ok(text) => text,
+            Err(e) if e.chain().any(|cause| cause.is::<PageUnavailable>()) => {
+                debug!(%url, "No robots.txt, every page is allowed");
+                return Ok(());
+            }
+            Err(e) => return Err(e.context(format!("Failed to fetch {}", url))),
+        };
+
+        // Crawlers are named by the product token at the start of their
+        // User-Agent, eg "dynamixel-scraper"
+        let agent = user_agent.split(['/', ' ']).next().unwrap_or_default();
+        let robots = Robots::parse(&text, agent);
+        if let Some(delay) = robots.crawl_delay {
+            debug!(%url, ?delay, "Spacing requests by the crawl delay robots.txt asks for");
+        }
+        self.robots = Some(Arc::new((origin, robots)));
+
+        Ok(())
+    }
+
+    pub fn max_in_flight(&self) -> usize {
+        self.max_in_flight
+    }
+
+    /// The requests made by this client and its clones so far
+    pub fn metrics(&self) -> &NetworkMetrics {
+        &self.metrics
+    }
+
+    /// Download the text at `url`, once fewer than `max_in_flight` other
+    /// requests are being made. Redirects are followed, and pages that are
+    /// missing or gone are a [`PageUnavailable`] error, and pages robots.txt
+    /// disallows are a [`Disallowed`] error once [`respect_robots`] has been
+    /// called. `file://` URLs are read from disk, with directories read from
+    /// their index.html as a web server would, eg for a local build of the
+    /// E-Manual. A client given a [`PageSource`] reads every other URL from
+    /// it instead.
+    ///
+    /// [`respect_robots`]: HttpClient::respect_robots
+    pub async fn get_text(&self, url: &str) -> Result<String> {
+        self.get_text_within(url, None).await
+    }
+
+    /// Download the text at `url` as [`get_text`] does, failing if it takes
+    /// longer than `timeout`. Time spent waiting for a permit or for the
+    /// crawl delay isn't counted, as nothing has been sent yet.
+    ///
+    /// [`get_text`]: HttpClient::get_text
+    pub async fn get_text_within(&self, url: &str, timeout: Option<Duration>) -> Result<String> {
+        if url.starts_with("file://") || self.source.is_some() {
+            let read = self.read_locally(url);
+            return match timeout {
+                Some(timeout) => tokio::time::timeout(timeout, read)
+                    .await
+                    .unwrap_or_else(|_| Err(anyhow!("Timed out after {:?}", timeout))),
+                None => read.await,
+            };
+        }
+
+        match self.request(url, &Validators::default(), timeout).await? {
+            Revalidation::Changed { text, .. } => Ok(text),
+            Revalidation::Unchanged => {
+                bail!("The server said {} was unchanged without being asked", url)
+            }
+        }
+    }
+
+    /// Read a `file://` URL from disk, or any other URL from the client's
+    /// [`PageSource`]
+    async fn read_locally(&self, url: &str) -> Result<String> {
+        if let Some(path) = url.strip_prefix("file://") {
+            let path = match path.ends_with('/') {
+                true => format!("{}index.html", path),
+                false => path.to_string(),
+            };
+            return Ok(tokio::fs::read_to_string(path).await?);
+        }
+        match &self.source {
+            Some(source) => source.get_text(url).await,
+            None => bail!("{} isn't a local page", url),
+        }
+    }
+
+    /// Download the page at `url` if it has changed since it was sent with
+    /// `validators`, by asking the server with a conditional GET. Pages sent
+    /// without validators are downloaded again in full, as are pages read
+    /// from disk or from a [`PageSource`], which are always "changed".
+    pub async fn revalidate(&self, url: &str, validators: &Validators) -> Result<Revalidation> {
+        if url.starts_with("file://") || self.source.is_some() {
+            return Ok(Revalidation::Changed {
+                text: self.get_text(url).await?,
+                validators: Validators::default(),
+            });
+        }
+
+        self.request(url, validators, None).await
+    }
+
+    /// Request the page at `url` from the network, conditionally if there
+    /// are any `validators`, failing if the request takes longer than
+    /// `timeout` once it's sent
+    async fn request(
+        &self,
+        url: &str,
+        validators: &Validators,
+        timeout: Option<Duration>,
+    ) -> Result<Revalidation> {
+        let mut crawl_delay = None;
+        if let Some((origin, robots)) = self.robots.as_deref() {
+            let parsed = Url::parse(url)?;
+            let path = match parsed.query() {
+                Some(query) => format!("{}?{}", parsed.path(), query),
+                None => parsed.path().to_string(),
+            };
+            if parsed.origin() == origin.origin() {
+                if !robots.allows(&path) {
+                    return Err(Disallowed {
+                        url: url.to_string(),
+                    }
+                    .into());
+                }
+                crawl_delay = robots.crawl_delay;
+            }
+        }
+
+        let _permit = self.permits.acquire().await?;
+        // Requests holding a permit take turns to be sent, at most one per
+        // crawl delay
+        if let Some(delay) = crawl_delay {
+            let mut next_request = self.next_request.lock().await;
+            tokio::time::sleep_until((*next_request).into()).await;
+            *next_request = Instant::now().checked_add(delay).unwrap_or(*next_request);
+        }
+        // Latency is measured from when the request is sent, so time spent
+        // waiting for a permit isn't counted against the host
+        let sent = Instant::now();
+        let revalidation = match timeout {
+            Some(timeout) => tokio::time::timeout(timeout, self.send(url, validators))
+                .await
+                .unwrap_or_else(|_| Err(anyhow!("Timed out after {:?}", timeout))),
+            None => self.send(url, validators).await,
+        };
+        let host = match Url::parse(url) {
+            Ok(parsed) => match (parsed.host_str(), parsed.port()) {
+                (Some(host), Some(port)) => format!("{}:{}", host, port),
+                (host, _) => host.unwrap_or_default().to_string(),
+            },
+            Err(_) => String::new(),
+        };
+        let bytes = match &revalidation {
+            Ok(Revalidation::Changed { text, .. }) => text.len(),
+            _ => 0,
+        };
+        self.metrics.record_request(&host, sent.elapsed(), bytes);
+
+        revalidation
+    }
+
+    async fn send(&self, url: &str, validators: &Validators) -> Result<Revalidation> {
+        let mut request = self.client.get(url);
+        if let Some(etag) = &validators.etag {
+            request = request.header(IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = &validators.last_modified {
+            request = request.header(IF_MODIFIED_SINCE, last_modified);
+        }
+        let response = request.send().await?;
+        if response.url().as_str() != url {
+            debug!(from = %url, to = %response.url(), "Followed redirect");
+        }
+
+        match response.status() {
+            StatusCode::NOT_MODIFIED => Ok(Revalidation::Unchanged),
+            status @ (StatusCode::NOT_FOUND | StatusCode::GONE) => Err(PageUnavailable {
+                url: response.url().to_string(),
+                status,
+            }
+            .into()),
+            _ => {
+                let response = response.error_for_status()?;
+                let header = |name: HeaderName| {
+                    response
+                        .headers()
+                        .get(name)
+                        .and_then(|value| value.to_str().ok())
+                        .map(String::from)
+                };
+                let validators = Validators {
+                    etag: header(ETAG),
+                    last_modified: header(LAST_MODIFIED),
+                };
+
+                Ok(Revalidation::Changed {
+                    text: response.text().await?,
+                    validators,
+                })
+            }
+        }
+    }
+}
+
+/// What a server sent with a page that it can be asked about later to find
+/// out whether the page has changed since
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Validators {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+/// Whether a page has changed since it was downloaded with some validators
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Revalidation {
+    Unchanged,
+    /// The page as it is now, and its new validators
+    Changed {
+        text: String,
+        validators: Validators,
+    },
+}
+
+/// Settings for downloading and parsing many Dynamixels at once
+#[derive(Clone, Debug)]
+pub struct FetchOptions {
+    /// The number of downloaded pages allowed to wait for a parser, and the
+    /// number parsed at once, before downloads pause
+    pub queue_size: usize,
+    /// Save the fetched HTML and merged control table under artifacts/
+    pub keep_intermediates: bool,
+    /// Stop at the first Dynamixel that fails to download or parse, rather
+    /// than scraping the rest
+    pub fail_fast: bool,
+    /// List every table on pages whose control tables aren't where they're
+    /// expected, in the log and the report
+    pub debug_tables: bool,
+    /// Stops the scrape once it's sent true, eg on Ctrl-C. Downloads in
+    /// flight are abandoned, but models already being parsed are finished.
+    pub cancel: Option<watch::Receiver<bool>>,
+    /// Where the control tables are on the pages of models whose tables
+    /// aren't where [`crate::download::TABLE_INDEXES`] expects, by URL slug
+    pub table_indexes: BTreeMap<String, (usize, usize)>,
+    /// Keep the text of each item's cells in the RON objects, alongside the
+    /// values parsed from them
+    pub with_raw: bool,
+    /// How long each of downloading and parsing a page may take before the
+    /// model is given up on as failed, so one hung connection or pathological
+    /// page can't stall a run that's otherwise finished. Downloads are timed
+    /// from when they're sent, not while they wait for their turn.
+    pub page_timeout: Duration,
+    /// Where to send an event for each step of the scrape, in place of the
+    /// terminal's spinners, which are hidden when this is set
+    pub progress: Option<ProgressSender>,
+}
+
+impl Default for FetchOptions {
+    fn default() -> FetchOptions {
+        FetchOptions {
+            queue_size: 8,
+            keep_intermediates: false,
+            fail_fast: false,
+            debug_tables: false,
+            cancel: None,
+            page_timeout: DEFAULT_PAGE_TIMEOUT,
+            table_indexes: BTreeMap::new(),
+            with_raw: false,
+            progress: None,
+        }
+    }
+}
+
+/// Resolves once the scrape is cancelled, if it can be
+async fn cancelled(cancel: &mut Option<watch::Receiver<bool>>) {
+    if let Some(cancel) = cancel {
+        while !*cancel.borrow() {
+            if cancel.changed().await.is_err() {
+                break;
+            }
+        }
+        if *cancel.borrow() {
+            return;
+        }
+    }
+
+    future::pending().await
+}
+
+/// Download the page of every Dynamixel, without parsing them. Given a
+/// `cache`, pages saved in it by an earlier run (with `keep_intermediates`)
+/// are read instead of downloaded, once those older than its TTL have been
+/// refreshed. Returns each Dynamixel with its page, in no particular order.
+pub async fn fetch_pages(
+    client: &HttpClient,
+    indexes: Vec<ActuatorIndex>,
+    cache: Option<&PageCache>,
+) -> Vec<(ActuatorIndex, Result<String>)> {
+    let max_in_flight = client.max_in_flight();
+    stream::iter(indexes)
+        .map(|dxl| async move {
+            let saved = match cache {
+                Some(cache) => match cache.refresh(client, &dxl.url).await {
+                    Ok(Refreshed::Missing) => None,
+                    Ok(_) => Some(cache.page_path(&dxl.url)),
+                    // A stale page is better than none while the server
+                    // can't be reached
+                    Err(e) => {
+                        warn!(model = %dxl.name, "Using the stale saved page: {:#}", e);
+                        Some(cache.page_path(&dxl.url))
+                    }
+                },
+                None => None,
+            };
+            let text = match saved {
+                Some(saved) => {
+                    debug!(model = %dxl.name, path = %saved.display(), "Reading saved page");
+                    client.metrics().record_cache_hit();
+                    tokio::fs::read_to_string(&saved)
+                        .await
+                        .with_context(|| format!("Failed to read {}", saved.display()))
+                }
+                None => client
+                    .get_text(&dxl.url)
+                    .await
+                    .with_context(|| format!("Failed to download {} from {}", dxl.name, dxl.url)),
+            };
+
+            (dxl, text)
+        })
+        .buffer_unordered(max_in_flight)
+        .collect()
+        .await
+}
+
+/// Record the outcome of a model, telling anyone following the progress
+fn record(models: &mut Vec<ModelReport>, progress: &Option<ProgressSender>, model: ModelReport) {
+    let (name, url) = (model.name.clone(), model.url.clone());
+    let event = match &model.status {
+        ModelStatus::Succeeded => ProgressEvent::ModelParsed {
+            name,
+            url,
+            registers: model.registers,
+        },
+        status => ProgressEvent::ModelFailed {
+            name,
+            url,
+            status: status.clone(),
+        },
+    };
+    emit(progress, event);
+    models.push(model);
+}
+
+fn configure_dxl_spinner(spinner: &ProgressBar) {
+    let style = ProgressStyle::default_spinner()
+        .tick_chars("⠁⠂⠄⡀⢀⠠⠐⠈ ")
+        .template("{prefix:.magenta.bold} {msg:.green}");
+    spinner.set_style(style);
+}
+
+/// Download and parse every Dynamixel, handing each to `on_parsed` on the
+/// blocking pool as soon as it's parsed. Only what `on_parsed` returns is
+/// kept, so output can be written as the scrape goes without holding every
+/// model in memory. Returns what was kept, sorted by URL, and the report of
+/// each model.
+///
+/// A model failing to download, parse or be handled is recorded as failed in
+/// its report, and unless `fail_fast` is set, doesn't stop the others. So is
+/// one whose download or parse takes longer than the page timeout, although a
+/// parse can't be stopped, so is left to finish in the background without
+/// being handed to `on_parsed`.
+pub async fn fetch_all<T, F>(
+    client: &HttpClient,
+    indexes: Vec<ActuatorIndex>,
+    opts: &FetchOptions,
+    on_parsed: F,
+) -> Result<(Vec<T>, Vec<ModelReport>)>
+where
+    T: Send + 'static,
+    F: Fn(Actuator) -> Result<T> + Send + Sync + 'static,
+{
+    let counter: Arc<AtomicUsize> = Arc::new(AtomicUsize::new(0));
+    let total = Arc::new(indexes.len());
+
+    // Downloads are IO-bound and run on the async executor, while parsing is
+    // CPU-bound and is handed off to the blocking pool so it doesn't stall
+    // in-flight requests. The bounded channel, and only taking pages off it
+    // while fewer than `queue_size` are being parsed, stop downloads from
+    // racing too far ahead of the parsers.
+    let (page_tx, mut page_rx) =
+        mpsc::channel::<(ActuatorIndex, Result<String>, ProgressBar)>(opts.queue_size);
+
+    // Thanks to http://patshaughnessy.net/2020/1/20/downloading-100000-files-using-async-rust
+    let max_in_flight = client.max_in_flight();
+    let page_timeout = opts.page_timeout;
+    let client = client.clone();
+    let progress = opts.progress.clone();
+    let downloads = stream::iter(indexes)
+        .map(move |dxl| {
+            let spinner = match progress {
+                Some(_) => ProgressBar::hidden(),
+                None => ProgressBar::new_spinner().with_message(dxl.name.clone()),
+            };
+            configure_dxl_spinner(&spinner);
+
+            counter.store(counter.load(Ordering::Relaxed) + 1, Ordering::Relaxed);
+            spinner.set_prefix(format!("{:?}/{}", counter, total));
+            emit(
+                &progress,
+                ProgressEvent::ModelStarted {
+                    name: dxl.name.clone(),
+                    url: dxl.url.clone(),
+                    position: counter.load(Ordering::Relaxed),
+                    total: *total,
+                },
+            );
+
+            let span = info_span!("download", model = %dxl.name, url = %dxl.url);
+            let client = client.clone();
+            async move {
+                // The timeout starts once the request is sent, after waiting
+                // for a permit and any crawl delay
+                let text = client
+                    .get_text_within(&dxl.url, Some(page_timeout))
+                    .await
+                    .with_context(|| format!("Failed to download {} from {}", dxl.name, dxl.url));
+                match &text {
+                    Ok(text) => debug!(bytes = text.len(), "Downloaded page"),
+                    Err(e) => warn!("{:#}", e),
+                }
+
+                (dxl, text, spinner)
+            }
+            .instrument(span)
+        })
+        .buffer_unordered(max_in_flight);
+
+    let download_task = tokio::spawn(async move {
+        tokio::pin!(downloads);
+        while let Some(page) = downloads.next().await {
+            page_tx.send(page).await?;
+        }
+
+        Ok::<_, anyhow::Error>(())
+    });
+
+    let (keep_intermediates, debug_tables) = (opts.keep_intermediates, opts.debug_tables);
+    let parse_options = Arc::new(ParseOptions {
+        table_indexes: opts.table_indexes.clone(),
+        with_raw: opts.with_raw,
+        ..ParseOptions::default()
+    });
+    let on_parsed = Arc::new(on_parsed);
+    let mut parses = FuturesUnordered::new();
+    let mut parsed: Vec<(String, T)> = Vec::new();
+    let mut models: Vec<ModelReport> = Vec::new();
+    let mut receiving = true;
+    let mut cancel = opts.cancel.clone();
+    let mut interrupted = false;
+
+    while receiving || !parses.is_empty() {
+        tokio::select! {
+            _ = cancelled(&mut cancel), if receiving && !interrupted => {
+                warn!("Cancelled, finishing the Dynamixels already downloaded");
+                interrupted = true;
+                download_task.abort();
+                // Pages already waiting in the channel are still received,
+                // until it's empty
+                page_rx.close();
+            }
+            page = page_rx.recv(), if receiving && parses.len() < opts.queue_size => {
+                let (dxl, text, spinner) = match page {
+                    Some(page) => page,
+                    None => {
+                        receiving = false;
+                        continue;
+                    }
+                };
+                let text = match text {
+                    Ok(text) => text,
+                    // Retired pages are expected, and pages robots.txt asks
+                    // not to be scraped are skipped, so neither are failures
+                    Err(e) if e.chain().any(|cause| cause.is::<PageUnavailable>() || cause.is::<Disallowed>()) => {
+                        spinner.finish_and_clear();
+                        record(&mut models, &opts.progress, ModelReport {
+                            name: dxl.name,
+                            url: dxl.url,
+                            status: ModelStatus::Unavailable {
+                                reason: format!("{:#}", e),
+                            },
+                            registers: 0,
+                            bytes: 0,
+                            tables: None,
+                        });
+                        continue;
+                    }
+                    Err(e) => {
+                        spinner.finish_and_clear();
+                        record(&mut models, &opts.progress, ModelReport {
+                            name: dxl.name,
+                            url: dxl.url,
+                            status: ModelStatus::Failed {
+                                category: ErrorCategory::Network,
+                                reason: format!("{:#}", e),
+                            },
+                            registers: 0,
+                            bytes: 0,
+                            tables: None,
+                        });
+                        if opts.fail_fast {
+                            break;
+                        }
+                        continue;
+                    }
+                };
+
+                let (name, url, bytes) = (dxl.name.clone(), dxl.url.clone(), text.len());
+                let id = ActuatorId::from_url(dxl.url.clone(), dxl.name.clone());
+                let indexes = parse_options.indexes_for(&id.raw_name);
+                let parse_options = parse_options.clone();
+                let on_parsed = on_parsed.clone();
+                let parse = task::spawn_blocking(move || {
+                    if keep_intermediates {
+                        if let Err(e) = write_intermediates(&dxl.url, &text, indexes) {
+                            warn!(model = %dxl.name, url = %dxl.url, "Failed to save intermediates: {:#}", e);
+                        }
+                    }
+
+                    let tables = match debug_tables {
+                        true => diagnose_tables(&Html::parse_document(&text), indexes).unwrap_or_else(|e| {
+                            warn!(model = %dxl.name, "Failed to list the tables on the page: {:#}", e);
+                            None
+                        }),
+                        false => None,
+                    };
+                    for table in tables.iter().flatten() {
+                        warn!(model = %dxl.name, "Control tables not at the expected indexes, found {}", table);
+                    }
+
+                    let context = format!("Failed to parse {} from {}", dxl.name, dxl.url);
+                    let actuator = Actuator::from_html(id, &text, &parse_options)
+                        .context(context)
+                        .map(|mut actuator| {
+                            actuator.set_discontinued(dxl.discontinued);
+                            actuator.set_section(dxl.section);
+                            actuator
+                        });
+
+                    (actuator, tables)
+                });
+                parses.push(async move {
+                    // A parser panicking or hanging only fails its own model
+                    let (actuator, tables) = match tokio::time::timeout(page_timeout, parse).await {
+                        Ok(Ok((actuator, tables))) => (actuator, tables),
+                        Ok(Err(e)) => (Err(e.into()), None),
+                        Err(_) => {
+                            let e = anyhow!("Timed out after {:?}", page_timeout)
+                                .context(format!("Failed to parse {} from {}", name, url));
+                            warn!("{:#}", e);
+                            (Err(e), None)
+                        }
+                    };
+
+                    // Only the parse is timed, as saving the model takes as
+                    // long as the disk does
+                    let handled = match actuator {
+                        Ok(actuator) => {
+                            let registers = actuator.data().len();
+                            task::spawn_blocking(move || on_parsed(actuator))
+                                .await
+                                .map_err(anyhow::Error::from)
+                                .and_then(|kept| kept)
+                                .map(|kept| (kept, registers))
+                                .map_err(|e| (ErrorCategory::Io, e))
+                        }
+                        Err(e) => Err((ErrorCategory::Parse, e)),
+                    };
+                    spinner.finish_and_clear();
+
+                    (name, url, bytes, handled, tables)
+                });
+            }
+            Some((name, url, bytes, handled, tables)) = parses.next() => {
+                let (status, registers) = match handled {
+                    Ok((kept, registers)) => {
+                        parsed.push((url.clone(), kept));
+                        (ModelStatus::Succeeded, registers)
+                    }
+                    Err((category, e)) => {
+                        let reason = format!("{:#}", e);
+                        (ModelStatus::Failed { category, reason }, 0)
+                    }
+                };
+                let failed = matches!(status, ModelStatus::Failed { .. });
+
+                record(&mut models, &opts.progress, ModelReport {
+                    name,
+                    url,
+                    status,
+                    registers,
+                    bytes,
+                    tables,
+                });
+                if failed && opts.fail_fast {
+                    break;
+                }
+            }
+        }
+    }
+
+    // Stopping early leaves downloads and parses in flight, which are
+    // abandoned. Otherwise every page has been received.
+    match receiving || !parses.is_empty() {
+        true => {
+            warn!("Stopping at the first failure");
+            download_task.abort();
+        }
+        false if interrupted => (),
+        false => download_task.await??,
+    }
+
+    // Pages are parsed in whatever order they finish downloading, so sort
+    // them to keep the output from depending on the network
+    parsed.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    Ok((parsed.into_iter().map(|(_, kept)| kept).collect(), models))
+}
@@ -0,0 +1,310 @@
+use crate::analysis::{compare, layout_issues, ModelTable, Span};
+use crate::report::{ModelReport, ModelStatus};
+use crate::serialize::{ControlTableData, InitialValue};
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+
+/// Where `analyze report` writes the report if it isn't given a file
+pub static DEFAULT_HTML_REPORT_PATH: &str = "analysis.html";
+
+static HTML_HEAD: &str = "<!DOCTYPE html>
+<html lang=\"en\">
+<head>
+<meta charset=\"utf-8\">
+<title>Dynamixel control table analysis</title>
+<style>
+body { font-family: sans-serif; margin: 2em; color: #222; }
+table { border-collapse: collapse; margin-bottom: 2em; }
+th, td { border: 1px solid #ccc; padding: 0.25em 0.6em; text-align: left; }
+th { background: #eee; cursor: pointer; user-select: none; }
+th[aria-sort=ascending]::after { content: \" \\25B2\"; }
+th[aria-sort=descending]::after { content: \" \\25BC\"; }
+td.number { text-align: right; }
+td.low, td.differs { background: #fff3b0; }
+</style>
+</head>
+<body>
+<h1>Dynamixel control table analysis</h1>
+";
+
+// Sorts a table by a column when its heading is clicked, by number if every
+// cell of the column is one. Cells sort by their data-sort attribute if they
+// have one, and "-" (a missing value) sorts last.
+static HTML_TAIL: &str = "<script>
+document.querySelectorAll(\"th\").forEach(function (th) {
+  th.addEventListener(\"click\", function () {
+    var table = th.closest(\"table\");
+    var body = table.tBodies[0];
+    var ascending = th.getAttribute(\"aria-sort\") !== \"ascending\";
+    table.querySelectorAll(\"th\").forEach(function (other) {
+      other.removeAttribute(\"aria-sort\");
+    });
+    th.setAttribute(\"aria-sort\", ascending ? \"ascending\" : \"descending\");
+
+    var key = function (row) {
+      var cell = row.cells[th.cellIndex];
+      return cell.getAttribute(\"data-sort\") || cell.textContent;
+    };
+    var number = function (text) {
+      return text === \"-\" ? Infinity : Number(text);
+    };
+    var rows = Array.from(body.rows);
+    var numeric = rows.every(function (row) {
+      return !isNaN(number(key(row)));
+    });
+    rows.sort(function (a, b) {
+      var order = numeric
+        ? number(key(a)) - number(key(b))
+        : key(a).localeCompare(key(b));
+      return ascending ? order : -order;
+    });
+    rows.forEach(function (row) {
+      body.appendChild(row);
+    });
+  });
+});
+</script>
+</body>
+</html>
+";
+
+/// The columns of a control table whose coverage is reported, and whether an
+/// item has a value in each
+type Coverage = (&'static str, fn(&ControlTableData) -> bool);
+static COVERAGE: &[Coverage] = &[
+    ("Description", |row| row.description.is_some()),
+    ("Initial Value", |row| {
+        !matches!(row.initial_value, InitialValue::None)
+    }),
+    ("Range", |row| row.range.is_some()),
+    ("Unit", |row| row.units.is_some()),
+];
+
+// Columns filled in for fewer items than this are highlighted
+static LOW_COVERAGE: f64 = 0.5;
+
+/// The part of a run's report the HTML report needs
+#[derive(Deserialize)]
+struct RunReport {
+    models: Vec<ModelReport>,
+}
+
+/// Read the models of a run's report, as written by `--report`
+pub fn load_run_report(path: &Path) -> Result<Vec<ModelReport>> {
+    let text =
+        fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let report: RunReport = serde_json::from_str(&text)
+        .with_context(|| format!("Failed to parse {}", path.display()))?;
+
+    Ok(report.models)
+}
+
+/// Escape text for the body or an attribute of an HTML element
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn cell(text: &str) -> String {
+    format!("<td>{}</td>", escape(text))
+}
+
+fn number_cell(number: impl ToString) -> String {
+    format!("<td class=\"number\">{}</td>", number.to_string())
+}
+
+/// A sortable table with the given headings and rows of `<td>` cells, or a
+/// line saying there's nothing to list if there are no rows
+fn table(headings: &[String], rows: &[Vec<String>], empty: &str) -> String {
+    if rows.is_empty() {
+        return format!("<p>{}</p>\n", escape(empty));
+    }
+
+    let mut html = String::from("<table>\n<thead><tr>");
+    for heading in headings {
+        html.push_str(&format!("<th>{}</th>", escape(heading)));
+    }
+    html.push_str("</tr></thead>\n<tbody>\n");
+    for row in rows {
+        html.push_str(&format!("<tr>{}</tr>\n", row.concat()));
+    }
+    html.push_str("</tbody>\n</table>\n");
+
+    html
+}
+
+/// How many of each model's items have a value in each column
+fn coverage(tables: &[ModelTable]) -> String {
+    let mut headings = vec!["Model".to_string(), "Items".to_string()];
+    headings.extend(COVERAGE.iter().map(|(column, _)| column.to_string()));
+
+    let rows: Vec<Vec<String>> = tables
+        .iter()
+        .map(|table| {
+            let mut row = vec![cell(&table.name), number_cell(table.data.len())];
+            for (_, covered) in COVERAGE {
+                let count = table.data.iter().filter(|item| covered(item)).count();
+                let fraction = count as f64 / table.data.len().max(1) as f64;
+                row.push(format!(
+                    "<td class=\"number{}\" data-sort=\"{}\">{:.0}% ({})</td>",
+                    match fraction < LOW_COVERAGE {
+                        true => " low",
+                        false => "",
+                    },
+                    fraction,
+                    fraction * 100.0,
+                    count
+                ));
+            }
+            row
+        })
+        .collect();
+
+    table(&headings, &rows, "No models were analysed.")
+}
+
+/// The models that failed or were skipped in the run
+fn failures(models: &[ModelReport]) -> String {
+    let rows: Vec<Vec<String>> = models
+        .iter()
+        .filter_map(|model| {
+            let (status, reason) = match &model.status {
+                ModelStatus::Succeeded => return None,
+                ModelStatus::Failed { category, reason } => (category.to_string(), reason),
+                ModelStatus::Unavailable { reason } => ("unavailable".to_string(), reason),
+            };
+            Some(vec![
+                cell(&model.name),
+                cell(&status),
+                cell(reason),
+                cell(&model.url),
+            ])
+        })
+        .collect();
+    let headings = ["Model", "Status", "Reason", "URL"].map(String::from);
+
+    table(&headings, &rows, "Every model in the run was scraped.")
+}
+
+/// Where each model's control table could only be parsed leniently
+fn warnings(tables: &[ModelTable]) -> String {
+    let rows: Vec<Vec<String>> = tables
+        .iter()
+        .flat_map(|table| {
+            table.warnings.iter().map(move |warning| {
+                vec![
+                    cell(&table.name),
+                    number_cell(warning.row),
+                    warning.address.map_or_else(|| cell("-"), number_cell),
+                    cell(warning.column.as_deref().unwrap_or("-")),
+                    cell(&warning.reason),
+                ]
+            })
+        })
+        .collect();
+    let headings = ["Model", "Row", "Address", "Column", "Reason"].map(String::from);
+
+    table(&headings, &rows, "Every row was parsed without guessing.")
+}
+
+/// The gaps and overlapping items in each model's control table
+fn layouts(tables: &[ModelTable]) -> String {
+    let rows: Vec<Vec<String>> = tables
+        .iter()
+        .flat_map(|table| {
+            layout_issues(&table.data)
+                .into_iter()
+                .filter_map(move |(area, span)| {
+                    let (address, issue) = match span {
+                        Span::Gap { address, size } => (address, format!("{} unused bytes", size)),
+                        Span::Overlap {
+                            address,
+                            data_name,
+                            overlaps,
+                            ..
+                        } => (address, format!("{} overlaps {}", data_name, overlaps)),
+                        Span::Item { .. } => return None,
+                    };
+                    Some(vec![
+                        cell(&table.name),
+                        cell(&area.to_string()),
+                        number_cell(address),
+                        cell(&issue),
+                    ])
+                })
+        })
+        .collect();
+    let headings = ["Model", "Area", "Address", "Issue"].map(String::from);
+
+    table(&headings, &rows, "No control table has gaps or overlaps.")
+}
+
+/// The items missing from some models or at differing addresses
+fn comparison(tables: &[ModelTable]) -> String {
+    let comparison = compare(tables);
+    let mut headings = vec!["Data Name".to_string()];
+    headings.extend(comparison.models.iter().cloned());
+
+    let rows: Vec<Vec<String>> = comparison
+        .items
+        .iter()
+        .filter(|item| item.differs() || !item.is_common())
+        .map(|item| {
+            let mut row = vec![cell(&item.data_name)];
+            row.extend(item.addresses.iter().map(|address| {
+                let text = address.map_or("-".to_string(), |address| address.to_string());
+                match item.differs() {
+                    true => format!("<td class=\"number differs\">{}</td>", text),
+                    false => number_cell(text),
+                }
+            }));
+            row
+        })
+        .collect();
+
+    format!(
+        "<p>{}</p>\n{}",
+        escape(&comparison.summary()),
+        table(
+            &headings,
+            &rows,
+            "Every item is at the same address in every model."
+        )
+    )
+}
+
+/// Render the findings of every analysis of the given models as a single
+/// self-contained HTML page, whose tables are sorted by clicking their
+/// headings: how much of each column is filled in, the models that failed
+/// in the run (from its report, if given), the warnings from parsing, gaps
+/// and overlaps in the layouts, and the items that differ between models.
+pub fn html_report(tables: &[ModelTable], run: &[ModelReport]) -> String {
+    let warning_count: usize = tables.iter().map(|table| table.warnings.len()).sum();
+    let mut html = HTML_HEAD.to_string();
+    html.push_str(&format!(
+        "<p>{} models, {} items, {} warnings</p>\n",
+        tables.len(),
+        tables.iter().map(|table| table.data.len()).sum::<usize>(),
+        warning_count
+    ));
+
+    html.push_str("<h2>Column coverage</h2>\n");
+    html.push_str(&coverage(tables));
+    if !run.is_empty() {
+        html.push_str("<h2>Failed models</h2>\n");
+        html.push_str(&failures(run));
+    }
+    html.push_str("<h2>Parse warnings</h2>\n");
+    html.push_str(&warnings(tables));
+    html.push_str("<h2>Layout issues</h2>\n");
+    html.push_str(&layouts(tables));
+    html.push_str("<h2>Differences between models</h2>\n");
+    html.push_str(&comparison(tables));
+    html.push_str(HTML_TAIL);
+
+    html
+}
@@ -0,0 +1,534 @@
+pub mod analysis;
+pub mod cache;
+pub mod cpp;
+pub mod create_lib;
+pub mod download;
+pub mod fetch;
+pub mod html_report;
+pub mod locale;
+pub mod lock;
+pub mod pipeline;
+pub mod progress;
+pub mod report;
+pub mod robots;
+pub mod serialize;
+pub mod snapshot;
+pub mod source;
+pub mod sqlite;
+pub mod typescript;
+pub mod verify;
+
+#[macro_use]
+extern crate lazy_static;
+
+use analysis::{layout_issues, series_object, ModelTable, Span};
+use anyhow::{bail, Context, Result};
+use download::{
+    bit_tables, conversion_tables, description_links, diagnose_tables, find_protocols,
+    firmware_notes, footnotes, merge_tables, table_indexes, table_to_csv, value_tables,
+    DEFAULT_TABLE_INDEXES,
+};
+use scraper::Html;
+use serialize::{
+    apply_bit_fields, apply_conversions, apply_description_links, apply_firmware_notes,
+    apply_footnotes, apply_initial_value_sources, apply_value_tables,
+    deserialize_servo_with_warnings, parse_servo_with_warnings, resolve_model_values,
+    resolve_references, serialize_series, serialize_servo, serialize_servo_markdown,
+    serialize_servo_msgpack, serialize_servo_schema, serialize_servo_summary, serialize_servo_yaml,
+    split_joints, ControlTableData, IndexEntry, InitialValue, ParseError, ParseWarning, RangeValue,
+};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::{debug, instrument, warn};
+
+#[derive(Clone, Debug)]
+pub struct Actuator {
+    series: String,
+    raw_name: String,
+    name: String,
+    url: String,
+    protocols: Vec<String>,
+    data: Vec<ControlTableData>,
+    warnings: Vec<ParseWarning>,
+    discontinued: bool,
+    section: Option<String>,
+}
+
+/// Split a model page URL into its series and raw name
+pub fn split_url(url: &str) -> (String, String) {
+    // Example URL: https://emanual.robotis.com/docs/en/dxl/ax/ax-12a/
+    // Raw name: ax-12a
+    // Series: ax
+    // Saved pages are named after the model (eg saved/ax/ax-12a.html), or
+    // saved as page.html in a directory named after it by --keep-intermediates
+    let path = url.trim_end_matches('/');
+    let path = path
+        .strip_suffix("/page.html")
+        .or_else(|| path.strip_suffix(".html"))
+        .unwrap_or(path);
+    let mut url_parts = path.rsplit('/');
+    let raw_name = url_parts.next().unwrap();
+    let series = url_parts.next().unwrap_or_default();
+
+    (series.to_string(), raw_name.to_string())
+}
+
+/// The directory hardware other than the Dynamixels, scraped from a
+/// navigation section, is written to under each output directory, eg
+/// objects/hardware/controller/cm-550.ron
+pub static HARDWARE_DIR: &str = "hardware";
+
+/// The series directory a page's files are written under, which is under
+/// [`HARDWARE_DIR`] for pages from a navigation section
+pub fn output_series(series: &str, section: Option<&str>) -> String {
+    match section {
+        Some(_) => format!("{}/{}", HARDWARE_DIR, series),
+        None => series.to_string(),
+    }
+}
+
+pub static INDEX_PATH: &str = "objects/index.ron";
+pub static MSGPACK_INDEX_PATH: &str = "objects/index.msgpack";
+
+/// The items of a control table, and the warnings from parsing it
+pub type ParsedPage = (Vec<ControlTableData>, Vec<ParseWarning>);
+
+/// The path of a model's RON file, relative to `objects/`
+pub fn relative_object_path(series: &str, raw_name: &str) -> String {
+    format!("{}/{}.ron", series, raw_name)
+}
+
+pub fn object_path(series: &str, raw_name: &str) -> String {
+    format!("objects/{}", relative_object_path(series, raw_name))
+}
+
+pub fn yaml_object_path(series: &str, raw_name: &str) -> String {
+    format!("objects/{}/{}.yaml", series, raw_name)
+}
+
+/// The path of the aggregate of every model in a series, beside their RON
+/// objects
+pub fn series_object_path(series: &str) -> String {
+    format!("objects/{}/{}", series, SERIES_OBJECT_NAME)
+}
+
+static SERIES_OBJECT_NAME: &str = "_series.ron";
+
+/// Aggregate the RON objects of every model in a series, including those
+/// scraped by earlier runs, into [`series_object_path`]. Returns the number
+/// of models aggregated.
+#[instrument]
+pub fn write_series_object(series: &str) -> Result<usize> {
+    let dir = Path::new("objects").join(series);
+    let mut paths: Vec<PathBuf> = fs::read_dir(&dir)
+        .with_context(|| format!("Failed to list {}", dir.display()))?
+        .map(|entry| Ok(entry?.path()))
+        .collect::<Result<_>>()?;
+    paths.retain(|path| {
+        path.extension().is_some_and(|extension| extension == "ron")
+            && path
+                .file_name()
+                .is_some_and(|name| name != SERIES_OBJECT_NAME)
+    });
+    paths.sort();
+
+    let tables = paths
+        .iter()
+        .map(|path| {
+            let text = fs::read_to_string(path)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            let slug = path.file_stem().unwrap().to_string_lossy().to_string();
+            let (data, warnings) = deserialize_servo_with_warnings(&text)
+                .with_context(|| format!("Failed to parse {}", path.display()))?;
+            Ok(ModelTable {
+                name: slug.to_uppercase(),
+                slug,
+                data,
+                warnings,
+            })
+        })
+        .collect::<Result<Vec<ModelTable>>>()?;
+
+    let path = series_object_path(series);
+    debug!(%path, models = tables.len(), "Writing series object");
+    write_atomic(path, serialize_series(&series_object(series, &tables))?)?;
+
+    Ok(tables.len())
+}
+
+/// The path of a model's MessagePack object, relative to `objects/`
+pub fn relative_msgpack_path(series: &str, raw_name: &str) -> String {
+    format!("{}/{}.msgpack", series, raw_name)
+}
+
+pub fn msgpack_object_path(series: &str, raw_name: &str) -> String {
+    format!("objects/{}", relative_msgpack_path(series, raw_name))
+}
+
+/// The path of a model's summary, beside its RON object
+pub fn summary_path(series: &str, raw_name: &str) -> String {
+    format!("objects/{}/{}.md", series, raw_name)
+}
+
+pub fn schema_path(series: &str, raw_name: &str) -> String {
+    format!("schemas/{}/{}.schema.json", series, raw_name)
+}
+
+pub fn markdown_path(series: &str, raw_name: &str) -> String {
+    format!("docs/{}/{}.md", series, raw_name)
+}
+
+pub fn artifacts_dir(series: &str, raw_name: &str) -> String {
+    format!("artifacts/{}/{}", series, raw_name)
+}
+
+/// Write a file by writing a temporary file beside it and renaming it into
+/// place, so that a crash partway through never leaves a partial file
+pub fn write_atomic<P: AsRef<Path>, C: AsRef<[u8]>>(path: P, contents: C) -> Result<()> {
+    let path = path.as_ref();
+    let file_name = path
+        .file_name()
+        .with_context(|| format!("{} isn't a file", path.display()))?;
+    let temp_path = path.with_file_name(format!(
+        ".{}.{}.tmp",
+        file_name.to_string_lossy(),
+        std::process::id()
+    ));
+
+    let written = fs::write(&temp_path, contents).and_then(|_| fs::rename(&temp_path, path));
+    if written.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    written.with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Write a file atomically unless it already has exactly these contents, in
+/// which case it's left alone so its modification time is kept, eg so cargo
+/// doesn't rebuild a generated crate that didn't change. Returns whether the
+/// file was written.
+pub fn write_if_changed<P: AsRef<Path>, C: AsRef<[u8]>>(path: P, contents: C) -> Result<bool> {
+    let path = path.as_ref();
+    if fs::read(path).is_ok_and(|existing| existing == contents.as_ref()) {
+        return Ok(false);
+    }
+    write_atomic(path, contents)?;
+
+    Ok(true)
+}
+
+/// Save the fetched page and the merged control table (from the tables at
+/// `indexes`) for offline debugging. The page is written first so that it's
+/// kept even if it can't be parsed.
+#[instrument(skip(text))]
+pub fn write_intermediates(url: &str, text: &str, indexes: (usize, usize)) -> Result<()> {
+    let (series, raw_name) = split_url(url);
+    let dir = artifacts_dir(&series, &raw_name);
+    fs::create_dir_all(&dir)?;
+    write_atomic(format!("{}/page.html", dir), text)?;
+
+    let table = merge_tables(&Html::parse_document(text), indexes)?;
+    write_atomic(format!("{}/table.csv", dir), table_to_csv(&table))?;
+
+    Ok(())
+}
+
+/// A model page to parse: where it is, and what the E-Manual calls the model
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ActuatorId {
+    pub url: String,
+    pub name: String,
+    pub series: String,
+    pub raw_name: String,
+}
+
+impl ActuatorId {
+    /// Identify the model named `name` (eg "XM430-W210") by the URL of its
+    /// page, which gives its series and raw name
+    pub fn from_url(url: String, name: String) -> ActuatorId {
+        let (series, raw_name) = split_url(&url);
+        ActuatorId {
+            url,
+            name,
+            series,
+            raw_name,
+        }
+    }
+}
+
+/// How the control tables of a page are found
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TableDetection {
+    /// At the expected indexes, or searched for on the page if they aren't
+    /// there
+    #[default]
+    Search,
+    /// Only at the expected indexes, so a page with a new layout is an error
+    /// rather than parsed from whichever tables look like control tables
+    Fixed,
+}
+
+/// Settings for parsing a model's page
+#[derive(Clone, Debug, Default)]
+pub struct ParseOptions {
+    /// Fail on a page with any row that could only be parsed leniently,
+    /// rather than recording a warning for it
+    pub strict: bool,
+    pub table_detection: TableDetection,
+    /// Where the control tables are on the pages of models whose tables
+    /// aren't where [`download::TABLE_INDEXES`] expects, by URL slug
+    pub table_indexes: BTreeMap<String, (usize, usize)>,
+    /// Keep the text of each item's cells alongside the values parsed from
+    /// them
+    pub with_raw: bool,
+}
+
+impl ParseOptions {
+    /// The positions of a model's control tables on its page, by URL slug
+    pub fn indexes_for(&self, slug: &str) -> (usize, usize) {
+        table_indexes(slug, &self.table_indexes)
+    }
+}
+
+impl Actuator {
+    /// Parse a model's page, with its control tables where
+    /// [`download::TABLE_INDEXES`] says they are
+    pub fn new(url: String, name: String, text: String) -> Result<Actuator> {
+        Actuator::from_html(
+            ActuatorId::from_url(url, name),
+            &text,
+            &ParseOptions::default(),
+        )
+    }
+
+    /// Parse a model's page from its HTML. Nothing is read or written, so
+    /// pages can be parsed however they were obtained.
+    #[instrument(skip(html, options), err)]
+    pub fn from_html(id: ActuatorId, html: &str, options: &ParseOptions) -> Result<Actuator> {
+        let indexes = options.indexes_for(&id.raw_name);
+        let document = Html::parse_document(html);
+        if options.table_detection == TableDetection::Fixed
+            && diagnose_tables(&document, indexes)?.is_some()
+        {
+            return Err(ParseError::NoControlTable).with_context(|| {
+                format!(
+                    "Expected the control tables at tables {} and {}",
+                    indexes.0, indexes.1
+                )
+            });
+        }
+
+        let (mut data, warnings) = parse_page(&document, indexes, options.with_raw)?;
+        if let Some(warning) = warnings.first().filter(|_| options.strict) {
+            bail!(
+                "{} rows could only be parsed leniently, such as row {}: {}",
+                warnings.len(),
+                warning.row,
+                warning.reason
+            );
+        }
+        resolve_model_values(&mut data, &id.name);
+        for (area, span) in layout_issues(&data) {
+            match span {
+                Span::Overlap { .. } => warn!(%area, "Control table layout: {}", span),
+                _ => debug!(%area, "Control table layout: {}", span),
+            }
+        }
+
+        Ok(Actuator {
+            series: id.series,
+            raw_name: id.raw_name,
+            name: id.name,
+            url: id.url,
+            data,
+            warnings,
+            protocols: find_protocols(&document),
+            discontinued: false,
+            section: None,
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// The items of the model's control table
+    pub fn data(&self) -> &[ControlTableData] {
+        &self.data
+    }
+
+    /// Where the page's control table could only be parsed leniently
+    pub fn warnings(&self) -> &[ParseWarning] {
+        &self.warnings
+    }
+
+    /// Whether ROBOTIS has stopped making the model, as marked in the
+    /// E-Manual's navigation
+    pub fn is_discontinued(&self) -> bool {
+        self.discontinued
+    }
+
+    pub fn set_discontinued(&mut self, discontinued: bool) {
+        self.discontinued = discontinued;
+    }
+
+    /// The navigation section the page was found in, if it isn't a Dynamixel
+    pub fn section(&self) -> Option<&str> {
+        self.section.as_deref()
+    }
+
+    /// Mark the page as found in a navigation section other than the
+    /// Dynamixels', moving its files under [`HARDWARE_DIR`]
+    pub fn set_section(&mut self, section: Option<String>) {
+        let (series, _) = split_url(&self.url);
+        self.series = output_series(&series, section.as_deref());
+        self.section = section;
+    }
+
+    /// The model number, taken from the initial value of the Model Number item
+    pub fn model_number(&self) -> Option<i32> {
+        self.data
+            .iter()
+            .filter(|row| row.data_name.as_deref() == Some("Model Number"))
+            .find_map(|row| match row.initial_value {
+                InitialValue::Value(RangeValue::Integer(number)) => Some(number),
+                _ => None,
+            })
+    }
+
+    pub fn index_entry(&self) -> IndexEntry {
+        IndexEntry {
+            series: self.series.clone(),
+            slug: self.raw_name.clone(),
+            name: self.name.clone(),
+            model_number: self.model_number(),
+            protocols: self.protocols.clone(),
+            path: relative_object_path(&self.series, &self.raw_name),
+        }
+    }
+
+    #[instrument(skip(self), fields(model = %self.name))]
+    pub fn write_object(&self) -> Result<PathBuf> {
+        fs::create_dir_all(format!("objects/{}", &self.series))?;
+        let path = object_path(&self.series, &self.raw_name);
+        debug!(%path, "Writing RON object");
+        write_atomic(&path, serialize_servo(&self.data, &self.warnings)?)?;
+
+        Ok(path.into())
+    }
+
+    #[instrument(skip(self), fields(model = %self.name))]
+    pub fn write_summary(&self) -> Result<PathBuf> {
+        fs::create_dir_all(format!("objects/{}", &self.series))?;
+        let path = summary_path(&self.series, &self.raw_name);
+        debug!(%path, "Writing summary");
+        write_atomic(
+            &path,
+            serialize_servo_summary(
+                &self.name,
+                &self.url,
+                self.model_number(),
+                &self.protocols,
+                self.discontinued,
+                &self.data,
+            ),
+        )?;
+
+        Ok(path.into())
+    }
+
+    #[instrument(skip(self), fields(model = %self.name))]
+    pub fn write_yaml(&self) -> Result<PathBuf> {
+        fs::create_dir_all(format!("objects/{}", &self.series))?;
+        let path = yaml_object_path(&self.series, &self.raw_name);
+        debug!(%path, "Writing YAML object");
+        write_atomic(&path, serialize_servo_yaml(&self.data)?)?;
+
+        Ok(path.into())
+    }
+
+    #[instrument(skip(self), fields(model = %self.name))]
+    pub fn write_msgpack(&self) -> Result<PathBuf> {
+        fs::create_dir_all(format!("objects/{}", &self.series))?;
+        let path = msgpack_object_path(&self.series, &self.raw_name);
+        debug!(%path, "Writing MessagePack object");
+        write_atomic(&path, serialize_servo_msgpack(&self.data)?)?;
+
+        Ok(path.into())
+    }
+
+    #[instrument(skip(self), fields(model = %self.name))]
+    pub fn write_markdown(&self) -> Result<PathBuf> {
+        fs::create_dir_all(format!("docs/{}", &self.series))?;
+        let path = markdown_path(&self.series, &self.raw_name);
+        debug!(%path, "Writing Markdown documentation");
+        write_atomic(
+            &path,
+            serialize_servo_markdown(&self.name, &self.url, &self.data),
+        )?;
+
+        Ok(path.into())
+    }
+
+    #[instrument(skip(self), fields(model = %self.name))]
+    pub fn write_schema(&self) -> Result<PathBuf> {
+        fs::create_dir_all(format!("schemas/{}", &self.series))?;
+        let path = schema_path(&self.series, &self.raw_name);
+        debug!(%path, "Writing JSON Schema");
+        write_atomic(
+            &path,
+            serialize_servo_schema(&self.name, &self.url, &self.data)?,
+        )?;
+
+        Ok(path.into())
+    }
+}
+
+/// Parse the control table of a model page, along with any notes, footnotes, tables
+/// of bits and conversion equations on the page about the items in it. The
+/// text of the items' cells is only kept if `with_raw` is set.
+fn parse_page(document: &Html, indexes: (usize, usize), with_raw: bool) -> Result<ParsedPage> {
+    let (mut data, warnings) =
+        parse_servo_with_warnings(merge_tables(document, indexes)?, with_raw)?;
+    apply_footnotes(&mut data, &footnotes(document, indexes)?);
+    apply_initial_value_sources(&mut data);
+    split_joints(&mut data);
+    apply_firmware_notes(&mut data, &firmware_notes(document));
+    apply_bit_fields(&mut data, &bit_tables(document)?);
+    apply_value_tables(&mut data, &value_tables(document)?);
+    apply_conversions(&mut data, &conversion_tables(document)?);
+    resolve_references(&mut data);
+    apply_description_links(&mut data, &description_links(document, indexes)?);
+
+    Ok((data, warnings))
+}
+
+/// Parse the control table of a model page from its HTML, along with the
+/// notes, footnotes and tables of bits on the page, for pages obtained some
+/// other way than by scraping the E-Manual. The control tables are expected
+/// where most pages put them, and searched for if they aren't there.
+pub fn parse_control_table(html: &str) -> std::result::Result<Vec<ControlTableData>, ParseError> {
+    Ok(parse_page(&Html::parse_document(html), DEFAULT_TABLE_INDEXES, false)?.0)
+}
+
+/// Parse the control table of a saved model page, such as the fixtures used
+/// by the regression tests. Pages are named after their model's URL slug.
+pub fn parse_fixture<P: AsRef<Path>>(path: P) -> Result<Vec<ControlTableData>> {
+    Ok(parse_fixture_with_warnings(path)?.0)
+}
+
+/// Parse a saved model page as [`parse_fixture`] does, along with the
+/// warnings from parsing it
+pub fn parse_fixture_with_warnings<P: AsRef<Path>>(path: P) -> Result<ParsedPage> {
+    let path = path.as_ref();
+    let slug = path.file_stem().unwrap_or_default().to_string_lossy();
+    parse_page(
+        &Html::parse_document(&fs::read_to_string(path)?),
+        table_indexes(&slug, &BTreeMap::new()),
+        false,
+    )
+}
@@ -0,0 +1,153 @@
+use anyhow::{bail, Error, Result};
+use std::fmt;
+use std::str::FromStr;
+
+/// A language the E-Manual is published in. Pages in every locale are parsed
+/// into the same canonical (English) field names, but keep their localized
+/// descriptions.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Locale {
+    English,
+    Korean,
+    Chinese,
+}
+
+pub static LOCALES: &[&str] = &["en", "kr", "cn"];
+
+/// Where the raw files of the E-Manual's source repository are served from
+pub static MANUAL_SOURCE: &str = "https://raw.githubusercontent.com/ROBOTIS-GIT/emanual";
+
+/// Whether a revision of the E-Manual's source names a single commit, rather
+/// than a branch or tag that can move
+pub fn is_commit_hash(revision: &str) -> bool {
+    (7..=40).contains(&revision.len()) && revision.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+impl Locale {
+    /// The segment of the E-Manual's URLs naming the locale
+    pub fn code(&self) -> &'static str {
+        match self {
+            Locale::English => "en",
+            Locale::Korean => "kr",
+            Locale::Chinese => "cn",
+        }
+    }
+
+    /// The URL model pages in the navigation are relative to
+    pub fn base_url(&self) -> String {
+        format!("https://emanual.robotis.com/docs/{}", self.code())
+    }
+
+    /// The URL of the navigation listing every model page
+    pub fn navigation_url(&self) -> String {
+        self.navigation_url_at("master")
+    }
+
+    /// The URL of the navigation as of a revision (a commit, branch or tag)
+    /// of the E-Manual's source repository
+    pub fn navigation_url_at(&self, revision: &str) -> String {
+        let file = match self {
+            Locale::English => "navigation.yml".to_string(),
+            locale => format!("navigation_{}.yml", locale.code()),
+        };
+
+        format!("{}/{}/_data/{}", MANUAL_SOURCE, revision, file)
+    }
+}
+
+impl FromStr for Locale {
+    type Err = Error;
+
+    fn from_str(code: &str) -> Result<Locale> {
+        match code {
+            "en" => Ok(Locale::English),
+            "kr" | "ko" => Ok(Locale::Korean),
+            "cn" | "zh" => Ok(Locale::Chinese),
+            _ => bail!("Unknown locale {:?}, expected one of {:?}", code, LOCALES),
+        }
+    }
+}
+
+impl fmt::Display for Locale {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.code())
+    }
+}
+
+/// Control table headings used by the localized E-Manuals, and the English
+/// heading each corresponds to. Headings are compared without whitespace.
+static LOCALIZED_HEADINGS: &[(&str, &str)] = &[
+    // Korean
+    ("주소", "Address"),
+    ("크기(byte)", "Size(byte)"),
+    ("크기(Byte)", "Size(byte)"),
+    ("크기", "Size(byte)"),
+    ("데이터명", "Data Name"),
+    ("명칭", "Data Name"),
+    ("설명", "Description"),
+    ("접근", "Access"),
+    ("접근권한", "Access"),
+    ("초기값", "Initial Value"),
+    ("범위", "Range"),
+    ("단위", "Unit"),
+    // Chinese
+    ("地址", "Address"),
+    ("大小(byte)", "Size(byte)"),
+    ("大小(字节)", "Size(byte)"),
+    ("大小（字节）", "Size(byte)"),
+    ("大小", "Size(byte)"),
+    ("数据名称", "Data Name"),
+    ("名称", "Data Name"),
+    ("说明", "Description"),
+    ("描述", "Description"),
+    ("访问", "Access"),
+    ("访问权限", "Access"),
+    ("初始值", "Initial Value"),
+    ("范围", "Range"),
+    ("单位", "Unit"),
+];
+
+/// The English heading for a localized control table heading, if it is one
+pub fn canonical_heading(heading: &str) -> Option<&'static str> {
+    let compact = || heading.chars().filter(|c| !c.is_whitespace());
+    LOCALIZED_HEADINGS
+        .iter()
+        .find(|(localized, _)| localized.chars().eq(compact()))
+        .map(|(_, canonical)| *canonical)
+}
+
+/// Words marking a navigation entry as a series of Dynamixels, in each locale
+static SERIES_WORDS: &[&str] = &["Series", "시리즈", "系列"];
+
+/// Whether a navigation title names a series of Dynamixels
+pub fn is_series_title(title: &str) -> bool {
+    SERIES_WORDS.iter().any(|word| title.contains(word))
+}
+
+/// The words navigation titles mark discontinued models with, in brackets
+static DISCONTINUED_WORDS: &[&str] = &["discontinued", "단종", "停产"];
+
+/// Split the "(Discontinued)" marker off a model's navigation title,
+/// returning the model's name and whether it was marked
+pub fn strip_discontinued(title: &str) -> (&str, bool) {
+    let trimmed = title.trim_end();
+    let marker = trimmed
+        .strip_suffix(')')
+        .and_then(|rest| rest.rsplit_once('('))
+        .or_else(|| {
+            trimmed
+                .strip_suffix(']')
+                .and_then(|rest| rest.rsplit_once('['))
+        });
+
+    match marker {
+        Some((name, word))
+            if DISCONTINUED_WORDS
+                .iter()
+                .any(|marker| word.trim().eq_ignore_ascii_case(marker)) =>
+        {
+            (name.trim_end(), true)
+        }
+        _ => (title, false),
+    }
+}
@@ -0,0 +1,78 @@
+use std::error::Error;
+use std::fmt;
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tracing::debug;
+
+/// The lock file a run holds while writing output, relative to the working
+/// directory the output is written to
+pub static LOCK_PATH: &str = ".dynamixel-scraper.lock";
+
+/// Another run is writing output to the same directory
+#[derive(Debug)]
+pub struct OutputLocked {
+    pub path: PathBuf,
+    /// The process ID of the run holding the lock, if it could be read
+    pub holder: Option<u32>,
+}
+
+impl fmt::Display for OutputLocked {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Another dynamixel-scraper ")?;
+        if let Some(pid) = self.holder {
+            write!(f, "(process {}) ", pid)?;
+        }
+        write!(
+            f,
+            "is writing output to this directory, as it holds the lock on {}. Wait for it to finish, or pass --force to write anyway",
+            self.path.display()
+        )
+    }
+}
+
+impl Error for OutputLocked {}
+
+/// An advisory lock on the output of a run, released when it's dropped or
+/// the process exits, however it exits. The lock file is left behind, as
+/// removing it could let two runs each lock a different file.
+#[derive(Debug)]
+pub struct OutputLock {
+    _file: File,
+}
+
+impl OutputLock {
+    /// Lock the file at `path`, creating it if needed, and record the ID of
+    /// this process in it. Fails with [`OutputLocked`] if another run holds
+    /// the lock.
+    pub fn acquire<P: AsRef<Path>>(path: P) -> anyhow::Result<OutputLock> {
+        let path = path.as_ref();
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)?;
+        match file.try_lock() {
+            Ok(()) => {}
+            Err(TryLockError::WouldBlock) => {
+                let holder = fs::read_to_string(path)
+                    .ok()
+                    .and_then(|pid| pid.trim().parse().ok());
+                return Err(OutputLocked {
+                    path: path.to_path_buf(),
+                    holder,
+                }
+                .into());
+            }
+            Err(TryLockError::Error(error)) => return Err(error.into()),
+        }
+
+        file.set_len(0)?;
+        write!(file, "{}", std::process::id())?;
+        file.flush()?;
+        debug!(path = %path.display(), "Locked the output");
+
+        Ok(OutputLock { _file: file })
+    }
+}
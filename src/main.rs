@@ -1,87 +1,329 @@
-mod create_lib;
-
-pub mod analysis;
-pub mod download;
-pub mod serialize;
-
-#[macro_use]
-extern crate lazy_static;
-
-use anyhow::Result;
-use clap::{App, Arg, ArgGroup};
-use download::merge_tables;
-use futures_util::stream::StreamExt;
-use indicatif::{ProgressBar, ProgressStyle};
-use serde_yaml::Value;
-use serialize::{parse_servo, serialize_servo, ControlTableData};
-use std::fs;
-use std::sync::{
-    atomic::{AtomicUsize, Ordering},
-    Arc,
+use anyhow::{Context, Error, Result};
+use clap::{App, Arg, ArgGroup, ArgMatches, SubCommand};
+use dialoguer::{theme::ColorfulTheme, MultiSelect};
+use dynamixel_scraper::analysis::{
+    compare, heading_layouts, headings_table, layout_issues, load_objects, memory_map,
+};
+use dynamixel_scraper::cache::{PageCache, Refreshed};
+use dynamixel_scraper::create_lib::{LibTarget, PackageMetadata, NAMING_STRATEGIES};
+use dynamixel_scraper::download::table_headings;
+use dynamixel_scraper::fetch::{
+    client_builder, fetch_pages, ActuatorIndex, FetchOptions, Filters, HttpClient, PageUnavailable,
+    Sample, DEFAULT_MAX_IN_FLIGHT, USER_AGENT,
 };
-use tokio_stream as stream;
+use dynamixel_scraper::html_report::{html_report, load_run_report, DEFAULT_HTML_REPORT_PATH};
+use dynamixel_scraper::locale::{is_commit_hash, Locale, LOCALES};
+use dynamixel_scraper::pipeline::{find_dynamixels, scrape, Formats, RunOptions};
+use dynamixel_scraper::report::{ErrorCategory, WarningCounter};
+use dynamixel_scraper::robots::Disallowed;
+use dynamixel_scraper::snapshot::CHANGED_EXIT_CODE;
+use dynamixel_scraper::source::{DirectorySource, SourceSpec};
+use dynamixel_scraper::verify::verify_lib;
+use dynamixel_scraper::{
+    artifacts_dir, create_lib, markdown_path, msgpack_object_path, object_path, schema_path,
+    series_object_path, split_url, summary_path, typescript, write_atomic, yaml_object_path,
+    INDEX_PATH, MSGPACK_INDEX_PATH,
+};
+use reqwest::{Certificate, Proxy};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::signal;
+use tokio::sync::watch;
+use tokio::task;
+use tracing::{info, warn, Level};
+use tracing_subscriber::{filter::LevelFilter, fmt, prelude::*};
+
+/// Build the HTTP client shared by every request, applying any proxy,
+/// certificate and timeout settings. Proxies set through the `HTTP_PROXY`
+/// and `HTTPS_PROXY` environment variables are used unless overridden.
+fn build_client(matches: &ArgMatches) -> Result<HttpClient> {
+    let timeout = Duration::from_secs(matches.value_of("timeout").unwrap().parse()?);
+    let mut builder = client_builder().timeout(timeout);
+
+    if let Some(user_agent) = matches.value_of("user_agent") {
+        builder = builder.user_agent(user_agent);
+    }
+
+    if let Some(proxy) = matches.value_of("proxy") {
+        builder = builder.proxy(Proxy::all(proxy)?);
+    }
+
+    if let Some(path) = matches.value_of("ca_cert") {
+        let pem = fs::read(path).with_context(|| format!("Failed to read {}", path))?;
+        builder = builder.add_root_certificate(Certificate::from_pem(&pem)?);
+    }
+
+    let client = HttpClient::new(builder.build()?, DEFAULT_MAX_IN_FLIGHT);
+    match matches.value_of("source").unwrap().parse()? {
+        SourceSpec::Http => Ok(client),
+        SourceSpec::Directory(root) => {
+            info!(root = %root.display(), "Reading pages from a mirror of the E-Manual");
+            Ok(client.with_source(Arc::new(DirectorySource::new(root))))
+        }
+    }
+}
+
+/// Let the user pick which Dynamixels to scrape and which formats to output.
+/// Returns the chosen Dynamixels and formats.
+fn select_interactively(indexes: Vec<ActuatorIndex>) -> Result<(Vec<ActuatorIndex>, Formats)> {
+    let theme = ColorfulTheme::default();
 
-static TICK_RATE: u64 = 50;
+    let mut series: Vec<String> = indexes.iter().map(|dxl| dxl.series.clone()).collect();
+    series.dedup();
+    let chosen_series: Vec<String> = MultiSelect::with_theme(&theme)
+        .with_prompt("Which series should be scraped?")
+        .items(&series)
+        .interact()?
+        .into_iter()
+        .map(|i| series[i].clone())
+        .collect();
 
-#[derive(Clone, Debug)]
-pub struct Actuator {
-    series: String,
-    raw_name: String,
-    name: String,
-    data: Vec<ControlTableData>,
+    let candidates: Vec<ActuatorIndex> = indexes
+        .into_iter()
+        .filter(|dxl| chosen_series.contains(&dxl.series))
+        .collect();
+    let names: Vec<&str> = candidates.iter().map(|dxl| dxl.name.as_str()).collect();
+    let chosen_models = MultiSelect::with_theme(&theme)
+        .with_prompt("Which Dynamixels should be scraped?")
+        .items(&names)
+        .defaults(&vec![true; names.len()])
+        .interact()?;
+
+    let formats = MultiSelect::with_theme(&theme)
+        .with_prompt("Which formats should be output?")
+        .items(&[
+            "Rust library",
+            "RON",
+            "YAML",
+            "Markdown",
+            "JSON Schema",
+            "MessagePack",
+        ])
+        .defaults(&[true, false, false, false, false, false])
+        .interact()?;
+
+    let selected = candidates
+        .into_iter()
+        .enumerate()
+        .filter(|(i, _)| chosen_models.contains(i))
+        .map(|(_, dxl)| dxl)
+        .collect();
+
+    let formats = Formats {
+        lib: formats.contains(&0),
+        ron: formats.contains(&1),
+        ron_summaries: false,
+        yaml: formats.contains(&2),
+        markdown: formats.contains(&3),
+        json_schema: formats.contains(&4),
+        msgpack: formats.contains(&5),
+    };
+
+    Ok((selected, formats))
 }
 
-impl Actuator {
-    pub fn new(url: String, name: String, text: String) -> Result<Actuator> {
-        // Example URL: https://emanual.robotis.com/docs/en/dxl/ax/ax-12a/
-        // Raw name: ax-12a
-        // Series: ax
-        let mut url_parts = url.split('/');
-        let raw_name = url_parts.nth_back(1).unwrap();
-        let series = url_parts.next_back().unwrap();
+/// Run the chosen analysis over previously scraped RON objects
+fn analyze_objects(matches: &ArgMatches) -> Result<ExitCode> {
+    match matches.subcommand() {
+        ("compare", Some(matches)) => {
+            let models: Vec<String> = matches
+                .values_of("models")
+                .map_or(vec![], |values| values.map(String::from).collect());
+            let tables = load_objects(
+                &PathBuf::from(matches.value_of("objects").unwrap()),
+                &models,
+            )?;
+            let comparison = compare(&tables);
+
+            comparison
+                .table(matches.is_present("differences_only"))
+                .printstd();
+            println!("\n{}", comparison.summary());
+        }
+        ("layout", Some(matches)) => {
+            let models: Vec<String> = matches
+                .values_of("models")
+                .map_or(vec![], |values| values.map(String::from).collect());
+            let tables = load_objects(
+                &PathBuf::from(matches.value_of("objects").unwrap()),
+                &models,
+            )?;
+
+            let mut issues = 0;
+            for table in &tables {
+                print!("{}", memory_map(&table.name, &table.data));
+                issues += layout_issues(&table.data).len();
+            }
+            println!("{} gaps or overlaps across {} models", issues, tables.len());
+        }
+        ("report", Some(matches)) => {
+            let models: Vec<String> = matches
+                .values_of("models")
+                .map_or(vec![], |values| values.map(String::from).collect());
+            let tables = load_objects(
+                &PathBuf::from(matches.value_of("objects").unwrap()),
+                &models,
+            )?;
+            let run = match matches.value_of("run_report") {
+                Some(path) => load_run_report(Path::new(path))?,
+                None => vec![],
+            };
 
-        Ok(Actuator {
-            series: series.to_string(),
-            raw_name: raw_name.to_string(),
-            name,
-            data: parse_servo(merge_tables(&text, (1, 2))?)?,
-        })
+            let path = matches.value_of("output").unwrap();
+            write_atomic(path, html_report(&tables, &run))?;
+            println!("Wrote the analysis of {} models to {}", tables.len(), path);
+        }
+        _ => println!("{}", matches.usage()),
     }
 
-    pub fn write_object(&mut self) -> Result<()> {
-        fs::create_dir_all(format!("objects/{}", &self.series))?;
-        let path = format!("objects/{}/{}.ron", &self.series, &self.raw_name);
-        fs::write(path, serialize_servo(&self.data)?)?;
+    Ok(ExitCode::SUCCESS)
+}
 
-        Ok(())
+/// Print each unique combination of table headings on the pages of the given
+/// Dynamixels, and the models using it
+async fn analyze_headings(
+    client: &HttpClient,
+    indexes: Vec<ActuatorIndex>,
+    matches: &ArgMatches<'_>,
+) -> Result<ExitCode> {
+    let mut total = indexes.len();
+    let mut pages = vec![];
+    let cache = match matches.is_present("cached") {
+        true => Some(page_cache(matches)?),
+        false => None,
+    };
+    for (dxl, text) in fetch_pages(client, indexes, cache.as_ref()).await {
+        match text.and_then(|text| table_headings(&text)) {
+            Ok(tables) => pages.push((dxl.name, tables)),
+            // Retired models have no page to analyse, and pages robots.txt
+            // disallows aren't fetched
+            Err(e)
+                if e.chain()
+                    .any(|cause| cause.is::<PageUnavailable>() || cause.is::<Disallowed>()) =>
+            {
+                info!("{:#}", e);
+                total -= 1;
+            }
+            Err(e) => warn!("{:#}", e),
+        }
+    }
+    if pages.is_empty() {
+        return Err(Error::new(ErrorCategory::Network).context("No pages could be read"));
     }
+    pages.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let layouts = heading_layouts(&pages, matches.is_present("all_tables"));
+    headings_table(&layouts).printstd();
+    println!(
+        "\n{} heading layouts across {} of {} models",
+        layouts.len(),
+        pages.len(),
+        total
+    );
+
+    Ok(match pages.len() == total {
+        true => ExitCode::SUCCESS,
+        false => ExitCode::from(ErrorCategory::PartialSuccess.exit_code()),
+    })
 }
 
-#[derive(Debug)]
-struct ActuatorIndex {
-    pub url: String,
-    pub name: String,
+/// The pages saved under artifacts/, used for as many hours as `--cache-ttl`
+/// says before they're checked for changes
+fn page_cache(matches: &ArgMatches<'_>) -> Result<PageCache> {
+    let hours: u64 = matches.value_of("cache_ttl").unwrap().parse()?;
+    Ok(PageCache::artifacts(Duration::from_secs(hours * 60 * 60)))
 }
 
-fn configure_spinner(spinner: &ProgressBar) {
-    let style = ProgressStyle::default_spinner()
-        .tick_chars("⠁⠂⠄⡀⢀⠠⠐⠈ ")
-        .template("{spinner:.green} [{elapsed_precise}] {msg:.cyan.bold}");
-    spinner.set_style(style);
-    spinner.enable_steady_tick(TICK_RATE);
+/// Check the saved pages of the given Dynamixels that have gone stale for
+/// changes, printing the models whose pages changed
+async fn refresh_pages(
+    client: &HttpClient,
+    indexes: Vec<ActuatorIndex>,
+    cache: &PageCache,
+) -> Result<ExitCode> {
+    let mut refreshed = cache.refresh_all(client, indexes).await;
+    refreshed.sort_by(|a, b| a.0.url.cmp(&b.0.url));
+
+    let (mut changed, mut unchanged, mut fresh, mut missing, mut failed) = (0, 0, 0, 0, 0);
+    for (dxl, result) in refreshed {
+        match result {
+            Ok(Refreshed::Changed) => {
+                println!("{} ({}) has changed", dxl.name, dxl.url);
+                changed += 1;
+            }
+            Ok(Refreshed::Unchanged) => unchanged += 1,
+            Ok(Refreshed::Fresh) => fresh += 1,
+            Ok(Refreshed::Missing) => missing += 1,
+            // Retired models keep the page saved before they were retired
+            Err(e)
+                if e.chain()
+                    .any(|cause| cause.is::<PageUnavailable>() || cause.is::<Disallowed>()) =>
+            {
+                info!("{:#}", e);
+                unchanged += 1;
+            }
+            Err(e) => {
+                warn!("{:#}", e);
+                failed += 1;
+            }
+        }
+    }
+    println!(
+        "{} changed, {} unchanged, {} checked within the last {} hours, {} not saved, {} failed",
+        changed,
+        unchanged,
+        fresh,
+        cache.ttl.as_secs() / (60 * 60),
+        missing,
+        failed
+    );
+
+    Ok(match failed {
+        0 => ExitCode::SUCCESS,
+        _ => ExitCode::from(ErrorCategory::PartialSuccess.exit_code()),
+    })
 }
 
-fn configure_dxl_spinner(spinner: &ProgressBar) {
-    let style = ProgressStyle::default_spinner()
-        .tick_chars("⠁⠂⠄⡀⢀⠠⠐⠈ ")
-        .template("{prefix:.magenta.bold} {msg:.green}");
-    spinner.set_style(style);
+static EXIT_CODES: &str = "EXIT CODES:
+    0    Every Dynamixel was scraped
+    1    Any other error
+    2    No Dynamixels were selected
+    3    A network failure
+    4    A page or the navigation couldn't be parsed
+    5    Output couldn't be written
+    6    Some, but not all, Dynamixels failed
+    7    --check found the control tables have changed
+    130  Interrupted with Ctrl-C";
+
+fn main() -> ExitCode {
+    let runtime = match tokio::runtime::Runtime::new() {
+        Ok(runtime) => runtime,
+        Err(e) => {
+            eprintln!("Error: Failed to start the async runtime: {:?}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let code = match runtime.block_on(run()) {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("Error: {:?}", e);
+            ExitCode::from(ErrorCategory::of(&e).map_or(1, |category| category.exit_code()))
+        }
+    };
+    // Parses that timed out can't be stopped, so don't wait for them to exit
+    runtime.shutdown_background();
+
+    code
 }
 
-#[tokio::main]
-async fn main() -> Result<()> {
+async fn run() -> Result<ExitCode> {
     let matches = App::new("Dynamixel Control Table Scraper")
                         .version("0.1")
+                        .after_help(EXIT_CODES)
                         .author("Angus Finch <developer.finchie@gmail.com>")
                         .about("Scrapes the Robotis E-Manual for Dynamixel control tables")
                         .arg(Arg::with_name("lib")
@@ -92,9 +334,122 @@ async fn main() -> Result<()> {
                             .long("ron")
                             .takes_value(false)
                             .help("If the control table should be output in RON"))
+                        .arg(Arg::with_name("with_docs")
+                            .long("with-docs")
+                            .takes_value(false)
+                            .requires("ron")
+                            .help("Also summarise each model in Markdown beside its RON object, with its model number, protocols, source and key items"))
+                        .arg(Arg::with_name("yaml")
+                            .long("yaml")
+                            .takes_value(false)
+                            .help("If the control table should be output in YAML, eg for ROS parameter files"))
+                        .arg(Arg::with_name("msgpack")
+                            .long("msgpack")
+                            .takes_value(false)
+                            .help("If the control table should be output as compact MessagePack under objects/, with an index, eg to include_bytes! into firmware"))
+                        .arg(Arg::with_name("markdown")
+                            .long("markdown")
+                            .takes_value(false)
+                            .help("If the control table should be documented in Markdown, under docs/"))
+                        .arg(Arg::with_name("json_schema")
+                            .long("json-schema")
+                            .takes_value(false)
+                            .help("If a JSON Schema of the values each writable item accepts should be written, under schemas/"))
+                        .arg(Arg::with_name("sqlite")
+                            .long("sqlite")
+                            .value_name("FILE")
+                            .help("Write every control table to a SQLite database, to query across models with SQL"))
+                        .arg(Arg::with_name("cpp")
+                            .long("cpp")
+                            .value_name("FILE")
+                            .help("Write every control table to a C++17 header of constexpr tables, eg for the Dynamixel SDK's C++ API. Models are named as in the library"))
+                        .arg(Arg::with_name("typescript")
+                            .long("typescript")
+                            .value_name("FILE")
+                            .min_values(0)
+                            .max_values(1)
+                            .help("Write every control table to TypeScript definitions, eg for a web dashboard configuring servos, in dxlControlTables.ts unless given a file. Models are named as in the library"))
+                        .arg(Arg::with_name("lib_path")
+                            .long("lib-path")
+                            .value_name("DIR")
+                            .default_value(create_lib::DEFAULT_LIB_PATH)
+                            .help("The crate to generate the library into. An existing crate keeps its Cargo.toml and gets src/generated.rs"))
+                        .arg(Arg::with_name("lib_name")
+                            .long("lib-name")
+                            .value_name("NAME")
+                            .default_value(create_lib::DEFAULT_LIB_NAME)
+                            .help("The package name of the generated library, if the scraper creates the crate"))
+                        .arg(Arg::with_name("lib_version")
+                            .long("lib-version")
+                            .value_name("VERSION")
+                            .default_value(create_lib::DEFAULT_LIB_VERSION)
+                            .help("The version of the generated library, if the scraper creates the crate"))
+                        .arg(Arg::with_name("lib_authors")
+                            .long("lib-authors")
+                            .value_name("AUTHOR")
+                            .multiple(true)
+                            .number_of_values(1)
+                            .help("An author of the generated library, if the scraper creates the crate"))
+                        .arg(Arg::with_name("lib_license")
+                            .long("lib-license")
+                            .value_name("SPDX")
+                            .help("The license of the generated library, eg \"MIT OR Apache-2.0\", which crates.io requires to publish it"))
+                        .arg(Arg::with_name("lib_repository")
+                            .long("lib-repository")
+                            .value_name("URL")
+                            .help("The repository the generated library is published from"))
+                        .arg(Arg::with_name("msrv")
+                            .long("msrv")
+                            .value_name("VERSION")
+                            .help("The oldest Rust the generated library should build with, eg 1.56. Newer language features are avoided, and a created crate gets rust-version and a test checking it"))
+                        .arg(Arg::with_name("naming")
+                            .long("naming")
+                            .value_name("STRATEGY")
+                            .possible_values(NAMING_STRATEGIES)
+                            .default_value("compact")
+                            .help("How models are named in the library: compact (XM430W210) or underscored (XM430_W210)"))
+                        .arg(Arg::with_name("snapshot")
+                            .long("snapshot")
+                            .takes_value(false)
+                            .help("Compare the control tables with the last snapshot under snapshots/, suggest a version bump for the library and save a new snapshot"))
+                        .arg(Arg::with_name("keep_intermediates")
+                            .long("keep-intermediates")
+                            .takes_value(false)
+                            .help("Save the fetched HTML and merged control table of each Dynamixel under artifacts/"))
+                        .arg(Arg::with_name("debug_tables")
+                            .long("debug-tables")
+                            .takes_value(false)
+                            .help("List every table on pages whose control tables aren't where they're expected, in the log and the report"))
+                        .arg(Arg::with_name("table_indexes")
+                            .long("table-indexes")
+                            .value_name("FILE")
+                            .help("A RON map of model slugs to the positions of their EEPROM and RAM tables on the page, eg {\"xm430-w210\": (3, 4)}"))
+                        .arg(Arg::with_name("with_raw")
+                            .long("with-raw")
+                            .takes_value(false)
+                            .help("Keep the text of each item's cells in the RON objects alongside the values parsed from them, to check for parsing bugs without scraping again"))
+                        .arg(Arg::with_name("interactive")
+                            .short("i")
+                            .long("interactive")
+                            .takes_value(false)
+                            .conflicts_with_all(&["format", "servo_choice", "url"])
+                            .help("Choose which Dynamixels to scrape and which formats to output from a list"))
+                        .arg(Arg::with_name("check")
+                            .long("check")
+                            .takes_value(false)
+                            .conflicts_with_all(&["format", "interactive", "snapshot", "dry_run"])
+                            .help("Compare the control tables with the RON objects under objects/ without writing anything, printing the changes as JSON and exiting with 7 if there are any"))
+                        .arg(Arg::with_name("force")
+                            .long("force")
+                            .takes_value(false)
+                            .help("Write output even if another run in the same directory holds the lock on it"))
+                        .arg(Arg::with_name("dry_run")
+                            .long("dry-run")
+                            .takes_value(false)
+                            .help("Print which Dynamixels would be downloaded and which files would be written, without fetching or writing them"))
                         .group(ArgGroup::with_name("format")
                             .multiple(true)
-                            .args(&["lib", "ron"]))
+                            .args(&["lib", "ron", "yaml", "msgpack", "markdown", "json_schema", "sqlite", "cpp", "typescript"]))
                         .arg(Arg::with_name("dynamixel")
                             .short("d")
                             .long("dxl")
@@ -109,125 +464,499 @@ async fn main() -> Result<()> {
                             .help("Specifies which series of Dynamixel to download.")
                             .takes_value(true)
                             .multiple(true))
+                        .arg(Arg::with_name("section")
+                            .long("section")
+                            .value_name("SECTION")
+                            .help("Also scrape the pages of hardware other than the Dynamixels in this section of the navigation (eg Parts), by title or dot-separated path, writing them under hardware/ in each output directory")
+                            .takes_value(true)
+                            .multiple(true))
+                        .arg(Arg::with_name("url")
+                            .long("url")
+                            .value_name("PAGE")
+                            .help("Also scrape a page missing from the navigation, by URL or the path of a saved page (eg saved/x/xm430-w210.html). Unless --dxl or --series are given, only these pages are scraped")
+                            .takes_value(true)
+                            .multiple(true)
+                            .number_of_values(1))
+                        .arg(Arg::with_name("limit")
+                            .long("limit")
+                            .value_name("N")
+                            .validator(|count| count.parse::<usize>().map(|_| ()).map_err(|e| e.to_string()))
+                            .help("Only scrape the first N of the Dynamixels selected, for a quick end to end run"))
+                        .arg(Arg::with_name("sample")
+                            .long("sample")
+                            .value_name("SAMPLE")
+                            .conflicts_with("limit")
+                            .validator(|sample| sample.parse::<Sample>().map(|_| ()).map_err(|e| e.to_string()))
+                            .help("Only scrape a sample of the Dynamixels selected, either first:N or random:N for N picked at random each run"))
                         .group(ArgGroup::with_name("servo_choice")
                             .args(&["dynamixel", "series"])
                             .multiple(true))
+                        .arg(Arg::with_name("locale")
+                            .long("locale")
+                            .value_name("LOCALE")
+                            .possible_values(LOCALES)
+                            .default_value("en")
+                            .help("Which language of the E-Manual to scrape. Field names are always in English"))
                         .arg(Arg::with_name("navigation_url")
                             .long("navigation_url")
-                            .default_value("https://raw.githubusercontent.com/ROBOTIS-GIT/emanual/master/_data/navigation.yml")
-                            .help("Specify the location of the navigation URL used to locate Dynamixels"))
+                            .takes_value(true)
+                            .help("Specify the location of the navigation URL used to locate Dynamixels (defaults to the one for --locale)"))
+                        .arg(Arg::with_name("manual_rev")
+                            .long("manual-rev")
+                            .value_name("REVISION")
+                            .conflicts_with("navigation_url")
+                            .help("Scrape the navigation as of a commit of the E-Manual's source repository, and tag the outputs with it"))
+                        .arg(Arg::with_name("navigation_path")
+                            .long("navigation-path")
+                            .value_name("PATH")
+                            .help("The dot-separated key path of the series in the navigation YAML, eg main.0.children (searched for by default)"))
                         .arg(Arg::with_name("base_url")
                             .long("base_url")
-                            .default_value("https://emanual.robotis.com/docs/en")
-                            .help("Specify the base URL to use")).get_matches();
-
-    let nav_download = ProgressBar::new_spinner().with_message("Fetching navigation index");
-    configure_spinner(&nav_download);
-    let yaml = reqwest::get(matches.value_of("navigation_url").unwrap()).await?;
-    nav_download.finish();
-
-    let yaml_parse = ProgressBar::new_spinner().with_message("Parsing YAML");
-    configure_spinner(&yaml_parse);
-    let navigation: Value = serde_yaml::from_str(&yaml.text().await?)?;
-    let dropdown_elements = &navigation["main"][0]["children"];
-
-    let dxls: Vec<&str> = match matches.is_present("dynamixel") {
-        true => matches.values_of("dynamixel").unwrap().collect(),
-        false => vec![],
+                            .takes_value(true)
+                            .help("Specify the base URL to use (defaults to the one for --locale)"))
+                        .arg(Arg::with_name("proxy")
+                            .long("proxy")
+                            .value_name("URL")
+                            .help("Send all requests through this proxy, overriding HTTP_PROXY and HTTPS_PROXY"))
+                        .arg(Arg::with_name("ca_cert")
+                            .long("ca-cert")
+                            .value_name("FILE")
+                            .help("Trust the PEM-encoded certificate(s) in this file, eg for a corporate proxy"))
+                        .arg(Arg::with_name("user_agent")
+                            .long("user-agent")
+                            .value_name("AGENT")
+                            .help("The User-Agent to send with requests, and to follow robots.txt as"))
+                        .arg(Arg::with_name("ignore_robots")
+                            .long("ignore-robots")
+                            .help("Scrape pages even if the E-Manual's robots.txt disallows them, eg for a local mirror"))
+                        .arg(Arg::with_name("source")
+                            .long("source")
+                            .value_name("SOURCE")
+                            .default_value("http")
+                            .help("Where to read pages from: http, or dir:PATH for a mirror of the E-Manual such as wget --mirror makes")
+                            .validator(|source| source.parse::<SourceSpec>().map(|_| ()).map_err(|e| e.to_string())))
+                        .arg(Arg::with_name("timeout")
+                            .long("timeout")
+                            .value_name("SECONDS")
+                            .default_value("30")
+                            .help("How long to wait for each request to complete"))
+                        .arg(Arg::with_name("page_timeout")
+                            .long("page-timeout")
+                            .value_name("SECONDS")
+                            .default_value("30")
+                            .help("How long each Dynamixel's page may take to download, and then to parse, before it's recorded as failed"))
+                        .arg(Arg::with_name("log_level")
+                            .long("log-level")
+                            .value_name("LEVEL")
+                            .possible_values(&["error", "warn", "info", "debug", "trace"])
+                            .default_value("warn")
+                            .help("The most verbose level of log messages to print"))
+                        .arg(Arg::with_name("log_json")
+                            .long("log-json")
+                            .takes_value(false)
+                            .help("Print log messages as JSON, one object per line"))
+                        .arg(Arg::with_name("fail_fast")
+                            .long("fail-fast")
+                            .takes_value(false)
+                            .conflicts_with("keep_going")
+                            .help("Stop at the first Dynamixel that fails, without writing any output"))
+                        .arg(Arg::with_name("keep_going")
+                            .long("keep-going")
+                            .takes_value(false)
+                            .help("Scrape every Dynamixel even if some fail, writing output for the rest (the default)"))
+                        .arg(Arg::with_name("report")
+                            .long("report")
+                            .value_name("FILE")
+                            .min_values(0)
+                            .max_values(1)
+                            .help("Also write the summary of the run as JSON, to report.json unless a file is given"))
+                        .arg(Arg::with_name("metrics")
+                            .long("metrics")
+                            .value_name("FILE")
+                            .takes_value(true)
+                            .help("Also write the requests made, bytes downloaded and latency of each host in the Prometheus text format"))
+                        .subcommand(SubCommand::with_name("analyze")
+                            .about("Analyse control tables scraped with --ron, or the pages of the E-Manual")
+                            .subcommand(SubCommand::with_name("compare")
+                                .about("Print which items each model has, and where their addresses differ")
+                                .arg(Arg::with_name("models")
+                                    .value_name("SERVO")
+                                    .multiple(true)
+                                    .help("The models to compare, eg xm430-w210 (defaults to every scraped model)"))
+                                .arg(Arg::with_name("objects")
+                                    .long("objects")
+                                    .value_name("DIR")
+                                    .default_value("objects")
+                                    .help("The directory the RON objects were written to"))
+                                .arg(Arg::with_name("differences_only")
+                                    .long("differences-only")
+                                    .takes_value(false)
+                                    .help("Only list items that are missing from a model or at differing addresses")))
+                            .subcommand(SubCommand::with_name("layout")
+                                .about("Print a map of the bytes of each model's control table, marking gaps and overlapping items")
+                                .arg(Arg::with_name("models")
+                                    .value_name("SERVO")
+                                    .multiple(true)
+                                    .help("The models to map, eg xm430-w210 (defaults to every scraped model)"))
+                                .arg(Arg::with_name("objects")
+                                    .long("objects")
+                                    .value_name("DIR")
+                                    .default_value("objects")
+                                    .help("The directory the RON objects were written to")))
+                            .subcommand(SubCommand::with_name("report")
+                                .about("Write the findings of every analysis, and the warnings from parsing, to a single HTML page with sortable tables")
+                                .arg(Arg::with_name("models")
+                                    .value_name("SERVO")
+                                    .multiple(true)
+                                    .help("The models to report on, eg xm430-w210 (defaults to every scraped model)"))
+                                .arg(Arg::with_name("objects")
+                                    .long("objects")
+                                    .value_name("DIR")
+                                    .default_value("objects")
+                                    .help("The directory the RON objects were written to"))
+                                .arg(Arg::with_name("run_report")
+                                    .long("run-report")
+                                    .value_name("FILE")
+                                    .takes_value(true)
+                                    .help("The JSON report of the run that scraped them, written by --report, to also list the models that failed"))
+                                .arg(Arg::with_name("output")
+                                    .long("output")
+                                    .value_name("FILE")
+                                    .default_value(DEFAULT_HTML_REPORT_PATH)
+                                    .help("Where to write the report")))
+                            .subcommand(SubCommand::with_name("headings")
+                                .about("Download the page of every selected Dynamixel and list each unique combination of table headings, with the models using it")
+                                .arg(Arg::with_name("cached")
+                                    .long("cached")
+                                    .takes_value(false)
+                                    .help("Read the pages saved under artifacts/ by --keep-intermediates instead of downloading them, once those older than --cache-ttl are checked for changes"))
+                                .arg(Arg::with_name("cache_ttl")
+                                    .long("cache-ttl")
+                                    .value_name("HOURS")
+                                    .default_value("24")
+                                    .help("How long a saved page is read before it's checked with the E-Manual for changes"))
+                                .arg(Arg::with_name("all_tables")
+                                    .long("all-tables")
+                                    .takes_value(false)
+                                    .help("List the headings of every table, not only control tables"))))
+                        .subcommand(SubCommand::with_name("verify")
+                            .about("Scrape and generate the library as usual, then check that it compiles, reporting the model and item of any code that doesn't")
+                            .arg(Arg::with_name("test")
+                                .long("test")
+                                .takes_value(false)
+                                .help("Also run the library's tests")))
+                        .subcommand(SubCommand::with_name("refresh")
+                            .about("Check the pages of the selected Dynamixels saved under artifacts/ that are older than --cache-ttl for changes, with conditional requests, and save those that have changed")
+                            .arg(Arg::with_name("cache_ttl")
+                                .long("cache-ttl")
+                                .value_name("HOURS")
+                                .default_value("24")
+                                .help("How long a saved page is used before it's checked with the E-Manual for changes"))).get_matches();
+
+    // Logs go to stderr alongside the progress bars, leaving stdout for output.
+    // Warnings are counted regardless of the log level for the final report.
+    let level: Level = matches.value_of("log_level").unwrap().parse()?;
+    let warnings = WarningCounter::default();
+    let logger = fmt::layer().with_writer(std::io::stderr);
+    let logger = match matches.is_present("log_json") {
+        true => logger.json().boxed(),
+        false => logger.boxed(),
+    };
+    tracing_subscriber::registry()
+        .with(logger.with_filter(LevelFilter::from_level(level)))
+        .with(warnings.clone())
+        .init();
+    let started = Instant::now();
+
+    // Analysing the headings of the pages needs the navigation, so waits
+    // until it's found
+    let analyze = matches.subcommand_matches("analyze");
+    if let Some(analyze) = analyze.filter(|analyze| analyze.subcommand_name() != Some("headings")) {
+        return analyze_objects(analyze);
+    }
+
+    let mut client = build_client(&matches)?;
+    let locale: Locale = matches.value_of("locale").unwrap().parse()?;
+    let mut opts = RunOptions::new(locale);
+    if let Some(url) = matches.value_of("navigation_url") {
+        opts.navigation_url = url.to_string();
+    }
+    if let Some(url) = matches.value_of("base_url") {
+        opts.base_url = url.to_string();
+    }
+    if let Some(revision) = matches.value_of("manual_rev") {
+        if !is_commit_hash(revision) {
+            warn!(%revision, "Not a commit hash, so the scrape can't be reproduced once it moves");
+        }
+        // The repository only has the source of the model pages, which can't
+        // be scraped until it's built
+        if !matches.is_present("base_url") {
+            warn!("Model pages are scraped from the live E-Manual, set --base_url to a build of the revision to scrape it as it was");
+        }
+        opts.navigation_url = locale.navigation_url_at(revision);
+        opts.manual_revision = Some(revision.to_string());
+    }
+    if !matches.is_present("ignore_robots") {
+        let user_agent = matches.value_of("user_agent").unwrap_or(USER_AGENT);
+        client.respect_robots(&opts.base_url, user_agent).await?;
+    }
+    opts.navigation_path = matches.value_of("navigation_path").map(String::from);
+    opts.filters = Filters {
+        dynamixels: matches
+            .values_of("dynamixel")
+            .map_or(vec![], |values| values.map(String::from).collect()),
+        series: matches
+            .values_of("series")
+            .map_or(vec![], |values| values.map(String::from).collect()),
+        sections: matches
+            .values_of("section")
+            .map_or(vec![], |values| values.map(String::from).collect()),
+    };
+    opts.extra_pages = matches
+        .values_of("url")
+        .map_or(vec![], |values| values.map(String::from).collect());
+    opts.sample = match (matches.value_of("limit"), matches.value_of("sample")) {
+        (Some(count), _) => Some(Sample::First(count.parse()?)),
+        (None, Some(sample)) => Some(sample.parse()?),
+        (None, None) => None,
+    };
+    opts.lib_target = LibTarget {
+        path: PathBuf::from(matches.value_of("lib_path").unwrap()),
+        name: matches.value_of("lib_name").unwrap().to_string(),
+        naming: matches.value_of("naming").unwrap().parse()?,
+        package: PackageMetadata {
+            version: matches.value_of("lib_version").unwrap().to_string(),
+            authors: matches
+                .values_of("lib_authors")
+                .map_or(vec![], |values| values.map(String::from).collect()),
+            license: matches.value_of("lib_license").map(String::from),
+            repository: matches.value_of("lib_repository").map(String::from),
+        },
+        msrv: matches.value_of("msrv").map(str::parse).transpose()?,
+        // Tagged with opts.manual_revision by the pipeline
+        manual_revision: None,
     };
-    let series: Vec<&str> = match matches.is_present("series") {
-        true => matches.values_of("series").unwrap().collect(),
-        false => vec![],
+    opts.fetch = FetchOptions {
+        keep_intermediates: matches.is_present("keep_intermediates"),
+        fail_fast: matches.is_present("fail_fast"),
+        debug_tables: matches.is_present("debug_tables"),
+        page_timeout: Duration::from_secs(matches.value_of("page_timeout").unwrap().parse()?),
+        table_indexes: match matches.value_of("table_indexes") {
+            Some(path) => ron::from_str(
+                &fs::read_to_string(path).with_context(|| format!("Failed to read {}", path))?,
+            )
+            .with_context(|| format!("Failed to parse {}", path))?,
+            None => BTreeMap::new(),
+        },
+        with_raw: matches.is_present("with_raw"),
+        ..FetchOptions::default()
     };
+    opts.snapshot = matches.is_present("snapshot");
+    opts.check = matches.is_present("check");
+    opts.force = matches.is_present("force");
+    opts.sqlite = matches.value_of("sqlite").map(PathBuf::from);
+    opts.cpp = matches.value_of("cpp").map(PathBuf::from);
+    if matches.is_present("typescript") {
+        let path = matches
+            .value_of("typescript")
+            .unwrap_or(typescript::DEFAULT_TYPESCRIPT_PATH);
+        opts.typescript = Some(PathBuf::from(path));
+    }
 
-    let mut indexes: Vec<ActuatorIndex> = Vec::new();
-
-    for element in dropdown_elements.as_sequence().unwrap() {
-        let title: String = element["title"]
-            .as_str()
-            .unwrap()
-            .chars()
-            .filter(|x| x != &'*')
-            .collect();
-        if title.contains("Series") {
-            let children = element["children"].as_sequence().unwrap();
-            for child in children {
-                let url = format!(
-                    "{}{}",
-                    matches.value_of("base_url").unwrap(),
-                    child["url"].as_str().unwrap()
-                );
-                let name = child["title"].as_str().unwrap().to_string();
-                let dxl = ActuatorIndex { url, name };
-
-                if matches.is_present("servo_choice") {
-                    if dxls.contains(&dxl.url.split('/').nth_back(1).unwrap()) {
-                        indexes.push(dxl);
-                        continue;
-                    }
-
-                    if series.contains(&title.split(' ').next().unwrap()) {
-                        indexes.push(dxl)
-                    }
-                } else {
-                    indexes.push(dxl);
-                }
+    info!(url = %opts.navigation_url, %locale, "Scraping the E-Manual");
+    let indexes = find_dynamixels(&client, &opts).await?;
+
+    if let Some(headings) = analyze.and_then(|analyze| analyze.subcommand_matches("headings")) {
+        return analyze_headings(&client, indexes, headings).await;
+    }
+    if let Some(refresh) = matches.subcommand_matches("refresh") {
+        return refresh_pages(&client, indexes, &page_cache(refresh)?).await;
+    }
+
+    // Default to only generating the library if no format is specified
+    let indexes = match matches.is_present("interactive") {
+        true => {
+            let (indexes, formats) = select_interactively(indexes)?;
+            opts.formats = formats;
+            indexes
+        }
+        false => {
+            if opts.check {
+                opts.formats = Formats::default();
+            } else if matches.is_present("format") {
+                opts.formats = Formats {
+                    lib: matches.is_present("lib"),
+                    ron: matches.is_present("ron"),
+                    ron_summaries: matches.is_present("with_docs"),
+                    yaml: matches.is_present("yaml"),
+                    msgpack: matches.is_present("msgpack"),
+                    markdown: matches.is_present("markdown"),
+                    json_schema: matches.is_present("json_schema"),
+                };
             }
+            indexes
         }
+    };
+    let verify = matches.subcommand_matches("verify");
+    if verify.is_some() {
+        opts.formats.lib = true;
     }
+    let (formats, lib_target, total) = (opts.formats, &opts.lib_target, indexes.len());
 
-    yaml_parse.finish();
+    if matches.is_present("dry_run") {
+        println!("Would download {} Dynamixels:", indexes.len());
+        for dxl in &indexes {
+            println!("    {} ({})", dxl.name, dxl.url);
+        }
 
-    let counter: Arc<AtomicUsize> = Arc::new(AtomicUsize::new(0));
-    let total = Arc::new(indexes.len());
-    let fetch_progress =
-        ProgressBar::new_spinner().with_message("Downloading & extracting Dynamixels");
-    configure_spinner(&fetch_progress);
-    fetch_progress.disable_steady_tick();
+        println!("Would write:");
+        if formats.lib {
+            println!("    {}", lib_target.manifest_path().display());
+            println!("    {}", lib_target.source_path().display());
+            println!("    {}", lib_target.discriminants_path().display());
+            if !lib_target.is_existing_crate() {
+                println!("    {}", lib_target.readme_path().display());
+                if lib_target.msrv.is_some() {
+                    println!("    {}", lib_target.msrv_test_path().display());
+                }
+            }
+        }
 
-    // Thanks to http://patshaughnessy.net/2020/1/20/downloading-100000-files-using-async-rust
-    let fetches = stream::iter(indexes)
-        .map(|dxl| {
-            let spinner = Arc::new(ProgressBar::new_spinner().with_message(dxl.name.clone()));
-            configure_dxl_spinner(&spinner);
+        if formats.ron {
+            for dxl in &indexes {
+                let (series, raw_name) = dxl.output_name();
+                println!("    {}", object_path(&series, &raw_name));
+                if formats.ron_summaries {
+                    println!("    {}", summary_path(&series, &raw_name));
+                }
+            }
+            println!("    {}", INDEX_PATH);
+            let series: BTreeSet<String> = indexes.iter().map(|dxl| dxl.output_name().0).collect();
+            for series in series {
+                println!("    {}", series_object_path(&series));
+            }
+        }
+
+        if formats.yaml {
+            for dxl in &indexes {
+                let (series, raw_name) = dxl.output_name();
+                println!("    {}", yaml_object_path(&series, &raw_name));
+            }
+        }
+
+        if formats.msgpack {
+            for dxl in &indexes {
+                let (series, raw_name) = dxl.output_name();
+                println!("    {}", msgpack_object_path(&series, &raw_name));
+            }
+            println!("    {}", MSGPACK_INDEX_PATH);
+        }
 
-            counter.store(counter.load(Ordering::Relaxed) + 1, Ordering::Relaxed);
-            spinner.set_prefix(format!("{:?}/{}", counter, total));
+        if formats.markdown {
+            for dxl in &indexes {
+                let (series, raw_name) = dxl.output_name();
+                println!("    {}", markdown_path(&series, &raw_name));
+            }
+        }
 
-            tokio::spawn(async move {
-                let req = reqwest::get(&dxl.url).await.unwrap();
-                let text = req.text().await.unwrap();
-                let actuator = Actuator::new(dxl.url, dxl.name, text).unwrap();
-                spinner.finish_and_clear();
+        if formats.json_schema {
+            for dxl in &indexes {
+                let (series, raw_name) = dxl.output_name();
+                println!("    {}", schema_path(&series, &raw_name));
+            }
+        }
 
-                actuator
-            })
-        })
-        .buffer_unordered(20)
-        .collect::<Vec<_>>()
-        .await;
+        if let Some(path) = &opts.sqlite {
+            println!("    {}", path.display());
+        }
 
-    fetch_progress.tick();
-    fetch_progress.finish();
+        if let Some(path) = &opts.cpp {
+            println!("    {}", path.display());
+        }
 
-    let data_write = ProgressBar::new_spinner().with_message("Writing data");
-    configure_spinner(&data_write);
-    let actuators: Vec<Actuator> = fetches.into_iter().map(|dxl| dxl.unwrap()).collect();
-    if matches.is_present("format") {
-        if matches.is_present("lib") {
-            create_lib::create_lib(&actuators)?;
+        if let Some(path) = &opts.typescript {
+            println!("    {}", path.display());
         }
 
-        if matches.is_present("ron") {
-            for mut dxl in actuators {
-                dxl.write_object()?;
+        if matches.is_present("keep_intermediates") {
+            for dxl in &indexes {
+                let (series, raw_name) = split_url(&dxl.url);
+                let dir = artifacts_dir(&series, &raw_name);
+                println!("    {}/page.html", dir);
+                println!("    {}/table.csv", dir);
             }
         }
-    } else {
-        create_lib::create_lib(&actuators)?;
+
+        return Ok(ExitCode::SUCCESS);
     }
 
-    data_write.finish();
+    // Nothing is written before the scrape, so until then Ctrl-C can just
+    // kill the process. A second Ctrl-C does too, rather than waiting.
+    let (cancel, cancelled) = watch::channel(false);
+    opts.fetch.cancel = Some(cancelled);
+    tokio::spawn(async move {
+        if signal::ctrl_c().await.is_ok() {
+            eprintln!("Cancelling, press Ctrl-C again to stop immediately");
+            let _ = cancel.send(true);
+        }
+        if signal::ctrl_c().await.is_ok() {
+            std::process::exit(ErrorCategory::Interrupted.exit_code().into());
+        }
+    });
+
+    let mut report = scrape(&client, indexes, &opts).await?;
+    report.warnings = warnings.count();
+    report.elapsed_secs = started.elapsed().as_secs_f64();
 
-    Ok(())
+    // Only the changes go to stdout when checking, so they can be piped
+    match &report.changes {
+        Some(changes) => {
+            eprintln!("{}", report);
+            println!("{}", serde_json::to_string_pretty(changes)?);
+        }
+        None => println!("{}", report),
+    }
+    if matches.is_present("report") {
+        let path = matches.value_of("report").unwrap_or("report.json");
+        write_atomic(path, serde_json::to_string_pretty(&report)?)
+            .with_context(|| format!("Failed to write report to {}", path))?;
+    }
+    if let Some(path) = matches.value_of("metrics") {
+        write_atomic(path, report.network.to_prometheus())
+            .with_context(|| format!("Failed to write metrics to {}", path))?;
+    }
+
+    // The library is only written if the scrape finished, and with every
+    // model unless asked to keep going
+    let lib_written = !report.interrupted && (report.failed == 0 || !opts.fetch.fail_fast);
+    if let Some(verify) = verify.filter(|_| lib_written) {
+        let target = opts.lib_target.clone();
+        let run_tests = verify.is_present("test");
+        task::spawn_blocking(move || verify_lib(&target, run_tests)).await??;
+    }
+
+    match report.failure() {
+        Some(ErrorCategory::Interrupted) => {
+            eprintln!(
+                "Interrupted after {} of {} Dynamixels",
+                report.attempted, total
+            );
+            Ok(ExitCode::from(ErrorCategory::Interrupted.exit_code()))
+        }
+        Some(category) => {
+            eprintln!(
+                "{} of {} Dynamixels failed ({})",
+                report.failed, report.attempted, category
+            );
+            Ok(ExitCode::from(category.exit_code()))
+        }
+        None if report
+            .changes
+            .as_ref()
+            .is_some_and(|changes| changes.changed) =>
+        {
+            Ok(ExitCode::from(CHANGED_EXIT_CODE))
+        }
+        None => Ok(ExitCode::SUCCESS),
+    }
 }
@@ -0,0 +1,479 @@
+use crate::analysis::load_objects;
+use crate::cpp::write_header;
+use crate::create_lib::{self, LibTarget};
+use crate::fetch::{
+    build_index, check_output_paths, extra_page, fetch_all, ActuatorIndex, FetchOptions, Filters,
+    HttpClient, Sample,
+};
+use crate::locale::Locale;
+use crate::lock::{OutputLock, LOCK_PATH};
+use crate::progress::{self, ProgressEvent};
+use crate::report::{ErrorCategory, ModelStatus, Report};
+use crate::serialize::{serialize_index, serialize_index_msgpack, IndexEntry};
+use crate::snapshot::{self, ChangeSummary, Snapshot, SNAPSHOTS_DIR};
+use crate::sqlite::write_sqlite;
+use crate::typescript::write_typescript;
+use crate::{
+    relative_msgpack_path, series_object_path, write_atomic, write_series_object, Actuator,
+    INDEX_PATH, MSGPACK_INDEX_PATH,
+};
+use anyhow::{Context, Error, Result};
+use indicatif::{ProgressBar, ProgressStyle};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::task;
+use tracing::{debug, info, info_span};
+
+static TICK_RATE: u64 = 50;
+
+/// The formats to write the scraped control tables in
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Formats {
+    pub lib: bool,
+    pub ron: bool,
+    /// A Markdown summary of each model beside its RON object, only written
+    /// along with the RON
+    pub ron_summaries: bool,
+    pub yaml: bool,
+    /// Compact MessagePack objects beside the RON, with an index of their own
+    pub msgpack: bool,
+    pub markdown: bool,
+    /// A JSON Schema of the values each writable item accepts, under schemas/
+    pub json_schema: bool,
+}
+
+/// Where to find the Dynamixels, which of them to scrape and what to write
+#[derive(Clone, Debug)]
+pub struct RunOptions {
+    pub navigation_url: String,
+    /// The URL model pages in the navigation are relative to
+    pub base_url: String,
+    /// Where the series are listed in the navigation, if not searched for
+    pub navigation_path: Option<String>,
+    pub filters: Filters,
+    /// Pages to scrape that aren't in the navigation, by URL or path. Unless
+    /// filters are also given, only these are scraped.
+    pub extra_pages: Vec<String>,
+    /// Only scrape a sample of the Dynamixels selected, if given
+    pub sample: Option<Sample>,
+    pub formats: Formats,
+    pub lib_target: LibTarget,
+    pub fetch: FetchOptions,
+    /// Compare the control tables with the last snapshot and save a new one
+    pub snapshot: bool,
+    /// Compare the control tables with the RON objects already written under
+    /// objects/, for the report. Usually set along with no formats, so
+    /// nothing is written.
+    pub check: bool,
+    /// The SQLite database to write every control table to, if any
+    pub sqlite: Option<PathBuf>,
+    /// The C++ header to write every control table to, if any
+    pub cpp: Option<PathBuf>,
+    /// The TypeScript definitions to write every control table to, if any
+    pub typescript: Option<PathBuf>,
+    /// The revision of the E-Manual's source being scraped, if pinned, which
+    /// the report and the library are tagged with
+    pub manual_revision: Option<String>,
+    /// Write output even if another run holds the lock on the working
+    /// directory
+    pub force: bool,
+}
+
+impl RunOptions {
+    /// Scrape every Dynamixel in the given locale of the E-Manual into the
+    /// library
+    pub fn new(locale: Locale) -> RunOptions {
+        RunOptions {
+            navigation_url: locale.navigation_url(),
+            base_url: locale.base_url(),
+            navigation_path: None,
+            filters: Filters::default(),
+            extra_pages: vec![],
+            sample: None,
+            formats: Formats {
+                lib: true,
+                ..Formats::default()
+            },
+            lib_target: LibTarget::default(),
+            fetch: FetchOptions::default(),
+            snapshot: false,
+            check: false,
+            sqlite: None,
+            cpp: None,
+            typescript: None,
+            manual_revision: None,
+            force: false,
+        }
+    }
+}
+
+pub fn configure_spinner(spinner: &ProgressBar) {
+    let style = ProgressStyle::default_spinner()
+        .tick_chars("⠁⠂⠄⡀⢀⠠⠐⠈ ")
+        .template("{spinner:.green} [{elapsed_precise}] {msg:.cyan.bold}");
+    spinner.set_style(style);
+    spinner.enable_steady_tick(TICK_RATE);
+}
+
+/// A spinner with the given message, hidden if the progress is sent as
+/// events instead
+fn spinner(opts: &RunOptions, message: &'static str) -> ProgressBar {
+    match opts.fetch.progress {
+        Some(_) => ProgressBar::hidden(),
+        None => {
+            let spinner = ProgressBar::new_spinner().with_message(message);
+            configure_spinner(&spinner);
+            spinner
+        }
+    }
+}
+
+/// Tell anyone following the progress that a file was written
+fn written(opts: &RunOptions, path: impl Into<PathBuf>) {
+    progress::emit(
+        &opts.fetch.progress,
+        ProgressEvent::OutputWritten { path: path.into() },
+    );
+}
+
+/// Download the navigation and find the Dynamixels selected by the filters,
+/// along with any extra pages, then take the sample of them if there is one
+pub async fn find_dynamixels(client: &HttpClient, opts: &RunOptions) -> Result<Vec<ActuatorIndex>> {
+    let mut indexes = match opts.extra_pages.is_empty() || !opts.filters.is_empty() {
+        true => find_in_navigation(client, opts).await?,
+        false => vec![],
+    };
+
+    for location in &opts.extra_pages {
+        let page = extra_page(location)
+            .context(ErrorCategory::InvalidSelection)
+            .with_context(|| format!("Failed to add the page {}", location))?;
+        match indexes.iter().any(|dxl| dxl.url == page.url) {
+            true => debug!(url = %page.url, "Page is already in the navigation"),
+            false => indexes.push(page),
+        }
+    }
+
+    if let Some(sample) = opts.sample {
+        let selected = indexes.len();
+        indexes = sample.apply(indexes);
+        info!(
+            ?sample,
+            selected,
+            count = indexes.len(),
+            "Sampled the Dynamixels to scrape"
+        );
+    }
+
+    if indexes.is_empty() {
+        return Err(Error::new(ErrorCategory::InvalidSelection)
+            .context("No Dynamixels in the navigation match the selection"));
+    }
+    check_output_paths(&indexes)
+        .context(ErrorCategory::InvalidSelection)
+        .context("The selected Dynamixels' files would overwrite each other")?;
+
+    Ok(indexes)
+}
+
+async fn find_in_navigation(client: &HttpClient, opts: &RunOptions) -> Result<Vec<ActuatorIndex>> {
+    let nav_download = spinner(opts, "Fetching navigation index");
+    info!(url = %opts.navigation_url, "Fetching navigation index");
+    let yaml = client
+        .get_text(&opts.navigation_url)
+        .await
+        .with_context(|| {
+            format!(
+                "Failed to fetch navigation index from {}",
+                opts.navigation_url
+            )
+        })?;
+    nav_download.finish();
+
+    let yaml_parse = spinner(opts, "Parsing YAML");
+    let indexes = build_index(
+        &yaml,
+        &opts.base_url,
+        opts.navigation_path.as_deref(),
+        &opts.filters,
+    )
+    .context(ErrorCategory::Parse)
+    .context("Failed to find the Dynamixels in the navigation")?;
+
+    yaml_parse.finish();
+    info!(count = indexes.len(), "Resolved Dynamixels to scrape");
+    progress::emit(
+        &opts.fetch.progress,
+        ProgressEvent::IndexFetched {
+            models: indexes.len(),
+        },
+    );
+
+    Ok(indexes)
+}
+
+/// Download and parse the given Dynamixels, then write them in every chosen
+/// format. Returns the summary of the run, without a count of warnings.
+///
+/// Each model's own files are written as soon as it's parsed, so an
+/// interrupted run still leaves the models finished so far. Only the models
+/// needed by the library, database or a snapshot are kept until the end,
+/// which is when those and the index are written. If the run is cancelled,
+/// the index of the models that finished is still written, but the library,
+/// database and snapshot, which would be missing models, aren't. Hardware
+/// from navigation sections is only written to the formats with a file per
+/// model, under their hardware directory.
+///
+/// Fails if another run holds the lock on the working directory, unless
+/// asked to force it or only checking for changes.
+pub async fn scrape(
+    client: &HttpClient,
+    indexes: Vec<ActuatorIndex>,
+    opts: &RunOptions,
+) -> Result<Report> {
+    let started = Instant::now();
+    // Two runs writing to the same directory would interleave their files
+    let _lock = match opts.check || opts.force {
+        true => None,
+        false => Some(
+            OutputLock::acquire(LOCK_PATH)
+                .context(ErrorCategory::Io)
+                .context("Failed to lock the output")?,
+        ),
+    };
+    let fetch_progress = spinner(opts, "Downloading & extracting Dynamixels");
+    fetch_progress.disable_steady_tick();
+
+    let formats = opts.formats;
+    let keep_actuators = formats.lib
+        || opts.sqlite.is_some()
+        || opts.cpp.is_some()
+        || opts.typescript.is_some()
+        || opts.snapshot
+        || opts.check;
+    let hardware = indexes.iter().filter(|dxl| dxl.section.is_some()).count();
+    if keep_actuators && hardware > 0 {
+        info!(
+            hardware,
+            "Hardware from navigation sections is left out of the library, database, header, TypeScript and snapshots"
+        );
+    }
+    let emit = info_span!("emit");
+    let span = emit.clone();
+    let sender = opts.fetch.progress.clone();
+    let (parsed, models) = fetch_all(client, indexes, &opts.fetch, move |actuator| {
+        let _emit = span.enter();
+        let mut paths = Vec::new();
+        if formats.ron {
+            paths.push(actuator.write_object()?);
+            if formats.ron_summaries {
+                paths.push(actuator.write_summary()?);
+            }
+        }
+        if formats.yaml {
+            paths.push(actuator.write_yaml()?);
+        }
+        if formats.msgpack {
+            paths.push(actuator.write_msgpack()?);
+        }
+        if formats.markdown {
+            paths.push(actuator.write_markdown()?);
+        }
+        if formats.json_schema {
+            paths.push(actuator.write_schema()?);
+        }
+        for path in paths {
+            progress::emit(&sender, ProgressEvent::OutputWritten { path });
+        }
+
+        Ok((
+            actuator.index_entry(),
+            Some(actuator).filter(|_| keep_actuators),
+        ))
+    })
+    .await?;
+    let (index, actuators): (Vec<IndexEntry>, Vec<Option<Actuator>>) = parsed.into_iter().unzip();
+    // Hardware other than the Dynamixels has no place among the models of the
+    // library, database or snapshots, but is still checked for changes
+    let (hardware, actuators): (Vec<Actuator>, Vec<Actuator>) = actuators
+        .into_iter()
+        .flatten()
+        .partition(|actuator| actuator.section().is_some());
+
+    fetch_progress.tick();
+    fetch_progress.finish();
+
+    // Models already written are kept, but only write the library and index
+    // for a partial set of Dynamixels if asked to keep going
+    let failed = models
+        .iter()
+        .any(|model| matches!(model.status, ModelStatus::Failed { .. }));
+    let interrupted = opts
+        .fetch
+        .cancel
+        .as_ref()
+        .is_some_and(|cancel| *cancel.borrow());
+    let gave_up = opts.fetch.fail_fast && failed;
+    let complete = !(gave_up || interrupted);
+    let formats = match (gave_up, interrupted) {
+        (true, _) => Formats::default(),
+        (false, true) => Formats {
+            ron: formats.ron,
+            ron_summaries: formats.ron_summaries,
+            msgpack: formats.msgpack,
+            ..Formats::default()
+        },
+        (false, false) => formats,
+    };
+
+    let data_write = spinner(opts, "Writing data");
+
+    let actuators = Arc::new(actuators);
+    if formats.lib {
+        let lib_target = LibTarget {
+            manual_revision: opts.manual_revision.clone(),
+            ..opts.lib_target.clone()
+        };
+        let (actuators, span) = (actuators.clone(), emit.clone());
+        task::spawn_blocking(move || {
+            let _emit = span.entered();
+            create_lib::create_lib(&actuators, &lib_target)
+        })
+        .await??;
+        written(opts, opts.lib_target.source_path());
+        written(opts, opts.lib_target.manifest_path());
+    }
+
+    if let Some(path) = opts.sqlite.as_ref().filter(|_| complete) {
+        let (actuators, path, span) = (actuators.clone(), path.clone(), emit.clone());
+        let path = task::spawn_blocking(move || {
+            let _emit = span.entered();
+            write_sqlite(&path, &actuators).map(|_| path)
+        })
+        .await??;
+        written(opts, path);
+    }
+
+    if let Some(path) = opts.cpp.as_ref().filter(|_| complete) {
+        let (actuators, path, span) = (actuators.clone(), path.clone(), emit.clone());
+        let naming = opts.lib_target.naming;
+        let path = task::spawn_blocking(move || {
+            let _emit = span.entered();
+            write_header(&path, &actuators, naming).map(|_| path)
+        })
+        .await??;
+        written(opts, path);
+    }
+
+    if let Some(path) = opts.typescript.as_ref().filter(|_| complete) {
+        let (actuators, path, span) = (actuators.clone(), path.clone(), emit.clone());
+        let naming = opts.lib_target.naming;
+        let path = task::spawn_blocking(move || {
+            let _emit = span.entered();
+            write_typescript(&path, &actuators, naming).map(|_| path)
+        })
+        .await??;
+        written(opts, path);
+    }
+
+    let mut index = index;
+    index.sort_by(|a, b| a.path.cmp(&b.path));
+    if formats.ron {
+        write_atomic(INDEX_PATH, serialize_index(&index)?)?;
+        written(opts, INDEX_PATH);
+        // Each series is aggregated from all of its objects, including those
+        // of earlier runs, so scraping some of its models doesn't drop the
+        // others
+        let series: BTreeSet<&str> = index.iter().map(|entry| entry.series.as_str()).collect();
+        for series in series {
+            write_series_object(series)?;
+            written(opts, series_object_path(series));
+        }
+    }
+    if formats.msgpack {
+        let index: Vec<IndexEntry> = index
+            .into_iter()
+            .map(|entry| IndexEntry {
+                path: relative_msgpack_path(&entry.series, &entry.slug),
+                ..entry
+            })
+            .collect();
+        write_atomic(MSGPACK_INDEX_PATH, serialize_index_msgpack(&index)?)?;
+        written(opts, MSGPACK_INDEX_PATH);
+    }
+
+    data_write.finish();
+
+    if opts.snapshot && !interrupted {
+        track_changes(&actuators)?;
+    }
+
+    let mut report = Report::new(models, 0, started.elapsed());
+    report.interrupted = interrupted;
+    report.manual_revision = opts.manual_revision.clone();
+    report.network = client.metrics().summary();
+    if opts.check && !interrupted {
+        report.changes = Some(compare_with_objects(&actuators, &hardware)?);
+    }
+
+    Ok(report)
+}
+
+/// Find, download and parse the selected Dynamixels, then write them in
+/// every chosen format
+pub async fn run(client: &HttpClient, opts: &RunOptions) -> Result<Report> {
+    let indexes = find_dynamixels(client, opts).await?;
+
+    scrape(client, indexes, opts).await
+}
+
+/// Compare the scraped models and hardware with the RON objects under
+/// objects/, which are usually committed alongside the library. Models that
+/// weren't scraped are ignored.
+fn compare_with_objects(actuators: &[Actuator], hardware: &[Actuator]) -> Result<ChangeSummary> {
+    let dir = Path::new(INDEX_PATH).parent().unwrap();
+    let committed: Snapshot = match Path::new(INDEX_PATH).exists() {
+        true => load_objects(dir, &[])?
+            .into_iter()
+            .map(|table| (table.slug, table.data))
+            .collect(),
+        false => Snapshot::new(),
+    };
+    let current = snapshot::snapshot(&snapshot::snapshot(&Snapshot::new(), actuators), hardware);
+
+    Ok(ChangeSummary::new(snapshot::diff(&committed, &current)))
+}
+
+/// Compare the scraped models with the last snapshot, printing what changed
+/// and the version bump the generated library needs, then save a new
+/// snapshot if anything did
+fn track_changes(actuators: &[Actuator]) -> Result<()> {
+    let dir = Path::new(SNAPSHOTS_DIR);
+    let (previous_path, previous) = match snapshot::load_latest(dir)? {
+        Some((path, previous)) => (Some(path), previous),
+        None => (None, Snapshot::new()),
+    };
+    let current = snapshot::snapshot(&previous, actuators);
+    let changes = snapshot::diff(&previous, &current);
+
+    match (&previous_path, snapshot::suggested_bump(&changes)) {
+        (None, _) => println!("No previous snapshot to compare with"),
+        (Some(path), None) => println!("No changes since {}", path.display()),
+        (Some(path), Some(bump)) => {
+            println!("Changes since {}:", path.display());
+            for change in &changes {
+                println!("    {}", change);
+            }
+            println!("Suggested version bump for the library: {}", bump);
+        }
+    }
+
+    if previous_path.is_none() || !changes.is_empty() {
+        let path = snapshot::save(dir, &current)?;
+        println!("Saved snapshot to {}", path.display());
+    }
+
+    Ok(())
+}
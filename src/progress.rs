@@ -0,0 +1,44 @@
+use crate::report::ModelStatus;
+use std::path::PathBuf;
+use tokio::sync::mpsc;
+
+/// A step of a scrape, for callers rendering their own progress (eg a GUI or
+/// a chat bot) rather than the terminal's spinners
+#[derive(Clone, Debug, PartialEq)]
+pub enum ProgressEvent {
+    /// The navigation was downloaded and listed this many models to scrape
+    IndexFetched { models: usize },
+    /// A model's page started downloading, as the `position`th of `total`
+    ModelStarted {
+        name: String,
+        url: String,
+        position: usize,
+        total: usize,
+    },
+    /// A model's page was parsed and its own files written
+    ModelParsed {
+        name: String,
+        url: String,
+        registers: usize,
+    },
+    /// A model failed to download, parse or be written, or its page is gone,
+    /// as it will be recorded in the report
+    ModelFailed {
+        name: String,
+        url: String,
+        status: ModelStatus,
+    },
+    /// A file of output was written, or left alone as it was unchanged
+    OutputWritten { path: PathBuf },
+}
+
+/// Where progress events are sent. Events are dropped once the receiver is.
+pub type ProgressSender = mpsc::UnboundedSender<ProgressEvent>;
+
+/// Send `event` if anyone is listening
+pub fn emit(progress: &Option<ProgressSender>, event: ProgressEvent) {
+    if let Some(progress) = progress {
+        // The receiver going away only means nobody is watching any more
+        let _ = progress.send(event);
+    }
+}
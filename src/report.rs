@@ -0,0 +1,469 @@
+use crate::download::TableSummary;
+use crate::fetch::PageUnavailable;
+use crate::serialize::ParseError;
+use crate::snapshot::ChangeSummary;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::layer::{Context, Layer};
+
+/// The broad kinds of failure, each with its own exit code so that scripts
+/// wrapping the scraper can react to them differently
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq)]
+pub enum ErrorCategory {
+    /// A request failed or timed out
+    Network,
+    /// A page or the navigation couldn't be understood
+    Parse,
+    /// The arguments didn't select any Dynamixels
+    InvalidSelection,
+    /// Output couldn't be written
+    Io,
+    /// Some, but not all, Dynamixels failed
+    PartialSuccess,
+    /// The run was cancelled with Ctrl-C before every Dynamixel finished
+    Interrupted,
+}
+
+impl ErrorCategory {
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            ErrorCategory::InvalidSelection => 2,
+            ErrorCategory::Network => 3,
+            ErrorCategory::Parse => 4,
+            ErrorCategory::Io => 5,
+            ErrorCategory::PartialSuccess => 6,
+            // The conventional code for a process stopped by SIGINT
+            ErrorCategory::Interrupted => 130,
+        }
+    }
+
+    /// Find the category of an error from the errors in its chain, if it
+    /// has one. Errors can be tagged with a category by wrapping one in them,
+    /// or by adding one as context.
+    pub fn of(error: &anyhow::Error) -> Option<ErrorCategory> {
+        if let Some(category) = error.downcast_ref::<ErrorCategory>() {
+            return Some(*category);
+        }
+
+        error.chain().find_map(|cause| {
+            if let Some(category) = cause.downcast_ref::<ErrorCategory>() {
+                Some(*category)
+            } else if cause.is::<reqwest::Error>() || cause.is::<PageUnavailable>() {
+                Some(ErrorCategory::Network)
+            } else if cause.is::<std::io::Error>() {
+                Some(ErrorCategory::Io)
+            } else if cause.is::<serde_yaml::Error>() || cause.is::<ParseError>() {
+                Some(ErrorCategory::Parse)
+            } else {
+                None
+            }
+        })
+    }
+}
+
+impl fmt::Display for ErrorCategory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            ErrorCategory::Network => "network failure",
+            ErrorCategory::Parse => "parse failure",
+            ErrorCategory::InvalidSelection => "invalid selection",
+            ErrorCategory::Io => "IO error",
+            ErrorCategory::PartialSuccess => "partial success",
+            ErrorCategory::Interrupted => "interrupted",
+        };
+        write!(f, "{}", text)
+    }
+}
+
+impl std::error::Error for ErrorCategory {}
+
+/// The outcome of scraping a single model
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub enum ModelStatus {
+    Succeeded,
+    Failed {
+        category: ErrorCategory,
+        reason: String,
+    },
+    /// The model's page is missing or gone, eg because it was retired
+    Unavailable {
+        reason: String,
+    },
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct ModelReport {
+    pub name: String,
+    pub url: String,
+    pub status: ModelStatus,
+    /// The number of items parsed from the control table
+    pub registers: usize,
+    /// The size of the downloaded page
+    pub bytes: usize,
+    /// Every table on the page, if the control tables weren't where they
+    /// were expected and `--debug-tables` was given
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tables: Option<Vec<TableSummary>>,
+}
+
+/// A summary of a whole scrape, printed at the end of each run
+#[derive(Clone, Serialize, Debug)]
+pub struct Report {
+    pub attempted: usize,
+    pub succeeded: usize,
+    pub failed: usize,
+    pub unavailable: usize,
+    pub warnings: usize,
+    pub bytes_downloaded: usize,
+    pub elapsed_secs: f64,
+    /// Whether the run was cancelled, in which case only the models that
+    /// finished beforehand are reported
+    pub interrupted: bool,
+    /// How the control tables differ from the objects already written, if
+    /// run with `--check`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub changes: Option<ChangeSummary>,
+    /// The revision of the E-Manual's source that was scraped, if pinned
+    /// with `--manual-rev`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub manual_revision: Option<String>,
+    /// The requests made during the run, including for the navigation
+    pub network: NetworkSummary,
+    pub models: Vec<ModelReport>,
+}
+
+impl Report {
+    /// The category of the run's failures, if it was interrupted or any
+    /// models failed: partial success if any models succeeded, otherwise a
+    /// network failure if any download failed and a parse failure if not
+    pub fn failure(&self) -> Option<ErrorCategory> {
+        if self.interrupted {
+            return Some(ErrorCategory::Interrupted);
+        }
+        if self.failed == 0 {
+            return None;
+        }
+        if self.succeeded > 0 {
+            return Some(ErrorCategory::PartialSuccess);
+        }
+
+        let network = self.models.iter().any(|model| {
+            matches!(
+                model.status,
+                ModelStatus::Failed {
+                    category: ErrorCategory::Network,
+                    ..
+                }
+            )
+        });
+        match network {
+            true => Some(ErrorCategory::Network),
+            false => Some(ErrorCategory::Parse),
+        }
+    }
+
+    pub fn new(mut models: Vec<ModelReport>, warnings: usize, elapsed: Duration) -> Report {
+        models.sort_by(|a, b| a.name.cmp(&b.name));
+        let succeeded = models
+            .iter()
+            .filter(|model| matches!(model.status, ModelStatus::Succeeded))
+            .count();
+        let unavailable = models
+            .iter()
+            .filter(|model| matches!(model.status, ModelStatus::Unavailable { .. }))
+            .count();
+
+        Report {
+            attempted: models.len(),
+            succeeded,
+            failed: models.len() - succeeded - unavailable,
+            unavailable,
+            warnings,
+            bytes_downloaded: models.iter().map(|model| model.bytes).sum(),
+            elapsed_secs: elapsed.as_secs_f64(),
+            interrupted: false,
+            changes: None,
+            manual_revision: None,
+            network: NetworkSummary::default(),
+            models,
+        }
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name_width = self
+            .models
+            .iter()
+            .map(|model| model.name.len())
+            .chain(std::iter::once("Model".len()))
+            .max()
+            .unwrap_or_default();
+
+        writeln!(
+            f,
+            "{:<name_width$}  {:<11}  {:>9}  {:>9}",
+            "Model",
+            "Status",
+            "Registers",
+            "Bytes",
+            name_width = name_width
+        )?;
+        for model in &self.models {
+            let status = match model.status {
+                ModelStatus::Succeeded => "ok",
+                ModelStatus::Failed { .. } => "failed",
+                ModelStatus::Unavailable { .. } => "unavailable",
+            };
+            writeln!(
+                f,
+                "{:<name_width$}  {:<11}  {:>9}  {:>9}",
+                model.name,
+                status,
+                model.registers,
+                model.bytes,
+                name_width = name_width
+            )?;
+        }
+
+        for model in &self.models {
+            match &model.status {
+                ModelStatus::Failed { reason, .. } => {
+                    writeln!(f, "\n{} failed: {}", model.name, reason)?
+                }
+                ModelStatus::Unavailable { reason } => {
+                    writeln!(f, "\n{} skipped: {}", model.name, reason)?
+                }
+                ModelStatus::Succeeded => (),
+            }
+        }
+
+        for model in &self.models {
+            if let Some(tables) = &model.tables {
+                writeln!(f, "\nTables found on the {} page:", model.name)?;
+                for table in tables {
+                    writeln!(f, "    {}", table)?;
+                }
+            }
+        }
+
+        write!(
+            f,
+            "\n{} attempted, {} succeeded, {} failed, {} unavailable, {} warnings, {} bytes downloaded in {:.1}s",
+            self.attempted,
+            self.succeeded,
+            self.failed,
+            self.unavailable,
+            self.warnings,
+            self.bytes_downloaded,
+            self.elapsed_secs
+        )?;
+        if self.network.requests > 0 || self.network.cache_hits > 0 {
+            write!(f, "\n{}", self.network)?;
+        }
+        if let Some(revision) = &self.manual_revision {
+            write!(f, "\nScraped revision {} of the E-Manual", revision)?;
+        }
+        if self.interrupted {
+            write!(f, "\nInterrupted before the remaining Dynamixels finished")?;
+        }
+        if let Some(changes) = &self.changes {
+            write!(f, "\n{}", changes)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// A tracing layer counting the warnings logged during a run
+#[derive(Clone, Default)]
+pub struct WarningCounter {
+    count: Arc<AtomicUsize>,
+}
+
+impl WarningCounter {
+    pub fn count(&self) -> usize {
+        self.count.load(Ordering::Relaxed)
+    }
+}
+
+impl<S: Subscriber> Layer<S> for WarningCounter {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        if *event.metadata().level() == Level::WARN {
+            self.count.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Counts of the requests made by an [`HttpClient`] and its clones during a
+/// run, for tuning concurrency and finding what made a scrape slow
+///
+/// [`HttpClient`]: crate::fetch::HttpClient
+#[derive(Clone, Default, Debug)]
+pub struct NetworkMetrics {
+    state: Arc<Mutex<MetricsState>>,
+}
+
+#[derive(Default, Debug)]
+struct MetricsState {
+    requests: usize,
+    bytes: usize,
+    cache_hits: usize,
+    /// The latency of every request made to each host
+    latencies: BTreeMap<String, Vec<Duration>>,
+}
+
+impl NetworkMetrics {
+    /// Record a request to `host` which took `latency` to download `bytes`,
+    /// including requests that failed, which downloaded nothing
+    pub fn record_request(&self, host: &str, latency: Duration, bytes: usize) {
+        let mut state = self.state.lock().unwrap();
+        state.requests += 1;
+        state.bytes += bytes;
+        state
+            .latencies
+            .entry(host.to_string())
+            .or_default()
+            .push(latency);
+    }
+
+    /// Record a page read from disk instead of downloaded
+    pub fn record_cache_hit(&self) {
+        self.state.lock().unwrap().cache_hits += 1;
+    }
+
+    /// The totals so far, with the latency percentiles of each host
+    pub fn summary(&self) -> NetworkSummary {
+        let state = self.state.lock().unwrap();
+        let hosts = state
+            .latencies
+            .iter()
+            .map(|(host, latencies)| {
+                let mut latencies = latencies.clone();
+                latencies.sort();
+                HostLatency {
+                    host: host.clone(),
+                    requests: latencies.len(),
+                    p50_secs: percentile(&latencies, 50),
+                    p90_secs: percentile(&latencies, 90),
+                    p99_secs: percentile(&latencies, 99),
+                    total_secs: latencies.iter().sum::<Duration>().as_secs_f64(),
+                }
+            })
+            .collect();
+
+        NetworkSummary {
+            requests: state.requests,
+            bytes_downloaded: state.bytes,
+            cache_hits: state.cache_hits,
+            hosts,
+        }
+    }
+}
+
+/// The nearest-rank percentile of sorted, non-empty `latencies`
+fn percentile(latencies: &[Duration], percent: usize) -> f64 {
+    let rank = (latencies.len() * percent).div_ceil(100);
+    latencies[rank.saturating_sub(1)].as_secs_f64()
+}
+
+/// How long the requests made to a single host took
+#[derive(Clone, Serialize, Debug, PartialEq)]
+pub struct HostLatency {
+    pub host: String,
+    pub requests: usize,
+    pub p50_secs: f64,
+    pub p90_secs: f64,
+    pub p99_secs: f64,
+    pub total_secs: f64,
+}
+
+/// The network traffic of a whole run, which unlike the bytes of each model
+/// includes the navigation and robots.txt
+#[derive(Clone, Default, Serialize, Debug, PartialEq)]
+pub struct NetworkSummary {
+    pub requests: usize,
+    pub bytes_downloaded: usize,
+    pub cache_hits: usize,
+    pub hosts: Vec<HostLatency>,
+}
+
+impl NetworkSummary {
+    /// The summary in the Prometheus text exposition format, eg for the
+    /// textfile collector of node_exporter
+    pub fn to_prometheus(&self) -> String {
+        let mut text = String::new();
+        for (name, help, value) in [
+            ("requests_total", "HTTP requests made", self.requests),
+            (
+                "downloaded_bytes_total",
+                "Bytes of response bodies downloaded",
+                self.bytes_downloaded,
+            ),
+            (
+                "cache_hits_total",
+                "Pages read from disk instead of downloaded",
+                self.cache_hits,
+            ),
+        ] {
+            text.push_str(&format!(
+                "# HELP dynamixel_scraper_{name} {help}\n# TYPE dynamixel_scraper_{name} counter\ndynamixel_scraper_{name} {value}\n",
+                name = name,
+                help = help,
+                value = value
+            ));
+        }
+
+        text.push_str("# HELP dynamixel_scraper_request_duration_seconds Latency of HTTP requests by host\n# TYPE dynamixel_scraper_request_duration_seconds summary\n");
+        for host in &self.hosts {
+            for (quantile, value) in [
+                ("0.5", host.p50_secs),
+                ("0.9", host.p90_secs),
+                ("0.99", host.p99_secs),
+            ] {
+                text.push_str(&format!(
+                    "dynamixel_scraper_request_duration_seconds{{host=\"{}\",quantile=\"{}\"}} {}\n",
+                    host.host, quantile, value
+                ));
+            }
+            text.push_str(&format!(
+                "dynamixel_scraper_request_duration_seconds_sum{{host=\"{}\"}} {}\n",
+                host.host, host.total_secs
+            ));
+            text.push_str(&format!(
+                "dynamixel_scraper_request_duration_seconds_count{{host=\"{}\"}} {}\n",
+                host.host, host.requests
+            ));
+        }
+
+        text
+    }
+}
+
+impl fmt::Display for NetworkSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} requests, {} bytes transferred, {} cache hits",
+            self.requests, self.bytes_downloaded, self.cache_hits
+        )?;
+        for host in &self.hosts {
+            write!(
+                f,
+                "\n    {}: {} requests, p50 {:.0}ms, p90 {:.0}ms, p99 {:.0}ms",
+                host.host,
+                host.requests,
+                host.p50_secs * 1000.0,
+                host.p90_secs * 1000.0,
+                host.p99_secs * 1000.0
+            )?;
+        }
+
+        Ok(())
+    }
+}
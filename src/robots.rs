@@ -0,0 +1,132 @@
+use std::fmt;
+use std::time::Duration;
+
+/// The rules a site's robots.txt sets for one crawler, from the group naming
+/// it or, if there isn't one, the group for every crawler (`*`)
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Robots {
+    /// Path patterns and whether they're allowed
+    rules: Vec<(String, bool)>,
+    pub crawl_delay: Option<Duration>,
+}
+
+impl Robots {
+    /// Parse a robots.txt for the crawler whose product token is `agent`,
+    /// eg "dynamixel-scraper". Crawler names are matched case-insensitively.
+    pub fn parse(text: &str, agent: &str) -> Robots {
+        let agent = agent.to_lowercase();
+        let mut named: Option<Robots> = None;
+        let mut wildcard: Option<Robots> = None;
+
+        // The agents of the group being read, and whether its rules have
+        // started (a User-agent line after a rule starts a new group)
+        let mut agents: Vec<String> = Vec::new();
+        let mut in_rules = false;
+        let mut group = Robots::default();
+
+        let mut finish = |agents: &[String], group: Robots| {
+            if agents.iter().any(|name| agent.contains(name.as_str())) {
+                named.get_or_insert_with(Robots::default).extend(group);
+            } else if agents.iter().any(|name| name == "*") {
+                wildcard.get_or_insert_with(Robots::default).extend(group);
+            }
+        };
+
+        for line in text.lines() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            let (key, value) = match line.split_once(':') {
+                Some((key, value)) => (key.trim().to_lowercase(), value.trim()),
+                None => continue,
+            };
+
+            match key.as_str() {
+                "user-agent" => {
+                    if in_rules {
+                        finish(&agents, std::mem::take(&mut group));
+                        agents.clear();
+                        in_rules = false;
+                    }
+                    agents.push(value.to_lowercase());
+                }
+                "allow" | "disallow" => {
+                    in_rules = true;
+                    // An empty Disallow allows everything
+                    if !value.is_empty() {
+                        group.rules.push((value.to_string(), key == "allow"));
+                    }
+                }
+                "crawl-delay" => {
+                    in_rules = true;
+                    // Delays that are negative, not a number or too long to
+                    // be a duration are ignored
+                    group.crawl_delay = value
+                        .parse()
+                        .ok()
+                        .and_then(|secs| Duration::try_from_secs_f64(secs).ok());
+                }
+                _ => (),
+            }
+        }
+        finish(&agents, group);
+
+        named.or(wildcard).unwrap_or_default()
+    }
+
+    fn extend(&mut self, other: Robots) {
+        self.rules.extend(other.rules);
+        self.crawl_delay = self.crawl_delay.or(other.crawl_delay);
+    }
+
+    /// Whether `path` (with any query string) may be crawled. The longest
+    /// matching rule wins, with Allow winning ties, as Google does.
+    pub fn allows(&self, path: &str) -> bool {
+        self.rules
+            .iter()
+            .filter(|(pattern, _)| pattern_matches(pattern, path))
+            .max_by_key(|(pattern, allow)| (pattern.len(), *allow))
+            .is_none_or(|(_, allow)| *allow)
+    }
+}
+
+/// Match a robots.txt path pattern, where `*` matches any run of characters
+/// and a trailing `$` anchors the pattern to the end of the path
+fn pattern_matches(pattern: &str, path: &str) -> bool {
+    let (pattern, anchored) = match pattern.strip_suffix('$') {
+        Some(pattern) => (pattern, true),
+        None => (pattern, false),
+    };
+
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let mut rest = match path.strip_prefix(first) {
+        Some(rest) => rest,
+        None => return false,
+    };
+    let parts: Vec<&str> = parts.collect();
+    for (i, part) in parts.iter().enumerate() {
+        // The last part must be at the very end of an anchored pattern
+        if anchored && i == parts.len() - 1 {
+            return rest.ends_with(part);
+        }
+        match rest.find(part) {
+            Some(start) => rest = &rest[start + part.len()..],
+            None => return false,
+        }
+    }
+
+    !anchored || rest.is_empty()
+}
+
+/// A page the site's robots.txt asks crawlers not to fetch
+#[derive(Debug)]
+pub struct Disallowed {
+    pub url: String,
+}
+
+impl fmt::Display for Disallowed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} is disallowed by robots.txt", self.url)
+    }
+}
+
+impl std::error::Error for Disallowed {}
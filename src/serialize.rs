@@ -1,22 +1,34 @@
-use anyhow::Result;
+use crate::analysis::SeriesObject;
+use crate::download::{FirmwareNote, Link, RowNotes};
+use anyhow::{ensure, Result};
 use regex::Regex;
 use ron::ser::{to_string_pretty, PrettyConfig};
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, fmt};
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+};
+use tracing::{debug, warn};
 
-fn try_find(
-    indexes: &HashMap<&str, usize>,
-    line: &[Option<&str>],
+/// The cell under a heading, if the table has the heading and the cell isn't
+/// empty. Headings have already been normalized by
+/// [`crate::download::normalize_heading`].
+fn try_find<'a>(
+    indexes: &BTreeMap<&str, usize>,
+    line: &[Option<&'a str>],
     heading: &str,
-) -> Option<String> {
-    if indexes.contains_key(&heading) {
-        let item = line[indexes[heading]];
-        if let Some(i) = item {
-            return Some(i.to_string());
-        }
-    }
+) -> Option<&'a str> {
+    indexes.get(heading).and_then(|idx| line[*idx])
+}
 
-    None
+/// The characters of `text` that `keep` accepts, only allocating if any
+/// need to be removed
+fn retain_chars(text: &str, keep: impl Fn(&char) -> bool) -> Cow<'_, str> {
+    match text.chars().all(|c| keep(&c)) {
+        true => Cow::Borrowed(text),
+        false => Cow::Owned(text.chars().filter(keep).collect()),
+    }
 }
 
 /// The levels of permission a user is granted in terms of an item in the
@@ -25,6 +37,43 @@ fn try_find(
 pub enum AccessLevel {
     Read,
     ReadWrite,
+    /// Access the E-Manual gives that isn't understood, as written
+    Unknown(String),
+}
+
+impl AccessLevel {
+    /// Parse the text of an Access cell, ignoring how read and write are
+    /// separated (eg "R/W") and any footnote marker after it (eg "RW(1)",
+    /// "R*" or "RW※1"), which [`crate::download::footnotes`] finds the note of
+    pub fn parse(text: &str) -> AccessLevel {
+        lazy_static! {
+            static ref MARKER_RE: Regex = Regex::new(
+                r"\s*(?:\*+|\(\s*(?:\*+|[0-9]+)\s*\)|\[\s*[0-9]+\s*\]|※\s*[0-9]*|[0-9]+)\s*$"
+            )
+            .unwrap();
+        }
+
+        let level = MARKER_RE.replace(text.trim(), "");
+        let compact: String = level
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .collect::<String>()
+            .to_uppercase();
+        match compact.as_str() {
+            "R" | "READ" => AccessLevel::Read,
+            "RW" | "R/W" | "READ/WRITE" => AccessLevel::ReadWrite,
+            _ => AccessLevel::Unknown(level.trim().to_string()),
+        }
+    }
+
+    /// The access as the E-Manual abbreviates it, eg "RW"
+    pub fn as_str(&self) -> &str {
+        match self {
+            AccessLevel::Read => "R",
+            AccessLevel::ReadWrite => "RW",
+            AccessLevel::Unknown(text) => text,
+        }
+    }
 }
 
 /// A representation of an item in the control table, where only information
@@ -37,23 +86,413 @@ pub struct ControlTableData {
     pub data_name: Option<String>,
     pub description: Option<String>,
     pub access: AccessLevel,
-    pub initial_value: Option<RangeValue>,
+    pub initial_value: InitialValue,
+    /// Whether the initial value is the same on every unit and firmware
+    pub initial_value_source: InitialValueSource,
     pub range: Option<(RangeValue, RangeValue)>,
     pub units: Option<String>,
-    // pub modbus: Option<ModbusAddress>, // Need to understand this better before implementation
+    /// The type the item's bytes should be decoded as
+    pub value_type: ValueType,
+    /// The lowest firmware version that supports this item, if the E-Manual
+    /// notes one
+    pub min_firmware: Option<u8>,
+    /// The meaning of each bit, for items documented as a set of flags
+    pub bits: Option<Vec<BitField>>,
+    /// The values the item takes, for items documented with a table of them
+    /// (such as Operating Mode)
+    pub values: Vec<ItemValue>,
+    /// Which joint the item controls, on models with a bank of items for
+    /// each of their joints (such as the 2XL430-W250)
+    pub joint: Option<u8>,
+    /// Other items the description links to
+    pub related: Vec<DataNameRef>,
+    /// The footnotes the item's row refers to, such as caveats about firmware
+    pub notes: Vec<String>,
+    /// How the item's value converts to the gain the controller uses, for
+    /// gains the E-Manual gives a conversion equation for
+    pub conversion: Option<Conversion>,
+    /// The address of the item's Modbus register, on models that can also be
+    /// controlled over Modbus RTU (such as some of the PRO series)
+    pub modbus_address: Option<u16>,
+    /// The text of each cell of the item's row, by heading, kept when
+    /// scraping `--with-raw` to check the parsed values against the page
+    pub raw: Option<RawCells>,
+}
+
+/// The cells of a row of a control table as written on the page, by the
+/// heading they're under. Cells that are empty or only a placeholder such
+/// as "-" are left out.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Default)]
+pub struct RawCells(pub BTreeMap<String, String>);
+
+/// The primitive type of an item's value, inferred from its size and
+/// whether it can be negative
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq)]
+pub enum ValueType {
+    U8,
+    U16,
+    U32,
+    I8,
+    I16,
+    I32,
+    /// An item whose size doesn't match a primitive, kept as raw bytes
+    Bytes(u8),
+}
+
+impl ValueType {
+    /// Infer the type of an item from its size, and from its range, initial
+    /// value and units for whether it's signed
+    pub fn infer(
+        size: u8,
+        range: Option<&(RangeValue, RangeValue)>,
+        initial_value: Option<&RangeValue>,
+        units: Option<&str>,
+    ) -> ValueType {
+        let negative = |value: &RangeValue| match value {
+            RangeValue::Integer(i) => *i < 0,
+            RangeValue::Address { negative, .. } => *negative,
+        };
+        let signed = match range {
+            Some((min, _)) => negative(min),
+            // Items measured in these units (eg Present Current) can be
+            // negative even when the E-Manual gives no range for them
+            None => {
+                units.is_some_and(|units| SIGNED_UNITS.iter().any(|signed| units.contains(signed)))
+            }
+        } || initial_value.is_some_and(negative);
+
+        match (size, signed) {
+            (1, false) => ValueType::U8,
+            (2, false) => ValueType::U16,
+            (4, false) => ValueType::U32,
+            (1, true) => ValueType::I8,
+            (2, true) => ValueType::I16,
+            (4, true) => ValueType::I32,
+            (size, _) => ValueType::Bytes(size),
+        }
+    }
+
+    /// The name of the equivalent Rust type
+    pub fn rust_type(&self) -> String {
+        match self {
+            ValueType::U8 => "u8".to_string(),
+            ValueType::U16 => "u16".to_string(),
+            ValueType::U32 => "u32".to_string(),
+            ValueType::I8 => "i8".to_string(),
+            ValueType::I16 => "i16".to_string(),
+            ValueType::I32 => "i32".to_string(),
+            ValueType::Bytes(size) => format!("[u8; {}]", size),
+        }
+    }
+
+    /// The lowest and highest values the type can hold, or `None` for raw
+    /// bytes
+    pub fn bounds(&self) -> Option<(i64, i64)> {
+        match self {
+            ValueType::U8 => Some((0, u8::MAX.into())),
+            ValueType::U16 => Some((0, u16::MAX.into())),
+            ValueType::U32 => Some((0, u32::MAX.into())),
+            ValueType::I8 => Some((i8::MIN.into(), i8::MAX.into())),
+            ValueType::I16 => Some((i16::MIN.into(), i16::MAX.into())),
+            ValueType::I32 => Some((i32::MIN.into(), i32::MAX.into())),
+            ValueType::Bytes(_) => None,
+        }
+    }
+}
+
+static SIGNED_UNITS: &[&str] = &["mA", "rev/min", "%", "pulse"];
+
+/// A unit the E-Manual measures values in
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Unit {
+    /// A step of the position encoder, whose size depends on the model
+    Pulse,
+    Degree,
+    Rpm,
+    DegreePerSecond,
+    Percent,
+    Milliamp,
+    Volt,
+    Millivolt,
+    Millisecond,
+    Microsecond,
+    Second,
+    Celsius,
+    /// A count with no unit, or a unit not listed here
+    Raw,
+}
+
+/// The spellings of each unit in the E-Manual, compared ignoring case
+static UNIT_SYMBOLS: &[(Unit, &[&str])] = &[
+    (Unit::Pulse, &["pulse", "pulses"]),
+    (Unit::Degree, &["°", "deg", "degree"]),
+    (Unit::Rpm, &["rev/min", "rpm"]),
+    (Unit::DegreePerSecond, &["°/s", "°/sec", "deg/s"]),
+    (Unit::Percent, &["%"]),
+    (Unit::Milliamp, &["mA"]),
+    (Unit::Volt, &["V"]),
+    (Unit::Millivolt, &["mV"]),
+    (Unit::Millisecond, &["msec", "ms"]),
+    (
+        Unit::Microsecond,
+        &["usec", "μsec", "µsec", "us", "μs", "µs"],
+    ),
+    (Unit::Second, &["sec", "s"]),
+    (Unit::Celsius, &["°C", "℃", "degC"]),
+];
+
+impl Unit {
+    /// Find the unit with the given symbol, eg "rev/min", if it's known
+    pub fn from_symbol(symbol: &str) -> Option<Unit> {
+        let symbol = symbol.trim();
+        // Case matters for prefixes (mV and MV), so try an exact match first
+        UNIT_SYMBOLS
+            .iter()
+            .find(|(_, symbols)| symbols.contains(&symbol))
+            .or_else(|| {
+                UNIT_SYMBOLS.iter().find(|(_, symbols)| {
+                    symbols
+                        .iter()
+                        .any(|known| known.eq_ignore_ascii_case(symbol))
+                })
+            })
+            .map(|(unit, _)| *unit)
+    }
+}
+
+/// The unit of an item's value and the size of a step of it, parsed from
+/// the Unit column, eg "0.229 [rev/min]"
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq)]
+pub struct Units {
+    pub unit: Unit,
+    /// How much of the unit a step of the raw value is
+    pub scale: f64,
+}
+
+impl Units {
+    /// Parse the Unit column of an item. Returns `None` for items with no
+    /// units (eg "-"), and `Unit::Raw` for units that aren't known.
+    pub fn parse(text: &str) -> Option<Units> {
+        lazy_static! {
+            static ref UNITS: Regex =
+                Regex::new(r"^\s*([-+]?[0-9]*\.?[0-9]+)?\s*\[?\s*([^\]]*?)\s*\]?\s*$").unwrap();
+        }
+
+        let captures = UNITS.captures(text)?;
+        let scale = match captures.get(1) {
+            Some(scale) => scale.as_str().parse().ok()?,
+            None => 1.0,
+        };
+        let symbol = captures.get(2).map_or("", |symbol| symbol.as_str());
+        if is_placeholder(symbol) {
+            return None;
+        }
+
+        let unit = Unit::from_symbol(symbol).unwrap_or_else(|| {
+            debug!(%symbol, "Unknown unit");
+            Unit::Raw
+        });
+
+        Some(Units { unit, scale })
+    }
 }
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub enum RangeValue {
     Integer(i32),
-    Address { name: String, negative: bool },
+    /// The value of another item in the same control table. `reference`
+    /// holds the item `name` was resolved to, if one was found.
+    Address {
+        name: String,
+        negative: bool,
+        reference: Option<DataNameRef>,
+    },
+}
+
+/// The Initial Value cell of an item, which isn't always a plain value
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub enum InitialValue {
+    /// Objects written before initial values could be anything but a value
+    /// wrote them as an `Option`
+    #[serde(alias = "Some")]
+    Value(RangeValue),
+    /// The cell is empty or "-"
+    None,
+    /// Pages covering several models give a value for each, eg
+    /// "58(XM430-W210) 46(XM430-W350)". The list is empty if the page only
+    /// says the value depends on the model.
+    ModelDependent(Vec<(String, i32)>),
+    /// Anything else, such as a formula, kept as written
+    Expression(String),
+}
+
+/// Where an item's initial value comes from, for the values the E-Manual
+/// marks as set for each unit or as varying with the firmware
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq)]
+pub enum InitialValueSource {
+    /// The value every unit starts with and is reset to
+    #[default]
+    Default,
+    /// Set for each unit at the factory, eg by calibration, so the initial
+    /// value is only typical of a unit
+    FactorySet,
+    /// Differs between firmware versions
+    FirmwareDependent,
+}
+
+impl InitialValueSource {
+    /// Find the source an Initial Value cell or a footnote about an item
+    /// gives its initial value, if it gives one
+    pub fn of_text(text: &str) -> Option<InitialValueSource> {
+        lazy_static! {
+            // Notes about Factory Reset restoring the initial values don't
+            // count, only values set at the factory
+            static ref FACTORY_RE: Regex = Regex::new(
+                r"(?i)\b(?:set|calibrated|configured|adjusted)\s+(?:at|in|by|during)\s+(?:the\s+)?factory\b|\bfactory[- ]?(?:set\b|calibrat)|^\s*factory\s+settings?\s*$"
+            )
+            .unwrap();
+            static ref FIRMWARE_RE: Regex = Regex::new(
+                r"(?i)\b(?:initial|default)\s+values?\b.*\bfirmware\b|\bfirmware\b.*\b(?:initial|default)\s+values?\b|^\s*firmware[- ]dependent\s*$"
+            )
+            .unwrap();
+        }
+
+        if FACTORY_RE.is_match(text) {
+            Some(InitialValueSource::FactorySet)
+        } else if FIRMWARE_RE.is_match(text) {
+            Some(InitialValueSource::FirmwareDependent)
+        } else {
+            None
+        }
+    }
+}
+
+impl fmt::Display for InitialValueSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "InitialValueSource::{:?}", self)
+    }
+}
+
+impl InitialValue {
+    pub fn new(text: &str) -> InitialValue {
+        lazy_static! {
+            static ref MODEL_VALUE_RE: Regex =
+                Regex::new(r"(-?[0-9][0-9,]*)\s*\(\s*([A-Za-z][A-Za-z0-9 -]*?)\s*\)").unwrap();
+        }
+
+        let text = text.trim();
+        if text.chars().all(|c| c == '-' || c.is_whitespace()) {
+            return InitialValue::None;
+        }
+        if normalize_name(text).contains("modeldependent") {
+            return InitialValue::ModelDependent(vec![]);
+        }
+
+        let values: Vec<(String, i32)> = MODEL_VALUE_RE
+            .captures_iter(text)
+            .filter_map(|captures| {
+                let value = strip_thousands_separators(&captures[1]).parse().ok()?;
+                Some((captures[2].to_string(), value))
+            })
+            .collect();
+        if !values.is_empty() {
+            return InitialValue::ModelDependent(values);
+        }
+
+        // Cells such as "Factory Setting" aren't the names of other items
+        if InitialValueSource::of_text(text).is_some() {
+            return InitialValue::Expression(text.to_string());
+        }
+
+        let compact = retain_chars(text, |c| !c.is_whitespace());
+        match RangeValue::new(&compact) {
+            Ok(value) => InitialValue::Value(value),
+            Err(_) => InitialValue::Expression(text.to_string()),
+        }
+    }
+
+    /// The value, if the cell holds a single one
+    pub fn value(&self) -> Option<&RangeValue> {
+        match self {
+            InitialValue::Value(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for InitialValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Value(value) => write!(f, "InitialValue::Value({})", value),
+            Self::None => write!(f, "InitialValue::None"),
+            Self::ModelDependent(values) => {
+                write!(f, "InitialValue::ModelDependent(&{:?})", values)
+            }
+            Self::Expression(text) => write!(f, "InitialValue::Expression({:?})", text),
+        }
+    }
+}
+
+/// One or more bits of an item with a documented meaning, such as the
+/// Overload Error bit of Shutdown
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct BitField {
+    /// The lowest bit, counting from 0
+    pub bit: u8,
+    /// The number of bits, for fields such as "Bit 5 ~ 6"
+    pub width: u8,
+    pub name: String,
+    pub description: Option<String>,
+    /// What each value of the field means, if the description lists them,
+    /// eg "[0] Normal Mode ... [1] Reverse Mode ..." for a bit of Drive Mode
+    #[serde(default)]
+    pub values: Vec<BitValue>,
+}
+
+/// One of the documented values of an item, such as the Velocity Control
+/// Mode of Operating Mode or the 57,600 bps of Baud Rate
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct ItemValue {
+    pub value: i64,
+    /// The text of the value's row after the value, eg "Velocity Control Mode"
+    pub name: String,
+    pub description: Option<String>,
+}
+
+/// A documented value of a field of bits
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct BitValue {
+    pub value: u32,
+    /// The text following the value's marker, up to the next one or a note
+    pub meaning: String,
+}
+
+/// The E-Manual's equation converting a gain's value in the control table
+/// to the gain the controller uses, eg "KPP = KPP(TBL) / 128"
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct Conversion {
+    /// The gain's symbol in the equation, eg "KPP"
+    pub symbol: String,
+    /// The equation as written
+    pub formula: String,
+    /// What the value in the control table is divided by to give the gain,
+    /// if the equation is a plain division
+    pub divisor: Option<u32>,
+}
+
+/// A validated reference to an item in the same control table
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct DataNameRef {
+    /// The name of the item, exactly as it appears in the control table
+    pub data_name: String,
+    pub address: u16,
 }
 
 impl fmt::Display for RangeValue {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Self::Integer(i) => write!(f, "RangeValue::Integer({})", i),
-            Self::Address { name, negative } => write!(
+            Self::Address { name, negative, .. } => write!(
                 f,
                 "RangeValue::Address {{ name: DataName::{}, negative: {}}}",
                 name, negative
@@ -62,6 +501,19 @@ impl fmt::Display for RangeValue {
     }
 }
 
+/// Remove the thousands separators from the numbers in a cell, eg "1,023"
+/// becomes "1023". Only commas between groups of three digits are removed,
+/// so a list such as "0, 1, 2" keeps its commas rather than becoming "012".
+pub fn strip_thousands_separators(text: &str) -> Cow<'_, str> {
+    lazy_static! {
+        static ref GROUPED: Regex = Regex::new(r"\b[0-9]{1,3}(?:,[0-9]{3})+\b").unwrap();
+    }
+
+    GROUPED.replace_all(text, |captures: &regex::Captures| {
+        captures[0].replace(',', "")
+    })
+}
+
 impl RangeValue {
     pub fn new(text: &str) -> Result<RangeValue> {
         lazy_static! {
@@ -71,17 +523,13 @@ impl RangeValue {
             static ref INTEGER_RE: Regex = Regex::new(r"^-?[0-9]+$").unwrap();
         }
 
-        let filtered_text = text.chars().filter(|c| *c != ',').collect::<String>();
+        let filtered_text = strip_thousands_separators(text);
 
         let address_matches = ADDRESS_RE.captures(&filtered_text);
         let integer_matches = INTEGER_RE.captures(&filtered_text);
 
-        // Make sure only one regex matches
-        assert!(address_matches.is_none() || integer_matches.is_none());
-        assert!(address_matches.is_some() || integer_matches.is_some());
-
-        if address_matches.is_some() {
-            if let Some(captures) = address_matches {
+        if let Some(captures) = address_matches {
+            {
                 let mut captured_text = captures.get(0).unwrap().as_str().to_string();
                 // Some ranges can be negative, eg -PWMLimit ~ PWMLimit
                 let negative = captured_text.starts_with('-');
@@ -95,20 +543,68 @@ impl RangeValue {
                 return Ok(RangeValue::Address {
                     name: captured_text,
                     negative,
+                    reference: None,
                 });
             }
         } else if let Some(captures) = integer_matches {
             let num = captures.get(0).unwrap().as_str();
-            return Ok(RangeValue::Integer(num.parse::<i32>()?));
+            return num.parse().map(RangeValue::Integer).map_err(|_| {
+                ParseError::InvalidValue {
+                    text: text.to_string(),
+                }
+                .into()
+            });
         };
 
-        panic!("This should definitely not be possible");
+        Err(ParseError::InvalidValue {
+            text: text.to_string(),
+        }
+        .into())
+    }
+}
+
+/// Something the parser had to guess at or leave out to parse a row of a
+/// control table, so the item it became (if any) is less trustworthy
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct ParseWarning {
+    /// The row of the control table, counting from 1 after the headings.
+    /// Tables split into EEPROM and RAM tables are counted as one.
+    pub row: usize,
+    /// The address of the row, if it has one
+    pub address: Option<u16>,
+    /// The heading of the cell, if the warning isn't about the whole row
+    pub column: Option<String>,
+    pub reason: String,
+}
+
+/// Whether a cell is empty or only holds a placeholder for a missing value,
+/// such as "-", "…" or "~", in any of the ways the E-Manual writes them:
+/// padded with any kind of whitespace or invisible formatting characters,
+/// with any dash or ellipsis, or with an ellipsis or dash mis-encoded as
+/// Windows-1252 (eg "â€¦").
+pub fn is_placeholder(cell: &str) -> bool {
+    lazy_static! {
+        static ref PLACEHOLDER_RE: Regex =
+            Regex::new(r"^(?:[\p{White_Space}\p{Cf}\p{Pd}.…⋯~〜～−]|â€[¦\x{201C}\x{201D}])*$")
+                .unwrap();
     }
+
+    PLACEHOLDER_RE.is_match(cell)
 }
 
 pub fn parse_servo(servo: Vec<Vec<String>>) -> Result<Vec<ControlTableData>> {
-    let mut lines: Vec<Vec<Option<&str>>> = Vec::new();
-    let bad_chars: Vec<char> = vec!['.', '-', ' ', '…', '~', '\u{a0}'];
+    Ok(parse_servo_with_warnings(servo, false)?.0)
+}
+
+/// Parse a control table as [`parse_servo`] does, along with a warning for
+/// each row that could only be parsed leniently. The text of each item's
+/// cells is kept only if `with_raw` is set.
+pub fn parse_servo_with_warnings(
+    servo: Vec<Vec<String>>,
+    with_raw: bool,
+) -> Result<(Vec<ControlTableData>, Vec<ParseWarning>)> {
+    let mut lines: Vec<(usize, Vec<Option<&str>>)> = Vec::new();
+    let mut warnings: Vec<ParseWarning> = Vec::new();
 
     let mut lowest_address: Option<u32> = None;
     let mut highest_address: Option<u32> = None;
@@ -118,26 +614,28 @@ pub fn parse_servo(servo: Vec<Vec<String>>) -> Result<Vec<ControlTableData>> {
             Regex::new(r"Indirect (?:Address|Data) (?:N|[0-9]*)").unwrap();
     }
 
-    for line in servo.iter().skip(1) {
+    for (row, line) in servo.iter().enumerate().skip(1) {
         let mut line_to_add: Vec<Option<&str>> = vec![];
         for col in line {
-            if col.chars().all(|c| bad_chars.contains(&c)) {
-                line_to_add.push(None);
-            } else {
-                line_to_add.push(Some(col));
+            match is_placeholder(col) {
+                true => line_to_add.push(None),
+                false => line_to_add.push(Some(col)),
             }
         }
 
         if !line_to_add.iter().all(|o| o.is_none()) {
-            if let Some(captures) =
-                INDERECT_RE.captures(&line.clone().into_iter().collect::<String>())
-            {
+            if let Some(captures) = INDERECT_RE.captures(&line.concat()) {
                 let current_match = captures.get(captures.len() - 1).unwrap().as_str();
                 if !current_match.chars().all(char::is_numeric) {
                     continue;
                 }
 
-                let current_value = current_match.parse::<u32>()?;
+                let current_value: u32 =
+                    current_match
+                        .parse()
+                        .map_err(|_| ParseError::InvalidValue {
+                            text: current_match.to_string(),
+                        })?;
 
                 if lowest_address.unwrap_or(u32::MAX) > current_value {
                     lowest_address = Some(current_value);
@@ -147,83 +645,1682 @@ pub fn parse_servo(servo: Vec<Vec<String>>) -> Result<Vec<ControlTableData>> {
                     highest_address = Some(current_value);
                 }
             } else {
-                lines.push(line_to_add);
+                lines.push((row, line_to_add));
             }
         }
     }
 
-    let mut indexes: HashMap<&str, usize> = HashMap::new();
+    let mut indexes: BTreeMap<&str, usize> = BTreeMap::new();
     for (idx, heading) in servo[0].iter().enumerate() {
         indexes.insert(heading, idx);
     }
 
+    // These columns are needed by every row, so fail early if the layout
+    // isn't understood rather than panicking partway through
+    let required = |heading: &'static str| {
+        indexes
+            .get(heading)
+            .copied()
+            .ok_or_else(|| ParseError::MissingHeading {
+                heading,
+                headings: servo[0].clone(),
+            })
+    };
+    let address_idx = required("Address")?;
+    let access_idx = required("Access")?;
+    // Legacy pages have no size column, as every row is a single byte
+    let size_idx = indexes.get("Size(byte)").copied();
+    let columns = Columns {
+        width: servo[0].len(),
+        address: address_idx,
+        size: size_idx,
+        access: access_idx,
+    };
+
     let mut data: Vec<ControlTableData> = Vec::new();
-    for line in lines {
+    let mut previous_address = None;
+    for (row, line) in lines {
+        let written_address = line
+            .get(address_idx)
+            .copied()
+            .flatten()
+            .and_then(|text| parse_address(text).ok());
+        let line = match realign(line, &columns, previous_address) {
+            (Some(line), None) => line,
+            (line, Some(reason)) => {
+                warnings.push(ParseWarning {
+                    row,
+                    address: written_address,
+                    column: None,
+                    reason,
+                });
+                match line {
+                    Some(line) => line,
+                    None => continue,
+                }
+            }
+            (None, None) => continue,
+        };
+        let address = parse_address(line[address_idx].unwrap())?;
+        previous_address = Some(address);
+        // The address isn't known until the row is aligned
+        if let Some(warning) = warnings.last_mut().filter(|warning| warning.row == row) {
+            warning.address = Some(address);
+        }
+        let mut warn = |column: &str, reason: String| {
+            warnings.push(ParseWarning {
+                row,
+                address: Some(address),
+                column: Some(column.to_string()),
+                reason,
+            })
+        };
+
+        // A range that can't be understood is left out, rather than failing
+        // the whole page
+        let mut value_of = |column: &str, text: &str| {
+            RangeValue::new(text)
+                .map_err(|e| warn(column, format!("{:#}", e)))
+                .ok()
+        };
         let range: Option<(RangeValue, RangeValue)> =
             if let Some(text) = try_find(&indexes, &line, "Range") {
                 if text.matches('~').count() == 1 {
-                    assert_eq!(text.matches('~').count(), 1);
                     let mut text_parts = text.split('~').map(|s| {
-                        s.chars()
-                            .filter(|c| c.is_alphanumeric() || *c == '-')
-                            .collect::<String>()
+                        retain_chars(s, |c| c.is_alphanumeric() || *c == '-' || *c == ',')
                     });
 
-                    let min = RangeValue::new(&text_parts.next().unwrap())?;
-                    let max = RangeValue::new(&text_parts.next().unwrap())?;
-
-                    Some((min, max))
+                    let min = text_parts.next().unwrap();
+                    let max = text_parts.next().unwrap();
+                    value_of("Range", &min).zip(value_of("Range", &max))
                 } else {
                     // Need to fix these edge cases
+                    warn("Range", format!("{:?} isn't a single min ~ max", text));
                     None
                 }
             } else if let Some(min_text) = try_find(&indexes, &line, "Min") {
                 if let Some(max_text) = try_find(&indexes, &line, "Max") {
-                    let min = RangeValue::new(&min_text)?;
-                    let max = RangeValue::new(&max_text)?;
-
-                    Some((min, max))
+                    value_of("Min", min_text).zip(value_of("Max", max_text))
                 } else {
+                    warn("Max", format!("Min {:?} has no max", min_text));
                     None
                 }
             } else {
                 None
             };
 
-        data.push(ControlTableData {
-            address: line[*indexes.get("Address").unwrap()]
-                .unwrap()
-                .parse::<u16>()?,
-            size: line[*indexes.get("Size(byte)").unwrap()]
-                .unwrap()
-                .parse::<u8>()?, // NOTE: There should be a space inserted in front of applicable headings such as "Size(Byte)"
-            data_name: try_find(&indexes, &line, "Data Name"),
-            description: try_find(&indexes, &line, "Description"),
-            access: match line[*indexes.get("Access").unwrap()].unwrap() {
-                "R" => AccessLevel::Read,
-                "RW" => AccessLevel::ReadWrite,
-                "R/RW" => AccessLevel::ReadWrite, // Needs further research
-                e => panic!("Unknown level: {}", e),
-            },
-            initial_value: match try_find(&indexes, &line, "Initial Value") {
-                Some(val) => Some(RangeValue::new(
-                    &val.chars().filter(|c| *c != ' ').collect::<String>(),
-                )?),
-                None => None,
+        let size = match size_idx {
+            Some(idx) => line[idx].unwrap().parse::<u8>()?, // NOTE: There should be a space inserted in front of applicable headings such as "Size(Byte)"
+            None => 1,
+        };
+        let initial_value = match try_find(&indexes, &line, "Initial Value") {
+            Some(val) => InitialValue::new(val),
+            None => InitialValue::None,
+        };
+        let units = try_find(&indexes, &line, "Unit");
+        let modbus_address = match try_find(&indexes, &line, "Modbus Address") {
+            Some(text) => match parse_address(text) {
+                Ok(modbus_address) => Some(modbus_address),
+                Err(_) => {
+                    warn(
+                        "Modbus Address",
+                        format!("{:?} isn't a Modbus address", text),
+                    );
+                    None
+                }
             },
+            None => None,
+        };
+
+        let access = match AccessLevel::parse(line[access_idx].unwrap()) {
+            // Needs further research
+            AccessLevel::Unknown(level) if level.replace(' ', "") == "R/RW" => {
+                warn(
+                    "Access",
+                    "\"R/RW\" was taken to mean read and write".to_string(),
+                );
+                AccessLevel::ReadWrite
+            }
+            AccessLevel::Unknown(level) => {
+                warn(
+                    "Access",
+                    format!("{:?} isn't a known level of access", level),
+                );
+                AccessLevel::Unknown(level)
+            }
+            access => access,
+        };
+
+        data.push(ControlTableData {
+            address,
+            size,
+            data_name: try_find(&indexes, &line, "Data Name").map(String::from),
+            description: try_find(&indexes, &line, "Description").map(String::from),
+            access,
+            value_type: ValueType::infer(size, range.as_ref(), initial_value.value(), units),
+            initial_value,
+            initial_value_source: InitialValueSource::Default,
             range,
-            units: None,
+            units: units.map(String::from),
+            min_firmware: None,
+            bits: None,
+            values: vec![],
+            joint: None,
+            related: vec![],
+            notes: vec![],
+            conversion: None,
+            modbus_address,
+            raw: with_raw.then(|| {
+                RawCells(
+                    servo[0]
+                        .iter()
+                        .zip(line.iter().copied())
+                        .filter_map(|(heading, cell)| Some((heading.clone(), cell?.to_string())))
+                        .collect(),
+                )
+            }),
+        });
+    }
+
+    Ok((coalesce_split_bytes(data), warnings))
+}
+
+/// The columns of a control table that every row must have a sensible value
+/// in, and how many columns there are
+struct Columns {
+    width: usize,
+    address: usize,
+    size: Option<usize>,
+    access: usize,
+}
+
+/// Why a row doesn't look like a row of a control table, if it doesn't
+fn check_row(
+    line: &[Option<&str>],
+    columns: &Columns,
+    previous_address: Option<u16>,
+) -> Option<String> {
+    if line.len() != columns.width {
+        return Some(format!(
+            "{} cells under {} headings",
+            line.len(),
+            columns.width
+        ));
+    }
+
+    let address = match line[columns.address].map(parse_address) {
+        Some(Ok(address)) => address,
+        _ => return Some(format!("address {:?}", line[columns.address])),
+    };
+    if let Some(idx) = columns.size {
+        if !matches!(line[idx], Some("1" | "2" | "4")) {
+            return Some(format!("size {:?}", line[idx]));
+        }
+    }
+    // An access level that isn't understood is still one if it's abbreviated
+    // like the others (eg "W" or "R/O"), rather than a cell shifted from
+    // another column
+    let is_access = match line[columns.access].map(AccessLevel::parse) {
+        Some(AccessLevel::Unknown(level)) => {
+            !level.is_empty() && level.chars().all(|c| "RWO/".contains(c))
+        }
+        Some(_) => true,
+        None => false,
+    };
+    if !is_access {
+        return Some(format!("access {:?}", line[columns.access]));
+    }
+    match previous_address {
+        Some(previous) if address <= previous => Some(format!(
+            "address {} doesn't follow address {}",
+            address, previous
+        )),
+        _ => None,
+    }
+}
+
+/// Check that a row looks like a row of the control table. Rows that don't
+/// have usually had their cells shifted into the wrong columns by a cell
+/// spanning rows or columns that the page got wrong, so a run of cells is
+/// inserted or removed to shift them back. A row that can't be realigned is
+/// dropped, unless the only thing wrong is that its address doesn't
+/// increase, which is usually nothing to worry about. Returns the row, if it's
+/// kept, and why it's suspect, if it is.
+fn realign<'a>(
+    line: Vec<Option<&'a str>>,
+    columns: &Columns,
+    previous_address: Option<u16>,
+) -> (Option<Vec<Option<&'a str>>>, Option<String>) {
+    let problem = match check_row(&line, columns, previous_address) {
+        Some(problem) => problem,
+        None => return (Some(line), None),
+    };
+
+    let width = columns.width;
+    let mut candidates: Vec<Vec<Option<&str>>> = vec![];
+    for position in 0..=line.len() {
+        let mut candidate = line.clone();
+        if line.len() < width {
+            // Cells are missing, so later cells moved left
+            candidate.splice(position..position, vec![None; width - line.len()]);
+        } else if line.len() > width {
+            // Extra cells moved later cells right
+            if position > width {
+                continue;
+            }
+            candidate.drain(position..position + line.len() - width);
+        } else if position < line.len() {
+            // A cell was left out and another added, so the cells after it
+            // moved one way or the other
+            let mut right = candidate.clone();
+            right.insert(position, None);
+            right.pop();
+            candidates.push(right);
+            candidate.remove(position);
+            candidate.push(None);
+        } else {
+            continue;
+        }
+        candidates.push(candidate);
+    }
+
+    let address = line.get(columns.address).copied().flatten();
+    match candidates
+        .into_iter()
+        .find(|candidate| check_row(candidate, columns, previous_address).is_none())
+    {
+        Some(candidate) => {
+            warn!(?address, %problem, "Realigned a row with shifted columns");
+            (
+                Some(candidate),
+                Some(format!("Realigned shifted cells ({})", problem)),
+            )
+        }
+        // Addresses start again at the RAM table, and on some models at each
+        // joint's bank of items, so a row that's otherwise sound is kept
+        None if line.len() == width && check_row(&line, columns, None).is_none() => {
+            debug!(?address, %problem, "Row may have shifted columns");
+            (Some(line), None)
+        }
+        None => {
+            warn!(?address, %problem, "Dropped a row with shifted columns");
+            (
+                None,
+                Some(format!("Dropped, as its cells are shifted ({})", problem)),
+            )
+        }
+    }
+}
+
+/// Reduce a name to lowercase letters so that "PWM Limit" and "PWMLimit" match
+fn normalize_name(name: &str) -> String {
+    name.chars()
+        .filter(|c| c.is_alphabetic())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// The number of single letter insertions, deletions or substitutions
+/// needed to turn one name into the other
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a != *b);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+
+    previous[b.len()]
+}
+
+/// Find the joint of each item on models with a bank of items per joint.
+/// Items are either named after their joint (eg "Torque Enable (Joint 2)"),
+/// which is removed from the name, or repeated once per joint, in which case
+/// each repeat is the next joint. Items that appear once are shared.
+///
+/// Repeats are only taken to be joints when they form a duplicated block of
+/// addresses, as on the 2XC430-W250 where each joint's items are 100 bytes
+/// after the last's. Names are compared with their numbers, so numbered
+/// items such as "Indirect Address 1" and "Indirect Address 2" or "LED" and
+/// "LED 1" aren't repeats.
+pub fn split_joints(data: &mut [ControlTableData]) {
+    lazy_static! {
+        static ref JOINT_RE: Regex =
+            Regex::new(r"(?i)^(.*?)\s*\(?\s*\b(?:joint|axis)\s*([0-9])\s*\)?\s*$").unwrap();
+    }
+
+    for row in data.iter_mut() {
+        let name = match &row.data_name {
+            Some(name) => name,
+            None => continue,
+        };
+        if let Some(captures) = JOINT_RE.captures(name) {
+            if !captures[1].is_empty() {
+                row.joint = captures[2].parse().ok();
+                row.data_name = Some(captures[1].to_string());
+            }
+        }
+    }
+
+    // The rows of each name that's repeated, by its letters and numbers
+    let mut repeats: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+    for (idx, row) in data
+        .iter()
+        .enumerate()
+        .filter(|(_, row)| row.joint.is_none())
+    {
+        if let Some(name) = &row.data_name {
+            let key = name
+                .chars()
+                .filter(|c| c.is_alphanumeric())
+                .flat_map(char::to_lowercase)
+                .collect();
+            repeats.entry(key).or_default().push(idx);
+        }
+    }
+    repeats.retain(|_, rows| rows.len() > 1);
+
+    // Each joint's bank is the same distance from the first joint's for
+    // every item in it
+    let offsets = |rows: &Vec<usize>| -> Vec<i32> {
+        rows.iter()
+            .map(|idx| i32::from(data[*idx].address) - i32::from(data[rows[0]].address))
+            .collect()
+    };
+    let mut banks = repeats.values().map(offsets);
+    let first = banks.next();
+    let duplicated = repeats.len() > 1 && banks.all(|bank| Some(bank) == first);
+    if !duplicated {
+        if !repeats.is_empty() {
+            let names: Vec<&String> = repeats.keys().collect();
+            debug!(?names, "Repeated names aren't a bank per joint");
+        }
+        return;
+    }
+
+    for rows in repeats.values() {
+        for (joint, idx) in rows.iter().enumerate() {
+            data[*idx].joint = Some(joint as u8 + 1);
+        }
+    }
+}
+
+/// An item's normalized name and joint, with a reference to it
+type NamedItem = (String, Option<u8>, DataNameRef);
+
+/// Resolve every address-based range or initial value against the names of
+/// the items in the same control table, warning about any that don't match.
+/// Items of a joint refer to the items of the same joint where there are any.
+pub fn resolve_references(data: &mut [ControlTableData]) {
+    let items: Vec<NamedItem> = data
+        .iter()
+        .filter_map(|row| {
+            let data_name = row.data_name.as_ref()?.trim().to_string();
+            let reference = DataNameRef {
+                address: row.address,
+                data_name,
+            };
+            Some((normalize_name(&reference.data_name), row.joint, reference))
+        })
+        .collect();
+
+    for row in data.iter_mut() {
+        let row_joint = row.joint;
+        let values = row
+            .range
+            .iter_mut()
+            .flat_map(|(min, max)| vec![min, max])
+            .chain(match &mut row.initial_value {
+                InitialValue::Value(value) => Some(value),
+                _ => None,
+            });
+
+        for value in values {
+            if let RangeValue::Address {
+                name, reference, ..
+            } = value
+            {
+                let normalized = normalize_name(name);
+                let mut matches: Vec<_> = items
+                    .iter()
+                    .filter(|(item, _, _)| *item == normalized)
+                    .collect();
+                if matches.is_empty() {
+                    matches = closest_items(&items, &normalized);
+                    let mut names: Vec<&str> = matches
+                        .iter()
+                        .map(|(_, _, reference)| reference.data_name.as_str())
+                        .collect();
+                    names.sort_unstable();
+                    names.dedup();
+                    match names.len() {
+                        0 => {}
+                        1 => debug!(%name, item = names[0], "Matched a range to a similar item"),
+                        _ => warn!(
+                            %name,
+                            candidates = ?names,
+                            referenced_by = ?row.data_name,
+                            "Range could refer to several items, taking the first"
+                        ),
+                    }
+                }
+                *reference = matches
+                    .iter()
+                    .find(|(_, joint, _)| *joint == row_joint)
+                    .or_else(|| matches.first())
+                    .map(|(_, _, reference)| reference.clone());
+
+                match reference {
+                    // Spell the reference like the item, so it names the
+                    // same data name in the library
+                    Some(reference) => {
+                        *name = reference
+                            .data_name
+                            .chars()
+                            .filter(|c| c.is_alphabetic())
+                            .collect()
+                    }
+                    None => warn!(
+                        %name,
+                        referenced_by = ?row.data_name,
+                        "Range refers to an item not in the control table"
+                    ),
+                }
+            }
+        }
+    }
+}
+
+/// The items with the name closest to `normalized`, differing by at most one
+/// letter in four, for references the E-Manual misspells or abbreviates
+fn closest_items<'a>(items: &'a [NamedItem], normalized: &str) -> Vec<&'a NamedItem> {
+    let allowed = (normalized.chars().count() / 4).max(1);
+    let distances: Vec<(usize, &NamedItem)> = items
+        .iter()
+        .filter(|(item, _, _)| !item.is_empty())
+        .map(|item| (edit_distance(&item.0, normalized), item))
+        .filter(|(distance, _)| *distance <= allowed)
+        .collect();
+    let closest = distances.iter().map(|(distance, _)| *distance).min();
+
+    distances
+        .into_iter()
+        .filter(|(distance, _)| Some(*distance) == closest)
+        .map(|(_, item)| item)
+        .collect()
+}
+
+/// Record the items each item's description links to as related items. A
+/// link is matched to an item by the name in its anchor (eg
+/// "#operating-mode11") or its text (eg "Operating Mode(11)"), preferring
+/// the item at the address in either, then an item of the same joint. Links
+/// to anything else, such as other sections of the E-Manual, are ignored.
+pub fn apply_description_links(data: &mut [ControlTableData], links: &[(String, Vec<Link>)]) {
+    let items: Vec<NamedItem> = data
+        .iter()
+        .filter_map(|row| {
+            let data_name = row.data_name.as_ref()?.trim().to_string();
+            let reference = DataNameRef {
+                address: row.address,
+                data_name,
+            };
+            Some((normalize_name(&reference.data_name), row.joint, reference))
+        })
+        .collect();
+    let address_in = |text: &str| -> Option<u16> {
+        let digits: String = text
+            .chars()
+            .rev()
+            .skip_while(|c| !c.is_ascii_digit())
+            .take_while(char::is_ascii_digit)
+            .collect();
+        digits.chars().rev().collect::<String>().parse().ok()
+    };
+
+    for (address, row_links) in links {
+        let address = match parse_address(address) {
+            Ok(address) => address,
+            Err(_) => continue,
+        };
+
+        for row in data.iter_mut().filter(|row| row.address == address) {
+            for link in row_links {
+                let fragment = link.href.rsplit('#').next().unwrap_or_default();
+                let names = [normalize_name(fragment), normalize_name(&link.text)];
+                let addresses = [address_in(fragment), address_in(&link.text)];
+                let mut matches = items.iter().filter(|(name, _, reference)| {
+                    !name.is_empty() && names.contains(name) && reference.address != row.address
+                });
+                let related = matches
+                    .clone()
+                    .find(|(_, _, reference)| addresses.contains(&Some(reference.address)))
+                    .or_else(|| matches.clone().find(|(_, joint, _)| *joint == row.joint))
+                    .or_else(|| matches.next());
+
+                match related {
+                    Some((_, _, reference)) if !row.related.contains(reference) => {
+                        row.related.push(reference.clone())
+                    }
+                    Some(_) => (),
+                    None => debug!(
+                        href = %link.href,
+                        item = ?row.data_name,
+                        "Description links to something other than an item"
+                    ),
+                }
+            }
+        }
+    }
+}
+
+/// Record the footnotes referenced by each row as its item's notes, removing
+/// the markers from the end of its name so "Shutdown*" is just "Shutdown"
+pub fn apply_footnotes(data: &mut [ControlTableData], footnotes: &[RowNotes]) {
+    for row_notes in footnotes {
+        let address = match parse_address(&row_notes.address) {
+            Ok(address) => address,
+            Err(_) => continue,
+        };
+
+        for row in data.iter_mut().filter(|row| row.address == address) {
+            if let Some(name) = &mut row.data_name {
+                for marker in &row_notes.markers {
+                    if let Some(stripped) = name.trim_end().strip_suffix(marker.as_str()) {
+                        *name = stripped.trim_end().to_string();
+                    }
+                }
+            }
+
+            if row_notes.notes.is_empty() {
+                warn!(
+                    item = ?row.data_name,
+                    markers = ?row_notes.markers,
+                    "Item refers to a footnote that wasn't found"
+                );
+            }
+            for note in &row_notes.notes {
+                if !row.notes.contains(note) {
+                    row.notes.push(note.clone());
+                }
+            }
+        }
+    }
+}
+
+/// Set where the initial value of each item comes from, by its Initial Value
+/// cell or its notes, where the E-Manual marks a value as set at the factory
+/// or as differing between firmware versions
+pub fn apply_initial_value_sources(data: &mut [ControlTableData]) {
+    for row in data {
+        let cell = match &row.initial_value {
+            InitialValue::Expression(text) => Some(text),
+            _ => None,
+        };
+        let source = cell
+            .into_iter()
+            .chain(&row.notes)
+            .find_map(|text| InitialValueSource::of_text(text));
+        if let Some(source) = source {
+            debug!(item = ?row.data_name, ?source, "Initial value isn't the same everywhere");
+            row.initial_value_source = source;
+        }
+    }
+}
+
+/// Set the minimum firmware version of every item a firmware note names,
+/// using the lowest version if it's named more than once. Notes in prose
+/// only name an item along with its address, eg "Bus Watchdog(98)", so that
+/// short names such as "ID" aren't found in unrelated sentences, and tables
+/// of firmware features name them as whole entries of their lists.
+pub fn apply_firmware_notes(data: &mut [ControlTableData], notes: &[FirmwareNote]) {
+    lazy_static! {
+        static ref ADDRESS_RE: Regex = Regex::new(r"\(\s*([0-9]+)\s*\)").unwrap();
+    }
+    let key = |name: &str| -> String {
+        name.chars()
+            .filter(|c| c.is_alphanumeric())
+            .flat_map(char::to_lowercase)
+            .collect()
+    };
+
+    let mut by_address: BTreeMap<u16, Vec<usize>> = BTreeMap::new();
+    let mut by_name: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+    for (idx, row) in data.iter().enumerate() {
+        if let Some(name) = &row.data_name {
+            by_address.entry(row.address).or_default().push(idx);
+            by_name.entry(key(name)).or_default().push(idx);
+        }
+    }
+
+    let mut versions: BTreeMap<usize, u8> = BTreeMap::new();
+    let mut note = |idx: usize, version: u8| {
+        let lowest = versions.entry(idx).or_insert(version);
+        *lowest = version.min(*lowest);
+    };
+    for firmware_note in notes {
+        match firmware_note {
+            FirmwareNote::Prose { text, version } => {
+                for captures in ADDRESS_RE.captures_iter(text) {
+                    let before = text[..captures.get(0).unwrap().start()]
+                        .trim_end()
+                        .to_lowercase();
+                    let rows = captures[1]
+                        .parse()
+                        .ok()
+                        .and_then(|address: u16| by_address.get(&address));
+                    for idx in rows.into_iter().flatten() {
+                        let name = data[*idx].data_name.as_deref().unwrap_or_default();
+                        let name = name.trim().to_lowercase();
+                        let named = before
+                            .strip_suffix(&name)
+                            .is_some_and(|rest| !rest.ends_with(|c: char| c.is_alphanumeric()));
+                        if named {
+                            note(*idx, *version);
+                        }
+                    }
+                }
+            }
+            FirmwareNote::Listing { items, version } => {
+                for item in items {
+                    // Items may be listed with their address, eg "LED(65)"
+                    let name = item.split('(').next().unwrap_or(item);
+                    for idx in by_name.get(&key(name)).into_iter().flatten() {
+                        note(*idx, *version);
+                    }
+                }
+            }
+        }
+    }
+
+    for (idx, row) in data.iter_mut().enumerate() {
+        row.min_firmware = versions.get(&idx).copied();
+    }
+}
+
+/// Pick this model's value for every initial value given per model, where
+/// the page labels a value with the model (eg "XM430-W210") or the variant
+/// (eg "W210"). Values for other models are left as they are.
+pub fn resolve_model_values(data: &mut [ControlTableData], model: &str) {
+    let compact = |text: &str| -> String {
+        text.chars()
+            .filter(char::is_ascii_alphanumeric)
+            .map(|c| c.to_ascii_uppercase())
+            .collect()
+    };
+    let model = compact(model);
+
+    for row in data {
+        if let InitialValue::ModelDependent(values) = &row.initial_value {
+            let value = values.iter().find(|(label, _)| {
+                let label = compact(label);
+                !label.is_empty() && model.ends_with(&label)
+            });
+            if let Some((label, value)) = value {
+                debug!(item = ?row.data_name, %label, value, "Using the initial value for this model");
+                row.initial_value = InitialValue::Value(RangeValue::Integer(*value));
+            }
+        }
+    }
+}
+
+/// Parse a table of the bits of an item, skipping reserved or unused bits
+pub fn parse_bits(table: &[Vec<String>]) -> Vec<BitField> {
+    lazy_static! {
+        static ref BITS_RE: Regex =
+            Regex::new(r"([0-9]+)(?:\s*[~-]\s*(?:Bit\s*)?([0-9]+))?").unwrap();
+    }
+
+    let column = |names: &[&str]| table[0].iter().position(|h| names.contains(&h.as_str()));
+    let (bit_idx, name_idx) = match (column(&["Bit"]), column(&["Item", "Name", "Data Name"])) {
+        (Some(bit), Some(name)) => (bit, name),
+        _ => return vec![],
+    };
+    let description_idx = column(&["Description", "Explanation", "Contents", "Details"]);
+
+    let mut fields: Vec<BitField> = table
+        .iter()
+        .skip(1)
+        .filter_map(|row| {
+            let captures = BITS_RE.captures(row.get(bit_idx)?)?;
+            let low: u8 = captures[1].parse().ok()?;
+            let high: u8 = match captures.get(2) {
+                Some(high) => high.as_str().parse().ok()?,
+                None => low,
+            };
+            // Drop notes such as "(default)" from the name
+            let name = row.get(name_idx)?.split('(').next().unwrap().trim();
+            if is_placeholder(name)
+                || name.eq_ignore_ascii_case("reserved")
+                || name.eq_ignore_ascii_case("unused")
+            {
+                return None;
+            }
+
+            let description = description_idx
+                .and_then(|idx| row.get(idx))
+                .map(|text| text.trim().to_string())
+                .filter(|text| !is_placeholder(text));
+            Some(BitField {
+                bit: low.min(high),
+                // A range such as "0 ~ 255" is of values, not bits
+                width: (low.max(high) - low.min(high)).checked_add(1)?,
+                name: name.to_string(),
+                values: description
+                    .as_deref()
+                    .map(parse_bit_values)
+                    .unwrap_or_default(),
+                description,
+            })
+        })
+        .collect();
+    fields.sort_by_key(|field| field.bit);
+
+    fields
+}
+
+/// Parse the values a field of bits is described as taking, each marked like
+/// "[1]", eg "[0] Velocity-based Profile: ... [1] Time-based Profile: ...".
+/// Notes after the last value, marked with "※", aren't part of its meaning.
+pub fn parse_bit_values(description: &str) -> Vec<BitValue> {
+    lazy_static! {
+        static ref VALUE_RE: Regex = Regex::new(r"\[\s*([0-9]+)\s*\]").unwrap();
+    }
+
+    let markers: Vec<(u32, usize, usize)> = VALUE_RE
+        .captures_iter(description)
+        .filter_map(|captures| {
+            let marker = captures.get(0)?;
+            Some((captures[1].parse().ok()?, marker.start(), marker.end()))
+        })
+        .collect();
+
+    markers
+        .iter()
+        .enumerate()
+        .filter_map(|(i, &(value, _, end))| {
+            let next = markers.get(i + 1).map_or(description.len(), |m| m.1);
+            let meaning = description[end..next].split('※').next().unwrap().trim();
+            match meaning.is_empty() {
+                true => None,
+                false => Some(BitValue {
+                    value,
+                    meaning: meaning.to_string(),
+                }),
+            }
+        })
+        .collect()
+}
+
+/// Attach each table of bits to the item named by the heading above it,
+/// ignoring any address in the heading (eg "Shutdown(63)"). Fields past the
+/// last bit of the item are left out.
+pub fn apply_bit_fields(data: &mut [ControlTableData], tables: &[(String, Vec<Vec<String>>)]) {
+    for (heading, table) in tables {
+        let name = normalize_name(heading.split('(').next().unwrap_or(heading));
+        let row = data
+            .iter_mut()
+            .find(|row| row.data_name.as_deref().map(normalize_name) == Some(name.clone()));
+
+        match row {
+            Some(row) => {
+                let mut bits = parse_bits(table);
+                bits.retain(|field| {
+                    let fits =
+                        u16::from(field.bit) + u16::from(field.width) <= 8 * u16::from(row.size);
+                    if !fits {
+                        warn!(
+                            item = ?row.data_name,
+                            field = %field.name,
+                            bit = field.bit,
+                            width = field.width,
+                            "Field of bits doesn't fit in the item, leaving it out"
+                        );
+                    }
+                    fits
+                });
+                if !bits.is_empty() {
+                    row.bits = Some(bits);
+                }
+            }
+            None => debug!(%heading, "Bit table doesn't match any item"),
+        }
+    }
+}
+
+/// Attach each table of values to the items named by the heading above it,
+/// ignoring any address in the heading (eg "Operating Mode(11)"). Rows whose
+/// value isn't a single number, such as "3 ~ 252", are left out.
+pub fn apply_value_tables(data: &mut [ControlTableData], tables: &[(String, Vec<Vec<String>>)]) {
+    for (heading, table) in tables {
+        let name = normalize_name(heading.split('(').next().unwrap_or(heading));
+        let description_idx = table[0].iter().position(|h| h == "Description");
+        let values: Vec<ItemValue> = table[1..]
+            .iter()
+            .filter_map(|cells| {
+                let value = strip_thousands_separators(cells.first()?.trim())
+                    .parse()
+                    .ok()?;
+                let name = cells
+                    .get(1)?
+                    .split_whitespace()
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                if is_placeholder(&name) {
+                    return None;
+                }
+                let description = description_idx
+                    .filter(|idx| *idx > 1)
+                    .and_then(|idx| cells.get(idx))
+                    .map(|text| text.trim().to_string())
+                    .filter(|text| !is_placeholder(text));
+                Some(ItemValue {
+                    value,
+                    name,
+                    description,
+                })
+            })
+            .collect();
+        if values.is_empty() {
+            debug!(%heading, "Value table doesn't list any values");
+            continue;
+        }
+
+        let mut found = false;
+        for row in data
+            .iter_mut()
+            .filter(|row| row.data_name.as_deref().map(normalize_name) == Some(name.clone()))
+        {
+            row.values = values.clone();
+            found = true;
+        }
+        if !found {
+            debug!(%heading, "Value table doesn't match any item");
+        }
+    }
+}
+
+/// Attach the equations of each table of conversion equations to the gains
+/// they convert. Rows name a gain (eg "P Gain") which is either an item
+/// itself, or one of the items named by the heading above the table (eg
+/// "Position PID Gain(80, 82, 84)" for Position P Gain).
+pub fn apply_conversions(data: &mut [ControlTableData], tables: &[(String, Vec<Vec<String>>)]) {
+    lazy_static! {
+        static ref DIVISOR_RE: Regex =
+            Regex::new(r"\(\s*TBL\s*\)\s*/\s*([0-9][0-9,]*)\s*$").unwrap();
+    }
+
+    let names: BTreeSet<String> = data
+        .iter()
+        .filter_map(|row| row.data_name.as_deref().map(normalize_name))
+        .collect();
+
+    for (heading, table) in tables {
+        let column = |name: &str| table[0].iter().position(|h| h.starts_with(name));
+        let (equation_idx, gain_idx) = match (
+            column("Conversion Equation"),
+            column("Description").or_else(|| column("Controller Gain")),
+        ) {
+            (Some(equation_idx), Some(gain_idx)) => (equation_idx, gain_idx),
+            _ => {
+                debug!(%heading, "Conversion table doesn't name its gains");
+                continue;
+            }
+        };
+        let heading_name = normalize_name(heading);
+
+        for cells in &table[1..] {
+            let (formula, gain) = match (cells.get(equation_idx), cells.get(gain_idx)) {
+                (Some(formula), Some(gain)) => (
+                    formula.split_whitespace().collect::<Vec<_>>().join(" "),
+                    normalize_name(gain),
+                ),
+                _ => continue,
+            };
+            let symbol = match formula.split_once('=') {
+                Some((symbol, _)) if !gain.is_empty() => symbol.trim().to_string(),
+                _ => continue,
+            };
+
+            // The item named exactly by the row, otherwise the one named by
+            // the row and the heading. Models with a bank of items per joint
+            // have one of each item for every joint.
+            let name = match names.contains(&gain) {
+                true => Some(&gain),
+                false => {
+                    let mut matches = names.iter().filter(|name| {
+                        name.strip_suffix(gain.as_str()).is_some_and(|prefix| {
+                            !prefix.is_empty() && heading_name.contains(prefix)
+                        })
+                    });
+                    match (matches.next(), matches.next()) {
+                        (Some(name), None) => Some(name),
+                        _ => None,
+                    }
+                }
+            };
+            let name = match name {
+                Some(name) => name,
+                None => {
+                    debug!(%heading, %formula, "Conversion equation doesn't match a single item");
+                    continue;
+                }
+            };
+
+            let conversion = Conversion {
+                divisor: DIVISOR_RE
+                    .captures(&formula)
+                    .and_then(|captures| strip_thousands_separators(&captures[1]).parse().ok()),
+                symbol,
+                formula,
+            };
+            for row in data
+                .iter_mut()
+                .filter(|row| row.data_name.as_deref().map(normalize_name).as_ref() == Some(name))
+            {
+                row.conversion = Some(conversion.clone());
+            }
+        }
+    }
+}
+
+/// Parse an address cell, ignoring any hexadecimal annotation that legacy
+/// pages include alongside the decimal address (eg "6 (0X06)")
+fn parse_address(text: &str) -> Result<u16> {
+    let decimal = text.split_whitespace().next().unwrap_or(text);
+    match decimal
+        .strip_prefix("0x")
+        .or_else(|| decimal.strip_prefix("0X"))
+    {
+        Some(hex) => Ok(u16::from_str_radix(hex, 16)?),
+        None => Ok(decimal.parse::<u16>()?),
+    }
+}
+
+/// Split the name of a legacy low/high byte row into its base name and
+/// whether it is the high byte, eg "Goal Position(L)" -> ("Goal Position", false)
+fn split_byte_name(name: &str) -> Option<(&str, bool)> {
+    lazy_static! {
+        static ref SPLIT_BYTE_RE: Regex = Regex::new(r"^(.*?)\s*[(\[]([LH])[)\]]$").unwrap();
+    }
+
+    let captures = SPLIT_BYTE_RE.captures(name.trim())?;
+    Some((captures.get(1)?.as_str(), &captures[2] == "H"))
+}
+
+/// Older pages list multi-byte registers as separate low and high byte rows.
+/// Combine each such pair into a single register at the low byte's address.
+fn coalesce_split_bytes(data: Vec<ControlTableData>) -> Vec<ControlTableData> {
+    let mut coalesced: Vec<ControlTableData> = Vec::with_capacity(data.len());
+
+    for row in data {
+        let high_name = row
+            .data_name
+            .as_deref()
+            .and_then(split_byte_name)
+            .filter(|(_, high)| *high)
+            .map(|(name, _)| name.to_string());
+
+        let low = coalesced.last_mut().filter(|low| {
+            let low_name = low.data_name.as_deref().and_then(split_byte_name);
+            matches!((low_name, &high_name), (Some((name, false)), Some(high)) if name == high)
+                && low.address + low.size as u16 == row.address
         });
+
+        match (low, high_name) {
+            (Some(low), Some(name)) => {
+                debug!(%name, address = low.address, "Combining low and high byte rows");
+                if let (Some(RangeValue::Integer(l)), Some(RangeValue::Integer(h))) =
+                    (low.initial_value.value(), row.initial_value.value())
+                {
+                    let shift = 8 * u32::from(low.size);
+                    let combined = 1i32
+                        .checked_shl(shift)
+                        .filter(|scale| *scale > 0)
+                        .and_then(|scale| h.checked_mul(scale))
+                        .and_then(|high| high.checked_add(*l));
+                    // A value too large for an i32 is kept as the bytes add up
+                    low.initial_value = match combined {
+                        Some(value) => InitialValue::Value(RangeValue::Integer(value)),
+                        None => InitialValue::Expression(format!("{} + ({} << {})", l, h, shift)),
+                    };
+                }
+
+                low.data_name = Some(name);
+                low.size += row.size;
+                low.range = low.range.take().or(row.range);
+                low.units = low.units.take().or(row.units);
+                low.value_type = ValueType::infer(
+                    low.size,
+                    low.range.as_ref(),
+                    low.initial_value.value(),
+                    low.units.as_deref(),
+                );
+            }
+            _ => coalesced.push(row),
+        }
+    }
+
+    coalesced
+}
+
+/// An entry in the index of every model written to `objects/`
+#[derive(Clone, Serialize, Deserialize, Debug)]
+pub struct IndexEntry {
+    pub series: String,
+    pub slug: String,
+    pub name: String,
+    pub model_number: Option<i32>,
+    pub protocols: Vec<String>,
+    /// The path of the model's RON file, relative to `objects/`
+    pub path: String,
+}
+
+/// Serialize the aggregate of a series' control tables to RON
+pub fn serialize_series(series: &SeriesObject) -> Result<String> {
+    let pretty = PrettyConfig::new().with_separate_tuple_members(true);
+
+    Ok(to_string_pretty(series, pretty)?)
+}
+
+pub fn serialize_index(index: &[IndexEntry]) -> Result<String> {
+    let pretty = PrettyConfig::new().with_separate_tuple_members(true);
+    let s = to_string_pretty(&index, pretty)?;
+
+    Ok(s)
+}
+
+/// Why the control table of a page couldn't be parsed
+#[derive(Debug)]
+pub enum ParseError {
+    /// None of the tables on the page look like a control table
+    NoControlTable,
+    /// The page splits the control table into tables with different
+    /// headings, so they can't be merged
+    MismatchedHeadings {
+        first: Vec<String>,
+        other: Vec<String>,
+    },
+    /// The control table has no column for a heading every item needs
+    MissingHeading {
+        heading: &'static str,
+        headings: Vec<String>,
+    },
+    /// A cell that should hold a value or the name of an item holds neither
+    InvalidValue { text: String },
+    /// Anything else, described
+    Other(String),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::NoControlTable => write!(f, "No control table found on page"),
+            ParseError::MismatchedHeadings { first, other } => write!(
+                f,
+                "Control table headings differ: {:?} and {:?}",
+                first, other
+            ),
+            ParseError::MissingHeading { heading, headings } => {
+                write!(f, "Missing heading {:?} in {:?}", heading, headings)
+            }
+            ParseError::InvalidValue { text } => {
+                write!(f, "Can't parse {:?} as a value or item name", text)
+            }
+            ParseError::Other(reason) => write!(f, "{}", reason),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+impl From<anyhow::Error> for ParseError {
+    /// Recover the [`ParseError`] an error was raised with, even if context
+    /// was added to it since
+    fn from(error: anyhow::Error) -> ParseError {
+        match error.downcast::<ParseError>() {
+            Ok(error) => error,
+            Err(error) => ParseError::Other(format!("{:#}", error)),
+        }
     }
+}
+
+/// The version of the format of the RON objects, increased whenever a change
+/// to [`ControlTableData`] means older objects have to be migrated to read
+/// them. Objects written before the format was versioned are a bare list of
+/// items: version 0 for those written before items had a value type, and 1
+/// for those written since.
+pub static SCHEMA_VERSION: u32 = 6;
+
+#[derive(Serialize)]
+struct Object<'a> {
+    schema_version: u32,
+    items: &'a [ControlTableData],
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    warnings: &'a [ParseWarning],
+}
+
+#[derive(Deserialize)]
+struct StoredObject {
+    schema_version: u32,
+    items: Vec<StoredItem>,
+    #[serde(default)]
+    warnings: Vec<ParseWarning>,
+}
+
+/// An item as written by any version of the format, with the fields that
+/// have been added since the first version defaulted
+#[derive(Deserialize)]
+struct StoredItem {
+    address: u16,
+    size: u8,
+    data_name: Option<String>,
+    description: Option<String>,
+    access: AccessLevel,
+    initial_value: InitialValue,
+    #[serde(default)]
+    initial_value_source: InitialValueSource,
+    range: Option<(RangeValue, RangeValue)>,
+    units: Option<String>,
+    #[serde(default, deserialize_with = "present")]
+    value_type: Option<ValueType>,
+    min_firmware: Option<u8>,
+    bits: Option<Vec<BitField>>,
+    #[serde(default)]
+    values: Vec<ItemValue>,
+    joint: Option<u8>,
+    #[serde(default)]
+    related: Vec<DataNameRef>,
+    #[serde(default)]
+    notes: Vec<String>,
+    #[serde(default)]
+    conversion: Option<Conversion>,
+    #[serde(default)]
+    modbus_address: Option<u16>,
+    #[serde(default)]
+    raw: Option<RawCells>,
+}
 
-    Ok(data)
+/// Read a field that's written as a plain value, but missing from objects
+/// written before it was added
+fn present<'de, D, T>(deserializer: D) -> std::result::Result<Option<T>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    T::deserialize(deserializer).map(Some)
 }
 
-pub fn serialize_servo(servo: &[ControlTableData]) -> Result<String> {
+impl StoredItem {
+    fn migrate(self) -> ControlTableData {
+        let value_type = self.value_type.unwrap_or_else(|| {
+            ValueType::infer(
+                self.size,
+                self.range.as_ref(),
+                self.initial_value.value(),
+                self.units.as_deref(),
+            )
+        });
+
+        ControlTableData {
+            address: self.address,
+            size: self.size,
+            data_name: self.data_name,
+            description: self.description,
+            access: self.access,
+            initial_value: self.initial_value,
+            initial_value_source: self.initial_value_source,
+            range: self.range,
+            units: self.units,
+            value_type,
+            min_firmware: self.min_firmware,
+            bits: self.bits,
+            values: self.values,
+            joint: self.joint,
+            related: self.related,
+            notes: self.notes,
+            conversion: self.conversion,
+            modbus_address: self.modbus_address,
+            raw: self.raw,
+        }
+    }
+}
+
+/// Serialize a control table to RON, along with the warnings from parsing it
+/// (if there were any). Items keep the order of the page and the formatting
+/// has no options that vary between runs, so an unchanged page always
+/// serializes to the same bytes.
+pub fn serialize_servo(servo: &[ControlTableData], warnings: &[ParseWarning]) -> Result<String> {
     let pretty = PrettyConfig::new()
         .with_separate_tuple_members(true)
         .with_enumerate_arrays(true);
-    let s = to_string_pretty(&servo, pretty)?;
+    let object = Object {
+        schema_version: SCHEMA_VERSION,
+        items: servo,
+        warnings,
+    };
+    let s = to_string_pretty(&object, pretty)?;
 
     Ok(s)
 }
+
+/// Read a control table serialized by [`serialize_servo`], migrating objects
+/// written by older versions of the scraper to the current format. Objects
+/// written by newer versions are an error rather than being misread.
+pub fn deserialize_servo(text: &str) -> Result<Vec<ControlTableData>> {
+    Ok(deserialize_servo_with_warnings(text)?.0)
+}
+
+/// Read a control table as [`deserialize_servo`] does, along with the
+/// warnings from parsing it. Objects written before warnings were recorded
+/// have none.
+pub fn deserialize_servo_with_warnings(
+    text: &str,
+) -> Result<(Vec<ControlTableData>, Vec<ParseWarning>)> {
+    let (items, warnings): (Vec<StoredItem>, _) = match text.trim_start().starts_with('[') {
+        true => (ron::from_str(text)?, vec![]),
+        false => {
+            let object: StoredObject = ron::from_str(text)?;
+            ensure!(
+                object.schema_version <= SCHEMA_VERSION,
+                "Written with version {} of the format, but only up to {} can be read",
+                object.schema_version,
+                SCHEMA_VERSION
+            );
+            (object.items, object.warnings)
+        }
+    };
+
+    Ok((
+        items.into_iter().map(StoredItem::migrate).collect(),
+        warnings,
+    ))
+}
+
+/// Format a range or initial value as the E-Manual writes it, eg "-PWM Limit"
+fn markdown_value(value: &RangeValue) -> String {
+    match value {
+        RangeValue::Integer(i) => i.to_string(),
+        RangeValue::Address {
+            name,
+            negative,
+            reference,
+        } => {
+            let name = reference.as_ref().map_or(name, |r| &r.data_name);
+            format!("{}{}", if *negative { "-" } else { "" }, name)
+        }
+    }
+}
+
+/// Escape the text of a Markdown table cell, using "-" for a missing value
+fn markdown_cell(text: Option<&str>) -> String {
+    match text.map(str::trim).filter(|text| !text.is_empty()) {
+        Some(text) => text.replace('|', "\\|").replace('\n', " "),
+        None => "-".to_string(),
+    }
+}
+
+/// The items most often needed to get a model moving, and so summarised for
+/// each model, under the names different series give them
+static KEY_ITEMS: &[&str] = &[
+    "Model Number",
+    "ID",
+    "Baud Rate",
+    "Operating Mode",
+    "Drive Mode",
+    "Torque Enable",
+    "Goal Position",
+    "Goal Velocity",
+    "Moving Speed",
+    "Goal Current",
+    "Present Position",
+    "Present Velocity",
+    "Present Speed",
+    "Present Current",
+    "Present Load",
+    "Present Temperature",
+    "Hardware Error Status",
+];
+
+/// Summarise a model in Markdown for someone browsing the RON objects: its
+/// model number, protocols and source, and where its key items are
+pub fn serialize_servo_summary(
+    name: &str,
+    url: &str,
+    model_number: Option<i32>,
+    protocols: &[String],
+    discontinued: bool,
+    servo: &[ControlTableData],
+) -> String {
+    let mut markdown = format!("# {}\n\n", name);
+    if discontinued {
+        markdown.push_str("Discontinued by ROBOTIS.\n\n");
+    }
+    markdown.push_str(&format!(
+        "- Model number: {}\n- Protocols: {}\n- Items: {}\n- Source: <{}>\n",
+        model_number.map_or("-".to_string(), |number| number.to_string()),
+        match protocols.is_empty() {
+            true => "-".to_string(),
+            false => protocols.join(", "),
+        },
+        servo.len(),
+        url
+    ));
+
+    markdown.push_str("\n## Key Items\n\n| Address | Size | Data Name | Access | Range | Unit |\n");
+    markdown.push_str("|--------:|-----:|-----------|:------:|-------|------|\n");
+    for row in servo {
+        let is_key = row
+            .data_name
+            .as_deref()
+            .is_some_and(|name| KEY_ITEMS.contains(&name.trim()));
+        if !is_key {
+            continue;
+        }
+        let range = row
+            .range
+            .as_ref()
+            .map(|(min, max)| format!("{} ~ {}", markdown_value(min), markdown_value(max)));
+        let access = row.access.as_str();
+        markdown.push_str(&format!(
+            "| {} | {} | {} | {} | {} | {} |\n",
+            row.address,
+            row.size,
+            markdown_cell(row.data_name.as_deref()),
+            access,
+            markdown_cell(range.as_deref()),
+            markdown_cell(row.units.as_deref()),
+        ));
+    }
+
+    markdown
+}
+
+/// Document a model's control table in Markdown, with a table of every item,
+/// the bits of any items documented as flags and a footer naming the page it
+/// was scraped from
+pub fn serialize_servo_markdown(name: &str, url: &str, servo: &[ControlTableData]) -> String {
+    let mut markdown = format!("# {}\n\n## Control Table\n\n", name);
+    markdown.push_str("| Address | Size | Data Name | Access | Initial Value | Range | Unit |\n");
+    markdown.push_str("|--------:|-----:|-----------|:------:|---------------|-------|------|\n");
+
+    for row in servo {
+        let initial_value = match &row.initial_value {
+            InitialValue::Value(value) => Some(markdown_value(value)),
+            InitialValue::None => None,
+            InitialValue::ModelDependent(values) if values.is_empty() => {
+                Some("Model dependent".to_string())
+            }
+            InitialValue::ModelDependent(values) => Some(
+                values
+                    .iter()
+                    .map(|(model, value)| format!("{} ({})", value, model))
+                    .collect::<Vec<String>>()
+                    .join(", "),
+            ),
+            InitialValue::Expression(text) => Some(text.clone()),
+        };
+        let range = row
+            .range
+            .as_ref()
+            .map(|(min, max)| format!("{} ~ {}", markdown_value(min), markdown_value(max)));
+        let access = row.access.as_str();
+
+        let data_name = match (&row.data_name, row.joint) {
+            (Some(name), Some(joint)) => Some(format!("{} (Joint {})", name, joint)),
+            (name, _) => name.clone(),
+        };
+
+        markdown.push_str(&format!(
+            "| {} | {} | {} | {} | {} | {} | {} |\n",
+            row.address,
+            row.size,
+            markdown_cell(data_name.as_deref()),
+            access,
+            markdown_cell(initial_value.as_deref()),
+            markdown_cell(range.as_deref()),
+            markdown_cell(row.units.as_deref()),
+        ));
+    }
+
+    for row in servo {
+        let bits = match &row.bits {
+            Some(bits) => bits,
+            None => continue,
+        };
+        markdown.push_str(&format!(
+            "\n### {} ({})\n\n| Bit | Name | Description |\n|----:|------|-------------|\n",
+            markdown_cell(row.data_name.as_deref()),
+            row.address
+        ));
+        for field in bits {
+            let bit = match field.width {
+                1 => field.bit.to_string(),
+                width => format!("{} ~ {}", field.bit, field.bit + width - 1),
+            };
+            markdown.push_str(&format!(
+                "| {} | {} | {} |\n",
+                bit,
+                markdown_cell(Some(&field.name)),
+                markdown_cell(field.description.as_deref())
+            ));
+        }
+    }
+
+    let related: Vec<&ControlTableData> =
+        servo.iter().filter(|row| !row.related.is_empty()).collect();
+    if !related.is_empty() {
+        markdown.push_str("\n## Related Items\n\n| Item | Related |\n|------|---------|\n");
+        for row in related {
+            let items = row
+                .related
+                .iter()
+                .map(|item| format!("{} ({})", item.data_name, item.address))
+                .collect::<Vec<String>>()
+                .join(", ");
+            markdown.push_str(&format!(
+                "| {} ({}) | {} |\n",
+                markdown_cell(row.data_name.as_deref()),
+                row.address,
+                markdown_cell(Some(&items))
+            ));
+        }
+    }
+
+    let noted: Vec<&ControlTableData> = servo.iter().filter(|row| !row.notes.is_empty()).collect();
+    if !noted.is_empty() {
+        markdown.push_str("\n## Notes\n\n| Item | Note |\n|------|------|\n");
+        for row in noted {
+            for note in &row.notes {
+                markdown.push_str(&format!(
+                    "| {} ({}) | {} |\n",
+                    markdown_cell(row.data_name.as_deref()),
+                    row.address,
+                    markdown_cell(Some(note))
+                ));
+            }
+        }
+    }
+
+    let converted: Vec<(&ControlTableData, &Conversion)> = servo
+        .iter()
+        .filter_map(|row| Some((row, row.conversion.as_ref()?)))
+        .collect();
+    if !converted.is_empty() {
+        markdown.push_str("\n## Conversions\n\n| Item | Equation |\n|------|----------|\n");
+        for (row, conversion) in converted {
+            markdown.push_str(&format!(
+                "| {} ({}) | {} |\n",
+                markdown_cell(row.data_name.as_deref()),
+                row.address,
+                markdown_cell(Some(&conversion.formula))
+            ));
+        }
+    }
+
+    markdown.push_str(&format!(
+        "\n---\n\nScraped from <{}> by [dynamixel-scraper](https://github.com/kiros-rs/dynamixel-scraper) {}. Check the E-Manual before relying on these values.\n",
+        url,
+        env!("CARGO_PKG_VERSION")
+    ));
+
+    markdown
+}
+
+/// Serialize a control table as YAML, eg for ROS parameter files
+pub fn serialize_servo_yaml(servo: &[ControlTableData]) -> Result<String> {
+    Ok(serde_yaml::to_string(&servo)?)
+}
+
+/// A control table as written to MessagePack. Unlike the RON object, fields
+/// are never skipped, as they're written by position.
+#[derive(Serialize, Deserialize)]
+struct BinaryObject {
+    schema_version: u32,
+    items: Vec<ControlTableData>,
+}
+
+/// Serialize a control table as MessagePack, with structs written as arrays
+/// of their fields rather than maps, for firmware to `include_bytes!` and
+/// read at run time (eg with `rmp-serde`). The blob starts with the
+/// [`SCHEMA_VERSION`] like the RON object, but warnings are left out.
+pub fn serialize_servo_msgpack(servo: &[ControlTableData]) -> Result<Vec<u8>> {
+    let object = BinaryObject {
+        schema_version: SCHEMA_VERSION,
+        items: servo.to_vec(),
+    };
+
+    Ok(rmp_serde::to_vec(&object)?)
+}
+
+/// Read a control table serialized by [`serialize_servo_msgpack`]. Blobs
+/// written by any other version of the format are an error, as fields are
+/// read by position and can't be migrated.
+pub fn deserialize_servo_msgpack(bytes: &[u8]) -> Result<Vec<ControlTableData>> {
+    let object: BinaryObject = rmp_serde::from_slice(bytes)?;
+    ensure!(
+        object.schema_version == SCHEMA_VERSION,
+        "Written with version {} of the format, but only {} can be read",
+        object.schema_version,
+        SCHEMA_VERSION
+    );
+
+    Ok(object.items)
+}
+
+/// Serialize the index of every model as MessagePack, in the same layout as
+/// [`serialize_servo_msgpack`]
+pub fn serialize_index_msgpack(index: &[IndexEntry]) -> Result<Vec<u8>> {
+    Ok(rmp_serde::to_vec(index)?)
+}
+
+// References to other items are followed at most this deep when finding the
+// bounds of a range, in case items refer to each other
+static MAX_REFERENCE_DEPTH: usize = 4;
+
+/// The lowest (or highest) value an item's range allows, following bounds
+/// that refer to other items to the bounds of those items' ranges, eg the
+/// highest Goal Position is the highest Max Position Limit can be set to
+fn range_bound(
+    servo: &[ControlTableData],
+    row: &ControlTableData,
+    upper: bool,
+    depth: usize,
+) -> Option<i64> {
+    let (min, max) = match &row.range {
+        Some(range) => range,
+        None => {
+            let (min, max) = row.value_type.bounds()?;
+            return Some(if upper { max } else { min });
+        }
+    };
+
+    match if upper { max } else { min } {
+        RangeValue::Integer(value) => Some((*value).into()),
+        RangeValue::Address {
+            negative,
+            reference: Some(reference),
+            ..
+        } if depth < MAX_REFERENCE_DEPTH => {
+            let item = servo.iter().find(|item| {
+                item.address == reference.address
+                    && item.data_name.as_deref().map(str::trim) == Some(&reference.data_name)
+            })?;
+            // The lowest a negated item can be is minus its highest value
+            let bound = range_bound(servo, item, upper != *negative, depth + 1)?;
+            Some(if *negative { -bound } else { bound })
+        }
+        RangeValue::Address { .. } => None,
+    }
+}
+
+/// Describe the values each writable item of a control table accepts as a
+/// JSON Schema, so tools writing servo setup files can validate them before
+/// they reach a servo. Items are keyed by name (with their joint, on models
+/// with several), and bounds referring to other items are replaced by the
+/// widest values those items allow.
+pub fn serialize_servo_schema(name: &str, url: &str, servo: &[ControlTableData]) -> Result<String> {
+    let mut properties = serde_json::Map::new();
+    for row in servo {
+        let data_name = match (&row.data_name, &row.access) {
+            (Some(data_name), AccessLevel::ReadWrite) => data_name.trim(),
+            _ => continue,
+        };
+        let key = match row.joint {
+            Some(joint) => format!("{} (Joint {})", data_name, joint),
+            None => data_name.to_string(),
+        };
+        if properties.contains_key(&key) {
+            warn!(item = %key, "Item is in the control table twice, only describing the first");
+            continue;
+        }
+
+        let mut property = match row.value_type {
+            ValueType::Bytes(size) => serde_json::json!({
+                "type": "array",
+                "items": { "type": "integer", "minimum": 0, "maximum": u8::MAX },
+                "minItems": size,
+                "maxItems": size,
+            }),
+            _ => serde_json::json!({
+                "type": "integer",
+                "minimum": range_bound(servo, row, false, 0),
+                "maximum": range_bound(servo, row, true, 0),
+            }),
+        };
+        let fields = property.as_object_mut().unwrap();
+        fields.retain(|_, value| !value.is_null());
+        if let Some(description) = &row.description {
+            fields.insert("description".to_string(), description.trim().into());
+        }
+        if let InitialValue::Value(RangeValue::Integer(value)) = row.initial_value {
+            fields.insert("default".to_string(), value.into());
+        }
+        // Items documented with a table of their values take only those
+        if !row.values.is_empty() {
+            let values: Vec<i64> = row.values.iter().map(|value| value.value).collect();
+            fields.insert("enum".to_string(), values.into());
+        }
+        fields.insert("x-address".to_string(), row.address.into());
+        fields.insert("x-size".to_string(), row.size.into());
+        if let Some(units) = &row.units {
+            fields.insert("x-units".to_string(), units.trim().into());
+        }
+
+        properties.insert(key, property);
+    }
+
+    let schema = serde_json::json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "title": format!("{} control table", name),
+        "description": format!("The values each writable item of the {} accepts, scraped from {}", name, url),
+        "type": "object",
+        "properties": properties,
+        "additionalProperties": false,
+    });
+
+    Ok(format!("{}\n", serde_json::to_string_pretty(&schema)?))
+}
@@ -0,0 +1,226 @@
+use crate::serialize::ControlTableData;
+use crate::{write_atomic, Actuator};
+use anyhow::{Context, Result};
+use ron::ser::{to_string_pretty, PrettyConfig};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::debug;
+
+pub static SNAPSHOTS_DIR: &str = "snapshots";
+/// The exit code of `--check` when the control tables have changed, distinct
+/// from the codes of every [`ErrorCategory`](crate::report::ErrorCategory)
+pub static CHANGED_EXIT_CODE: u8 = 7;
+
+/// The parsed control table of every model scraped so far, by URL slug (eg
+/// "xm430-w210")
+pub type Snapshot = BTreeMap<String, Vec<ControlTableData>>;
+
+/// How a change to the control tables affects the generated library, in
+/// semantic versioning terms
+#[derive(Clone, Copy, Serialize, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Bump {
+    /// Only documentation (such as descriptions or ranges) changed
+    Patch,
+    /// Models or registers were added
+    Minor,
+    /// Registers were removed or moved, breaking code that uses them
+    Major,
+}
+
+impl fmt::Display for Bump {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Bump::Patch => write!(f, "patch"),
+            Bump::Minor => write!(f, "minor"),
+            Bump::Major => write!(f, "major"),
+        }
+    }
+}
+
+/// A difference between a model's control table in two snapshots
+#[derive(Clone, Serialize, Debug, PartialEq)]
+pub struct Change {
+    pub model: String,
+    pub description: String,
+    pub bump: Bump,
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}] {}: {}", self.bump, self.model, self.description)
+    }
+}
+
+/// The changes from the previous snapshot to the current one. Models missing
+/// from the current snapshot are ignored, as they may have been filtered out
+/// of the scrape rather than removed from the E-Manual.
+pub fn diff(previous: &Snapshot, current: &Snapshot) -> Vec<Change> {
+    let mut changes = vec![];
+
+    for (model, data) in current {
+        let change = |description: String, bump: Bump| Change {
+            model: model.clone(),
+            description,
+            bump,
+        };
+        let old = match previous.get(model) {
+            Some(old) => old,
+            None => {
+                changes.push(change("added".to_string(), Bump::Minor));
+                continue;
+            }
+        };
+
+        // Models with a bank of items per joint name each joint's items alike
+        let items =
+            |data: &'_ [ControlTableData]| -> BTreeMap<(String, Option<u8>), ControlTableData> {
+                data.iter()
+                    .filter_map(|row| {
+                        let name = row.data_name.as_ref()?.trim().to_string();
+                        Some(((name, row.joint), row.clone()))
+                    })
+                    .collect()
+            };
+        let (old, new) = (items(old), items(data));
+        let label = |(name, joint): &(String, Option<u8>)| match joint {
+            Some(joint) => format!("{} (Joint {})", name, joint),
+            None => name.clone(),
+        };
+
+        for (key, row) in &new {
+            let name = label(key);
+            let old_row = match old.get(key) {
+                Some(old_row) => old_row,
+                None => {
+                    changes.push(change(
+                        format!("{} added at address {}", name, row.address),
+                        Bump::Minor,
+                    ));
+                    continue;
+                }
+            };
+
+            if (old_row.address, old_row.size) != (row.address, row.size) {
+                changes.push(change(
+                    format!(
+                        "{} moved from address {} ({} bytes) to {} ({} bytes)",
+                        name, old_row.address, old_row.size, row.address, row.size
+                    ),
+                    Bump::Major,
+                ));
+            } else if old_row.value_type != row.value_type {
+                changes.push(change(
+                    format!(
+                        "{} changed type from {} to {}",
+                        name,
+                        old_row.value_type.rust_type(),
+                        row.value_type.rust_type()
+                    ),
+                    Bump::Major,
+                ));
+            } else if format!("{:?}", old_row) != format!("{:?}", row) {
+                changes.push(change(format!("{} details changed", name), Bump::Patch));
+            }
+        }
+
+        for key in old.keys().filter(|key| !new.contains_key(*key)) {
+            changes.push(change(format!("{} removed", label(key)), Bump::Major));
+        }
+    }
+
+    changes
+}
+
+/// The largest bump needed by any of the changes, if there are any
+pub fn suggested_bump(changes: &[Change]) -> Option<Bump> {
+    changes.iter().map(|change| change.bump).max()
+}
+
+/// The changes found by `--check`, summarised for automation such as a
+/// scheduled job opening a pull request
+#[derive(Clone, Serialize, Debug, PartialEq)]
+pub struct ChangeSummary {
+    pub changed: bool,
+    pub bump: Option<Bump>,
+    pub changes: Vec<Change>,
+}
+
+impl ChangeSummary {
+    pub fn new(changes: Vec<Change>) -> ChangeSummary {
+        ChangeSummary {
+            changed: !changes.is_empty(),
+            bump: suggested_bump(&changes),
+            changes,
+        }
+    }
+}
+
+impl fmt::Display for ChangeSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.bump {
+            None => write!(f, "No changes since the objects were written"),
+            Some(bump) => {
+                writeln!(f, "Changes since the objects were written:")?;
+                for change in &self.changes {
+                    writeln!(f, "    {}", change)?;
+                }
+                write!(f, "Suggested version bump for the library: {}", bump)
+            }
+        }
+    }
+}
+
+/// Build a snapshot from the scraped models, carrying over any models from
+/// the previous snapshot that weren't scraped this time
+pub fn snapshot(previous: &Snapshot, actuators: &[Actuator]) -> Snapshot {
+    let mut snapshot = previous.clone();
+    for dxl in actuators {
+        snapshot.insert(dxl.index_entry().slug, dxl.data().to_vec());
+    }
+
+    snapshot
+}
+
+/// The most recent snapshot in `dir`, along with its path, if there is one.
+/// Snapshots are named after the time they were taken, so sort in order.
+pub fn load_latest(dir: &Path) -> Result<Option<(PathBuf, Snapshot)>> {
+    if !dir.exists() {
+        return Ok(None);
+    }
+
+    let mut paths: Vec<PathBuf> = fs::read_dir(dir)?
+        .map(|entry| Ok(entry?.path()))
+        .collect::<Result<Vec<PathBuf>>>()?
+        .into_iter()
+        .filter(|path| path.extension().is_some_and(|ext| ext == "ron"))
+        .collect();
+    paths.sort();
+
+    match paths.pop() {
+        Some(path) => {
+            let text = fs::read_to_string(&path)?;
+            let snapshot = ron::from_str(&text)
+                .with_context(|| format!("Failed to parse snapshot {}", path.display()))?;
+            Ok(Some((path, snapshot)))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Save a snapshot in `dir`, named after the current time, returning its path
+pub fn save(dir: &Path, snapshot: &Snapshot) -> Result<PathBuf> {
+    fs::create_dir_all(dir)?;
+    let seconds = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    let path = dir.join(format!("{:012}.ron", seconds));
+    debug!(path = %path.display(), models = snapshot.len(), "Saving snapshot");
+
+    let pretty = PrettyConfig::new().with_separate_tuple_members(true);
+    write_atomic(&path, to_string_pretty(snapshot, pretty)?)?;
+
+    Ok(path)
+}
@@ -0,0 +1,130 @@
+use crate::fetch::{HttpClient, PageUnavailable};
+use anyhow::{bail, Context, Result};
+use futures_util::future::{BoxFuture, FutureExt};
+use reqwest::{StatusCode, Url};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+use tracing::debug;
+
+/// Somewhere pages can be read from in place of the live E-Manual, such as a
+/// mirror of it on disk or fixtures in memory. An [`HttpClient`] given a
+/// source reads every page from it, including the navigation.
+///
+/// [`HttpClient`]: crate::fetch::HttpClient
+pub trait PageSource: fmt::Debug + Send + Sync {
+    /// Read the page at `url`, or fail with a [`PageUnavailable`] error if
+    /// the source doesn't have it
+    fn get_text<'a>(&'a self, url: &'a str) -> BoxFuture<'a, Result<String>>;
+}
+
+fn unavailable(url: &str) -> anyhow::Error {
+    PageUnavailable {
+        url: url.to_string(),
+        status: StatusCode::NOT_FOUND,
+    }
+    .into()
+}
+
+/// A mirror of the sites the E-Manual is scraped from, eg made with
+/// `wget --mirror`, where the page at a URL is the file at its host and path
+/// under the directory (eg emanual.robotis.com/docs/en/dxl/x/xm430-w210/), or
+/// at its path alone (eg docs/en/dxl/x/xm430-w210/). Pages ending in a slash
+/// are read from their index.html, as a web server would.
+#[derive(Clone, Debug)]
+pub struct DirectorySource {
+    pub root: PathBuf,
+}
+
+impl DirectorySource {
+    pub fn new(root: impl Into<PathBuf>) -> DirectorySource {
+        DirectorySource { root: root.into() }
+    }
+
+    /// Where the page at `url` could be saved, most specific first
+    fn candidates(&self, url: &str) -> Result<Vec<PathBuf>> {
+        let url = Url::parse(url)?;
+        let mut path = url.path().trim_start_matches('/').to_string();
+        if path.is_empty() || path.ends_with('/') {
+            path.push_str("index.html");
+        }
+
+        let mut candidates = vec![];
+        if let Some(host) = url.host_str() {
+            candidates.push(self.root.join(host).join(&path));
+        }
+        candidates.push(self.root.join(&path));
+
+        Ok(candidates)
+    }
+}
+
+impl PageSource for DirectorySource {
+    fn get_text<'a>(&'a self, url: &'a str) -> BoxFuture<'a, Result<String>> {
+        async move {
+            for path in self.candidates(url)? {
+                if path.is_file() {
+                    debug!(%url, path = %path.display(), "Reading mirrored page");
+                    return tokio::fs::read_to_string(&path)
+                        .await
+                        .with_context(|| format!("Failed to read {}", path.display()));
+                }
+            }
+
+            Err(unavailable(url))
+        }
+        .boxed()
+    }
+}
+
+/// Pages held in memory, eg fixtures for tests, keyed by their URL
+#[derive(Clone, Debug, Default)]
+pub struct MemorySource {
+    pages: BTreeMap<String, String>,
+}
+
+impl MemorySource {
+    pub fn new() -> MemorySource {
+        MemorySource::default()
+    }
+
+    /// Add the page at `url`, replacing any page already there
+    pub fn insert(&mut self, url: impl Into<String>, text: impl Into<String>) {
+        self.pages.insert(url.into(), text.into());
+    }
+}
+
+impl PageSource for MemorySource {
+    fn get_text<'a>(&'a self, url: &'a str) -> BoxFuture<'a, Result<String>> {
+        let page = self.pages.get(url).cloned().ok_or_else(|| unavailable(url));
+        async move { page }.boxed()
+    }
+}
+
+/// Where the scraper reads pages from, as given on the command line
+#[derive(Clone, Debug, PartialEq)]
+pub enum SourceSpec {
+    /// The live E-Manual, over HTTP
+    Http,
+    /// A mirror of the E-Manual in a directory, as `dir:PATH`
+    Directory(PathBuf),
+}
+
+impl FromStr for SourceSpec {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.split_once(':') {
+            _ if s == "http" => Ok(SourceSpec::Http),
+            Some(("dir", path)) if !path.is_empty() => Ok(SourceSpec::Directory(path.into())),
+            _ => bail!("Unknown page source {:?}, expected http or dir:PATH", s),
+        }
+    }
+}
+
+impl PageSource for HttpClient {
+    fn get_text<'a>(&'a self, url: &'a str) -> BoxFuture<'a, Result<String>> {
+        HttpClient::get_text(self, url).boxed()
+    }
+}
@@ -0,0 +1,201 @@
+use crate::serialize::{InitialValue, RangeValue, Units};
+use crate::Actuator;
+use anyhow::{Context, Result};
+use rusqlite::{params, Connection};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use tracing::{debug, instrument};
+
+/// The tables of the database. Every item of every model is a register, with
+/// the unit it's measured in and the bounds of its range in their own tables
+/// so they can be queried across models, eg
+///
+/// ```sql
+/// SELECT models.name, units.name, registers.scale FROM registers
+/// JOIN models ON models.id = registers.model_id
+/// LEFT JOIN units ON units.id = registers.unit_id
+/// WHERE registers.data_name = 'Current Limit';
+/// ```
+static SCHEMA: &str = "
+CREATE TABLE models (
+    id INTEGER PRIMARY KEY,
+    slug TEXT NOT NULL UNIQUE,
+    name TEXT NOT NULL,
+    series TEXT NOT NULL,
+    url TEXT NOT NULL,
+    model_number INTEGER,
+    protocols TEXT NOT NULL
+);
+
+CREATE TABLE units (
+    id INTEGER PRIMARY KEY,
+    name TEXT NOT NULL UNIQUE
+);
+
+CREATE TABLE registers (
+    id INTEGER PRIMARY KEY,
+    model_id INTEGER NOT NULL REFERENCES models(id),
+    address INTEGER NOT NULL,
+    size INTEGER NOT NULL,
+    data_name TEXT,
+    description TEXT,
+    access TEXT NOT NULL,
+    value_type TEXT NOT NULL,
+    -- The initial value if it's a plain number, and as written otherwise
+    initial_value INTEGER,
+    initial_value_text TEXT,
+    unit_id INTEGER REFERENCES units(id),
+    -- How much of the unit a step of the raw value is
+    scale REAL,
+    units_text TEXT,
+    min_firmware INTEGER,
+    joint INTEGER,
+    modbus_address INTEGER
+);
+
+CREATE INDEX registers_by_name ON registers(data_name);
+
+-- A bound is either a number or the value of another item, by name (and
+-- address, if the item was found in the same control table)
+CREATE TABLE ranges (
+    register_id INTEGER NOT NULL REFERENCES registers(id),
+    bound TEXT NOT NULL CHECK (bound IN ('min', 'max')),
+    value INTEGER,
+    item TEXT,
+    item_address INTEGER,
+    negative INTEGER NOT NULL,
+    PRIMARY KEY (register_id, bound)
+);
+";
+
+/// Write every model's control table to a new SQLite database at `path`,
+/// replacing any database already there. The database is built beside it
+/// first, so a failure never leaves a partial one behind.
+#[instrument(skip(actuators), fields(models = actuators.len()))]
+pub fn write_sqlite(path: &Path, actuators: &[Actuator]) -> Result<()> {
+    let file_name = path
+        .file_name()
+        .with_context(|| format!("{} isn't a file", path.display()))?;
+    let temp_path = path.with_file_name(format!(
+        ".{}.{}.tmp",
+        file_name.to_string_lossy(),
+        std::process::id()
+    ));
+    let _ = fs::remove_file(&temp_path);
+
+    let written = fill_database(&temp_path, actuators).and_then(|_| {
+        fs::rename(&temp_path, path)?;
+        Ok(())
+    });
+    if written.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+
+    written.with_context(|| format!("Failed to write {}", path.display()))
+}
+
+fn fill_database(path: &Path, actuators: &[Actuator]) -> Result<()> {
+    let mut connection = Connection::open(path)?;
+    connection.execute_batch(SCHEMA)?;
+    let transaction = connection.transaction()?;
+
+    let mut units: BTreeMap<String, i64> = BTreeMap::new();
+    for dxl in actuators {
+        transaction.execute(
+            "INSERT INTO models (slug, name, series, url, model_number, protocols)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                dxl.raw_name,
+                dxl.name,
+                dxl.series,
+                dxl.url,
+                dxl.model_number(),
+                dxl.protocols.join(", ")
+            ],
+        )?;
+        let model_id = transaction.last_insert_rowid();
+
+        for row in &dxl.data {
+            let parsed_units = row.units.as_deref().and_then(Units::parse);
+            let unit_id = match parsed_units {
+                Some(parsed) => {
+                    let name = format!("{:?}", parsed.unit);
+                    match units.get(&name) {
+                        Some(id) => Some(*id),
+                        None => {
+                            transaction.execute("INSERT INTO units (name) VALUES (?1)", [&name])?;
+                            let id = transaction.last_insert_rowid();
+                            units.insert(name, id);
+                            Some(id)
+                        }
+                    }
+                }
+                None => None,
+            };
+            let (initial_value, initial_value_text) = match &row.initial_value {
+                InitialValue::Value(RangeValue::Integer(value)) => (Some(*value), None),
+                InitialValue::None => (None, None),
+                other => (None, Some(other.to_string())),
+            };
+
+            transaction.execute(
+                "INSERT INTO registers (model_id, address, size, data_name, description, access,
+                     value_type, initial_value, initial_value_text, unit_id, scale, units_text,
+                     min_firmware, joint, modbus_address)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15)",
+                params![
+                    model_id,
+                    row.address,
+                    row.size,
+                    row.data_name.as_deref().map(str::trim),
+                    row.description.as_deref().map(str::trim),
+                    row.access.as_str(),
+                    row.value_type.rust_type(),
+                    initial_value,
+                    initial_value_text,
+                    unit_id,
+                    parsed_units.map(|parsed| parsed.scale),
+                    row.units,
+                    row.min_firmware,
+                    row.joint,
+                    row.modbus_address,
+                ],
+            )?;
+            let register_id = transaction.last_insert_rowid();
+
+            for (bound, value) in row
+                .range
+                .iter()
+                .flat_map(|(min, max)| [("min", min), ("max", max)])
+            {
+                let (number, item, item_address, negative) = match value {
+                    RangeValue::Integer(number) => (Some(*number), None, None, false),
+                    RangeValue::Address {
+                        name,
+                        negative,
+                        reference,
+                    } => match reference {
+                        Some(reference) => (
+                            None,
+                            Some(reference.data_name.as_str()),
+                            Some(reference.address),
+                            *negative,
+                        ),
+                        None => (None, Some(name.as_str()), None, *negative),
+                    },
+                };
+                transaction.execute(
+                    "INSERT INTO ranges (register_id, bound, value, item, item_address, negative)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                    params![register_id, bound, number, item, item_address, negative],
+                )?;
+            }
+        }
+        debug!(model = %dxl.name, registers = dxl.data.len(), "Wrote model to the database");
+    }
+
+    transaction.commit()?;
+
+    Ok(())
+}
@@ -0,0 +1,119 @@
+use crate::create_lib::{collect_models, ItemFields, NamingStrategy};
+use crate::serialize::ControlTableData;
+use crate::{write_atomic, Actuator};
+use anyhow::Result;
+use std::path::Path;
+use tracing::instrument;
+
+/// Where `--typescript` writes the definitions if it isn't given a file
+pub static DEFAULT_TYPESCRIPT_PATH: &str = "dxlControlTables.ts";
+
+static TYPESCRIPT_PREAMBLE: &str = "// Dynamixel control tables scraped from the Robotis E-Manual
+// (https://emanual.robotis.com) by dynamixel-scraper. Regenerate this file
+// rather than editing it.
+//
+// Every supported model is a constant holding its control table, keyed by
+// the names of its items, eg XM430W210.GoalPosition.address, and is also
+// found by name in controlTables. Items can be looked up at run time with
+// data().
+
+/** The levels of permission a user is granted in terms of an item */
+export type Access = \"Read\" | \"ReadWrite\";
+
+/**
+ * The primitive type an item's bytes should be decoded as, where Bytes is an
+ * item whose size doesn't match a primitive
+ */
+export type ValueType = \"U8\" | \"U16\" | \"U32\" | \"I8\" | \"I16\" | \"I32\" | \"Bytes\";
+
+/** Where and how an item is stored in the control table of a model */
+export interface ControlTableData {
+  readonly address: number;
+  readonly size: number;
+  readonly access: Access;
+  readonly valueType: ValueType;
+  /** The initial value, if the E-Manual gives a single number */
+  readonly initialValue: number | null;
+  /**
+   * The bounds of the item's range, if they're numbers rather than the
+   * values of other items
+   */
+  readonly min: number | null;
+  readonly max: number | null;
+}
+";
+
+/// Write TypeScript definitions of every model's control table to `path`
+#[instrument(skip(actuators), fields(models = actuators.len()))]
+pub fn write_typescript(path: &Path, actuators: &[Actuator], naming: NamingStrategy) -> Result<()> {
+    write_atomic(path, create_typescript(actuators, naming)?)
+}
+
+/// Generate TypeScript definitions with the same models and items as the
+/// library, where each model is a constant object of its items, typed down
+/// to the values of their fields
+pub fn create_typescript(actuators: &[Actuator], naming: NamingStrategy) -> Result<String> {
+    let models = collect_models(actuators, naming)?;
+
+    let mut definitions = TYPESCRIPT_PREAMBLE.to_string();
+
+    definitions.push_str(
+        "\n/** An item found in the control table of any model */\nexport type DataName =\n",
+    );
+    for name in &models.data_names {
+        definitions.push_str(&format!("  | \"{}\"\n", name));
+    }
+    definitions.push_str(";\n");
+
+    definitions.push_str(
+        "\n/** The control table of a model, with the items it has */\nexport type ControlTable = Readonly<Partial<Record<DataName, ControlTableData>>>;\n",
+    );
+
+    for (model, items) in &models.items {
+        let (name, url) = models.docs[model];
+        definitions.push_str(&format!(
+            "\n/** {} ({}) */\nexport const {} = {{\n",
+            name, url, model
+        ));
+        for (name, data) in items {
+            definitions.push_str(&format!("  {}: {},\n", name, object(data)));
+        }
+        definitions.push_str("} as const;\n");
+    }
+
+    definitions
+        .push_str("\n/** A Dynamixel model with a known control table */\nexport type Model =\n");
+    for model in models.items.keys() {
+        definitions.push_str(&format!("  | \"{}\"\n", model));
+    }
+    definitions.push_str(";\n");
+
+    definitions.push_str("\n/** The control table of every model */\nexport const controlTables: Readonly<Record<Model, ControlTable>> = {\n");
+    for model in models.items.keys() {
+        definitions.push_str(&format!("  {},\n", model));
+    }
+    definitions.push_str("};\n");
+
+    definitions.push_str(
+        "\n/** Look up where and how `name` is stored in the control table of `model` */\nexport function data(model: Model, name: DataName): ControlTableData | undefined {\n  return controlTables[model][name];\n}\n",
+    );
+
+    Ok(definitions)
+}
+
+/// An item's `ControlTableData` as an object literal
+fn object(data: &ControlTableData) -> String {
+    let fields = ItemFields::new(data);
+    let number = |value: Option<i32>| value.map_or("null".to_string(), |i| i.to_string());
+
+    format!(
+        "{{ address: {}, size: {}, access: \"{}\", valueType: \"{}\", initialValue: {}, min: {}, max: {} }}",
+        data.address,
+        data.size,
+        fields.access,
+        fields.value_type,
+        number(fields.initial_value),
+        number(fields.min),
+        number(fields.max)
+    )
+}
@@ -0,0 +1,218 @@
+use crate::create_lib::LibTarget;
+use anyhow::{bail, Context, Result};
+use regex::Regex;
+use serde::Deserialize;
+use std::ffi::OsString;
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+use tracing::{debug, info, instrument};
+
+/// An error the compiler found in the generated library, along with the
+/// models and item the offending code was generated for, if it could be told
+#[derive(Clone, Debug, PartialEq)]
+pub struct CompilerError {
+    /// The compiler's diagnostic, as it would print it
+    pub rendered: String,
+    /// The file and line of the error, relative to the crate
+    pub location: Option<(String, usize)>,
+    pub models: Vec<String>,
+    pub data_name: Option<String>,
+}
+
+impl fmt::Display for CompilerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.data_name, self.models.is_empty()) {
+            (Some(data_name), false) => writeln!(
+                f,
+                "Generated for {} of {}:",
+                data_name,
+                self.models.join(", ")
+            )?,
+            (None, false) => writeln!(f, "Generated for {}:", self.models.join(", "))?,
+            (Some(data_name), true) => writeln!(f, "Generated for {}:", data_name)?,
+            (None, true) => (),
+        }
+        write!(f, "{}", self.rendered.trim_end())
+    }
+}
+
+/// The parts of cargo's JSON messages needed to find errors
+#[derive(Deserialize)]
+struct CargoMessage {
+    reason: String,
+    message: Option<Diagnostic>,
+}
+
+#[derive(Deserialize)]
+struct Diagnostic {
+    level: String,
+    rendered: Option<String>,
+    spans: Vec<Span>,
+}
+
+#[derive(Deserialize)]
+struct Span {
+    file_name: String,
+    line_start: usize,
+    is_primary: bool,
+}
+
+/// The cargo running the scraper, if it's run by cargo, otherwise the one on
+/// the path
+fn cargo() -> OsString {
+    std::env::var_os("CARGO").unwrap_or_else(|| "cargo".into())
+}
+
+/// Where the library is built, outside the crate so that verifying doesn't
+/// leave a target directory in the output, and shared between runs so that
+/// the dependencies are only built once
+pub fn verify_target_dir() -> PathBuf {
+    std::env::temp_dir().join("dynamixel-scraper-verify")
+}
+
+/// Check that the generated library compiles with every feature enabled,
+/// and if `run_tests` is set, that its tests pass. Compiler errors are
+/// reported with the models and item the offending code was generated for.
+#[instrument(skip_all, fields(path = %target.path.display()))]
+pub fn verify_lib(target: &LibTarget, run_tests: bool) -> Result<()> {
+    let manifest = target.manifest_path();
+    let output = Command::new(cargo())
+        .args([
+            "check",
+            "--all-features",
+            "--all-targets",
+            "--message-format=json",
+        ])
+        .arg("--manifest-path")
+        .arg(&manifest)
+        .env("CARGO_TARGET_DIR", verify_target_dir())
+        .output()
+        .context("Failed to run cargo check")?;
+
+    let errors = compiler_errors(target, &String::from_utf8_lossy(&output.stdout));
+    if !errors.is_empty() {
+        let errors: Vec<String> = errors.iter().map(|error| error.to_string()).collect();
+        bail!(
+            "The generated library at {} doesn't compile:\n\n{}",
+            target.path.display(),
+            errors.join("\n\n")
+        );
+    }
+    if !output.status.success() {
+        bail!(
+            "cargo check failed on the generated library at {}:\n{}",
+            target.path.display(),
+            String::from_utf8_lossy(&output.stderr).trim_end()
+        );
+    }
+    info!("The generated library compiles");
+
+    if run_tests {
+        let output = Command::new(cargo())
+            .args(["test", "--all-features"])
+            .arg("--manifest-path")
+            .arg(&manifest)
+            .env("CARGO_TARGET_DIR", verify_target_dir())
+            .output()
+            .context("Failed to run cargo test")?;
+        if !output.status.success() {
+            bail!(
+                "The tests of the generated library at {} failed:\n{}{}",
+                target.path.display(),
+                String::from_utf8_lossy(&output.stdout),
+                String::from_utf8_lossy(&output.stderr).trim_end()
+            );
+        }
+        info!("The generated library's tests pass");
+    }
+
+    Ok(())
+}
+
+/// Find the errors in the JSON messages of `cargo check`, and what the code
+/// at each was generated for
+pub fn compiler_errors(target: &LibTarget, messages: &str) -> Vec<CompilerError> {
+    let mut errors = vec![];
+    for line in messages.lines() {
+        let diagnostic = match serde_json::from_str::<CargoMessage>(line) {
+            Ok(CargoMessage {
+                reason,
+                message: Some(diagnostic),
+            }) if reason == "compiler-message" && diagnostic.level == "error" => diagnostic,
+            _ => continue,
+        };
+
+        let location = diagnostic
+            .spans
+            .iter()
+            .find(|span| span.is_primary)
+            .map(|span| (span.file_name.clone(), span.line_start));
+        let (models, data_name) = match &location {
+            Some((file, line)) => match fs::read_to_string(target.path.join(file)) {
+                Ok(source) => generated_for(&source, *line),
+                Err(e) => {
+                    debug!(%file, %e, "Couldn't read the file with the error");
+                    (vec![], None)
+                }
+            },
+            None => (vec![], None),
+        };
+
+        errors.push(CompilerError {
+            rendered: diagnostic.rendered.unwrap_or_default(),
+            location,
+            models,
+            data_name,
+        });
+    }
+
+    errors
+}
+
+/// Find the models and item the code at `line` (counting from 1) of the
+/// generated source was generated for, from the nearest item arm, control
+/// table or register list above it. Control tables are shared by every model
+/// with the same table, so name all of them.
+pub fn generated_for(source: &str, line: usize) -> (Vec<String>, Option<String>) {
+    lazy_static! {
+        static ref DATA_NAME_RE: Regex = Regex::new(r"^\s*DataName::(\w+)\s*=>").unwrap();
+        static ref TABLE_RE: Regex = Regex::new(r"fn (table_[0-9a-f]+)\s*\(").unwrap();
+        static ref REGISTERS_RE: Regex = Regex::new(r"^\s*(\w+)\s*\{").unwrap();
+        static ref ITEM_RE: Regex =
+            Regex::new(r"^(?:pub |const |fn |impl|enum |struct |mod |static |macro_rules!)")
+                .unwrap();
+    }
+
+    let lines: Vec<&str> = source.lines().collect();
+    let mut data_name = None;
+    for idx in (0..line.min(lines.len())).rev() {
+        let text = lines[idx];
+        if let Some(captures) = DATA_NAME_RE.captures(text) {
+            data_name.get_or_insert_with(|| captures[1].to_string());
+        } else if let Some(captures) = TABLE_RE.captures(text) {
+            let call = format!("=> {}(", &captures[1]);
+            let models = lines
+                .iter()
+                .filter(|line| line.contains(&call))
+                .filter_map(|line| {
+                    let model = line.trim_start().strip_prefix("Model::")?;
+                    Some(model.split_whitespace().next()?.to_string())
+                })
+                .collect();
+            return (models, data_name);
+        } else if text.trim_start().starts_with("impl_registers!(") {
+            let model = lines
+                .get(idx + 1)
+                .and_then(|next| REGISTERS_RE.captures(next))
+                .map(|captures| captures[1].to_string());
+            return (model.into_iter().collect(), None);
+        } else if ITEM_RE.is_match(text) {
+            // Any other item at the top level isn't generated for a model
+            break;
+        }
+    }
+
+    (vec![], data_name)
+}
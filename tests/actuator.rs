@@ -0,0 +1,90 @@
+//! Tests for parsing model pages into actuators with different options.
+
+mod common;
+
+use common::fixture_page;
+use dynamixel_scraper::{Actuator, ActuatorId, ParseOptions, TableDetection};
+use std::collections::BTreeMap;
+
+#[test]
+fn identifies_models_by_url() {
+    let id = ActuatorId::from_url(
+        "https://emanual.robotis.com/docs/en/dxl/x/xm430-w210/".to_string(),
+        "XM430-W210".to_string(),
+    );
+
+    assert_eq!(id.series, "x");
+    assert_eq!(id.raw_name, "xm430-w210");
+    assert_eq!(id.name, "XM430-W210");
+}
+
+#[test]
+fn fixed_detection_only_reads_the_expected_tables() {
+    // The PRO series puts the whole control table in a single table, which
+    // the overrides in TABLE_INDEXES know about but these ones don't
+    let h54 = fixture_page("h54-200-s500-r");
+    let id = ActuatorId::from_url(
+        "https://emanual.robotis.com/docs/en/dxl/pro/h54-200-s500-r/".to_string(),
+        "H54-200-S500-R".to_string(),
+    );
+    let mut options = ParseOptions {
+        table_detection: TableDetection::Fixed,
+        table_indexes: BTreeMap::from([("h54-200-s500-r".to_string(), (1, 2))]),
+        ..ParseOptions::default()
+    };
+
+    assert!(Actuator::from_html(id.clone(), &h54, &options).is_err());
+
+    options.table_detection = TableDetection::Search;
+    let actuator = Actuator::from_html(id, &h54, &options).unwrap();
+    assert!(!actuator.data().is_empty());
+}
+
+#[test]
+fn strict_parsing_fails_on_warnings() {
+    let headings = "<tr><th>Address</th><th>Size(Byte)</th><th>Data Name</th><th>Access</th></tr>";
+    let page = format!(
+        "<table><tr><th>Item</th></tr></table>\
+         <table>{headings}<tr><td>0</td><td>2</td><td>Model Number</td><td>R</td></tr></table>\
+         <table>{headings}<tr><td>65</td><td>1</td><td>LED</td><td>W</td></tr></table>",
+        headings = headings,
+    );
+    let id = ActuatorId::from_url(
+        "https://emanual.robotis.com/docs/en/dxl/x/xm430-w210/".to_string(),
+        "XM430-W210".to_string(),
+    );
+
+    let lenient = Actuator::from_html(id.clone(), &page, &ParseOptions::default()).unwrap();
+    assert_eq!(lenient.warnings().len(), 1);
+
+    let strict = ParseOptions {
+        strict: true,
+        ..ParseOptions::default()
+    };
+    assert!(Actuator::from_html(id, &page, &strict).is_err());
+}
+
+#[test]
+fn keeps_the_text_of_cells_with_raw() {
+    let xm430 = fixture_page("xm430-w210");
+    let id = ActuatorId::from_url(
+        "https://emanual.robotis.com/docs/en/dxl/x/xm430-w210/".to_string(),
+        "XM430-W210".to_string(),
+    );
+
+    let actuator = Actuator::from_html(id.clone(), &xm430, &ParseOptions::default()).unwrap();
+    assert!(actuator.data().iter().all(|item| item.raw.is_none()));
+
+    let options = ParseOptions {
+        with_raw: true,
+        ..ParseOptions::default()
+    };
+    let actuator = Actuator::from_html(id, &xm430, &options).unwrap();
+    let model_number = &actuator.data()[0];
+    let raw = &model_number.raw.as_ref().unwrap().0;
+    assert_eq!(raw["Address"], "0");
+    assert_eq!(
+        raw.get("Data Name").map(String::as_str),
+        model_number.data_name.as_deref()
+    );
+}
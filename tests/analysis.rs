@@ -0,0 +1,164 @@
+//! Tests for comparing control tables across models and checking their
+//! layout.
+
+mod common;
+
+use common::{fixture_page, fixture_path};
+use dynamixel_scraper::analysis::{
+    compare, heading_layouts, layout, layout_issues, memory_map, series_object, Area, ModelTable,
+    Span,
+};
+use dynamixel_scraper::download::table_headings;
+use dynamixel_scraper::html_report::html_report;
+use dynamixel_scraper::parse_fixture_with_warnings;
+use dynamixel_scraper::report::{ErrorCategory, ModelReport, ModelStatus};
+
+fn fixture(name: &str) -> ModelTable {
+    let (data, warnings) =
+        parse_fixture_with_warnings(fixture_path(&format!("{}.html", name))).unwrap();
+    ModelTable {
+        name: name.to_string(),
+        slug: name.to_string(),
+        data,
+        warnings,
+    }
+}
+
+#[test]
+fn compares_addresses_across_models() {
+    let comparison = compare(&[fixture("ax-12a"), fixture("xm430-w210")]);
+    let item = |name: &str| {
+        comparison
+            .items
+            .iter()
+            .find(|item| item.data_name == name)
+            .unwrap()
+    };
+
+    assert_eq!(comparison.models, ["ax-12a", "xm430-w210"]);
+    assert!(item("Model Number").is_common());
+    assert!(!item("Model Number").differs());
+    assert_eq!(item("Torque Enable").addresses, [Some(24), Some(64)]);
+    assert!(item("Torque Enable").differs());
+    assert_eq!(item("Operating Mode").addresses, [None, Some(11)]);
+    assert!(!item("Operating Mode").is_common());
+}
+
+#[test]
+fn aggregates_the_registers_of_a_series() {
+    let series = series_object("x", &[fixture("xm430-w210"), fixture("ax-12a")]);
+    let register = |name: &str| {
+        series
+            .union
+            .iter()
+            .find(|register| register.data_name == name)
+            .unwrap()
+    };
+
+    assert_eq!(series.models, ["xm430-w210", "ax-12a"]);
+    assert!(series.intersection.contains(&"Model Number".to_string()));
+    assert!(!series.intersection.contains(&"Operating Mode".to_string()));
+    assert!(register("Model Number").consistent);
+    assert!(!register("Torque Enable").consistent);
+    assert_eq!(register("Torque Enable").models["ax-12a"], (24, 1));
+    // Registers only one model has are consistent with themselves
+    assert_eq!(register("Operating Mode").models.len(), 1);
+    assert!(register("Operating Mode").consistent);
+    assert_eq!(series.union[0].data_name, "Model Number");
+}
+
+#[test]
+fn lays_out_eeprom_and_ram() {
+    let table = fixture("xm430-w210");
+    let areas = layout(&table.data);
+
+    assert_eq!(areas.len(), 2);
+    assert_eq!(areas[0].area, Area::Eeprom);
+    assert_eq!(
+        areas[1].spans[0],
+        Span::Item {
+            address: 64,
+            size: 1,
+            data_name: "Torque Enable".to_string(),
+        }
+    );
+    assert!(layout_issues(&table.data)
+        .iter()
+        .all(|(_, span)| matches!(span, Span::Gap { .. })));
+    assert!(memory_map(&table.name, &table.data).contains("xm430-w210 RAM\n   64  #  "));
+}
+
+#[test]
+fn finds_overlapping_items() {
+    let mut table = fixture("xm430-w210");
+    // As if the size of Model Information had been read from the wrong column
+    table.data[1].size = 8;
+
+    let overlaps: Vec<Span> = layout_issues(&table.data)
+        .into_iter()
+        .map(|(_, span)| span)
+        .filter(|span| matches!(span, Span::Overlap { .. }))
+        .collect();
+
+    assert_eq!(overlaps.len(), 3, "{:?}", overlaps);
+    assert!(overlaps[0]
+        .to_string()
+        .contains("Firmware Version overlaps Model Information"));
+}
+
+#[test]
+fn groups_models_by_their_table_headings() {
+    let pages: Vec<(String, _)> = ["ax-12a", "xm430-w210", "xh430-w350"]
+        .iter()
+        .map(|name| {
+            let page = fixture_page(name);
+            (name.to_string(), table_headings(&page).unwrap())
+        })
+        .collect();
+
+    let layouts = heading_layouts(&pages, false);
+    assert!(layouts.iter().all(|layout| layout.control_table));
+    let x_series = layouts
+        .iter()
+        .find(|layout| layout.models.contains(&"xm430-w210".to_string()))
+        .unwrap();
+    assert_eq!(x_series.headings[1], "Size(Byte)");
+    assert_eq!(x_series.normalized[1], "Size(byte)");
+    assert!(x_series.unrecognized().is_empty(), "{:?}", x_series);
+    assert!(!layouts[0].models.is_empty());
+
+    // Other tables, such as the specifications, are only listed if asked for
+    let all = heading_layouts(&pages, true);
+    assert!(all.len() > layouts.len());
+    assert!(all
+        .iter()
+        .any(|layout| layout.headings == ["Item", "Specifications"]));
+}
+
+#[test]
+fn reports_every_finding_as_html() {
+    let failed = ModelReport {
+        name: "XM540-W270 <new>".to_string(),
+        url: "https://emanual.robotis.com/docs/en/dxl/x/xm540-w270/".to_string(),
+        status: ModelStatus::Failed {
+            category: ErrorCategory::Parse,
+            reason: "No control table found on page".to_string(),
+        },
+        registers: 0,
+        bytes: 0,
+        tables: None,
+    };
+    let html = html_report(&[fixture("ax-12a"), fixture("xm430-w210")], &[failed]);
+
+    assert!(html.starts_with("<!DOCTYPE html>"), "{}", html);
+    assert!(html.contains("<h2>Column coverage</h2>"), "{}", html);
+    assert!(html.contains("<td>XM540-W270 &lt;new&gt;</td>"), "{}", html);
+    assert!(html.contains("<td>parse failure</td>"), "{}", html);
+    // Torque Enable is at 24 on the AX-12A and 64 on the XM430-W210
+    assert!(
+        html.contains("<td>Torque Enable</td><td class=\"number differs\">24</td>"),
+        "{}",
+        html
+    );
+    assert!(html.contains("<script>"), "{}", html);
+}
@@ -0,0 +1,98 @@
+//! Tests for refreshing the pages saved by earlier runs once they go stale.
+
+mod common;
+
+use common::start_server;
+use dynamixel_scraper::cache::{PageCache, Refreshed};
+use dynamixel_scraper::fetch::{
+    client_builder, HttpClient, Revalidation, Validators, DEFAULT_MAX_IN_FLIGHT,
+};
+use hyper::{Body, Request, Response, StatusCode};
+use std::convert::Infallible;
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+/// The version of the page being served, which is its ETag
+static VERSION: AtomicUsize = AtomicUsize::new(1);
+/// How many times the page has been sent in full
+static DOWNLOADS: AtomicUsize = AtomicUsize::new(0);
+
+/// Serve the current version of a page at every path, or an empty 304 Not
+/// Modified to a request that already has it
+async fn serve(request: Request<Body>) -> Result<Response<Body>, Infallible> {
+    let version = VERSION.load(Ordering::SeqCst);
+    let etag = format!("\"v{}\"", version);
+    let unchanged = request
+        .headers()
+        .get("if-none-match")
+        .is_some_and(|tag| tag == etag.as_str());
+
+    Ok(match unchanged {
+        true => Response::builder()
+            .status(StatusCode::NOT_MODIFIED)
+            .body(Body::empty())
+            .unwrap(),
+        false => {
+            DOWNLOADS.fetch_add(1, Ordering::SeqCst);
+            Response::builder()
+                .header("etag", etag)
+                .body(Body::from(format!("<p>Version {}</p>", version)))
+                .unwrap()
+        }
+    })
+}
+
+#[tokio::test]
+async fn only_stale_pages_are_checked_for_changes() {
+    let address = start_server(serve);
+    let url = format!("http://{}/docs/en/dxl/x/xm430-w210/", address);
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("cache");
+    let _ = fs::remove_dir_all(&dir);
+    let client = HttpClient::new(client_builder().build().unwrap(), DEFAULT_MAX_IN_FLIGHT);
+
+    let cache = PageCache::new(&dir, Duration::from_secs(60 * 60));
+    match client
+        .revalidate(&url, &Validators::default())
+        .await
+        .unwrap()
+    {
+        Revalidation::Changed { text, validators } => {
+            assert_eq!(validators.etag.as_deref(), Some("\"v1\""));
+            cache.save(&url, &text, validators).unwrap();
+        }
+        Revalidation::Unchanged => panic!("Nothing was saved to be unchanged from"),
+    }
+    assert_eq!(
+        cache.refresh(&client, &url).await.unwrap(),
+        Refreshed::Fresh
+    );
+
+    // Once stale, the page is checked without being downloaded again
+    let stale = PageCache::new(&dir, Duration::ZERO);
+    assert_eq!(
+        stale.refresh(&client, &url).await.unwrap(),
+        Refreshed::Unchanged
+    );
+    assert_eq!(DOWNLOADS.load(Ordering::SeqCst), 1);
+
+    VERSION.store(2, Ordering::SeqCst);
+    assert_eq!(
+        stale.refresh(&client, &url).await.unwrap(),
+        Refreshed::Changed
+    );
+    assert_eq!(
+        fs::read_to_string(cache.page_path(&url)).unwrap(),
+        "<p>Version 2</p>"
+    );
+    let entry = cache.entry(&url).unwrap().unwrap();
+    assert_eq!(entry.validators.etag.as_deref(), Some("\"v2\""));
+    assert!(!cache.is_stale(&entry));
+
+    let unsaved = format!("http://{}/docs/en/dxl/x/xl430-w250/", address);
+    assert_eq!(
+        stale.refresh(&client, &unsaved).await.unwrap(),
+        Refreshed::Missing
+    );
+}
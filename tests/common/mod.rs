@@ -0,0 +1,53 @@
+//! Fixtures shared by the integration tests. Not every test uses all of
+//! them.
+#![allow(dead_code)]
+
+use dynamixel_scraper::Actuator;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Request, Response, Server};
+use std::convert::Infallible;
+use std::fs;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+
+/// The path of a file under fixtures/, eg "xm430-w210.ron"
+pub fn fixture_path(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("fixtures")
+        .join(name)
+}
+
+/// The saved page of a model, by its URL slug (eg "xm430-w210")
+pub fn fixture_page(name: &str) -> String {
+    fs::read_to_string(fixture_path(&format!("{}.html", name))).unwrap()
+}
+
+/// Parse the fixtures of the given pages, by series and slug (eg
+/// "x/xm430-w210"), as if they'd been scraped from the E-Manual
+pub fn servos(pages: &[&str]) -> Vec<Actuator> {
+    pages
+        .iter()
+        .map(|page| {
+            let name = page.split('/').nth(1).unwrap();
+            let url = format!("https://emanual.robotis.com/docs/en/dxl/{}/", page);
+            Actuator::new(url, name.to_uppercase(), fixture_page(name)).unwrap()
+        })
+        .collect()
+}
+
+/// Serve every request with `serve` on a local port in the background,
+/// returning the address it's listening on
+pub fn start_server<F, R>(serve: F) -> SocketAddr
+where
+    F: Fn(Request<Body>) -> R + Copy + Send + 'static,
+    R: Future<Output = Result<Response<Body>, Infallible>> + Send + 'static,
+{
+    let make_service =
+        make_service_fn(move |_| async move { Ok::<_, Infallible>(service_fn(serve)) });
+    let server = Server::bind(&([127, 0, 0, 1], 0).into()).serve(make_service);
+    let address = server.local_addr();
+    tokio::spawn(server);
+
+    address
+}
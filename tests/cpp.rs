@@ -0,0 +1,48 @@
+//! Tests for generating the C++ header from the fixtures.
+
+mod common;
+
+use common::servos;
+use dynamixel_scraper::cpp::create_header;
+use dynamixel_scraper::create_lib::NamingStrategy;
+
+#[test]
+fn generates_constexpr_tables_for_each_model() {
+    let servos = servos(&["ax/ax-12a", "x/xm430-w210"]);
+    let header = create_header(&servos, NamingStrategy::Compact).unwrap();
+
+    assert!(header.contains("#pragma once"), "{}", header);
+    assert!(header.contains("enum class Model {"), "{}", header);
+    assert!(header.contains("    XM430W210,\n"), "{}", header);
+    assert!(
+        header.contains("template <>\nstruct ControlTable<Model::AX12A> {"),
+        "{}",
+        header
+    );
+    assert!(
+        header.contains(
+            "    static constexpr ControlTableData ModelNumber{0, 2, Access::Read, ValueType::U16, 12, std::nullopt, std::nullopt};"
+        ),
+        "{}",
+        header
+    );
+    assert!(
+        header.contains(
+            "        case DataName::GoalPosition:\n            return ControlTable<Model::XM430W210>::GoalPosition;"
+        ),
+        "{}",
+        header
+    );
+}
+
+#[test]
+fn names_models_as_the_library_does() {
+    let servos = servos(&["x/xm430-w210"]);
+    let header = create_header(&servos, NamingStrategy::Underscored).unwrap();
+
+    assert!(
+        header.contains("ControlTable<Model::XM430_W210>"),
+        "{}",
+        header
+    );
+}
@@ -0,0 +1,368 @@
+//! Tests for generating the control table library from the fixtures.
+
+mod common;
+
+use common::{fixture_page, servos};
+use dynamixel_scraper::create_lib::{
+    create_lib, LibTarget, NamingStrategy, PackageMetadata, RustVersion,
+};
+use dynamixel_scraper::serialize::{Unit, Units};
+use dynamixel_scraper::Actuator;
+use std::fs;
+use std::path::Path;
+
+/// Generate a library into a fresh directory under the target directory
+fn generate(servos: &[Actuator], dir: &str) -> LibTarget {
+    let target = LibTarget {
+        path: Path::new(env!("CARGO_TARGET_TMPDIR")).join(dir),
+        ..LibTarget::default()
+    };
+    let _ = fs::remove_dir_all(&target.path);
+    create_lib(servos, &target).unwrap();
+
+    target
+}
+
+#[test]
+fn generates_a_formatted_library() {
+    let target = generate(&servos(&["ax/ax-12a", "x/xm430-w210"]), "generated-lib");
+
+    let source = fs::read_to_string(target.source_path()).unwrap();
+    assert!(source.contains("pub enum DataName {"), "{}", source);
+    assert!(source.contains("    XM430W210 = "), "{}", source);
+    assert!(
+        source.contains("pub const ALL_MODELS: &[Model]"),
+        "{}",
+        source
+    );
+    assert!(
+        source.contains("impl core::str::FromStr for DataName"),
+        "{}",
+        source
+    );
+    assert!(source.contains("pub trait XSeriesCommon {"), "{}", source);
+    assert!(source.contains("pub fn validate_write("), "{}", source);
+    assert!(
+        source.contains("pub const fn supported(model: Model) -> &'static [DataName]"),
+        "{}",
+        source
+    );
+    assert!(
+        source.contains("Model::XM430W210 => models::XM430W210::SUPPORTED,"),
+        "{}",
+        source
+    );
+    assert!(source.contains("    OutOfRange {"), "{}", source);
+    assert!(
+        source.contains("pub struct XHardwareError(pub u8);"),
+        "{}",
+        source
+    );
+    assert!(
+        source.contains(
+            "/// Detects that persistent load that exceeds maximum output\n        pub const OVERLOAD_ERROR: u8 = 0x20;"
+        ),
+        "{}",
+        source
+    );
+    assert!(
+        source.contains("pub const OVERLOAD: Self = Self(0x20);"),
+        "{}",
+        source
+    );
+    // The XM430-W210's fixture gives Velocity Limit a default per variant
+    assert!(
+        source.contains("initial_value: InitialValue::Value(RangeValue::Integer(330)),"),
+        "{}",
+        source
+    );
+    assert!(
+        source.contains("initial_value_source: InitialValueSource::Default,"),
+        "{}",
+        source
+    );
+}
+
+#[test]
+fn output_does_not_depend_on_model_order() {
+    let pages = ["ax/ax-12a", "x/xh430-w350", "x/xm430-w210"];
+    let forwards = generate(&servos(&pages), "generated-lib-forwards");
+    let mut reversed = servos(&pages);
+    reversed.reverse();
+    let backwards = generate(&reversed, "generated-lib-backwards");
+
+    for path in ["src/lib.rs", "Cargo.toml"] {
+        assert_eq!(
+            fs::read(forwards.path.join(path)).unwrap(),
+            fs::read(backwards.path.join(path)).unwrap(),
+            "{} differs",
+            path
+        );
+    }
+}
+
+#[test]
+fn names_models_by_strategy() {
+    for (raw_name, compact, underscored) in [
+        ("xm430-w210", "XM430W210", "XM430_W210"),
+        ("2xl430-w250", "Dxl2XL430W250", "Dxl2XL430_W250"),
+        ("h54-200-s500-r", "H54200S500R", "H54_200_S500_R"),
+    ] {
+        assert_eq!(NamingStrategy::Compact.model_name(raw_name), compact);
+        assert_eq!(
+            NamingStrategy::Underscored.model_name(raw_name),
+            underscored
+        );
+    }
+}
+
+#[test]
+fn models_named_the_same_are_an_error() {
+    let mut servos = servos(&["x/xm430-w210"]);
+    let text = fixture_page("xm430-w210");
+    let url = "https://emanual.robotis.com/docs/en/dxl/x/xm430w210/".to_string();
+    servos.push(Actuator::new(url, "XM430W210".to_string(), text).unwrap());
+
+    let target = LibTarget {
+        path: Path::new(env!("CARGO_TARGET_TMPDIR")).join("generated-lib-collision"),
+        ..LibTarget::default()
+    };
+    let error = create_lib(&servos, &target).unwrap_err();
+    assert!(error.to_string().contains("XM430W210"), "{}", error);
+
+    let target = LibTarget {
+        naming: NamingStrategy::Underscored,
+        ..target
+    };
+    create_lib(&servos, &target).unwrap();
+    let source = fs::read_to_string(target.source_path()).unwrap();
+    assert!(source.contains("    XM430_W210 = "), "{}", source);
+}
+
+#[test]
+fn created_crates_are_publishable() {
+    let target = LibTarget {
+        path: Path::new(env!("CARGO_TARGET_TMPDIR")).join("generated-lib-publishable"),
+        name: "my-dxl".to_string(),
+        package: PackageMetadata {
+            version: "1.2.0".to_string(),
+            authors: vec!["Jane Doe <jane@example.com>".to_string()],
+            license: Some("MIT OR Apache-2.0".to_string()),
+            repository: Some("https://github.com/example/my-dxl".to_string()),
+        },
+        ..LibTarget::default()
+    };
+    let _ = fs::remove_dir_all(&target.path);
+    create_lib(&servos(&["ax/ax-12a", "x/xm430-w210"]), &target).unwrap();
+
+    let manifest = fs::read_to_string(target.manifest_path()).unwrap();
+    for key in [
+        "name = \"my-dxl\"",
+        "version = \"1.2.0\"",
+        "readme = \"README.md\"",
+        "authors = [\"Jane Doe <jane@example.com>\"]",
+        "license = \"MIT OR Apache-2.0\"",
+        "repository = \"https://github.com/example/my-dxl\"",
+    ] {
+        assert!(manifest.contains(key), "{}", manifest);
+    }
+    assert!(manifest.contains("description = "), "{}", manifest);
+
+    let readme = fs::read_to_string(target.readme_path()).unwrap();
+    assert!(readme.starts_with("# my-dxl\n"), "{}", readme);
+    assert!(
+        readme.contains(
+            "| XM430-W210 | X | `xm430-w210` | <https://emanual.robotis.com/docs/en/dxl/x/xm430-w210/> |"
+        ),
+        "{}",
+        readme
+    );
+    assert!(readme.contains(env!("CARGO_PKG_VERSION")), "{}", readme);
+
+    // The generated manifest is still recognised, so is replaced next time
+    assert!(!target.is_existing_crate());
+}
+
+#[test]
+fn items_of_each_joint_are_distinct() {
+    let target = generate(&servos(&["x/2xc430-w250"]), "generated-lib-joints");
+
+    let source = fs::read_to_string(target.source_path()).unwrap();
+    for variant in ["GoalPositionJoint1 = ", "GoalPositionJoint2 = ", "ID = "] {
+        assert!(source.contains(variant), "{}", source);
+    }
+}
+
+#[test]
+fn items_named_alike_are_numbered() {
+    let headings = "<tr><th>Address</th><th>Size(Byte)</th><th>Data Name</th><th>Access</th></tr>";
+    let page = format!(
+        "<table><tr><th>Item</th></tr></table>\
+         <table>{headings}<tr><td>0</td><td>2</td><td>Model Number</td><td>R</td></tr></table>\
+         <table>{headings}<tr><td>65</td><td>1</td><td>LED</td><td>RW</td></tr>\
+         <tr><td>66</td><td>1</td><td>LED 1</td><td>RW</td></tr>\
+         <tr><td>152</td><td>2</td><td>External Port Data 1</td><td>RW</td></tr>\
+         <tr><td>154</td><td>2</td><td>External Port Data 2</td><td>RW</td></tr></table>",
+        headings = headings,
+    );
+    let url = "https://emanual.robotis.com/docs/en/dxl/x/xm430-w210/".to_string();
+    let servos = vec![Actuator::new(url, "XM430-W210".to_string(), page).unwrap()];
+    let target = generate(&servos, "generated-lib-numbered");
+
+    let source = fs::read_to_string(target.source_path()).unwrap();
+    for variant in [
+        "    LED = ",
+        "    LED1 = ",
+        "    ExternalPortData1 = ",
+        "    ExternalPortData2 = ",
+    ] {
+        assert!(source.contains(variant), "{}", source);
+    }
+    assert!(!source.contains("    ExternalPortData = "), "{}", source);
+    // The numbers are the items' own, not joints of a single-joint model
+    assert!(!source.contains("Joint"), "{}", source);
+}
+
+#[test]
+fn units_are_typed() {
+    let parse = |text: &str| Units::parse(text).map(|units| (units.unit, units.scale));
+    assert_eq!(parse("0.229 [rev/min]"), Some((Unit::Rpm, 0.229)));
+    assert_eq!(parse("2.69 [mA]"), Some((Unit::Milliamp, 2.69)));
+    assert_eq!(parse("[%]"), Some((Unit::Percent, 1.0)));
+    assert_eq!(parse("1 [furlong]"), Some((Unit::Raw, 1.0)));
+    assert_eq!(parse("-"), None);
+
+    let target = generate(&servos(&["x/xm430-w210"]), "generated-lib-units");
+    let source = fs::read_to_string(target.source_path()).unwrap();
+    assert!(source.contains("pub enum Unit {"), "{}", source);
+    assert!(
+        source.contains("unit: Unit::Rpm,\n                    scale: 0.229,"),
+        "{}",
+        source
+    );
+}
+
+#[test]
+fn differently_spelled_items_share_a_variant() {
+    let mut servos = servos(&["ax/ax-12a", "x/xm430-w210"]);
+    let text = fixture_page("xm430-w210")
+        .replace(">Goal Position<", ">Goal position<")
+        .replace(">Present Current<", ">Present Temp<");
+    let url = "https://emanual.robotis.com/docs/en/dxl/x/xm430-w350/".to_string();
+    servos.push(Actuator::new(url, "XM430-W350".to_string(), text).unwrap());
+
+    let target = generate(&servos, "generated-lib-canonical");
+    let source = fs::read_to_string(target.source_path()).unwrap();
+
+    assert!(source.contains("    GoalPosition = "), "{}", source);
+    assert!(!source.contains("Goalposition"), "{}", source);
+    assert!(source.contains("    PresentTemperature = "), "{}", source);
+    assert!(!source.contains("PresentTemp "), "{}", source);
+}
+
+#[test]
+fn ranges_only_refer_to_items_that_exist() {
+    let text = fixture_page("xm430-w210")
+        .replace("-PWM Limit(36) ~", "-PWM Limt(36) ~")
+        .replace("-Velocity Limit(44) ~", "-Speed Cap(44) ~");
+    let url = "https://emanual.robotis.com/docs/en/dxl/x/xm430-w210/".to_string();
+    let servos = vec![Actuator::new(url, "XM430-W210".to_string(), text).unwrap()];
+
+    let target = generate(&servos, "generated-lib-references");
+    let source = fs::read_to_string(target.source_path()).unwrap();
+
+    assert!(source.contains("name: DataName::PWMLimit,"), "{}", source);
+    assert!(!source.contains("PWMLimt"), "{}", source);
+    assert!(!source.contains("SpeedCap"), "{}", source);
+}
+
+#[test]
+fn adapts_to_the_minimum_rust_version() {
+    assert!("1.30".parse::<RustVersion>().is_err());
+    assert!("2.0".parse::<RustVersion>().is_err());
+    assert_eq!(
+        "1.56.1".parse::<RustVersion>().unwrap().to_string(),
+        "1.56.1"
+    );
+
+    let target = LibTarget {
+        path: Path::new(env!("CARGO_TARGET_TMPDIR")).join("generated-lib-msrv"),
+        msrv: Some("1.45".parse().unwrap()),
+        ..LibTarget::default()
+    };
+    let _ = fs::remove_dir_all(&target.path);
+    create_lib(&servos(&["x/xm430-w210"]), &target).unwrap();
+
+    let source = fs::read_to_string(target.source_path()).unwrap();
+    // Matching in a const fn needs Rust 1.46
+    assert!(source.contains("pub fn data("), "{}", source);
+    assert!(!source.contains("const fn table_"), "{}", source);
+    assert!(source.contains("pub fn si_factor("), "{}", source);
+    assert!(source.contains("pub const fn bits("), "{}", source);
+    assert!(source.contains("#[non_exhaustive]"), "{}", source);
+
+    let manifest = fs::read_to_string(target.manifest_path()).unwrap();
+    assert!(manifest.contains("rust-version = \"1.45\""), "{}", manifest);
+
+    let test = fs::read_to_string(target.msrv_test_path()).unwrap();
+    assert!(
+        test.contains(
+            "assert_eq!(address(Model::XM430W210, DataName::ModelNumber).ok(), Some(0));"
+        ),
+        "{}",
+        test
+    );
+
+    // Without a version, the library keeps using every feature
+    let target = generate(&servos(&["x/xm430-w210"]), "generated-lib-latest");
+    let source = fs::read_to_string(target.source_path()).unwrap();
+    assert!(source.contains("pub const fn data("), "{}", source);
+    assert!(source.contains("pub const fn si_factor("), "{}", source);
+    assert!(!target.msrv_test_path().exists());
+}
+
+#[test]
+fn deprecates_discontinued_models() {
+    let mut servos = servos(&["ax/ax-12a", "x/xm430-w210"]);
+    servos[0].set_discontinued(true);
+    let target = generate(&servos, "generated-lib-discontinued");
+
+    let source = fs::read_to_string(target.source_path()).unwrap();
+    assert!(
+        source.contains(
+            "    #[cfg(feature = \"ax-12a\")]\n    #[deprecated(note = \"discontinued by ROBOTIS\")]\n    AX12A = "
+        ),
+        "{}",
+        source
+    );
+    assert_eq!(source.matches("#[deprecated").count(), 1, "{}", source);
+    assert!(source.contains("#![allow(deprecated)]"), "{}", source);
+}
+
+#[test]
+fn regenerating_leaves_unchanged_files_alone() {
+    let target = generate(
+        &servos(&["ax/ax-12a", "x/xm430-w210"]),
+        "generated-lib-unchanged",
+    );
+    let modified = |path: &Path| fs::metadata(path).unwrap().modified().unwrap();
+    let source = modified(&target.source_path());
+    let manifest = modified(&target.manifest_path());
+    let readme = modified(&target.readme_path());
+    let text = fs::read_to_string(target.source_path()).unwrap();
+
+    create_lib(&servos(&["ax/ax-12a", "x/xm430-w210"]), &target).unwrap();
+    assert_eq!(modified(&target.source_path()), source);
+    assert_eq!(modified(&target.manifest_path()), manifest);
+
+    // A new revision only changes the source. Whether it was rewritten is
+    // told by its contents, as mtimes may be too coarse to have moved on
+    let target = LibTarget {
+        manual_revision: Some("8c1f2e7".to_string()),
+        ..target
+    };
+    create_lib(&servos(&["ax/ax-12a", "x/xm430-w210"]), &target).unwrap();
+    assert_ne!(fs::read_to_string(target.source_path()).unwrap(), text);
+    assert_eq!(modified(&target.manifest_path()), manifest);
+    assert_eq!(modified(&target.readme_path()), readme);
+}
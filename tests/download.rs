@@ -0,0 +1,138 @@
+//! Tests for normalizing the headings of control tables and finding them on
+//! a page.
+
+mod common;
+
+use common::fixture_page;
+use dynamixel_scraper::download::{
+    description_links, diagnose_tables, footnotes, merge_tables, normalize_heading, table_indexes,
+    Link, RowNotes, DEFAULT_TABLE_INDEXES, HEADING_ALIASES,
+};
+use scraper::Html;
+use std::collections::BTreeMap;
+
+#[test]
+fn normalizes_heading_spellings() {
+    for heading in ["Size(Byte)", "Size (Byte)", "Size(byte)", "size"] {
+        assert_eq!(normalize_heading(heading, HEADING_ALIASES), "Size(byte)");
+    }
+    assert_eq!(normalize_heading("Data name", HEADING_ALIASES), "Data Name");
+    assert_eq!(
+        normalize_heading("크기(byte)", HEADING_ALIASES),
+        "Size(byte)"
+    );
+    assert_eq!(normalize_heading("bit", HEADING_ALIASES), "Bit");
+}
+
+#[test]
+fn uses_custom_aliases() {
+    let aliases: &[(&str, &[&str])] = &[("Data Name", &["Register"])];
+
+    assert_eq!(normalize_heading("Register", aliases), "Data Name");
+    assert_eq!(normalize_heading("Name", aliases), "Name");
+}
+
+#[test]
+fn diagnoses_control_tables_at_unexpected_indexes() {
+    let xm430 = Html::parse_document(&fixture_page("xm430-w210"));
+    let h54 = Html::parse_document(&fixture_page("h54-200-s500-r"));
+
+    assert_eq!(diagnose_tables(&xm430, (1, 2)).unwrap(), None);
+
+    // The PRO series puts the whole control table in a single table
+    let tables = diagnose_tables(&h54, (1, 2)).unwrap().unwrap();
+    assert_eq!(tables.len(), 2);
+    assert!(!tables[0].control_table);
+    assert!(tables[1].control_table);
+    assert_eq!(tables[1].headings[1], "Address");
+}
+
+#[test]
+fn table_indexes_can_be_overridden_per_model() {
+    let xm430 = Html::parse_document(&fixture_page("xm430-w210"));
+    let h54 = Html::parse_document(&fixture_page("h54-200-s500-r"));
+
+    let mut overrides = BTreeMap::new();
+    assert_eq!(
+        table_indexes("xm430-w210", &overrides),
+        DEFAULT_TABLE_INDEXES
+    );
+    let h54_indexes = table_indexes("h54-200-s500-r", &overrides);
+    assert_eq!(h54_indexes, (1, 1));
+    assert_eq!(diagnose_tables(&h54, h54_indexes).unwrap(), None);
+
+    // Only the RAM table, as if the EEPROM table weren't a control table
+    overrides.insert("xm430-w210".to_string(), (2, 2));
+    let indexes = table_indexes("xm430-w210", &overrides);
+    assert_eq!(indexes, (2, 2));
+    let table = merge_tables(&xm430, indexes).unwrap();
+    assert_eq!(table[1][0], "64");
+}
+
+#[test]
+fn finds_links_in_descriptions() {
+    let page = Html::parse_document(&fixture_page("ax-12a"));
+    let links = description_links(&page, (1, 2)).unwrap();
+
+    let addresses: Vec<&str> = links.iter().map(|(address, _)| address.as_str()).collect();
+    assert_eq!(addresses, vec!["24 (0X18)", "30 (0X1E)"]);
+    assert_eq!(
+        links[1].1,
+        vec![
+            Link {
+                text: "CW Angle Limit".to_string(),
+                href: "#cw-angle-limit".to_string(),
+            },
+            Link {
+                text: "CCW Angle Limit".to_string(),
+                href: "#ccw-angle-limit8".to_string(),
+            },
+        ]
+    );
+}
+
+#[test]
+fn finds_the_footnotes_rows_refer_to() {
+    let page = Html::parse_document(&fixture_page("xm430-w210"));
+
+    assert_eq!(
+        footnotes(&page, (1, 2)).unwrap(),
+        vec![
+            RowNotes {
+                address: "98".to_string(),
+                markers: vec!["(*)".to_string()],
+                notes: vec![
+                    "The Bus Watchdog is cleared when Torque Enable(64) is set to 0.".to_string()
+                ],
+            },
+            RowNotes {
+                address: "126".to_string(),
+                markers: vec!["1".to_string()],
+                notes: vec![
+                    "Present Current is a scaled estimate on firmware older than v42.".to_string()
+                ],
+            },
+        ]
+    );
+}
+
+#[test]
+fn finds_the_footnotes_of_access_levels() {
+    let headings = "<tr><th>Address</th><th>Size(Byte)</th><th>Data Name</th><th>Access</th></tr>";
+    let page = format!(
+        "<table><tr><th>Item</th></tr></table>\
+         <table>{headings}<tr><td>7</td><td>1</td><td>ID</td><td>RW</td></tr></table>\
+         <table>{headings}<tr><td>64</td><td>1</td><td>Torque Enable</td><td>RW(1)</td></tr></table>\
+         <p>(1) Only writable while the model is stopped.</p>",
+        headings = headings
+    );
+
+    assert_eq!(
+        footnotes(&Html::parse_document(&page), (1, 2)).unwrap(),
+        vec![RowNotes {
+            address: "64".to_string(),
+            markers: vec!["(1)".to_string()],
+            notes: vec!["Only writable while the model is stopped.".to_string()],
+        }]
+    );
+}
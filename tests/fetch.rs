@@ -0,0 +1,357 @@
+//! Tests for choosing which Dynamixels to scrape from the E-Manual's
+//! navigation.
+
+mod common;
+
+use common::fixture_path;
+use dynamixel_scraper::fetch::{
+    build_index, check_output_paths, client_builder, extra_page, ActuatorIndex, Filters,
+    HttpClient, PageUnavailable, Sample, DEFAULT_MAX_IN_FLIGHT,
+};
+use dynamixel_scraper::source::{DirectorySource, SourceSpec};
+use dynamixel_scraper::split_url;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+static BASE_URL: &str = "https://emanual.robotis.com/docs/en";
+static NAVIGATION: &str = r#"
+main:
+  - title: DYNAMIXEL
+    children:
+      - title: "**X Series**"
+        children:
+          - title: XM430-W210
+            url: /dxl/x/xm430-w210/
+          - title: XL430-W250
+            url: /dxl/x/xl430-w250/
+      - title: "PRO Series"
+        children:
+          - title: H54-200-S500-R
+            url: /dxl/pro/h54-200-s500-r/
+      - title: Quick Start
+        children:
+          - title: Wizard
+            url: /software/dynamixel_wizard2/
+"#;
+
+fn names(filters: &Filters) -> Vec<String> {
+    build_index(NAVIGATION, BASE_URL, None, filters)
+        .unwrap()
+        .into_iter()
+        .map(|dxl| dxl.name)
+        .collect()
+}
+
+#[test]
+fn no_filters_selects_every_series_entry() {
+    let indexes = build_index(NAVIGATION, BASE_URL, None, &Filters::default()).unwrap();
+
+    assert_eq!(indexes.len(), 3);
+    assert_eq!(indexes[0].url, format!("{}/dxl/x/xm430-w210/", BASE_URL));
+    assert_eq!(indexes[0].series, "X Series");
+}
+
+#[test]
+fn samples_keep_the_order_of_the_navigation() {
+    let indexes = build_index(NAVIGATION, BASE_URL, None, &Filters::default()).unwrap();
+    let names = |indexes: Vec<ActuatorIndex>| -> Vec<String> {
+        indexes.into_iter().map(|dxl| dxl.name).collect()
+    };
+
+    assert_eq!(
+        names(Sample::First(2).apply(indexes.clone())),
+        vec!["XM430-W210", "XL430-W250"]
+    );
+    assert_eq!(Sample::First(5).apply(indexes.clone()).len(), 3);
+    let all = names(indexes.clone());
+    for _ in 0..10 {
+        let sample = names(Sample::Random(2).apply(indexes.clone()));
+        assert_eq!(sample.len(), 2);
+        let positions: Vec<usize> = sample
+            .iter()
+            .map(|name| all.iter().position(|n| n == name).unwrap())
+            .collect();
+        assert!(positions[0] < positions[1], "{:?}", sample);
+    }
+
+    assert_eq!("random:3".parse::<Sample>().unwrap(), Sample::Random(3));
+    assert_eq!("first:1".parse::<Sample>().unwrap(), Sample::First(1));
+    assert!("random".parse::<Sample>().is_err());
+    assert!("random:many".parse::<Sample>().is_err());
+    assert!("every:2".parse::<Sample>().is_err());
+}
+
+#[test]
+fn filters_by_model_slug() {
+    let filters = Filters {
+        dynamixels: vec!["xl430-w250".to_string()],
+        ..Filters::default()
+    };
+
+    assert_eq!(names(&filters), ["XL430-W250"]);
+}
+
+#[test]
+fn filters_by_series_title_or_url() {
+    let by_title = Filters {
+        series: vec!["pro".to_string()],
+        ..Filters::default()
+    };
+    let by_url = Filters {
+        series: vec!["X".to_string()],
+        ..Filters::default()
+    };
+
+    assert_eq!(names(&by_title), ["H54-200-S500-R"]);
+    assert_eq!(names(&by_url), ["XM430-W210", "XL430-W250"]);
+}
+
+#[test]
+fn finds_series_after_reorganisation() {
+    let reorganised = r#"
+docs:
+  products:
+    - title: Actuators
+      children:
+        - title: "**X Series**"
+          children:
+            - title: XM430-W210
+              url: /dxl/x/xm430-w210/
+            - title: Missing URL
+"#;
+    let indexes = build_index(reorganised, BASE_URL, None, &Filters::default()).unwrap();
+    let explicit = build_index(
+        reorganised,
+        BASE_URL,
+        Some("docs.products.0.children"),
+        &Filters::default(),
+    )
+    .unwrap();
+
+    assert_eq!(indexes.len(), 1);
+    assert_eq!(explicit.len(), 1);
+    assert_eq!(indexes[0].name, "XM430-W210");
+}
+
+#[test]
+fn marks_discontinued_models() {
+    let navigation = r#"
+main:
+  - title: DYNAMIXEL
+    children:
+      - title: AX Series
+        children:
+          - title: AX-12A
+            url: /dxl/ax/ax-12a/
+          - title: AX-12W (Discontinued)
+            url: /dxl/ax/ax-12w/
+          - title: AX-18A (단종)
+            url: /dxl/ax/ax-18a/
+"#;
+    let indexes = build_index(navigation, BASE_URL, None, &Filters::default()).unwrap();
+    let marked: Vec<(&str, bool)> = indexes
+        .iter()
+        .map(|dxl| (dxl.name.as_str(), dxl.discontinued))
+        .collect();
+
+    assert_eq!(
+        marked,
+        [("AX-12A", false), ("AX-12W", true), ("AX-18A", true)]
+    );
+}
+
+#[test]
+fn sections_add_hardware_beyond_the_dynamixels() {
+    let navigation = r#"
+main:
+  - title: DYNAMIXEL
+    children:
+      - title: X Series
+        children:
+          - title: XM430-W210
+            url: /dxl/x/xm430-w210/
+  - title: "**Parts**"
+    children:
+      - title: Controller
+        children:
+          - title: CM-550
+            url: /parts/controller/cm-550/
+          - title: CM-530 (Discontinued)
+            url: /parts/controller/cm-530/
+      - title: U2D2
+        url: /parts/interface/u2d2/
+"#;
+    let filters = Filters {
+        sections: vec!["parts".to_string()],
+        ..Filters::default()
+    };
+    let indexes = build_index(navigation, BASE_URL, None, &filters).unwrap();
+    let found: Vec<(&str, &str, Option<&str>)> = indexes
+        .iter()
+        .map(|dxl| {
+            (
+                dxl.name.as_str(),
+                dxl.series.as_str(),
+                dxl.section.as_deref(),
+            )
+        })
+        .collect();
+
+    assert_eq!(
+        found,
+        [
+            ("XM430-W210", "X Series", None),
+            ("CM-550", "Controller", Some("Parts")),
+            ("CM-530", "Controller", Some("Parts")),
+            ("U2D2", "Parts", Some("Parts")),
+        ]
+    );
+    assert!(indexes[2].discontinued);
+    // Hardware is written apart from the Dynamixels
+    assert_eq!(
+        indexes[1].output_name(),
+        ("hardware/controller".to_string(), "cm-550".to_string())
+    );
+    assert_eq!(
+        indexes[0].output_name(),
+        ("x".to_string(), "xm430-w210".to_string())
+    );
+
+    // Filters apply to hardware too, and sections can be given by path
+    let filters = Filters {
+        dynamixels: vec!["u2d2".to_string()],
+        sections: vec!["main.1.children".to_string()],
+        ..Filters::default()
+    };
+    let indexes = build_index(navigation, BASE_URL, None, &filters).unwrap();
+    assert_eq!(indexes.len(), 1);
+    assert_eq!(
+        indexes[0].url,
+        format!("{}/parts/interface/u2d2/", BASE_URL)
+    );
+
+    let filters = Filters {
+        sections: vec!["Sensors".to_string()],
+        ..Filters::default()
+    };
+    let error = build_index(navigation, BASE_URL, None, &filters)
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains("\"Sensors\""), "{}", error);
+}
+
+#[test]
+fn missing_navigation_path_is_an_error() {
+    let error = build_index(NAVIGATION, BASE_URL, Some("main.3"), &Filters::default())
+        .unwrap_err()
+        .to_string();
+
+    assert!(error.contains("main"), "{}", error);
+}
+
+#[test]
+fn splits_page_urls_and_saved_pages() {
+    let xm430 = ("x".to_string(), "xm430-w210".to_string());
+
+    assert_eq!(split_url(&format!("{}/dxl/x/xm430-w210/", BASE_URL)), xm430);
+    assert_eq!(split_url("file:///saved/x/xm430-w210.html"), xm430);
+    assert_eq!(split_url("file:///artifacts/x/xm430-w210/page.html"), xm430);
+}
+
+#[tokio::test]
+async fn reads_extra_pages_from_disk() {
+    let path = fixture_path("xm430-w210.html");
+    let page = extra_page(path.to_str().unwrap()).unwrap();
+
+    assert_eq!(page.name, "XM430-W210");
+    assert_eq!(page.series, "FIXTURES");
+    assert!(page.url.starts_with("file://"), "{}", page.url);
+
+    let client = HttpClient::new(client_builder().build().unwrap(), DEFAULT_MAX_IN_FLIGHT);
+    let text = client.get_text(&page.url).await.unwrap();
+    assert!(text.contains("Shutdown"));
+}
+
+#[tokio::test]
+async fn reads_directories_of_a_local_build_from_their_index() {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("site/docs/en/dxl/x/xm430-w210");
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("index.html"), "<table></table>").unwrap();
+
+    let client = HttpClient::new(client_builder().build().unwrap(), DEFAULT_MAX_IN_FLIGHT);
+    let url = format!("file://{}/", dir.display());
+    assert_eq!(client.get_text(&url).await.unwrap(), "<table></table>");
+}
+
+#[tokio::test]
+async fn reads_pages_from_a_mirror_of_the_manual() {
+    let root = Path::new(env!("CARGO_TARGET_TMPDIR")).join("mirror");
+    let _ = fs::remove_dir_all(&root);
+    let dir = root.join("emanual.robotis.com/docs/en/dxl/x/xm430-w210");
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("index.html"), "<table></table>").unwrap();
+    fs::write(root.join("navigation.yml"), NAVIGATION).unwrap();
+
+    let client = HttpClient::new(client_builder().build().unwrap(), DEFAULT_MAX_IN_FLIGHT)
+        .with_source(Arc::new(DirectorySource::new(&root)));
+    let url = format!("{}/dxl/x/xm430-w210/", BASE_URL);
+    assert_eq!(client.get_text(&url).await.unwrap(), "<table></table>");
+    // Pages can be saved without their host too
+    let navigation = client
+        .get_text("https://raw.githubusercontent.com/navigation.yml")
+        .await
+        .unwrap();
+    assert_eq!(navigation, NAVIGATION);
+
+    let error = client
+        .get_text(&format!("{}/dxl/x/xl430-w250/", BASE_URL))
+        .await
+        .unwrap_err();
+    assert!(error.is::<PageUnavailable>(), "{}", error);
+}
+
+#[test]
+fn parses_page_sources() {
+    assert_eq!("http".parse::<SourceSpec>().unwrap(), SourceSpec::Http);
+    assert_eq!(
+        "dir:mirror/".parse::<SourceSpec>().unwrap(),
+        SourceSpec::Directory("mirror/".into())
+    );
+    assert!("dir:".parse::<SourceSpec>().is_err());
+    assert!("ftp:mirror".parse::<SourceSpec>().is_err());
+}
+
+#[test]
+fn missing_extra_pages_are_errors() {
+    assert!(extra_page("does/not/exist.html").is_err());
+    assert_eq!(
+        extra_page("https://emanual.robotis.com/docs/en/dxl/x/xw540-t260/")
+            .unwrap()
+            .name,
+        "XW540-T260"
+    );
+}
+
+#[test]
+fn pages_written_to_the_same_files_are_an_error() {
+    let mut indexes = build_index(NAVIGATION, BASE_URL, None, &Filters::default()).unwrap();
+    // The same page listed twice only overwrites its own files
+    indexes.push(indexes[0].clone());
+    assert!(check_output_paths(&indexes).is_ok());
+
+    let saved = extra_page("file:///saved/X/XM430-W210.html").unwrap();
+    indexes.push(saved);
+    let error = check_output_paths(&indexes).unwrap_err().to_string();
+
+    assert!(
+        error.contains(&format!("{}/dxl/x/xm430-w210/", BASE_URL)),
+        "{}",
+        error
+    );
+    assert!(
+        error.contains("file:///saved/X/XM430-W210.html"),
+        "{}",
+        error
+    );
+}
@@ -0,0 +1,60 @@
+//! Regression tests comparing the parser's output on saved E-Manual pages
+//! against known-good RON snapshots.
+//!
+//! Every `fixtures/<model>.html` is parsed and compared with
+//! `fixtures/<model>.ron`. After an intentional change to the parser's
+//! output, regenerate the snapshots with `UPDATE_GOLDEN=1 cargo test` and
+//! review the diff.
+
+use dynamixel_scraper::parse_fixture_with_warnings;
+use dynamixel_scraper::serialize::serialize_servo;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+fn fixtures() -> Vec<PathBuf> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures");
+    let mut pages: Vec<PathBuf> = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "html"))
+        .collect();
+    pages.sort();
+
+    pages
+}
+
+#[test]
+fn fixtures_match_snapshots() {
+    let update = env::var_os("UPDATE_GOLDEN").is_some();
+    let mut mismatches = vec![];
+
+    for page in fixtures() {
+        let snapshot = page.with_extension("ron");
+        let (parsed, warnings) = parse_fixture_with_warnings(&page)
+            .unwrap_or_else(|e| panic!("Failed to parse {}: {:#}", page.display(), e));
+        let actual = serialize_servo(&parsed, &warnings).unwrap();
+
+        if update {
+            fs::write(&snapshot, &actual).unwrap();
+            continue;
+        }
+
+        let expected = fs::read_to_string(&snapshot)
+            .unwrap_or_else(|_| panic!("Missing snapshot {}", snapshot.display()));
+        if actual != expected {
+            mismatches.push(page.display().to_string());
+        }
+    }
+
+    assert!(
+        mismatches.is_empty(),
+        "Parsed output differs from the snapshots for {:?}; rerun with UPDATE_GOLDEN=1 to update them",
+        mismatches
+    );
+}
+
+#[test]
+fn fixtures_exist() {
+    assert!(!fixtures().is_empty());
+}
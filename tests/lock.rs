@@ -0,0 +1,24 @@
+//! Tests for locking the output against concurrent runs.
+
+use dynamixel_scraper::lock::{OutputLock, OutputLocked};
+use std::fs;
+use std::path::Path;
+
+#[test]
+fn only_one_run_holds_the_lock() {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("lock");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(".dynamixel-scraper.lock");
+
+    let lock = OutputLock::acquire(&path).unwrap();
+    let error = OutputLock::acquire(&path).unwrap_err();
+    let locked = error.downcast_ref::<OutputLocked>().unwrap();
+    assert_eq!(locked.holder, Some(std::process::id()));
+    assert!(locked.to_string().contains("--force"), "{}", locked);
+
+    // The lock is released with the run, leaving the file for the next
+    drop(lock);
+    let _lock = OutputLock::acquire(&path).unwrap();
+    assert!(path.exists());
+}
@@ -0,0 +1,525 @@
+//! End to end tests running the whole scrape against a local server serving
+//! the fixtures in place of the E-Manual.
+
+mod common;
+
+use common::{fixture_page, start_server};
+use dynamixel_scraper::create_lib::LibTarget;
+use dynamixel_scraper::fetch::{
+    build_index, client_builder, fetch_all, ActuatorIndex, FetchOptions, Filters, HttpClient,
+    DEFAULT_MAX_IN_FLIGHT, USER_AGENT,
+};
+use dynamixel_scraper::locale::{is_commit_hash, Locale};
+use dynamixel_scraper::pipeline::{run, Formats, RunOptions};
+use dynamixel_scraper::progress::ProgressEvent;
+use dynamixel_scraper::report::{ErrorCategory, ModelStatus};
+use dynamixel_scraper::source::{MemorySource, PageSource};
+use futures_util::future::{BoxFuture, FutureExt};
+use hyper::{Body, Request, Response, StatusCode};
+use std::convert::Infallible;
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, watch};
+
+static NAVIGATION: &str = r#"
+main:
+  - title: DYNAMIXEL
+    children:
+      - title: "**X Series**"
+        children:
+          - title: XM430-W210
+            url: /dxl/x/xm430-w210/
+          - title: XM430-W999
+            url: /dxl/x/xm430-w999/
+      - title: "AX Series"
+        children:
+          - title: AX-12A
+            url: /dxl/ax/ax-12a/
+"#;
+
+static ROBOTS: &str = "
+User-agent: *
+Disallow: /private/
+
+User-agent: dynamixel-scraper
+Disallow: /docs/en/dxl/ax/
+Crawl-delay: 0.3
+";
+
+/// Serve the navigation and the fixture of each model page it lists, with
+/// the XM430-W999 missing like a retired model. Pages under /stalled/ never
+/// finish downloading, and robots.txt disallows the AX series for the
+/// scraper.
+async fn serve(request: Request<Body>) -> Result<Response<Body>, Infallible> {
+    if request.uri().path().starts_with("/stalled/") {
+        std::future::pending::<()>().await;
+    }
+    let page = match request.uri().path() {
+        "/navigation.yml" => Some(NAVIGATION.to_string()),
+        "/robots.txt" => Some(ROBOTS.to_string()),
+        "/user-agent" => request
+            .headers()
+            .get("user-agent")
+            .and_then(|agent| agent.to_str().ok())
+            .map(String::from),
+        "/docs/en/dxl/x/xm430-w210/" => Some(fixture_page("xm430-w210")),
+        "/docs/en/dxl/ax/ax-12a/" => Some(fixture_page("ax-12a")),
+        _ => None,
+    };
+
+    Ok(match page {
+        Some(page) => Response::new(Body::from(page)),
+        None => Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .body(Body::empty())
+            .unwrap(),
+    })
+}
+
+#[tokio::test]
+async fn scrapes_every_format_from_a_local_manual() {
+    let address = start_server(serve);
+
+    // Output is written relative to the working directory, which is only
+    // changed by this test in this binary
+    let out = Path::new(env!("CARGO_TARGET_TMPDIR")).join("pipeline");
+    let _ = fs::remove_dir_all(&out);
+    fs::create_dir_all(&out).unwrap();
+    std::env::set_current_dir(&out).unwrap();
+
+    let mut opts = RunOptions::new(Locale::English);
+    opts.navigation_url = format!("http://{}/navigation.yml", address);
+    opts.base_url = format!("http://{}/docs/en", address);
+    opts.formats = Formats {
+        lib: true,
+        ron: true,
+        ron_summaries: true,
+        yaml: true,
+        msgpack: true,
+        markdown: true,
+        json_schema: true,
+    };
+    opts.lib_target = LibTarget {
+        path: out.join("lib"),
+        ..LibTarget::default()
+    };
+    opts.manual_revision = Some("8c1f2e7".to_string());
+    let (progress, mut events) = mpsc::unbounded_channel();
+    opts.fetch.progress = Some(progress);
+    let client = HttpClient::new(client_builder().build().unwrap(), DEFAULT_MAX_IN_FLIGHT);
+
+    let report = run(&client, &opts).await.unwrap();
+
+    assert_eq!(report.attempted, 3, "{}", report);
+    assert_eq!(report.succeeded, 2, "{}", report);
+    assert_eq!(report.unavailable, 1, "{}", report);
+    assert_eq!(report.failure(), None);
+    assert_eq!(report.manual_revision.as_deref(), Some("8c1f2e7"));
+    // The navigation and each of the three pages, one of which is missing
+    assert_eq!(report.network.requests, 4, "{}", report);
+    assert_eq!(report.network.hosts.len(), 1, "{}", report);
+    assert_eq!(report.network.hosts[0].host, address.to_string());
+    assert!(report.network.bytes_downloaded > report.bytes_downloaded);
+    let metrics = report.network.to_prometheus();
+    assert!(
+        metrics.contains("\ndynamixel_scraper_requests_total 4\n"),
+        "{}",
+        metrics
+    );
+    assert!(
+        metrics.contains(&format!(
+            "dynamixel_scraper_request_duration_seconds_count{{host=\"{}\"}} 4\n",
+            address
+        )),
+        "{}",
+        metrics
+    );
+
+    for path in [
+        "objects/x/xm430-w210.ron",
+        "objects/x/xm430-w210.md",
+        "objects/ax/ax-12a.ron",
+        "objects/index.ron",
+        "objects/x/_series.ron",
+        "objects/x/xm430-w210.yaml",
+        "objects/x/xm430-w210.msgpack",
+        "objects/index.msgpack",
+        "docs/x/xm430-w210.md",
+        "schemas/x/xm430-w210.schema.json",
+        "lib/src/lib.rs",
+    ] {
+        assert!(out.join(path).exists(), "{} wasn't written", path);
+    }
+    // Unlike the golden, the scraped object has the default for its variant
+    let object = fs::read_to_string(out.join("objects/x/xm430-w210.ron")).unwrap();
+    assert!(
+        object.contains("initial_value: Value(Integer(330)),"),
+        "{}",
+        object
+    );
+    let summary = fs::read_to_string(out.join("objects/x/xm430-w210.md")).unwrap();
+    assert!(summary.starts_with("# XM430-W210\n"), "{}", summary);
+    assert!(
+        summary.contains("| 116 | 4 | Goal Position | RW | "),
+        "{}",
+        summary
+    );
+    let lib = fs::read_to_string(out.join("lib/src/lib.rs")).unwrap();
+    assert!(lib.contains("pub const MANUAL_REVISION: Option<&str> = Some(\"8c1f2e7\");"));
+    let series = fs::read_to_string(out.join("objects/x/_series.ron")).unwrap();
+    assert!(
+        series.contains("models: [\n        \"xm430-w210\",\n    ],"),
+        "{}",
+        series
+    );
+    let index = fs::read_to_string(out.join("objects/index.ron")).unwrap();
+    assert!(!index.contains("xm430-w999"), "{}", index);
+
+    drop(opts);
+    let mut received = Vec::new();
+    while let Some(event) = events.recv().await {
+        received.push(event);
+    }
+    assert_eq!(received[0], ProgressEvent::IndexFetched { models: 3 });
+    let started = received
+        .iter()
+        .filter(|event| matches!(event, ProgressEvent::ModelStarted { total: 3, .. }))
+        .count();
+    assert_eq!(started, 3, "{:?}", received);
+    let position = |wanted: &ProgressEvent| received.iter().position(|event| event == wanted);
+    let written = |path: &str| ProgressEvent::OutputWritten { path: path.into() };
+    let parsed = received
+        .iter()
+        .position(|event| matches!(event, ProgressEvent::ModelParsed { name, registers, .. } if name == "XM430-W210" && *registers > 0))
+        .unwrap();
+    // A model's files are written before it's reported as parsed
+    assert!(position(&written("objects/x/xm430-w210.ron")).unwrap() < parsed);
+    assert!(received.iter().any(|event| matches!(
+        event,
+        ProgressEvent::ModelFailed { name, status: ModelStatus::Unavailable { .. }, .. } if name == "XM430-W999"
+    )));
+    // The index is only written once every model is done
+    let index = position(&written("objects/index.ron")).unwrap();
+    assert!(index > parsed);
+    assert!(position(&ProgressEvent::OutputWritten {
+        path: out.join("lib/src/lib.rs")
+    })
+    .is_some());
+}
+
+#[tokio::test]
+async fn models_are_handed_over_as_they_are_parsed() {
+    let address = start_server(serve);
+    let base_url = format!("http://{}/docs/en", address);
+    let indexes = build_index(NAVIGATION, &base_url, None, &Filters::default()).unwrap();
+    let client = HttpClient::new(client_builder().build().unwrap(), DEFAULT_MAX_IN_FLIGHT);
+
+    // Only what the handler returns is kept, and it failing fails the model
+    let (kept, models) =
+        fetch_all(
+            &client,
+            indexes,
+            &FetchOptions::default(),
+            |actuator| match actuator.url().contains("ax-12a") {
+                true => Err(anyhow::anyhow!("Disk full")),
+                false => Ok(actuator.index_entry().slug),
+            },
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(kept, vec!["xm430-w210".to_string()]);
+    let ax = models.iter().find(|model| model.name == "AX-12A").unwrap();
+    assert!(
+        matches!(
+            &ax.status,
+            ModelStatus::Failed { category: ErrorCategory::Io, reason } if reason == "Disk full"
+        ),
+        "{:?}",
+        ax.status
+    );
+}
+
+#[tokio::test]
+async fn scrapes_fixtures_held_in_memory() {
+    let base_url = "https://emanual.robotis.com/docs/en";
+    let mut source = MemorySource::new();
+    for page in ["x/xm430-w210", "ax/ax-12a"] {
+        let name = page.split('/').nth(1).unwrap();
+        source.insert(format!("{}/dxl/{}/", base_url, page), fixture_page(name));
+    }
+    let indexes = build_index(NAVIGATION, base_url, None, &Filters::default()).unwrap();
+    let client = HttpClient::new(client_builder().build().unwrap(), DEFAULT_MAX_IN_FLIGHT)
+        .with_source(Arc::new(source));
+
+    let (mut kept, models) = fetch_all(&client, indexes, &FetchOptions::default(), |actuator| {
+        Ok(actuator.index_entry().slug)
+    })
+    .await
+    .unwrap();
+
+    kept.sort();
+    assert_eq!(kept, vec!["ax-12a".to_string(), "xm430-w210".to_string()]);
+    let retired = models
+        .iter()
+        .find(|model| model.name == "XM430-W999")
+        .unwrap();
+    assert!(
+        matches!(retired.status, ModelStatus::Unavailable { .. }),
+        "{:?}",
+        retired.status
+    );
+    // Nothing was requested from the network
+    assert_eq!(client.metrics().summary().requests, 0);
+}
+
+#[tokio::test]
+async fn hardware_from_sections_is_written_apart() {
+    let url = "https://emanual.robotis.com/docs/en/parts/controller/cm-550/";
+    let mut source = MemorySource::new();
+    source.insert(url, fixture_page("ax-12a"));
+    let client = HttpClient::new(client_builder().build().unwrap(), DEFAULT_MAX_IN_FLIGHT)
+        .with_source(Arc::new(source));
+    let index = ActuatorIndex {
+        url: url.to_string(),
+        name: "CM-550".to_string(),
+        series: "Controller".to_string(),
+        discontinued: false,
+        section: Some("Parts".to_string()),
+    };
+
+    let (kept, _) = fetch_all(&client, vec![index], &FetchOptions::default(), |actuator| {
+        Ok((actuator.section().map(String::from), actuator.index_entry()))
+    })
+    .await
+    .unwrap();
+
+    let (section, entry) = &kept[0];
+    assert_eq!(section.as_deref(), Some("Parts"));
+    assert_eq!(entry.series, "hardware/controller");
+    assert_eq!(entry.path, "hardware/controller/cm-550.ron");
+}
+
+#[tokio::test]
+async fn cancelling_finishes_the_models_already_downloaded() {
+    let address = start_server(serve);
+    let base_url = format!("http://{}/docs/en", address);
+    let mut indexes = build_index(NAVIGATION, &base_url, None, &Filters::default()).unwrap();
+    indexes.push(ActuatorIndex {
+        url: format!("http://{}/stalled/x/xl430-w250/", address),
+        name: "XL430-W250".to_string(),
+        series: "X Series".to_string(),
+        discontinued: false,
+        section: None,
+    });
+    let client = HttpClient::new(client_builder().build().unwrap(), DEFAULT_MAX_IN_FLIGHT);
+
+    let (cancel, cancelled) = watch::channel(false);
+    let opts = FetchOptions {
+        cancel: Some(cancelled),
+        ..FetchOptions::default()
+    };
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        cancel.send(true).unwrap();
+    });
+
+    // The stalled page would keep the scrape going forever if not cancelled
+    let fetch = fetch_all(&client, indexes, &opts, |actuator| {
+        Ok(actuator.index_entry().slug)
+    });
+    let (kept, models) = tokio::time::timeout(Duration::from_secs(10), fetch)
+        .await
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(kept, vec!["ax-12a".to_string(), "xm430-w210".to_string()]);
+    assert_eq!(models.len(), 3);
+    assert!(models.iter().all(|model| model.name != "XL430-W250"));
+}
+
+/// The same page at every URL, counting how many have been read
+#[derive(Debug, Default)]
+struct CountingSource {
+    reads: AtomicUsize,
+}
+
+impl PageSource for CountingSource {
+    fn get_text<'a>(&'a self, _url: &'a str) -> BoxFuture<'a, anyhow::Result<String>> {
+        self.reads.fetch_add(1, Ordering::SeqCst);
+        async { Ok(fixture_page("xm430-w210")) }.boxed()
+    }
+}
+
+#[tokio::test]
+async fn slow_parsers_stall_downloads() {
+    let source = Arc::new(CountingSource::default());
+    let client = HttpClient::new(client_builder().build().unwrap(), 2).with_source(source.clone());
+    let indexes: Vec<ActuatorIndex> = (0..30)
+        .map(|i| ActuatorIndex {
+            url: format!("https://emanual.robotis.com/docs/en/dxl/x/xm430-w{}/", i),
+            name: format!("XM430-W{}", i),
+            series: "X Series".to_string(),
+            discontinued: false,
+            section: None,
+        })
+        .collect();
+    let opts = FetchOptions {
+        queue_size: 1,
+        ..FetchOptions::default()
+    };
+
+    // While the first model is being saved, only a few more pages may be
+    // downloaded: one in the queue, one waiting for room in it, and the
+    // two in flight
+    let first = Arc::new(AtomicBool::new(true));
+    let reads_while_saving = Arc::new(AtomicUsize::new(0));
+    let (reads, saving) = (source.clone(), reads_while_saving.clone());
+    let (_, models) = fetch_all(&client, indexes, &opts, move |actuator| {
+        if first.swap(false, Ordering::SeqCst) {
+            std::thread::sleep(Duration::from_millis(500));
+            saving.store(reads.reads.load(Ordering::SeqCst), Ordering::SeqCst);
+        }
+        Ok(actuator.index_entry().slug)
+    })
+    .await
+    .unwrap();
+
+    let reads = reads_while_saving.load(Ordering::SeqCst);
+    assert!(reads <= 6, "{} pages were downloaded", reads);
+    assert_eq!(models.len(), 30);
+}
+
+#[tokio::test]
+async fn pages_taking_too_long_fail_on_their_own() {
+    let address = start_server(serve);
+    let base_url = format!("http://{}/docs/en", address);
+    let mut indexes = build_index(NAVIGATION, &base_url, None, &Filters::default()).unwrap();
+    indexes.push(ActuatorIndex {
+        url: format!("http://{}/stalled/x/xl430-w250/", address),
+        name: "XL430-W250".to_string(),
+        series: "X Series".to_string(),
+        discontinued: false,
+        section: None,
+    });
+    let client = HttpClient::new(client_builder().build().unwrap(), DEFAULT_MAX_IN_FLIGHT);
+    let opts = FetchOptions {
+        page_timeout: Duration::from_millis(500),
+        ..FetchOptions::default()
+    };
+
+    // Saving the AX-12A is slow, which isn't the parse taking too long
+    let fetch = fetch_all(&client, indexes, &opts, |actuator| {
+        let slug = actuator.index_entry().slug;
+        if slug == "ax-12a" {
+            std::thread::sleep(Duration::from_secs(1));
+        }
+        Ok(slug)
+    });
+    let (kept, models) = tokio::time::timeout(Duration::from_secs(10), fetch)
+        .await
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(kept, vec!["ax-12a".to_string(), "xm430-w210".to_string()]);
+    let stalled = models
+        .iter()
+        .find(|model| model.name == "XL430-W250")
+        .unwrap();
+    match &stalled.status {
+        ModelStatus::Failed { category, reason } => {
+            assert_eq!(*category, ErrorCategory::Network);
+            assert!(reason.contains("Timed out after 500ms"), "{}", reason);
+        }
+        status => panic!("The download should have timed out, not {:?}", status),
+    }
+}
+
+#[tokio::test]
+async fn parses_taking_too_long_fail_on_their_own() {
+    let url = "https://emanual.robotis.com/docs/en/dxl/x/xm430-w210/";
+    // Thousands of tables take far longer to parse than the timeout
+    let filler = "<table><tr><th>Bit</th></tr><tr><td>0</td></tr></table>".repeat(20_000);
+    let mut source = MemorySource::new();
+    source.insert(url, fixture_page("xm430-w210") + &filler);
+    let client = HttpClient::new(client_builder().build().unwrap(), DEFAULT_MAX_IN_FLIGHT)
+        .with_source(Arc::new(source));
+    let index = ActuatorIndex {
+        url: url.to_string(),
+        name: "XM430-W210".to_string(),
+        series: "X Series".to_string(),
+        discontinued: false,
+        section: None,
+    };
+    let opts = FetchOptions {
+        page_timeout: Duration::from_millis(1),
+        ..FetchOptions::default()
+    };
+
+    let (kept, models) = fetch_all(&client, vec![index], &opts, |actuator| {
+        Ok(actuator.index_entry().slug)
+    })
+    .await
+    .unwrap();
+
+    assert!(kept.is_empty());
+    match &models[0].status {
+        ModelStatus::Failed { category, reason } => {
+            assert_eq!(*category, ErrorCategory::Parse);
+            assert!(reason.contains("Timed out after 1ms"), "{}", reason);
+        }
+        status => panic!("The parse should have timed out, not {:?}", status),
+    }
+}
+
+#[tokio::test]
+async fn pages_disallowed_by_robots_are_skipped() {
+    let address = start_server(serve);
+    let base_url = format!("http://{}/docs/en", address);
+    let indexes = build_index(NAVIGATION, &base_url, None, &Filters::default()).unwrap();
+    let mut client = HttpClient::new(client_builder().build().unwrap(), DEFAULT_MAX_IN_FLIGHT);
+
+    let agent = client
+        .get_text(&format!("http://{}/user-agent", address))
+        .await
+        .unwrap();
+    assert_eq!(agent, USER_AGENT);
+
+    client.respect_robots(&base_url, USER_AGENT).await.unwrap();
+    // Waiting for the crawl delay doesn't count towards a page's timeout
+    let opts = FetchOptions {
+        page_timeout: Duration::from_millis(200),
+        ..FetchOptions::default()
+    };
+    let started = Instant::now();
+    let (kept, models) = fetch_all(&client, indexes, &opts, |actuator| {
+        Ok(actuator.index_entry().slug)
+    })
+    .await
+    .unwrap();
+
+    // The XM430-W210 and XM430-W999 are requested a crawl delay apart
+    assert!(started.elapsed() >= Duration::from_millis(300));
+    assert_eq!(kept, vec!["xm430-w210".to_string()]);
+    let ax = models.iter().find(|model| model.name == "AX-12A").unwrap();
+    match &ax.status {
+        ModelStatus::Unavailable { reason } => {
+            assert!(reason.contains("disallowed by robots.txt"), "{}", reason)
+        }
+        status => panic!("AX-12A should have been skipped, not {:?}", status),
+    }
+}
+
+#[test]
+fn navigation_can_be_pinned_to_a_revision() {
+    assert_eq!(
+        Locale::Korean.navigation_url_at("8c1f2e7"),
+        "https://raw.githubusercontent.com/ROBOTIS-GIT/emanual/8c1f2e7/_data/navigation_kr.yml"
+    );
+    assert!(Locale::English.navigation_url().contains("/master/"));
+    assert!(is_commit_hash("8c1f2e7"));
+    assert!(!is_commit_hash("master"));
+}
@@ -0,0 +1,61 @@
+//! Tests for reading robots.txt rules and crawl delays.
+
+use dynamixel_scraper::robots::Robots;
+use std::time::Duration;
+
+static ROBOTS: &str = "
+# Every crawler
+User-agent: *
+Disallow: /search
+Disallow: /*.pdf$
+Crawl-delay: 2
+
+User-agent: Googlebot
+User-agent: dynamixel-scraper
+Disallow: /docs/
+Allow: /docs/en/dxl/
+Disallow: /docs/en/dxl/*/drafts/
+";
+
+#[test]
+fn follows_the_group_naming_the_scraper() {
+    let robots = Robots::parse(ROBOTS, "Dynamixel-Scraper");
+
+    assert!(robots.allows("/docs/en/dxl/x/xm430-w210/"));
+    assert!(!robots.allows("/docs/en/parts/"));
+    assert!(!robots.allows("/docs/en/dxl/x/drafts/xm999/"));
+    // Only the most specific group applies
+    assert!(robots.allows("/search?q=xm430"));
+    assert_eq!(robots.crawl_delay, None);
+}
+
+#[test]
+fn falls_back_to_the_group_for_every_crawler() {
+    let robots = Robots::parse(ROBOTS, "other-bot");
+
+    assert!(robots.allows("/docs/en/parts/"));
+    assert!(!robots.allows("/search?q=xm430"));
+    assert!(!robots.allows("/docs/en/manual.pdf"));
+    assert!(robots.allows("/docs/en/manual.pdf?download=1"));
+    assert_eq!(robots.crawl_delay, Some(Duration::from_secs(2)));
+}
+
+#[test]
+fn allows_everything_without_rules() {
+    let robots = Robots::parse("User-agent: *\nDisallow:\n", "dynamixel-scraper");
+
+    assert!(robots.allows("/docs/en/dxl/x/xm430-w210/"));
+    assert_eq!(Robots::parse("", "dynamixel-scraper"), Robots::default());
+}
+
+#[test]
+fn ignores_crawl_delays_that_arent_durations() {
+    for delay in ["-1", "1e30", "nan", "inf", "soon"] {
+        let text = format!("User-agent: *\nCrawl-delay: {}\n", delay);
+        let robots = Robots::parse(&text, "dynamixel-scraper");
+
+        assert_eq!(robots.crawl_delay, None, "{}", delay);
+    }
+    let robots = Robots::parse("User-agent: *\nCrawl-delay: 0.5\n", "dynamixel-scraper");
+    assert_eq!(robots.crawl_delay, Some(Duration::from_millis(500)));
+}
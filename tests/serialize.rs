@@ -0,0 +1,771 @@
+//! Tests for parsing the cells of control tables and serializing them.
+
+mod common;
+
+use common::{fixture_page, fixture_path};
+use dynamixel_scraper::download::{firmware_notes, table_to_csv};
+use dynamixel_scraper::serialize::{
+    apply_firmware_notes, apply_initial_value_sources, deserialize_servo,
+    deserialize_servo_msgpack, deserialize_servo_with_warnings, is_placeholder, parse_servo,
+    parse_servo_with_warnings, resolve_references, serialize_servo, serialize_servo_msgpack,
+    serialize_servo_schema, split_joints, strip_thousands_separators, AccessLevel, BitValue,
+    Conversion, InitialValue, InitialValueSource, ItemValue, ParseError, RangeValue,
+};
+use dynamixel_scraper::{parse_control_table, parse_fixture};
+use scraper::Html;
+use std::fs;
+
+#[test]
+fn only_thousands_separators_are_stripped() {
+    assert_eq!(strip_thousands_separators("1,023"), "1023");
+    assert_eq!(
+        strip_thousands_separators("-1,000,000 ~ 1,000,000"),
+        "-1000000 ~ 1000000"
+    );
+    assert_eq!(strip_thousands_separators("0, 1, 2"), "0, 1, 2");
+    assert_eq!(strip_thousands_separators("1,0234"), "1,0234");
+
+    assert!(matches!(
+        RangeValue::new("1,023").unwrap(),
+        RangeValue::Integer(1023)
+    ));
+    assert!(RangeValue::new("0,1").is_err());
+    assert!(matches!(
+        InitialValue::new("0, 1"),
+        InitialValue::Expression(text) if text == "0, 1"
+    ));
+}
+
+#[test]
+fn cells_with_commas_survive_as_csv() {
+    let table = vec![
+        vec!["Address".to_string(), "Description".to_string()],
+        vec!["64".to_string(), "Torque On, Torque Off".to_string()],
+        vec!["65".to_string(), "The \"LED\"".to_string()],
+    ];
+
+    assert_eq!(
+        table_to_csv(&table),
+        "Address,Description\n64,\"Torque On, Torque Off\"\n65,\"The \"\"LED\"\"\"\n"
+    );
+}
+
+#[test]
+fn schema_describes_writable_items() {
+    let data = parse_fixture(fixture_path("xm430-w210.html")).unwrap();
+    let schema: serde_json::Value = serde_json::from_str(
+        &serialize_servo_schema("XM430-W210", "https://example.com/xm430-w210/", &data).unwrap(),
+    )
+    .unwrap();
+    let properties = &schema["properties"];
+
+    assert_eq!(schema["additionalProperties"], false);
+    assert_eq!(properties["Velocity Limit"]["minimum"], 0);
+    assert_eq!(properties["Velocity Limit"]["maximum"], 1023);
+    assert_eq!(properties["Velocity Limit"]["x-address"], 44);
+    // Bounds referring to other items take the widest values those allow
+    assert_eq!(properties["Goal PWM"]["minimum"], -885);
+    assert_eq!(properties["Goal Position"]["maximum"], 4095);
+    // Items without a range are bounded by their type
+    assert_eq!(properties["Shutdown"]["maximum"], 255);
+    assert_eq!(properties["Shutdown"]["default"], 52);
+    // Only items that can be written are described
+    assert!(properties.get("Present Position").is_none());
+}
+
+#[test]
+fn realigns_rows_with_shifted_columns() {
+    let table: Vec<Vec<String>> = [
+        &[
+            "Address",
+            "Size(byte)",
+            "Data Name",
+            "Access",
+            "Initial Value",
+            "Range",
+        ][..],
+        &["64", "1", "Torque Enable", "RW", "0", "0 ~ 1"],
+        // The initial value's cell is missing
+        &["65", "1", "LED", "RW", "0 ~ 1"],
+        // The size was repeated, as a cell spanning two columns would be
+        &["68", "1", "1", "Status Return Level", "R", "2", "0 ~ 2"],
+        // Nothing lines up however the cells are shifted
+        &["70", "3", "Registered", "X", "-", "-"],
+    ]
+    .iter()
+    .map(|row| row.iter().map(|cell| cell.to_string()).collect())
+    .collect();
+
+    let data = parse_servo(table).unwrap();
+
+    let addresses: Vec<u16> = data.iter().map(|row| row.address).collect();
+    assert_eq!(addresses, [64, 65, 68]);
+    assert_eq!(data[1].data_name.as_deref(), Some("LED"));
+    assert!(matches!(data[1].initial_value, InitialValue::None));
+    assert!(data[1].range.is_some());
+    assert_eq!(data[2].data_name.as_deref(), Some("Status Return Level"));
+    assert!(matches!(
+        data[2].initial_value,
+        InitialValue::Value(RangeValue::Integer(2))
+    ));
+}
+
+/// An object written before items had a value type, when initial values were
+/// an `Option` and ranges only named the items they referred to
+static VERSION_0: &str = r#"[
+    (
+        address: 6,
+        size: 2,
+        data_name: Some("CW Angle Limit"),
+        description: None,
+        access: ReadWrite,
+        initial_value: Some(Integer(0)),
+        range: Some((
+            Integer(0),
+            Address(
+                name: "CCWAngleLimit",
+                negative: false,
+            ),
+        )),
+        units: None,
+    ),// [0]
+    (
+        address: 36,
+        size: 2,
+        data_name: Some("Present Position"),
+        description: None,
+        access: Read,
+        initial_value: None,
+        range: None,
+    ),// [1]
+]"#;
+
+#[test]
+fn migrates_objects_written_by_older_versions() {
+    let data = deserialize_servo(VERSION_0).unwrap();
+    assert_eq!(data.len(), 2);
+    assert!(matches!(
+        data[0].initial_value,
+        InitialValue::Value(RangeValue::Integer(0))
+    ));
+    assert_eq!(data[0].value_type.rust_type(), "u16");
+    assert!(data[1].units.is_none() && data[1].notes.is_empty());
+
+    // Objects from before the format was versioned read the same as now
+    let unversioned = fs::read_to_string(fixture_path("legacy/ax-12a.v1.ron")).unwrap();
+    let current = fs::read_to_string(fixture_path("ax-12a.ron")).unwrap();
+    assert_eq!(
+        serialize_servo(&deserialize_servo(&unversioned).unwrap(), &[]).unwrap(),
+        current
+    );
+
+    let newer = current.replacen("schema_version: 6", "schema_version: 99", 1);
+    assert!(deserialize_servo(&newer).is_err());
+}
+
+#[test]
+fn msgpack_objects_are_smaller_and_read_back_the_same() {
+    let ron = fs::read_to_string(fixture_path("xm430-w210.ron")).unwrap();
+    let data = deserialize_servo(&ron).unwrap();
+
+    let bytes = serialize_servo_msgpack(&data).unwrap();
+    assert!(bytes.len() * 4 < ron.len(), "{} bytes", bytes.len());
+    assert_eq!(
+        serialize_servo(&deserialize_servo_msgpack(&bytes).unwrap(), &[]).unwrap(),
+        serialize_servo(&data, &[]).unwrap()
+    );
+
+    // Fields are read by position, so other versions can't be migrated
+    let mut older = bytes;
+    assert_eq!(older[1], 6, "the schema version follows the array marker");
+    older[1] = 1;
+    assert!(deserialize_servo_msgpack(&older).is_err());
+}
+
+#[test]
+fn parses_html_from_anywhere() {
+    let page = fixture_page("xm430-w210");
+    let data = parse_control_table(&page).unwrap();
+    assert_eq!(
+        data.len(),
+        parse_fixture(fixture_path("xm430-w210.html"))
+            .unwrap()
+            .len()
+    );
+
+    assert!(matches!(
+        parse_control_table("<p>No tables here</p>"),
+        Err(ParseError::NoControlTable)
+    ));
+    let mismatched = "<table><tr><th>Item</th></tr></table>\
+                      <table><tr><th>Address</th><th>Size(Byte)</th><th>Access</th></tr></table>\
+                      <table><tr><th>Address</th><th>Access</th></tr></table>";
+    match parse_control_table(mismatched) {
+        Err(ParseError::MismatchedHeadings { first, other }) => {
+            assert_eq!(first.len(), 3);
+            assert_eq!(other.len(), 2);
+        }
+        other => panic!("Expected mismatched headings, not {:?}", other),
+    }
+}
+
+#[test]
+fn placeholders_are_empty_in_every_style() {
+    for cell in [
+        "",
+        "-",
+        "...",
+        "…",
+        "~",
+        " - ",
+        "\u{a0}",
+        "\u{a0}-\u{a0}",
+        "\u{2003}\t\n",
+        "\u{200b}",
+        "\u{feff}…",
+        "–",
+        "—",
+        "−",
+        "－",
+        "⋯",
+        "〜",
+        "â€¦",
+        "â€“ ",
+        "â€”",
+    ] {
+        assert!(is_placeholder(cell), "{:?} should be a placeholder", cell);
+    }
+
+    for cell in ["0", "-1", "0 ~ 1", "RW", "…1", "-\u{a0}%", "â€¦x", "â€"] {
+        assert!(!is_placeholder(cell), "{:?} isn't a placeholder", cell);
+    }
+}
+
+#[test]
+fn cells_with_placeholders_are_missing() {
+    let table: Vec<Vec<String>> = [
+        &[
+            "Address",
+            "Size(byte)",
+            "Data Name",
+            "Access",
+            "Initial Value",
+            "Range",
+        ][..],
+        &["64", "1", "Torque Enable", "RW", "0", "0 ~ 1"],
+        &["\u{a0}", "…", "\u{200b}", "â€¦", "–", " - "],
+        &["65", "1", "LED", "RW", "\u{a0}-", "—"],
+    ]
+    .iter()
+    .map(|row| row.iter().map(|cell| cell.to_string()).collect())
+    .collect();
+
+    let data = parse_servo(table).unwrap();
+
+    assert_eq!(data.len(), 2);
+    assert_eq!(data[1].address, 65);
+    assert!(matches!(data[1].initial_value, InitialValue::None));
+    assert!(data[1].range.is_none());
+}
+
+#[test]
+fn numbered_items_are_not_joints() {
+    let table: Vec<Vec<String>> = [
+        &["Address", "Size(byte)", "Data Name", "Access"][..],
+        &["65", "1", "LED", "RW"],
+        &["66", "1", "LED 1", "RW"],
+        &["152", "2", "External Port Data 1", "RW"],
+        &["154", "2", "External Port Data 2", "RW"],
+        &["156", "2", "External Port Data 3", "RW"],
+    ]
+    .iter()
+    .map(|row| row.iter().map(|cell| cell.to_string()).collect())
+    .collect();
+    let mut data = parse_servo(table).unwrap();
+    split_joints(&mut data);
+    assert!(data.iter().all(|row| row.joint.is_none()));
+
+    // Names repeated without a bank per joint aren't joints either
+    let table: Vec<Vec<String>> = [
+        &["Address", "Size(byte)", "Data Name", "Access"][..],
+        &["64", "1", "Torque Enable", "RW"],
+        &["65", "1", "LED", "RW"],
+        &["164", "1", "Torque Enable", "RW"],
+        &["170", "1", "LED", "RW"],
+    ]
+    .iter()
+    .map(|row| row.iter().map(|cell| cell.to_string()).collect())
+    .collect();
+    let mut data = parse_servo(table).unwrap();
+    split_joints(&mut data);
+    assert!(data.iter().all(|row| row.joint.is_none()));
+}
+
+#[test]
+fn firmware_notes_only_apply_to_the_items_they_name() {
+    let table: Vec<Vec<String>> = [
+        &["Address", "Size(byte)", "Data Name", "Access"][..],
+        &["7", "1", "ID", "RW"],
+        &["8", "1", "Baud Rate", "RW"],
+        &["65", "1", "LED", "RW"],
+        &["98", "1", "Bus Watchdog", "RW"],
+        &["146", "1", "Status Return Level", "RW"],
+    ]
+    .iter()
+    .map(|row| row.iter().map(|cell| cell.to_string()).collect())
+    .collect();
+    let mut data = parse_servo(table).unwrap();
+    let page = "<p>The Bus Watchdog(98) is available from firmware v38. Set the ID and \
+                Baud Rate, and the LED will blink, on firmware v40 or above.</p>\
+                <table><tr><th>Firmware</th><th>Added</th></tr>\
+                <tr><td>v41</td><td>Status Return Level(146), Bus Watchdog</td></tr></table>";
+    apply_firmware_notes(&mut data, &firmware_notes(&Html::parse_document(page)));
+
+    let versions: Vec<Option<u8>> = data.iter().map(|row| row.min_firmware).collect();
+    assert_eq!(versions, vec![None, None, None, Some(38), Some(41)]);
+}
+
+#[test]
+fn combines_the_initial_values_of_split_bytes() {
+    let table: Vec<Vec<String>> = [
+        &[
+            "Address",
+            "Size(byte)",
+            "Data Name",
+            "Access",
+            "Initial Value",
+        ][..],
+        &["30", "1", "Goal Position(L)", "RW", "255"],
+        &["31", "1", "Goal Position(H)", "RW", "3"],
+        &["40", "4", "Goal Velocity(L)", "RW", "1"],
+        &["44", "4", "Goal Velocity(H)", "RW", "2"],
+    ]
+    .iter()
+    .map(|row| row.iter().map(|cell| cell.to_string()).collect())
+    .collect();
+    let data = parse_servo(table).unwrap();
+
+    assert_eq!(data.len(), 2);
+    assert!(matches!(
+        data[0].initial_value,
+        InitialValue::Value(RangeValue::Integer(1023))
+    ));
+    // Eight bytes don't fit in an i32
+    assert!(matches!(
+        &data[1].initial_value,
+        InitialValue::Expression(text) if text == "1 + (2 << 32)"
+    ));
+}
+
+#[test]
+fn ranges_refer_to_the_closest_item() {
+    let table: Vec<Vec<String>> = [
+        &[
+            "Address",
+            "Size(byte)",
+            "Data Name",
+            "Access",
+            "Initial Value",
+            "Range",
+        ][..],
+        &["36", "2", "PWM Limit", "RW", "885", "0 ~ 885"],
+        &["48", "4", "Max Position Limit", "RW", "4095", "0 ~ 4095"],
+        &["52", "4", "Min Position Limit", "RW", "0", "0 ~ 4095"],
+        &["100", "2", "Goal PWM", "RW", "-", "-PWMLimt36 ~ PWM Limit"],
+        &[
+            "116",
+            "4",
+            "Goal Position",
+            "RW",
+            "-",
+            "MPositionLimit ~ Foo",
+        ],
+    ]
+    .iter()
+    .map(|row| row.iter().map(|cell| cell.to_string()).collect())
+    .collect();
+
+    let mut data = parse_servo(table).unwrap();
+    resolve_references(&mut data);
+
+    let reference = |value: &RangeValue| match value {
+        RangeValue::Address {
+            name, reference, ..
+        } => (
+            name.clone(),
+            reference.as_ref().map(|reference| reference.address),
+        ),
+        RangeValue::Integer(_) => panic!("{:?} isn't an address", value),
+    };
+    let (min, max) = data[3].range.as_ref().unwrap();
+    // A misspelling is matched, and spelled like the item it refers to
+    assert_eq!(reference(min), ("PWMLimit".to_string(), Some(36)));
+    assert_eq!(reference(max), ("PWMLimit".to_string(), Some(36)));
+    // Of several items as close, the first is taken
+    let (min, max) = data[4].range.as_ref().unwrap();
+    assert_eq!(reference(min), ("MaxPositionLimit".to_string(), Some(48)));
+    // Names like nothing in the table are left alone
+    assert_eq!(reference(max), ("Foo".to_string(), None));
+}
+
+#[test]
+fn initial_values_set_at_the_factory_are_marked() {
+    let table: Vec<Vec<String>> = [
+        &[
+            "Address",
+            "Size(byte)",
+            "Data Name",
+            "Access",
+            "Initial Value",
+        ][..],
+        &["31", "1", "Temperature Limit", "RW", "80"],
+        &["44", "4", "Velocity Limit", "RW", "330"],
+        &["20", "4", "Homing Offset", "RW", "Factory Setting"],
+    ]
+    .iter()
+    .map(|row| row.iter().map(|cell| cell.to_string()).collect())
+    .collect();
+    let mut data = parse_servo(table).unwrap();
+    data[0].notes = vec!["Every item is reset to its initial value by Factory Reset".to_string()];
+    data[1].notes = vec!["The initial value differs with the firmware version".to_string()];
+
+    apply_initial_value_sources(&mut data);
+
+    let sources: Vec<InitialValueSource> =
+        data.iter().map(|row| row.initial_value_source).collect();
+    assert_eq!(
+        sources,
+        vec![
+            InitialValueSource::Default,
+            InitialValueSource::FirmwareDependent,
+            InitialValueSource::FactorySet,
+        ]
+    );
+    assert_eq!(
+        InitialValueSource::of_text("Calibrated at the factory for each unit"),
+        Some(InitialValueSource::FactorySet)
+    );
+    assert_eq!(
+        InitialValueSource::of_text("Available from firmware v42"),
+        None
+    );
+
+    let object = serialize_servo(&data, &[]).unwrap();
+    assert!(
+        object.contains("initial_value_source: FactorySet,"),
+        "{}",
+        object
+    );
+    let read = deserialize_servo(&object).unwrap();
+    assert_eq!(
+        read[1].initial_value_source,
+        InitialValueSource::FirmwareDependent
+    );
+}
+
+#[test]
+fn records_what_was_parsed_leniently() {
+    let table: Vec<Vec<String>> = [
+        &[
+            "Address",
+            "Size(byte)",
+            "Data Name",
+            "Access",
+            "Initial Value",
+            "Range",
+        ][..],
+        &["64", "1", "Torque Enable", "R/RW", "0", "0 ~ 1"],
+        &["65", "1", "LED", "RW", "0 ~ 1"],
+        &["68", "2", "Goal Current", "RW", "-", "0 ~ 1, 3 ~ 4"],
+        &["70", "3", "Registered", "X", "-", "-"],
+        &["72", "1", "Shutdown", "RW", "52", "0 ~ 12 bits"],
+    ]
+    .iter()
+    .map(|row| row.iter().map(|cell| cell.to_string()).collect())
+    .collect();
+
+    let (data, warnings) = parse_servo_with_warnings(table, false).unwrap();
+
+    let summary: Vec<(usize, Option<u16>, Option<&str>)> = warnings
+        .iter()
+        .map(|warning| (warning.row, warning.address, warning.column.as_deref()))
+        .collect();
+    assert_eq!(
+        summary,
+        [
+            (1, Some(64), Some("Access")),
+            (2, Some(65), None),
+            (3, Some(68), Some("Range")),
+            (4, Some(70), None),
+            (5, Some(72), Some("Range")),
+        ]
+    );
+    assert!(
+        warnings[3].reason.starts_with("Dropped"),
+        "{:?}",
+        warnings[3]
+    );
+
+    let ron = serialize_servo(&data, &warnings).unwrap();
+    let (read, read_warnings) = deserialize_servo_with_warnings(&ron).unwrap();
+    assert_eq!(read.len(), 4);
+    assert!(read[3].range.is_none());
+    assert_eq!(read_warnings, warnings);
+}
+
+#[test]
+fn attaches_conversion_equations_to_gains() {
+    let row = |address: u16, name: &str| {
+        format!(
+            "<tr><td>{}</td><td>2</td><td>{}</td><td>RW</td><td>0</td><td>0 ~ 16,383</td></tr>",
+            address, name
+        )
+    };
+    let conversions = |rows: &[(&str, &str)]| {
+        let rows: String = rows
+            .iter()
+            .map(|(equation, gain)| format!("<tr><td>{}</td><td>{}</td></tr>", equation, gain))
+            .collect();
+        format!(
+            "<table><tr><th>Conversion Equations</th><th>Description</th></tr>{}</table>",
+            rows
+        )
+    };
+    let headings = "<tr><th>Address</th><th>Size(Byte)</th><th>Data Name</th><th>Access</th>\
+                    <th>Initial Value</th><th>Range</th></tr>";
+    let page = format!(
+        "<table><tr><th>Item</th></tr></table>\
+         <table>{headings}{}</table>\
+         <table>{headings}{}{}{}{}{}{}</table>\
+         <h3>Velocity PI Gain(76, 78)</h3>{}\
+         <h3>Position PID Gain(80, 82, 84), Feedforward 1st/2nd Gains(88, 90)</h3>{}",
+        row(7, "ID"),
+        row(76, "Velocity I Gain"),
+        row(78, "Velocity P Gain"),
+        row(80, "Position D Gain"),
+        row(82, "Position I Gain"),
+        row(84, "Position P Gain"),
+        row(88, "Feedforward 2nd Gain"),
+        conversions(&[
+            ("K<sub>V</sub>I = K<sub>V</sub>I(TBL) / 65536", "I Gain"),
+            ("K<sub>V</sub>P = K<sub>V</sub>P(TBL) / 128", "P Gain"),
+        ]),
+        conversions(&[
+            ("K<sub>P</sub>D = K<sub>P</sub>D(TBL) / 16", "D Gain"),
+            ("K<sub>P</sub>P = K<sub>P</sub>P(TBL) / 128", "P Gain"),
+            (
+                "K<sub>FF2nd</sub> = K<sub>FF2nd</sub>(TBL) / 4",
+                "Feedforward 2nd Gain"
+            ),
+        ]),
+        headings = headings,
+    );
+    let data = parse_control_table(&page).unwrap();
+    let conversion = |name: &str| {
+        data.iter()
+            .find(|row| row.data_name.as_deref() == Some(name))
+            .unwrap()
+            .conversion
+            .clone()
+    };
+
+    assert_eq!(
+        conversion("Position P Gain"),
+        Some(Conversion {
+            symbol: "KPP".to_string(),
+            formula: "KPP = KPP(TBL) / 128".to_string(),
+            divisor: Some(128),
+        })
+    );
+    assert_eq!(conversion("Velocity I Gain").unwrap().divisor, Some(65536));
+    assert_eq!(conversion("Position D Gain").unwrap().symbol, "KPD");
+    assert_eq!(conversion("Feedforward 2nd Gain").unwrap().divisor, Some(4));
+    // No equation is given for these
+    assert_eq!(conversion("Position I Gain"), None);
+    assert_eq!(conversion("ID"), None);
+}
+
+#[test]
+fn attaches_the_values_of_drive_mode_bits() {
+    let headings = "<tr><th>Address</th><th>Size(Byte)</th><th>Data Name</th><th>Access</th>\
+                    <th>Initial Value</th><th>Range</th></tr>";
+    let page = format!(
+        "<table><tr><th>Item</th></tr></table>\
+         <table>{headings}<tr><td>10</td><td>1</td><td>Drive Mode</td><td>RW</td><td>0</td><td>0 ~ 13</td></tr></table>\
+         <table>{headings}<tr><td>64</td><td>1</td><td>Torque Enable</td><td>RW</td><td>0</td><td>0 ~ 1</td></tr></table>\
+         <h4>Drive Mode(10)</h4>\
+         <table><tr><th>Bit</th><th>Item</th><th>Description</th></tr>\
+         <tr><td>Bit 7</td><td>-</td><td>Unused, Always '0'</td></tr>\
+         <tr><td>Bit 3</td><td>Torque On by Goal Update</td><td>[0] Goal Update is ignored while Torque is off<br>[1] Goal Update turns Torque on</td></tr>\
+         <tr><td>Bit 2</td><td>Profile Configuration</td><td>[0] Velocity-based Profile<br>[1] Time-based Profile<br>※ See Profile Velocity(112)</td></tr>\
+         <tr><td>Bit 0</td><td>Normal/Reverse Mode</td><td>[0] Normal Mode: CCW(Positive), CW(Negative)<br>[1] Reverse Mode: CCW(Negative), CW(Positive)</td></tr>\
+         </table>",
+        headings = headings,
+    );
+    let data = parse_control_table(&page).unwrap();
+    let bits = data[0].bits.as_ref().unwrap();
+
+    let names: Vec<&str> = bits.iter().map(|field| field.name.as_str()).collect();
+    assert_eq!(
+        names,
+        [
+            "Normal/Reverse Mode",
+            "Profile Configuration",
+            "Torque On by Goal Update"
+        ]
+    );
+    assert_eq!(
+        bits[0].values,
+        [
+            BitValue {
+                value: 0,
+                meaning: "Normal Mode: CCW(Positive), CW(Negative)".to_string(),
+            },
+            BitValue {
+                value: 1,
+                meaning: "Reverse Mode: CCW(Negative), CW(Positive)".to_string(),
+            },
+        ]
+    );
+    // The note is about the whole bit, not the time-based profile
+    assert_eq!(bits[1].values[1].meaning, "Time-based Profile");
+    assert_eq!(data[1].bits, None);
+}
+
+#[test]
+fn fields_of_bits_past_the_item_are_left_out() {
+    let headings = "<tr><th>Address</th><th>Size(Byte)</th><th>Data Name</th><th>Access</th></tr>";
+    let page = format!(
+        "<table><tr><th>Item</th></tr></table>\
+         <table>{headings}<tr><td>63</td><td>1</td><td>Shutdown</td><td>RW</td></tr></table>\
+         <table>{headings}<tr><td>64</td><td>1</td><td>Torque Enable</td><td>RW</td></tr></table>\
+         <h4>Shutdown(63)</h4>\
+         <table><tr><th>Bit</th><th>Item</th></tr>\
+         <tr><td>0 ~ 255</td><td>Every Value</td></tr>\
+         <tr><td>Bit 8</td><td>Past The Item</td></tr>\
+         <tr><td>Bit 64</td><td>Past Any Mask</td></tr>\
+         <tr><td>Bit 5 ~ 7</td><td>Overload Error</td></tr>\
+         </table>",
+        headings = headings,
+    );
+    let data = parse_control_table(&page).unwrap();
+
+    let names: Vec<&str> = data[0]
+        .bits
+        .iter()
+        .flatten()
+        .map(|field| field.name.as_str())
+        .collect();
+    assert_eq!(names, ["Overload Error"]);
+}
+
+#[test]
+fn attaches_the_values_of_operating_mode() {
+    let headings = "<tr><th>Address</th><th>Size(Byte)</th><th>Data Name</th><th>Access</th>\
+                    <th>Initial Value</th><th>Range</th></tr>";
+    let page = format!(
+        "<table><tr><th>Item</th></tr></table>\
+         <table>{headings}<tr><td>11</td><td>1</td><td>Operating Mode</td><td>RW</td><td>3</td><td>0 ~ 16</td></tr></table>\
+         <table>{headings}<tr><td>64</td><td>1</td><td>Torque Enable</td><td>RW</td><td>0</td><td>0 ~ 1</td></tr></table>\
+         <h4>Operating Mode(11)</h4>\
+         <table><tr><th>Value</th><th>Operating Mode</th><th>Description</th></tr>\
+         <tr><td>1</td><td>Velocity Control Mode</td><td>Controls velocity</td></tr>\
+         <tr><td>3 ~ 252</td><td>Reserved</td><td>-</td></tr>\
+         <tr><td>16</td><td>PWM Control Mode</td><td>-</td></tr>\
+         </table>",
+        headings = headings,
+    );
+    let data = parse_control_table(&page).unwrap();
+
+    assert_eq!(
+        data[0].values,
+        [
+            ItemValue {
+                value: 1,
+                name: "Velocity Control Mode".to_string(),
+                description: Some("Controls velocity".to_string()),
+            },
+            ItemValue {
+                value: 16,
+                name: "PWM Control Mode".to_string(),
+                description: None,
+            },
+        ]
+    );
+    assert!(data[1].values.is_empty());
+
+    let schema: serde_json::Value = serde_json::from_str(
+        &serialize_servo_schema("XM430-W210", "https://example.com/xm430-w210/", &data).unwrap(),
+    )
+    .unwrap();
+    let properties = &schema["properties"];
+    assert_eq!(
+        properties["Operating Mode"]["enum"],
+        serde_json::json!([1, 16])
+    );
+    assert!(properties["Torque Enable"].get("enum").is_none());
+}
+
+#[test]
+fn parses_modbus_addresses_when_published() {
+    let headings = "<tr><th>Address</th><th>Size(Byte)</th><th>Data Name</th><th>Access</th>\
+                    <th>Initial Value</th><th>Modbus Address</th></tr>";
+    let page = format!(
+        "<table><tr><th>Item</th></tr></table>\
+         <table>{headings}<tr><td>0</td><td>2</td><td>Model Number</td><td>R</td><td>2020</td><td>40001</td></tr>\
+         <tr><td>7</td><td>1</td><td>ID</td><td>RW</td><td>1</td><td>-</td></tr></table>\
+         <table>{headings}<tr><td>512</td><td>1</td><td>Torque Enable</td><td>RW</td><td>0</td><td>40257</td></tr></table>",
+        headings = headings,
+    );
+    let data = parse_control_table(&page).unwrap();
+
+    let modbus_addresses: Vec<Option<u16>> = data.iter().map(|row| row.modbus_address).collect();
+    assert_eq!(modbus_addresses, [Some(40001), None, Some(40257)]);
+}
+
+#[test]
+fn access_levels_ignore_footnote_markers() {
+    for text in ["R", " R ", "R*", "R(2)", "Read"] {
+        assert!(
+            matches!(AccessLevel::parse(text), AccessLevel::Read),
+            "{:?}",
+            text
+        );
+    }
+    for text in ["RW", "R/W", "R / W", "RW(1)", "RW[1]", "RW※1", "rw"] {
+        assert!(
+            matches!(AccessLevel::parse(text), AccessLevel::ReadWrite),
+            "{:?}",
+            text
+        );
+    }
+    assert!(matches!(
+        AccessLevel::parse("W(1)"),
+        AccessLevel::Unknown(level) if level == "W"
+    ));
+}
+
+#[test]
+fn unknown_access_levels_are_warnings() {
+    let table: Vec<Vec<String>> = [
+        &["Address", "Size(byte)", "Data Name", "Access"][..],
+        &["64", "1", "Torque Enable", "RW(1)"],
+        &["65", "1", "LED", "W"],
+    ]
+    .iter()
+    .map(|row| row.iter().map(|cell| cell.to_string()).collect())
+    .collect();
+
+    let (data, warnings) = parse_servo_with_warnings(table, false).unwrap();
+
+    assert!(matches!(data[0].access, AccessLevel::ReadWrite));
+    assert!(matches!(&data[1].access, AccessLevel::Unknown(level) if level == "W"));
+    assert_eq!(warnings.len(), 1, "{:?}", warnings);
+    assert_eq!(
+        (warnings[0].address, warnings[0].column.as_deref()),
+        (Some(65), Some("Access"))
+    );
+    // Unknown access is kept as written
+    let ron = serialize_servo(&data, &warnings).unwrap();
+    assert!(ron.contains("access: Unknown(\"W\"),"), "{}", ron);
+}
@@ -0,0 +1,84 @@
+//! Tests for classifying changes between snapshots of the control tables.
+
+mod common;
+
+use common::fixture_path;
+use dynamixel_scraper::parse_fixture;
+use dynamixel_scraper::snapshot::{diff, suggested_bump, Bump, ChangeSummary, Snapshot};
+
+fn snapshot() -> Snapshot {
+    let path = fixture_path("xm430-w210.html");
+    let mut snapshot = Snapshot::new();
+    snapshot.insert("xm430-w210".to_string(), parse_fixture(path).unwrap());
+
+    snapshot
+}
+
+#[test]
+fn unchanged_tables_need_no_bump() {
+    assert_eq!(suggested_bump(&diff(&snapshot(), &snapshot())), None);
+}
+
+#[test]
+fn added_models_and_registers_are_minor() {
+    let mut current = snapshot();
+    current.insert("xm430-w350".to_string(), current["xm430-w210"].clone());
+    let mut led = current["xm430-w210"][0].clone();
+    led.data_name = Some("Status Return Level".to_string());
+    current.get_mut("xm430-w210").unwrap().push(led);
+
+    let changes = diff(&snapshot(), &current);
+
+    assert_eq!(changes.len(), 2, "{:?}", changes);
+    assert_eq!(suggested_bump(&changes), Some(Bump::Minor));
+}
+
+#[test]
+fn moved_or_removed_registers_are_major() {
+    let mut current = snapshot();
+    let data = current.get_mut("xm430-w210").unwrap();
+    data[1].address += 1;
+    data.pop();
+
+    let changes = diff(&snapshot(), &current);
+
+    assert_eq!(changes.len(), 2, "{:?}", changes);
+    assert!(changes.iter().all(|change| change.bump == Bump::Major));
+}
+
+#[test]
+fn each_joint_is_compared_apart() {
+    let path = fixture_path("2xc430-w250.html");
+    let mut previous = Snapshot::new();
+    previous.insert("2xc430-w250".to_string(), parse_fixture(path).unwrap());
+    let mut current = previous.clone();
+    let data = current.get_mut("2xc430-w250").unwrap();
+    let second = data.iter().rposition(|row| row.joint == Some(2)).unwrap();
+    data[second].address += 1;
+
+    let changes = diff(&previous, &current);
+
+    assert_eq!(changes.len(), 1, "{:?}", changes);
+    assert!(
+        changes[0].description.contains("(Joint 2) moved"),
+        "{:?}",
+        changes
+    );
+}
+
+#[test]
+fn summarises_changes_as_json() {
+    let mut current = snapshot();
+    current.get_mut("xm430-w210").unwrap()[1].address += 1;
+
+    let summary = ChangeSummary::new(diff(&snapshot(), &current));
+    let json: serde_json::Value = serde_json::to_value(&summary).unwrap();
+
+    assert_eq!(json["changed"], true);
+    assert_eq!(json["bump"], "major");
+    assert_eq!(json["changes"][0]["model"], "xm430-w210");
+    assert_eq!(
+        serde_json::to_value(ChangeSummary::new(vec![])).unwrap(),
+        serde_json::json!({ "changed": false, "bump": null, "changes": [] })
+    );
+}
@@ -0,0 +1,60 @@
+//! Tests for writing the control tables to a SQLite database.
+
+mod common;
+
+use common::servos;
+use dynamixel_scraper::sqlite::write_sqlite;
+use rusqlite::Connection;
+use std::path::Path;
+
+#[test]
+fn models_can_be_queried_together() {
+    let servos = servos(&["ax/ax-12a", "x/xm430-w210"]);
+    let path = Path::new(env!("CARGO_TARGET_TMPDIR")).join("control-tables.sqlite");
+    write_sqlite(&path, &servos).unwrap();
+    // Writing again replaces the database rather than adding to it
+    write_sqlite(&path, &servos).unwrap();
+
+    let connection = Connection::open(&path).unwrap();
+    let models: i64 = connection
+        .query_row("SELECT COUNT(*) FROM models", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(models, 2);
+
+    let (model, unit, scale): (String, String, f64) = connection
+        .query_row(
+            "SELECT models.slug, units.name, registers.scale FROM registers
+             JOIN models ON models.id = registers.model_id
+             JOIN units ON units.id = registers.unit_id
+             WHERE registers.data_name = 'Velocity Limit'",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .unwrap();
+    assert_eq!(
+        (model.as_str(), unit.as_str(), scale),
+        ("xm430-w210", "Rpm", 0.229)
+    );
+
+    let mut bounds = connection
+        .prepare(
+            "SELECT ranges.bound, ranges.item, ranges.item_address FROM ranges
+             JOIN registers ON registers.id = ranges.register_id
+             JOIN models ON models.id = registers.model_id
+             WHERE models.slug = 'xm430-w210' AND registers.data_name = 'Goal Position'
+             ORDER BY ranges.bound DESC",
+        )
+        .unwrap();
+    let bounds: Vec<(String, String, u16)> = bounds
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(
+        bounds,
+        vec![
+            ("min".to_string(), "Min Position Limit".to_string(), 52),
+            ("max".to_string(), "Max Position Limit".to_string(), 48),
+        ]
+    );
+}
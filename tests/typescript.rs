@@ -0,0 +1,61 @@
+//! Tests for generating the TypeScript definitions from the fixtures.
+
+mod common;
+
+use common::servos;
+use dynamixel_scraper::create_lib::NamingStrategy;
+use dynamixel_scraper::typescript::create_typescript;
+
+#[test]
+fn generates_a_typed_object_for_each_model() {
+    let servos = servos(&["ax/ax-12a", "x/xm430-w210"]);
+    let definitions = create_typescript(&servos, NamingStrategy::Compact).unwrap();
+
+    assert!(
+        definitions.contains("export interface ControlTableData {"),
+        "{}",
+        definitions
+    );
+    assert!(
+        definitions.contains("export const AX12A = {\n"),
+        "{}",
+        definitions
+    );
+    assert!(
+        definitions.contains(
+            "  ModelNumber: { address: 0, size: 2, access: \"Read\", valueType: \"U16\", initialValue: 12, min: null, max: null },\n"
+        ),
+        "{}",
+        definitions
+    );
+    assert!(definitions.contains("} as const;\n"), "{}", definitions);
+    assert!(
+        definitions.contains("export type Model =\n  | \"AX12A\"\n  | \"XM430W210\"\n;"),
+        "{}",
+        definitions
+    );
+    assert!(
+        definitions.contains("  | \"GoalPosition\"\n"),
+        "{}",
+        definitions
+    );
+    assert!(
+        definitions.contains(
+            "export const controlTables: Readonly<Record<Model, ControlTable>> = {\n  AX12A,\n  XM430W210,\n};"
+        ),
+        "{}",
+        definitions
+    );
+}
+
+#[test]
+fn names_models_as_the_library_does() {
+    let servos = servos(&["x/xm430-w210"]);
+    let definitions = create_typescript(&servos, NamingStrategy::Underscored).unwrap();
+
+    assert!(
+        definitions.contains("export const XM430_W210 = {"),
+        "{}",
+        definitions
+    );
+}
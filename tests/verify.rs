@@ -0,0 +1,91 @@
+//! Tests for compiling the generated library and tracing its errors back to
+//! the models they were generated for.
+
+mod common;
+
+use common::servos;
+use dynamixel_scraper::create_lib::{create_lib, LibTarget};
+use dynamixel_scraper::verify::{generated_for, verify_lib};
+use std::fs;
+use std::path::Path;
+
+fn generate(dir: &str) -> LibTarget {
+    let target = LibTarget {
+        path: Path::new(env!("CARGO_TARGET_TMPDIR")).join(dir),
+        ..LibTarget::default()
+    };
+    let _ = fs::remove_dir_all(&target.path);
+    create_lib(&servos(&["ax/ax-12a", "x/xm430-w210"]), &target).unwrap();
+
+    target
+}
+
+/// The line (counting from 1) of the first line containing `text` after the
+/// first line containing `after`
+fn line_of(source: &str, after: &str, text: &str) -> usize {
+    let start = source
+        .lines()
+        .position(|line| line.contains(after))
+        .unwrap();
+    start
+        + source
+            .lines()
+            .skip(start)
+            .position(|line| line.contains(text))
+            .unwrap()
+        + 1
+}
+
+#[test]
+fn finds_what_code_was_generated_for() {
+    let target = generate("verify-generated-for");
+    let source = fs::read_to_string(target.source_path()).unwrap();
+
+    let line = line_of(&source, "DataName::GoalPosition =>", "address: 116,");
+    assert_eq!(
+        generated_for(&source, line),
+        (
+            vec!["XM430W210".to_string()],
+            Some("GoalPosition".to_string())
+        )
+    );
+
+    let line = line_of(&source, "impl_registers!(", "ModelNumber");
+    assert_eq!(
+        generated_for(&source, line),
+        (vec!["AX12A".to_string()], None)
+    );
+
+    // The enums aren't generated for any one model
+    let line = line_of(&source, "pub enum DataName", "GoalPosition");
+    assert_eq!(generated_for(&source, line), (vec![], None));
+}
+
+#[test]
+fn reports_the_models_of_code_that_does_not_compile() {
+    // Dependencies are already in the registry's cache
+    std::env::set_var("CARGO_NET_OFFLINE", "true");
+    let target = generate("verify-broken");
+    let source = fs::read_to_string(target.source_path()).unwrap();
+    let line = line_of(&source, "DataName::GoalPosition =>", "address: 116,");
+    let broken: Vec<&str> = source
+        .lines()
+        .enumerate()
+        .map(|(idx, text)| match idx + 1 == line {
+            true => "                address: \"116\",",
+            false => text,
+        })
+        .collect();
+    fs::write(target.source_path(), broken.join("\n")).unwrap();
+
+    let error = verify_lib(&target, false).unwrap_err().to_string();
+    assert!(
+        error.contains("Generated for GoalPosition of XM430W210:"),
+        "{}",
+        error
+    );
+    assert!(error.contains("mismatched types"), "{}", error);
+
+    fs::write(target.source_path(), source).unwrap();
+    verify_lib(&target, false).unwrap();
+}